@@ -0,0 +1,375 @@
+//! Assembler for CHIPPER-style sources, the syntax printed by the disassembler. Every line holds
+//! an optional label, an optional instruction or directive and an optional comment. Programs are
+//! assembled to start at 0x200, unless an `ORG` directive moves the following statements to
+//! another address and the addresses of their labels with them. A program whose first statement
+//! is moved, e.g. to 0x600 as on the ETI-660, still loads at 0x200: it starts with a jump to its
+//! origin and the gap in between is filled with zeros.
+//!
+//! ```text
+//! ; draw a digit forever
+//! start:  LD V0, 5          ; numbers are decimal, or hexadecimal with 0x, # or $
+//!         LD F, V0
+//!         DRW V1, V1, 5
+//! loop:   JP loop
+//! sprite: DB 0xF0, 0x90, %11110000
+//!         DW 0x1234
+//!         LD I, long        ; XO-CHIP F000 NNNN, the address follows as a word
+//!         DW sprite
+//!         PRINT "V1 is %1"  ; a debug print through the host traps of crate::trap
+//!         ORG 0x400         ; the next statements are assembled at 0x400
+//! table:  DB 1, 2, 3
+//! ```
+
+use crate::symbols::Symbols;
+use crate::trap;
+use crate::Inst;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Address where assembled programs are loaded and start, unless they are moved with `ORG`
+pub const ORIGIN: u16 = 0x200;
+
+/// Assemble a source into a program image to be loaded at [`ORIGIN`]
+pub fn assemble(source: &str) -> Result<Vec<u8>, AsmError> {
+    assemble_with_symbols(source).map(|(image, _)| image)
+}
+
+/// Assemble a source into a program image to be loaded at [`ORIGIN`], along with the addresses
+/// of its labels
+pub fn assemble_with_symbols(source: &str) -> Result<(Vec<u8>, Symbols), AsmError> {
+    let lines: Vec<_> = source
+        .lines()
+        .enumerate()
+        .map(|(idx, text)| parse_line(text).map_err(|message| AsmError::new(idx, message)))
+        .collect::<Result<_, _>>()?;
+
+    // The size of every statement is known without the labels, so they are resolved first
+    let mut labels = HashMap::new();
+    let mut symbols = Symbols::new();
+    let mut addrs = Vec::with_capacity(lines.len());
+    let mut start = None;
+    let mut addr = ORIGIN as usize;
+    for (idx, line) in lines.iter().enumerate() {
+        if let Some(origin) = line
+            .origin()
+            .map_err(|message| AsmError::new(idx, message))?
+        {
+            if origin < addr {
+                return Err(AsmError::new(
+                    idx,
+                    format!("ORG {:#05X} is before the address {:#05X}", origin, addr),
+                ));
+            }
+            addr = origin;
+        }
+        if line.size() > 0 && start.is_none() {
+            start = Some((addr, idx));
+        }
+        addrs.push(addr);
+        if let Some(label) = &line.label {
+            if labels.insert(label.to_lowercase(), addr as u16).is_some() {
+                return Err(AsmError::new(
+                    idx,
+                    format!("label {} is defined twice", label),
+                ));
+            }
+            symbols.insert(addr as u16, label);
+        }
+        addr += line.size();
+        if addr > 0x1000 {
+            return Err(AsmError::new(idx, "the program does not fit in memory"));
+        }
+    }
+
+    // A program moved past the load address jumps over the gap to its first statement
+    let mut image = Vec::new();
+    match start {
+        Some((start, _)) if start >= ORIGIN as usize + 2 => {
+            image.extend_from_slice(&Inst::Op1NNN(start as u16).encode().to_be_bytes());
+        }
+        Some((start, idx)) if start > ORIGIN as usize => {
+            return Err(AsmError::new(
+                idx,
+                format!("ORG {:#05X} leaves no room for the jump at 0x200", start),
+            ));
+        }
+        _ => {}
+    }
+    for (idx, line) in lines.iter().enumerate() {
+        if line.size() > 0 {
+            image.resize(image.len().max(addrs[idx] - ORIGIN as usize), 0);
+        }
+        line.emit(&labels, &mut image)
+            .map_err(|message| AsmError::new(idx, message))?;
+    }
+    Ok((image, symbols))
+}
+
+/// A line of source with its comment removed
+#[derive(Debug)]
+struct Line {
+    label: Option<String>,
+    statement: Option<(String, Vec<String>)>,
+}
+
+fn parse_line(text: &str) -> Result<Line, String> {
+    // Comments and labels are only looked for before the text of a PRINT
+    let quote = text.find('"').unwrap_or(text.len());
+    let text = match text[..quote].find(';') {
+        Some(comment) => &text[..comment],
+        None => text,
+    }
+    .trim();
+    let quote = text.find('"').unwrap_or(text.len());
+    let (label, rest) = match text[..quote].find(':').map(|colon| text.split_at(colon)) {
+        Some((label, rest)) => {
+            let rest = &rest[1..];
+            let label = label.trim();
+            if !is_identifier(label) {
+                return Err(format!("invalid label {}", label));
+            }
+            (Some(label.to_string()), rest.trim())
+        }
+        None => (None, text),
+    };
+    let statement = if rest.is_empty() {
+        None
+    } else {
+        let (mnemonic, operands) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        let operands = if operands.trim_start().starts_with('"') {
+            vec![operands.trim().to_string()]
+        } else {
+            operands
+                .split(',')
+                .map(|operand| operand.trim().to_string())
+                .filter(|operand| !operand.is_empty())
+                .collect()
+        };
+        Some((mnemonic.to_uppercase(), operands))
+    };
+    Ok(Line { label, statement })
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .is_some_and(|ch| ch.is_ascii_alphabetic() || ch == '_')
+        && chars.all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+}
+
+impl Line {
+    /// The address given by an `ORG` directive, which must be a number
+    fn origin(&self) -> Result<Option<usize>, String> {
+        match &self.statement {
+            Some((mnemonic, operands)) if mnemonic == "ORG" => match operands.as_slice() {
+                [operand] => Operand::parse(operand, &HashMap::new())
+                    .and_then(|operand| operand.number(0xFFF))
+                    .map(|origin| Some(origin as usize))
+                    .map_err(|_| format!("invalid origin {}, expected an address", operand)),
+                _ => Err("ORG takes an address".to_string()),
+            },
+            _ => Ok(None),
+        }
+    }
+
+    /// Number of bytes emitted by the line
+    fn size(&self) -> usize {
+        match &self.statement {
+            None => 0,
+            Some((mnemonic, _)) if mnemonic == "ORG" => 0,
+            Some((mnemonic, operands)) if mnemonic == "DB" => operands.len(),
+            Some((mnemonic, operands)) if mnemonic == "DW" => operands.len() * 2,
+            Some((mnemonic, operands)) if mnemonic == "PRINT" => match operands.as_slice() {
+                [text] if text.starts_with('"') => 2 + (text.len() - 1).next_multiple_of(2),
+                _ => 2,
+            },
+            Some(_) => 2,
+        }
+    }
+
+    fn emit(&self, labels: &HashMap<String, u16>, image: &mut Vec<u8>) -> Result<(), String> {
+        let Some((mnemonic, operands)) = &self.statement else {
+            return Ok(());
+        };
+        if mnemonic == "ORG" {
+            return Ok(());
+        }
+        if let (true, [text]) = (mnemonic == "PRINT", operands.as_slice()) {
+            if let Some(text) = text.strip_prefix('"') {
+                let text = text
+                    .strip_suffix('"')
+                    .ok_or_else(|| "unterminated text".to_string())?;
+                if !text.is_ascii() {
+                    return Err("the text must be ASCII".to_string());
+                }
+                image.extend_from_slice(&Inst::Op0NNN(trap::PRINT_TEXT).encode().to_be_bytes());
+                image.extend_from_slice(text.as_bytes());
+                image.resize(
+                    image.len() + (text.len() + 1).next_multiple_of(2) - text.len(),
+                    0,
+                );
+                return Ok(());
+            }
+        }
+        let operands: Vec<_> = operands
+            .iter()
+            .map(|operand| Operand::parse(operand, labels))
+            .collect::<Result<_, _>>()?;
+        match mnemonic.as_str() {
+            "DB" => {
+                for operand in &operands {
+                    image.push(operand.byte()?);
+                }
+            }
+            "DW" => {
+                for operand in &operands {
+                    image.extend_from_slice(&operand.number(0xFFFF)?.to_be_bytes());
+                }
+            }
+            _ => {
+                let opcode = instruction(mnemonic, &operands)?.encode();
+                image.extend_from_slice(&opcode.to_be_bytes());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An operand of an instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operand {
+    Register(usize),
+    Number(u16),
+    /// `I`, `[I]`, `DT`, `ST`, `K`, `F`, `HF`, `B`, `R` or `LONG`
+    Keyword(&'static str),
+}
+
+impl Operand {
+    fn parse(s: &str, labels: &HashMap<String, u16>) -> Result<Self, String> {
+        const KEYWORDS: [&str; 10] = ["I", "[I]", "DT", "ST", "K", "F", "HF", "B", "R", "LONG"];
+        let upper = s.to_uppercase();
+        if let Some(keyword) = KEYWORDS.iter().find(|keyword| **keyword == upper) {
+            return Ok(Self::Keyword(keyword));
+        }
+        if upper.len() == 2 && upper.starts_with('V') {
+            if let Ok(x) = usize::from_str_radix(&upper[1..], 16) {
+                return Ok(Self::Register(x));
+            }
+        }
+        let number = if let Some(hex) = s
+            .strip_prefix("0x")
+            .or_else(|| s.strip_prefix('#'))
+            .or_else(|| s.strip_prefix('$'))
+        {
+            u16::from_str_radix(hex, 16).ok()
+        } else if let Some(bin) = s.strip_prefix('%').or_else(|| s.strip_prefix("0b")) {
+            u16::from_str_radix(bin, 2).ok()
+        } else if s.starts_with(|ch: char| ch.is_ascii_digit()) {
+            s.parse().ok()
+        } else {
+            labels.get(&s.to_lowercase()).copied()
+        };
+        number
+            .map(Self::Number)
+            .ok_or_else(|| format!("invalid operand {}", s))
+    }
+
+    fn number(self, max: u16) -> Result<u16, String> {
+        match self {
+            Self::Number(n) if n <= max => Ok(n),
+            Self::Number(n) => Err(format!("{:#X} is larger than {:#X}", n, max)),
+            _ => Err("expected a number".to_string()),
+        }
+    }
+
+    fn byte(self) -> Result<u8, String> {
+        self.number(0xFF).map(|n| n as u8)
+    }
+}
+
+/// The instruction of a mnemonic and its operands
+fn instruction(mnemonic: &str, operands: &[Operand]) -> Result<Inst, String> {
+    use Operand::Keyword as K;
+    use Operand::Register as V;
+
+    let addr = |operand: &Operand| operand.number(0xFFF);
+    let inst = match (mnemonic, operands) {
+        ("CLS", []) => Inst::Op00E0,
+        ("RET", []) => Inst::Op00EE,
+        ("SCD", [n]) => Inst::Op00CN(n.number(0xF)?),
+        ("SCR", []) => Inst::Op00FB,
+        ("SCL", []) => Inst::Op00FC,
+        ("EXIT", []) => Inst::Op00FD,
+        ("LOW", []) => Inst::Op00FE,
+        ("HIGH", []) => Inst::Op00FF,
+        ("SYS", [nnn]) => Inst::Op0NNN(addr(nnn)?),
+        ("JP", [V(0), nnn]) => Inst::OpBNNN(addr(nnn)?),
+        ("JP", [nnn]) => Inst::Op1NNN(addr(nnn)?),
+        ("CALL", [nnn]) => Inst::Op2NNN(addr(nnn)?),
+        ("SE", [V(x), V(y)]) => Inst::Op5XY0(*x, *y),
+        ("SE", [V(x), kk]) => Inst::Op3XKK(*x, kk.byte()?),
+        ("SNE", [V(x), V(y)]) => Inst::Op9XY0(*x, *y),
+        ("SNE", [V(x), kk]) => Inst::Op4XKK(*x, kk.byte()?),
+        ("LD", [K("I"), K("LONG")]) => Inst::OpF000,
+        ("LD", [K("I"), nnn]) => Inst::OpANNN(addr(nnn)?),
+        ("LD", [V(x), K("DT")]) => Inst::OpFX07(*x),
+        ("LD", [V(x), K("K")]) => Inst::OpFX0A(*x),
+        ("LD", [K("DT"), V(x)]) => Inst::OpFX15(*x),
+        ("LD", [K("ST"), V(x)]) => Inst::OpFX18(*x),
+        ("LD", [K("F"), V(x)]) => Inst::OpFX29(*x),
+        ("LD", [K("HF"), V(x)]) => Inst::OpFX30(*x),
+        ("LD", [K("B"), V(x)]) => Inst::OpFX33(*x),
+        ("LD", [K("[I]"), V(x)]) => Inst::OpFX55(*x),
+        ("LD", [V(x), K("[I]")]) => Inst::OpFX65(*x),
+        ("LD", [K("R"), V(x)]) => Inst::OpFX75(*x),
+        ("LD", [V(x), K("R")]) => Inst::OpFX85(*x),
+        ("LD", [V(x), V(y)]) => Inst::Op8XY0(*x, *y),
+        ("LD", [V(x), kk]) => Inst::Op6XKK(*x, kk.byte()?),
+        ("ADD", [K("I"), V(x)]) => Inst::OpFX1E(*x),
+        ("ADD", [V(x), V(y)]) => Inst::Op8XY4(*x, *y),
+        ("ADD", [V(x), kk]) => Inst::Op7XKK(*x, kk.byte()?),
+        ("OR", [V(x), V(y)]) => Inst::Op8XY1(*x, *y),
+        ("AND", [V(x), V(y)]) => Inst::Op8XY2(*x, *y),
+        ("XOR", [V(x), V(y)]) => Inst::Op8XY3(*x, *y),
+        ("SUB", [V(x), V(y)]) => Inst::Op8XY5(*x, *y),
+        ("SHR", [V(x)]) => Inst::Op8XY6(*x, *x),
+        ("SHR", [V(x), V(y)]) => Inst::Op8XY6(*x, *y),
+        ("SUBN", [V(x), V(y)]) => Inst::Op8XY7(*x, *y),
+        ("SHL", [V(x)]) => Inst::Op8XYE(*x, *x),
+        ("SHL", [V(x), V(y)]) => Inst::Op8XYE(*x, *y),
+        ("RND", [V(x), kk]) => Inst::OpCXKK(*x, kk.byte()?),
+        ("DRW", [V(x), V(y), n]) => Inst::OpDXYN(*x, *y, n.number(0xF)?),
+        ("SKP", [V(x)]) => Inst::OpEX9E(*x),
+        ("SKNP", [V(x)]) => Inst::OpEXA1(*x),
+        ("PRINT", [V(x)]) => Inst::Op0NNN(trap::PRINT + *x as u16),
+        _ => return Err(format!("invalid instruction {}", mnemonic)),
+    };
+    Ok(inst)
+}
+
+/// An error in a source, with the line where it was found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    /// Line number, starting at 1
+    pub line: usize,
+    /// What is wrong
+    pub message: String,
+}
+
+impl AsmError {
+    fn new<S: Into<String>>(idx: usize, message: S) -> Self {
+        Self {
+            line: idx + 1,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for AsmError {}
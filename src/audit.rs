@@ -0,0 +1,126 @@
+//! Determinism audit. The same program is run twice side by side with the same settings and
+//! inputs, and the state of the two machines is compared after every frame. The first frame where
+//! they differ is reported with the parts of the state that diverged, which points at the source
+//! of nondeterminism: the wall clock for the timers, input timing for the keypad, and random
+//! numbers or uninitialised state for the registers and memory. Replays and reproduction bundles
+//! are only faithful while the audit passes.
+
+use crate::repro::InputChange;
+use crate::screenshot::STEPS_PER_FRAME;
+use crate::Chip8;
+use std::fmt;
+
+/// Hashes of the parts of the machine state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StateDigest {
+    /// Registers, program counter, stack and the progress of the current instruction
+    pub cpu: u64,
+    /// The 4K of memory
+    pub memory: u64,
+    /// Delay and sound timers
+    pub timers: u64,
+    /// Framebuffer and resolution
+    pub screen: u64,
+    /// Keypad state
+    pub keys: u64,
+}
+
+impl StateDigest {
+    /// Names of the parts that differ between two digests
+    pub fn diff(&self, other: &Self) -> Vec<&'static str> {
+        [
+            ("cpu", self.cpu == other.cpu),
+            ("memory", self.memory == other.memory),
+            ("timers", self.timers == other.timers),
+            ("screen", self.screen == other.screen),
+            ("keys", self.keys == other.keys),
+        ]
+        .iter()
+        .filter(|(_, same)| !same)
+        .map(|(name, _)| *name)
+        .collect()
+    }
+}
+
+/// The first frame where the two runs differed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// Number of the frame, starting at 1
+    pub frame: u64,
+    /// The parts of the state that differ, see [`StateDigest::diff`]
+    pub parts: Vec<&'static str>,
+    /// State of the first run
+    pub first: String,
+    /// State of the second run
+    pub second: String,
+}
+
+/// Result of an audit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    /// Number of frames compared
+    pub frames: u64,
+    /// The first divergence, if any
+    pub divergence: Option<Divergence>,
+}
+
+/// Run two machines created with the same settings and program in lockstep for the given number
+/// of frames, feeding both the same key changes, and compare their state after every frame
+pub fn audit(first: Chip8, second: Chip8, inputs: &[InputChange], frames: u64) -> Report {
+    let mut machines = [first, second];
+    let mut inputs = inputs.iter().peekable();
+    let mut step = 0;
+    for frame in 1..=frames {
+        for _ in 0..STEPS_PER_FRAME {
+            while let Some(change) = inputs.next_if(|change| change.step <= step) {
+                let keys = crate::repro::from_mask(change.keys);
+                machines.iter_mut().for_each(|chip8| chip8.set_keys(keys));
+            }
+            machines.iter_mut().for_each(Chip8::step);
+            step += 1;
+        }
+        machines.iter_mut().for_each(Chip8::next_frame);
+        let [first, second] = &machines;
+        let parts = first.state_digest().diff(&second.state_digest());
+        if !parts.is_empty() {
+            return Report {
+                frames: frame,
+                divergence: Some(Divergence {
+                    frame,
+                    parts,
+                    first: first.dump(),
+                    second: second.dump(),
+                }),
+            };
+        }
+    }
+    Report {
+        frames,
+        divergence: None,
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(divergence) = &self.divergence else {
+            return writeln!(f, "Deterministic over {} frames", self.frames);
+        };
+        writeln!(
+            f,
+            "Diverged at frame {} in: {}",
+            divergence.frame,
+            divergence.parts.join(", ")
+        )?;
+        for part in &divergence.parts {
+            let hint = match *part {
+                "timers" => "the timers follow the wall clock",
+                "keys" => "the key changes reached the runs at different steps",
+                "screen" => "a draw depended on state that differs between the runs",
+                _ => "random numbers, uninitialised memory or a timer that was read",
+            };
+            writeln!(f, "  {}: {}", part, hint)?;
+        }
+        writeln!(f, "First run:\n{}", divergence.first)?;
+        write!(f, "Second run:\n{}", divergence.second)
+    }
+}
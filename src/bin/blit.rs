@@ -0,0 +1,68 @@
+//! The emulator with the lightweight frontend of [`chip8::blit`], built without ggez:
+//!
+//! ```text
+//! cargo run --release --no-default-features --features blit --bin chip8-blit -- /path/to/rom
+//! ```
+
+use chip8::blit::BlitConfig;
+use chip8::datapaths::DataPaths;
+use chip8::romconfig::RomConfig;
+use chip8::storage::FileStorage;
+use chip8::*;
+use clap::Parser;
+use std::error::Error;
+use std::path::PathBuf;
+
+/// A CHIP-8 and SUPER-CHIP emulator in a plain scaled window
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// The ROM
+    rom: PathBuf,
+    /// Size of a CHIP-8 pixel in logical pixels
+    #[arg(long, value_name = "N", default_value_t = 16)]
+    scale: u32,
+    /// Colors of the lit and unlit pixels, then of the XO-CHIP second plane and of both planes
+    #[arg(long, num_args = 2..=4, value_name = "RRGGBB", value_parser = console::parse_color)]
+    palette: Option<Vec<u32>>,
+    /// Instructions per second, instead of the settings of the ROM
+    #[arg(long, value_name = "TICKS")]
+    speed: Option<u32>,
+    /// Quirk profile, instead of the settings of the ROM: chip8, schip or xochip
+    #[arg(long = "quirks", value_name = "PROFILE")]
+    profile: Option<quirks::Profile>,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let cli = Cli::parse();
+    let error = |err: &dyn std::fmt::Display| format!("{}: {}", cli.rom.display(), err);
+    let program = rom::Rom::open(&cli.rom).map_err(|err| error(&err))?;
+    for problem in program.warnings() {
+        eprintln!("{}", error(&format!("{}, {}", problem, problem.hint())));
+    }
+    // The settings are in the data directory when a portable.txt sits beside the executable
+    let paths = DataPaths::detect(false)?;
+    let rom_config = RomConfig::load(&FileStorage, &paths, &cli.rom).map_err(|err| error(&err))?;
+    let quirks = cli
+        .profile
+        .map(|profile| profile.quirks())
+        .or(rom_config.quirks)
+        .unwrap_or_default();
+    let mut chip8 = Chip8::builder().quirks(quirks).build();
+    chip8.load(program.data()).map_err(|err| error(&err))?;
+
+    let palette = cli.palette.as_deref().and_then(palette::Palette::from_list);
+    let config = BlitConfig {
+        ticks_per_sec: cli.speed.or(rom_config.speed).unwrap_or(TICKS_PER_SEC),
+        scale: cli.scale.max(1),
+        palette: palette.or(rom_config.palette).unwrap_or_default(),
+    };
+    blit::run(&mut chip8, &config)?;
+    for warning in chip8.take_warnings() {
+        eprintln!("{}", warning);
+    }
+    if let Some(fault) = chip8.fault() {
+        eprint!("Halted: {}\n{}", fault, chip8.dump());
+    }
+    Ok(())
+}
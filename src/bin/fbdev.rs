@@ -0,0 +1,68 @@
+//! The emulator on the Linux framebuffer with keys from evdev, see [`chip8::fbdev`], built
+//! without ggez and its sound libraries for consoles without a window system:
+//!
+//! ```text
+//! cargo run --release --no-default-features --features fbdev --bin chip8-fbdev -- /path/to/rom
+//! ```
+
+use chip8::datapaths::DataPaths;
+use chip8::fbdev::FbdevConfig;
+use chip8::romconfig::RomConfig;
+use chip8::storage::FileStorage;
+use chip8::*;
+use clap::Parser;
+use std::error::Error;
+use std::path::PathBuf;
+
+/// A CHIP-8 and SUPER-CHIP emulator on the Linux framebuffer
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// The ROM
+    rom: PathBuf,
+    /// The framebuffer device
+    #[arg(long, value_name = "DEVICE", default_value = "/dev/fb0")]
+    framebuffer: PathBuf,
+    /// The evdev keyboard device
+    #[arg(long, value_name = "DEVICE", default_value = "/dev/input/event0")]
+    keyboard: PathBuf,
+    /// Instructions per second, instead of the settings of the ROM
+    #[arg(long, value_name = "TICKS")]
+    speed: Option<u32>,
+    /// Quirk profile, instead of the settings of the ROM: chip8, schip or xochip
+    #[arg(long = "quirks", value_name = "PROFILE")]
+    profile: Option<quirks::Profile>,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let cli = Cli::parse();
+    let error = |err: &dyn std::fmt::Display| format!("{}: {}", cli.rom.display(), err);
+    let program = rom::Rom::open(&cli.rom).map_err(|err| error(&err))?;
+    for problem in program.warnings() {
+        eprintln!("{}", error(&format!("{}, {}", problem, problem.hint())));
+    }
+    // The settings are in the data directory when a portable.txt sits beside the executable
+    let paths = DataPaths::detect(false)?;
+    let rom_config = RomConfig::load(&FileStorage, &paths, &cli.rom).map_err(|err| error(&err))?;
+    let quirks = cli
+        .profile
+        .map(|profile| profile.quirks())
+        .or(rom_config.quirks)
+        .unwrap_or_default();
+    let mut chip8 = Chip8::builder().quirks(quirks).build();
+    chip8.load(program.data()).map_err(|err| error(&err))?;
+
+    let config = FbdevConfig {
+        framebuffer: cli.framebuffer,
+        keyboard: cli.keyboard,
+        ticks_per_sec: cli.speed.or(rom_config.speed).unwrap_or(TICKS_PER_SEC),
+    };
+    fbdev::run(&mut chip8, &config)?;
+    for warning in chip8.take_warnings() {
+        eprintln!("{}", warning);
+    }
+    if let Some(fault) = chip8.fault() {
+        eprint!("Halted: {}\n{}", fault, chip8.dump());
+    }
+    Ok(())
+}
@@ -0,0 +1,150 @@
+//! A lightweight frontend that blits the screen into a scaled window with `winit` and `pixels`,
+//! for users who only need to play and would rather not build ggez. It has none of the tools of
+//! [`crate::window`] and no sound.
+
+use super::pacer::Pacer;
+use super::palette::Palette;
+use super::Chip8;
+use pixels::Pixels;
+use pixels::SurfaceTexture;
+use std::error::Error;
+use std::time;
+use winit::dpi::LogicalSize;
+use winit::event::ElementState;
+use winit::event::Event;
+use winit::event::VirtualKeyCode;
+use winit::event::WindowEvent;
+use winit::event_loop::EventLoop;
+use winit::platform::run_return::EventLoopExtRunReturn;
+use winit::window::WindowBuilder;
+
+/// Settings of the frontend
+#[derive(Debug, Clone)]
+pub struct BlitConfig {
+    /// Number of instructions executed per second
+    pub ticks_per_sec: u32,
+    /// Size of a CHIP-8 pixel in the window, in logical pixels
+    pub scale: u32,
+    /// Colors of the screen
+    pub palette: Palette,
+}
+
+/// Run the emulator until the window is closed or Escape is pressed
+pub fn run(chip8: &mut Chip8, config: &BlitConfig) -> Result<(), Box<dyn Error>> {
+    let mut event_loop = EventLoop::new();
+    let (width, height) = chip8.screen_size();
+    let size = LogicalSize::new(
+        crate::CHIP8_SCREEN_WIDTH as u32 * config.scale,
+        crate::CHIP8_SCREEN_HEIGHT as u32 * config.scale,
+    );
+    let window = WindowBuilder::new()
+        .with_title("CHIP-8")
+        .with_inner_size(size)
+        .build(&event_loop)?;
+    let surface = window.inner_size();
+    let surface = SurfaceTexture::new(surface.width, surface.height, &window);
+    let mut pixels = Pixels::new(width as u32, height as u32, surface)?;
+    let mut buffer_size = (width, height);
+
+    let frame = time::Duration::from_secs(1) / 60;
+    let mut pacer = Pacer::default();
+    let mut next_frame = time::Instant::now();
+    let mut result = Ok(());
+    event_loop.run_return(|event, _, control_flow| match event {
+        Event::WindowEvent { event, .. } => match event {
+            WindowEvent::CloseRequested => control_flow.set_exit(),
+            WindowEvent::Resized(size) => {
+                if let Err(err) = pixels.resize_surface(size.width, size.height) {
+                    result = Err(err.into());
+                    control_flow.set_exit();
+                }
+            }
+            WindowEvent::KeyboardInput { input, .. } => match input.virtual_keycode {
+                Some(VirtualKeyCode::Escape) => control_flow.set_exit(),
+                Some(code) => {
+                    if let Some(key) = keypad_index(code) {
+                        chip8.set_keyboard_key(key, input.state == ElementState::Pressed);
+                    }
+                }
+                None => {}
+            },
+            _ => {}
+        },
+        Event::MainEventsCleared => {
+            let now = time::Instant::now();
+            if now >= next_frame {
+                chip8.poll_inputs();
+                pacer.run(chip8, config.ticks_per_sec);
+                window.request_redraw();
+                // Frames that were missed, e.g. while the window was moved, are dropped
+                next_frame = (next_frame + frame).max(now);
+            }
+            control_flow.set_wait_until(next_frame);
+        }
+        Event::RedrawRequested(_) => {
+            let size = chip8.screen_size();
+            if size != buffer_size {
+                buffer_size = size;
+                if let Err(err) = pixels.resize_buffer(size.0 as u32, size.1 as u32) {
+                    result = Err(err.into());
+                    return control_flow.set_exit();
+                }
+            }
+            let colors = [config.palette.color(0), config.palette.color(1)].map(|rgb| {
+                let [_, r, g, b] = rgb.to_be_bytes();
+                [r, g, b, 0xFF]
+            });
+            for (pixel, lit) in pixels
+                .frame_mut()
+                .chunks_exact_mut(4)
+                .zip(chip8.framebuffer())
+            {
+                pixel.copy_from_slice(&colors[*lit as usize]);
+            }
+            if let Err(err) = pixels.render() {
+                result = Err(err.into());
+                control_flow.set_exit();
+            }
+        }
+        _ => {}
+    });
+    result
+}
+
+/// The keypad key that a key is mapped to, using the same layout as the window
+fn keypad_index(code: VirtualKeyCode) -> Option<u8> {
+    let key = match code {
+        VirtualKeyCode::Key1 => 0x1,
+        VirtualKeyCode::Key2 => 0x2,
+        VirtualKeyCode::Key3 => 0x3,
+        VirtualKeyCode::Key4 => 0xC,
+        VirtualKeyCode::Q => 0x4,
+        VirtualKeyCode::W => 0x5,
+        VirtualKeyCode::E => 0x6,
+        VirtualKeyCode::R => 0xD,
+        VirtualKeyCode::A => 0x7,
+        VirtualKeyCode::S => 0x8,
+        VirtualKeyCode::D => 0x9,
+        VirtualKeyCode::F => 0xE,
+        VirtualKeyCode::Z => 0xA,
+        VirtualKeyCode::X => 0x0,
+        VirtualKeyCode::C => 0xB,
+        VirtualKeyCode::V => 0xF,
+        _ => return None,
+    };
+    Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::keypad_index;
+    use winit::event::VirtualKeyCode::*;
+
+    #[test]
+    fn keys_follow_the_layout_of_the_window() {
+        let codes = [Key1, Key2, Key3, Key4, Q, W, E, R, A, S, D, F, Z, X, C, V];
+        let layout = [1, 2, 3, 0xC, 4, 5, 6, 0xD, 7, 8, 9, 0xE, 0xA, 0, 0xB, 0xF];
+        assert_eq!(codes.map(keypad_index), layout.map(Some));
+        assert_eq!(keypad_index(Escape), None);
+    }
+}
@@ -0,0 +1,91 @@
+//! The public domain games of `roms/`, compiled into the emulator for builds that have no files
+//! to open them from, such as the offline web build. [`Bundled`] serves them as a read-only
+//! [`Storage`] under `roms/NAME`, so they are looked up like any other file.
+
+use crate::storage::Storage;
+use std::io;
+use std::path::Path;
+
+/// Directory of the bundled ROMs in the [`Bundled`] storage
+pub const DIR: &str = "roms";
+
+/// A ROM compiled into the emulator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BundledRom {
+    /// File name of the ROM in `roms/`
+    pub name: &'static str,
+    /// The program, loaded at 0x200
+    pub data: &'static [u8],
+}
+
+macro_rules! bundled {
+    ($name:literal) => {
+        BundledRom {
+            name: $name,
+            data: include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/roms/", $name)),
+        }
+    };
+}
+
+/// Every bundled ROM, by name
+pub const ROMS: [BundledRom; 23] = [
+    bundled!("15PUZZLE"),
+    bundled!("BLINKY"),
+    bundled!("BLITZ"),
+    bundled!("BRIX"),
+    bundled!("CONNECT4"),
+    bundled!("GUESS"),
+    bundled!("HIDDEN"),
+    bundled!("INVADERS"),
+    bundled!("KALEID"),
+    bundled!("MAZE"),
+    bundled!("MERLIN"),
+    bundled!("MISSILE"),
+    bundled!("PONG"),
+    bundled!("PONG2"),
+    bundled!("PUZZLE"),
+    bundled!("SYZYGY"),
+    bundled!("TANK"),
+    bundled!("TETRIS"),
+    bundled!("TICTAC"),
+    bundled!("UFO"),
+    bundled!("VBRIX"),
+    bundled!("VERS"),
+    bundled!("WIPEOFF"),
+];
+
+/// The bundled ROM with a name, ignoring case
+pub fn find(name: &str) -> Option<&'static BundledRom> {
+    ROMS.iter().find(|rom| rom.name.eq_ignore_ascii_case(name))
+}
+
+/// The bundled ROMs as files under [`DIR`], which cannot be written
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Bundled;
+
+impl Bundled {
+    /// The bundled ROM at a path of the storage
+    fn rom(path: &Path) -> Option<&'static BundledRom> {
+        let name = path.strip_prefix(DIR).ok()?.to_str()?;
+        ROMS.iter().find(|rom| rom.name == name)
+    }
+}
+
+impl Storage for Bundled {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        Self::rom(path)
+            .map(|rom| rom.data.to_vec())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no such bundled ROM"))
+    }
+
+    fn write(&self, _path: &Path, _data: &[u8]) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "the bundled ROMs cannot be written",
+        ))
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        Self::rom(path).is_some()
+    }
+}
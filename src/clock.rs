@@ -0,0 +1,46 @@
+//! A monotonic clock that also works in the browser, where `std::time::Instant` is not available.
+//! On `wasm32` targets the host page provides the time through the `chip8.now` import, usually
+//! backed by `performance.now()`.
+
+use std::time;
+
+/// A point in time, measured in milliseconds from an arbitrary origin
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Instant(f64);
+
+impl Instant {
+    /// The current time
+    pub fn now() -> Self {
+        Self(now_ms())
+    }
+
+    /// Time elapsed since this instant
+    pub fn elapsed(&self) -> time::Duration {
+        Self::now().duration_since(*self)
+    }
+
+    /// Time elapsed from an earlier instant to this one, zero if it is not earlier
+    pub fn duration_since(&self, earlier: Instant) -> time::Duration {
+        time::Duration::from_secs_f64((self.0 - earlier.0).max(0.0) / 1000.0)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn now_ms() -> f64 {
+    use std::sync::OnceLock;
+    static ORIGIN: OnceLock<time::Instant> = OnceLock::new();
+    ORIGIN
+        .get_or_init(time::Instant::now)
+        .elapsed()
+        .as_secs_f64()
+        * 1000.0
+}
+
+#[cfg(target_arch = "wasm32")]
+fn now_ms() -> f64 {
+    #[link(wasm_import_module = "chip8")]
+    extern "C" {
+        fn now() -> f64;
+    }
+    unsafe { now() }
+}
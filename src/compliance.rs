@@ -0,0 +1,323 @@
+//! A compliance suite that checks the instructions against the expected behaviour under a quirk
+//! configuration, and summarises the results in a machine-readable form for compatibility
+//! tables.
+//!
+//! ```toml
+//! emulator = "chip8"
+//! version = "0.1.0"
+//! passed = 14
+//! failed = 0
+//!
+//! [quirks]
+//! shift_in_place = true
+//! ...
+//!
+//! [[tests]]
+//! name = "8XY4 carry"
+//! passed = true
+//! ```
+
+use crate::quirks::Quirks;
+use crate::testrom::RomBuilder;
+use crate::Chip8;
+use crate::Inst;
+use serde::Serialize;
+
+/// A test of the suite
+#[derive(Debug, Clone, Copy)]
+pub struct Case {
+    /// Name of the test
+    pub name: &'static str,
+    /// Run the test with a quirk configuration, returns what went wrong if it failed
+    pub run: fn(Quirks) -> Result<(), String>,
+}
+
+/// Result of a test
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TestResult {
+    /// Name of the test
+    pub name: String,
+    /// Whether the test passed
+    pub passed: bool,
+    /// What went wrong
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// Results of the whole suite
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Summary {
+    /// Name of the emulator
+    pub emulator: String,
+    /// Version of the emulator
+    pub version: String,
+    /// Number of tests that passed
+    pub passed: usize,
+    /// Number of tests that failed
+    pub failed: usize,
+    /// The quirk configuration the suite ran with
+    pub quirks: Quirks,
+    /// Result of every test
+    pub tests: Vec<TestResult>,
+}
+
+impl Summary {
+    /// True if every test passed
+    pub fn passed(&self) -> bool {
+        self.failed == 0
+    }
+
+    /// The summary as TOML
+    pub fn to_toml(&self) -> Result<String, toml::ser::Error> {
+        toml::to_string(self)
+    }
+}
+
+/// Run the whole suite with a quirk configuration
+pub fn run(quirks: Quirks) -> Summary {
+    let tests: Vec<_> = CASES
+        .iter()
+        .map(|case| {
+            let result = (case.run)(quirks);
+            TestResult {
+                name: case.name.to_string(),
+                passed: result.is_ok(),
+                message: result.err(),
+            }
+        })
+        .collect();
+    let passed = tests.iter().filter(|test| test.passed).count();
+    Summary {
+        emulator: env!("CARGO_PKG_NAME").to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        passed,
+        failed: tests.len() - passed,
+        quirks,
+        tests,
+    }
+}
+
+/// Run a program until it has executed as many instructions as given
+fn exec(quirks: Quirks, insts: &[Inst], steps: usize) -> Chip8 {
+    let mut chip8 = RomBuilder::new().insts(insts).load();
+    chip8.set_quirks(quirks);
+    for _ in 0..steps {
+        step(&mut chip8);
+    }
+    chip8
+}
+
+/// Run the next instruction, after the frame that the display_wait quirk waits for
+fn step(chip8: &mut Chip8) {
+    if chip8.waiting_for_frame() {
+        chip8.next_frame();
+    }
+    chip8.step();
+}
+
+fn expect<T: PartialEq + std::fmt::Debug>(
+    what: &str,
+    actual: T,
+    expected: T,
+) -> Result<(), String> {
+    if actual == expected {
+        Ok(())
+    } else {
+        Err(format!("{} is {:?}, expected {:?}", what, actual, expected))
+    }
+}
+
+/// Every test of the suite
+pub const CASES: [Case; 14] = [
+    Case {
+        name: "7XKK wraps around",
+        run: |quirks| {
+            let chip8 = exec(quirks, &[Inst::Op6XKK(1, 0xFF), Inst::Op7XKK(1, 2)], 2);
+            expect("V1", chip8.registers()[1], 0x01)
+        },
+    },
+    Case {
+        name: "8XY4 carry",
+        run: |quirks| {
+            let insts = [
+                Inst::Op6XKK(0, 0xFF),
+                Inst::Op6XKK(1, 0x02),
+                Inst::Op8XY4(0, 1),
+            ];
+            let chip8 = exec(quirks, &insts, 3);
+            expect("V0", chip8.registers()[0], 0x01)?;
+            expect("VF", chip8.registers()[0xF], 1)
+        },
+    },
+    Case {
+        name: "8XY5 borrow",
+        run: |quirks| {
+            let insts = [
+                Inst::Op6XKK(0, 0x01),
+                Inst::Op6XKK(1, 0x02),
+                Inst::Op8XY5(0, 1),
+            ];
+            let chip8 = exec(quirks, &insts, 3);
+            expect("V0", chip8.registers()[0], 0xFF)?;
+            expect("VF", chip8.registers()[0xF], 0)
+        },
+    },
+    Case {
+        name: "8XY7 no borrow",
+        run: |quirks| {
+            let insts = [
+                Inst::Op6XKK(0, 0x01),
+                Inst::Op6XKK(1, 0x03),
+                Inst::Op8XY7(0, 1),
+            ];
+            let chip8 = exec(quirks, &insts, 3);
+            expect("V0", chip8.registers()[0], 0x02)?;
+            expect("VF", chip8.registers()[0xF], 1)
+        },
+    },
+    Case {
+        name: "8XY6 shift right",
+        run: |quirks| {
+            let insts = [
+                Inst::Op6XKK(0, 0x04),
+                Inst::Op6XKK(1, 0x03),
+                Inst::Op8XY6(0, 1),
+            ];
+            let chip8 = exec(quirks, &insts, 3);
+            let (value, flag) = if quirks.shift_in_place {
+                (0x02, 0)
+            } else {
+                (0x01, 1)
+            };
+            expect("V0", chip8.registers()[0], value)?;
+            expect("VF", chip8.registers()[0xF], flag)
+        },
+    },
+    Case {
+        name: "8XYE shift left",
+        run: |quirks| {
+            let insts = [
+                Inst::Op6XKK(0, 0x01),
+                Inst::Op6XKK(1, 0x81),
+                Inst::Op8XYE(0, 1),
+            ];
+            let chip8 = exec(quirks, &insts, 3);
+            let (value, flag) = if quirks.shift_in_place {
+                (0x02, 0)
+            } else {
+                (0x02, 1)
+            };
+            expect("V0", chip8.registers()[0], value)?;
+            expect("VF", chip8.registers()[0xF], flag)
+        },
+    },
+    Case {
+        name: "8XY1 VF reset",
+        run: |quirks| {
+            let insts = [
+                Inst::Op6XKK(0xF, 0x05),
+                Inst::Op6XKK(0, 0x10),
+                Inst::Op8XY1(0, 0),
+            ];
+            let chip8 = exec(quirks, &insts, 3);
+            let flag = if quirks.vf_reset { 0 } else { 0x05 };
+            expect("VF", chip8.registers()[0xF], flag)
+        },
+    },
+    Case {
+        name: "3XKK skips",
+        run: |quirks| {
+            let insts = [
+                Inst::Op6XKK(0, 0x12),
+                Inst::Op3XKK(0, 0x12),
+                Inst::Op6XKK(1, 0x01),
+                Inst::Op6XKK(2, 0x01),
+            ];
+            let chip8 = exec(quirks, &insts, 3);
+            expect("V1", chip8.registers()[1], 0)?;
+            expect("V2", chip8.registers()[2], 1)
+        },
+    },
+    Case {
+        name: "2NNN and 00EE",
+        run: |quirks| {
+            let insts = [
+                Inst::Op2NNN(0x206),
+                Inst::Op6XKK(1, 0x01),
+                Inst::Op1NNN(0x204),
+                Inst::Op6XKK(0, 0x01),
+                Inst::Op00EE,
+            ];
+            let chip8 = exec(quirks, &insts, 4);
+            expect("V0", chip8.registers()[0], 1)?;
+            expect("V1", chip8.registers()[1], 1)
+        },
+    },
+    Case {
+        name: "BNNN jump",
+        run: |quirks| {
+            let insts = [
+                Inst::Op6XKK(0, 0x02),
+                Inst::Op6XKK(2, 0x04),
+                Inst::OpBNNN(0x200),
+            ];
+            let chip8 = exec(quirks, &insts, 3);
+            let target = if quirks.jump_vx { 0x204 } else { 0x202 };
+            expect("PC", chip8.pc(), target)
+        },
+    },
+    Case {
+        name: "FX33 BCD",
+        run: |quirks| {
+            let insts = [Inst::Op6XKK(0, 234), Inst::OpANNN(0x300), Inst::OpFX33(0)];
+            let chip8 = exec(quirks, &insts, 3);
+            expect("memory", &chip8.memory()[0x300..0x303], &[2, 3, 4][..])
+        },
+    },
+    Case {
+        name: "FX55 and FX65",
+        run: |quirks| {
+            let insts = [
+                Inst::Op6XKK(0, 0x11),
+                Inst::Op6XKK(1, 0x22),
+                Inst::OpANNN(0x300),
+                Inst::OpFX55(1),
+                Inst::OpANNN(0x300),
+                Inst::Op6XKK(0, 0),
+                Inst::OpFX65(0),
+            ];
+            let chip8 = exec(quirks, &insts, 7);
+            expect("V0", chip8.registers()[0], 0x11)?;
+            expect("memory", &chip8.memory()[0x300..0x302], &[0x11, 0x22][..])?;
+            let index = if quirks.increment_index { 0x301 } else { 0x300 };
+            expect("I", chip8.index(), index)
+        },
+    },
+    Case {
+        name: "FX1E add to I",
+        run: |quirks| {
+            let insts = [Inst::Op6XKK(0, 0x10), Inst::OpANNN(0x300), Inst::OpFX1E(0)];
+            let chip8 = exec(quirks, &insts, 3);
+            expect("I", chip8.index(), 0x310)
+        },
+    },
+    Case {
+        name: "DXYN collision",
+        run: |quirks| {
+            let insts = [
+                Inst::OpANNN(0),
+                Inst::OpDXYN(0, 0, 5),
+                Inst::OpDXYN(0, 0, 5),
+            ];
+            let mut chip8 = exec(quirks, &insts, 2);
+            expect("VF after the first draw", chip8.registers()[0xF], 0)?;
+            step(&mut chip8);
+            expect("VF after the second draw", chip8.registers()[0xF], 1)?;
+            expect(
+                "lit pixels",
+                chip8.framebuffer().iter().filter(|lit| **lit).count(),
+                0,
+            )
+        },
+    },
+];
@@ -0,0 +1,224 @@
+//! A conformance harness that runs test ROMs without a window, such as the CHIP-8 test suite of
+//! Timendus, and compares the screen they end on with a known-good snapshot. It catches changes
+//! in the semantics of the opcodes that the opcode tests miss, since the suites check the
+//! instructions together as real programs use them.
+//!
+//! A suite is a directory with the ROMs, their snapshots and a `conformance.toml` manifest:
+//!
+//! ```toml
+//! [[tests]]
+//! name = "IBM logo"
+//! rom = "2-ibm-logo.ch8"
+//! cycles = 1000
+//!
+//! [[tests]]
+//! name = "Quirks, CHIP-8"
+//! rom = "5-quirks.ch8"
+//! cycles = 100000
+//! profile = "chip8"
+//! poke = [[0x1FF, 1]]
+//! ```
+//!
+//! Each ROM runs for its number of cycles (ticks) with the quirks of its profile, the default
+//! ones if it has none, after the bytes of `poke` are written to memory. The timers count down
+//! every [`TICKS_PER_SEC`]` / 60` cycles and the random numbers have a fixed seed, so a run does
+//! not depend on the host. The screen is then compared with the snapshot, a text file next to
+//! the ROM with the `txt` extension unless `snapshot` names another one, with a line per row
+//! and `#` for a lit pixel and `.` for an unlit one. Snapshots are written from the current
+//! output when the suite is blessed, after checking that the screens are right.
+
+use crate::compliance::TestResult;
+use crate::quirks::Profile;
+use crate::Chip8;
+use crate::TICKS_PER_SEC;
+use serde::de::Error as _;
+use serde::Deserialize;
+use serde::Deserializer;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Name of the manifest of a suite
+pub const MANIFEST: &str = "conformance.toml";
+
+/// The tests of a suite
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Suite {
+    /// The tests, run in order
+    pub tests: Vec<Case>,
+}
+
+/// A ROM of a suite and how to run it
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Case {
+    /// Name of the test
+    pub name: String,
+    /// The ROM, relative to the directory of the suite
+    pub rom: PathBuf,
+    /// Number of ticks the ROM runs for
+    pub cycles: u64,
+    /// The interpreter whose quirks are used
+    #[serde(default, deserialize_with = "profile")]
+    pub profile: Option<Profile>,
+    /// Bytes written to memory after the ROM is loaded, as addresses and values, e.g. to choose
+    /// an entry of a menu of the ROM without pressing keys
+    #[serde(default)]
+    pub poke: Vec<(u16, u8)>,
+    /// The snapshot of the screen, relative to the directory of the suite
+    pub snapshot: Option<PathBuf>,
+}
+
+fn profile<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Profile>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|name| name.parse().map_err(D::Error::custom))
+        .transpose()
+}
+
+impl Suite {
+    /// Read the manifest of the suite in a directory
+    pub fn from_dir<P: AsRef<Path>>(dir: P) -> Result<Self, ConformanceError> {
+        let text = fs::read_to_string(dir.as_ref().join(MANIFEST)).map_err(ConformanceError::Io)?;
+        toml::from_str(&text).map_err(ConformanceError::Parse)
+    }
+
+    /// Run every test of the suite in a directory. If `bless` is true, the snapshots are
+    /// replaced by the screens of this run instead of being compared with them.
+    pub fn run<P: AsRef<Path>>(&self, dir: P, bless: bool) -> Vec<TestResult> {
+        let dir = dir.as_ref();
+        self.tests
+            .iter()
+            .map(|case| {
+                let result = |passed, message| TestResult {
+                    name: case.name.clone(),
+                    passed,
+                    message,
+                };
+                let screen = match case.run(dir) {
+                    Ok(screen) => screen,
+                    Err(err) => return result(false, Some(err.to_string())),
+                };
+                let snapshot = dir.join(case.snapshot_path());
+                if bless {
+                    return match fs::write(&snapshot, &screen) {
+                        Ok(()) => result(true, None),
+                        Err(err) => result(false, Some(format!("{}: {}", snapshot.display(), err))),
+                    };
+                }
+                match fs::read_to_string(&snapshot) {
+                    Ok(expected) => match compare(&expected, &screen) {
+                        Some(difference) => result(false, Some(difference)),
+                        None => result(true, None),
+                    },
+                    Err(err) => result(
+                        false,
+                        Some(format!("no snapshot {}: {}", snapshot.display(), err)),
+                    ),
+                }
+            })
+            .collect()
+    }
+}
+
+impl Case {
+    /// The snapshot of the screen, relative to the directory of the suite
+    pub fn snapshot_path(&self) -> PathBuf {
+        self.snapshot
+            .clone()
+            .unwrap_or_else(|| self.rom.with_extension("txt"))
+    }
+
+    /// Run the ROM of the test, returns the screen it ends on, see [`screen_text`]
+    pub fn run(&self, dir: &Path) -> Result<String, ConformanceError> {
+        let program = fs::read(dir.join(&self.rom)).map_err(ConformanceError::Io)?;
+        let mut chip8 = Chip8::builder()
+            .quirks(self.profile.map(Profile::quirks).unwrap_or_default())
+            .seed(0)
+            .manual_timers(true)
+            .build();
+        chip8
+            .load(&program)
+            .map_err(|err| ConformanceError::Invalid(err.to_string()))?;
+        for (addr, value) in &self.poke {
+            chip8.memory_mut()[*addr as usize & 0xFFF] = *value;
+        }
+        let ticks_per_frame = (TICKS_PER_SEC / 60) as u64;
+        for cycle in 1..=self.cycles {
+            chip8.step();
+            if cycle % ticks_per_frame == 0 {
+                chip8.next_frame();
+            }
+            if let Some(fault) = chip8.fault() {
+                return Err(ConformanceError::Invalid(fault.to_string()));
+            }
+        }
+        Ok(screen_text(&chip8))
+    }
+}
+
+/// The screen of a machine as text, a line per row with `#` for a lit pixel and `.` for an
+/// unlit one
+pub fn screen_text(chip8: &Chip8) -> String {
+    let (width, _) = chip8.screen_size();
+    let mut text = String::new();
+    for row in chip8.framebuffer().chunks(width) {
+        text.extend(row.iter().map(|lit| if *lit { '#' } else { '.' }));
+        text.push('\n');
+    }
+    text
+}
+
+/// How a screen differs from the expected one, `None` if they are the same
+fn compare(expected: &str, actual: &str) -> Option<String> {
+    let (expected, actual): (Vec<_>, Vec<_>) =
+        (expected.lines().collect(), actual.lines().collect());
+    if expected.len() != actual.len()
+        || expected.first().map(|row| row.len()) != actual.first().map(|row| row.len())
+    {
+        return Some("the screen has another resolution than the snapshot".to_string());
+    }
+    let rows: Vec<_> = (0..actual.len())
+        .filter(|row| expected[*row] != actual[*row])
+        .collect();
+    let first = rows.first()?;
+    let pixels: usize = rows
+        .iter()
+        .map(|row| {
+            expected[*row]
+                .chars()
+                .zip(actual[*row].chars())
+                .filter(|(expected, actual)| expected != actual)
+                .count()
+        })
+        .sum();
+    Some(format!(
+        "{} pixels differ from the snapshot, first in row {}",
+        pixels, first
+    ))
+}
+
+/// Reasons for a suite or a test not to run
+#[derive(Debug)]
+pub enum ConformanceError {
+    /// A file could not be read
+    Io(io::Error),
+    /// The manifest is not valid
+    Parse(toml::de::Error),
+    /// The ROM could not be run
+    Invalid(String),
+}
+
+impl fmt::Display for ConformanceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "could not read the suite: {}", err),
+            Self::Parse(err) => write!(f, "invalid manifest: {}", err),
+            Self::Invalid(reason) => write!(f, "could not run the ROM: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ConformanceError {}
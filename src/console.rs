@@ -0,0 +1,251 @@
+//! The command console of the frontends, a single input line with history and completion whose
+//! commands drive the debugger and the display without leaving the window. It only edits and
+//! parses the commands, the frontend executes them.
+//!
+//! ```text
+//! break 0x2A4        set or remove a breakpoint
+//! poke V3 0x10       write a register (V0-VF, I, PC) or a byte of memory
+//! speed 1000         run the given number of ticks per second
+//! palette FFB000 202020
+//! palette amber      use a built-in palette
+//! save out.ch8       write the program memory to a file
+//! ```
+
+use std::str::FromStr;
+
+/// Names of the commands, in the order they are listed by `help`
+pub const COMMANDS: [&str; 8] = [
+    "break", "continue", "step", "poke", "speed", "palette", "save", "help",
+];
+
+/// Number of lines of output kept
+pub const OUTPUT_LINES: usize = 64;
+
+/// A parsed command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Set or remove a breakpoint
+    Break(u16),
+    /// Resume a paused machine
+    Continue,
+    /// Execute a single instruction
+    Step,
+    /// Write a value to a register or to memory
+    Poke(Target, u16),
+    /// Show the number of ticks per second, or change it
+    Speed(Option<u32>),
+    /// Change the colors of lit and unlit pixels, given as RGB
+    Palette(u32, u32),
+    /// Use a built-in palette by name
+    Theme(String),
+    /// Write the program memory to a file
+    Save(String),
+    /// List the commands
+    Help,
+}
+
+/// Where `poke` writes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    /// A byte of memory
+    Memory(u16),
+    /// A register V0-VF
+    Register(usize),
+    /// The index register
+    Index,
+    /// The program counter
+    Pc,
+}
+
+impl FromStr for Command {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let words: Vec<_> = s.split_whitespace().collect();
+        let usage = |usage: &str| Err(format!("usage: {}", usage));
+        match words.as_slice() {
+            ["break", addr] => Ok(Self::Break(parse_number(addr)?)),
+            ["break", ..] => usage("break ADDR"),
+            ["continue"] => Ok(Self::Continue),
+            ["step"] => Ok(Self::Step),
+            ["poke", target, value] => Ok(Self::Poke(target.parse()?, parse_number(value)?)),
+            ["poke", ..] => usage("poke ADDR|V0-VF|I|PC VALUE"),
+            ["speed"] => Ok(Self::Speed(None)),
+            ["speed", ticks] => ticks
+                .parse()
+                .ok()
+                .filter(|ticks| *ticks > 0)
+                .map(|ticks| Self::Speed(Some(ticks)))
+                .ok_or_else(|| format!("invalid number of ticks {}", ticks)),
+            ["speed", ..] => usage("speed [TICKS]"),
+            ["palette", on, off] => Ok(Self::Palette(parse_color(on)?, parse_color(off)?)),
+            ["palette", name] => Ok(Self::Theme(name.to_string())),
+            ["palette", ..] => usage("palette NAME | RRGGBB RRGGBB"),
+            ["save", path] => Ok(Self::Save(path.to_string())),
+            ["save", ..] => usage("save PATH"),
+            ["help"] => Ok(Self::Help),
+            [command, ..] => Err(format!("unknown command {}, try help", command)),
+            [] => Err("no command".to_string()),
+        }
+    }
+}
+
+impl FromStr for Target {
+    type Err = String;
+
+    /// Parse `V0`-`VF`, `I`, `PC` or an address
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "I" => Ok(Self::Index),
+            "PC" => Ok(Self::Pc),
+            reg if reg.len() == 2 && reg.starts_with('V') => {
+                let idx = usize::from_str_radix(&reg[1..], 16)
+                    .map_err(|_| format!("invalid register {}", s))?;
+                Ok(Self::Register(idx))
+            }
+            _ => parse_number(s).map(Self::Memory),
+        }
+    }
+}
+
+/// Parse a hexadecimal number, with an optional `0x`, `#` or label `L` prefix
+fn parse_number(s: &str) -> Result<u16, String> {
+    let digits = s
+        .strip_prefix("0x")
+        .or_else(|| s.strip_prefix('#'))
+        .or_else(|| s.strip_prefix('L'))
+        .unwrap_or(s);
+    u16::from_str_radix(digits, 16).map_err(|_| format!("invalid number {}", s))
+}
+
+/// Parse a color given as `RRGGBB`, optionally prefixed with `#`
+pub fn parse_color(s: &str) -> Result<u32, String> {
+    let digits = s.strip_prefix('#').unwrap_or(s);
+    match u32::from_str_radix(digits, 16) {
+        Ok(color) if digits.len() == 6 => Ok(color),
+        _ => Err(format!("invalid color {}", s)),
+    }
+}
+
+/// The input line of the console with its history and output
+#[derive(Debug, Clone, Default)]
+pub struct Console {
+    input: String,
+    history: Vec<String>,
+    /// Position in the history while browsing it
+    recalled: Option<usize>,
+    output: Vec<String>,
+}
+
+impl Console {
+    /// Create an empty console
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The line being typed
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    /// The output, oldest line first
+    pub fn output(&self) -> &[String] {
+        &self.output
+    }
+
+    /// Add a line to the output
+    pub fn print<S: Into<String>>(&mut self, line: S) {
+        self.output.push(line.into());
+        if self.output.len() > OUTPUT_LINES {
+            self.output.remove(0);
+        }
+    }
+
+    /// Type a character
+    pub fn push(&mut self, ch: char) {
+        if !ch.is_control() {
+            self.input.push(ch);
+        }
+    }
+
+    /// Delete the last character
+    pub fn backspace(&mut self) {
+        self.input.pop();
+    }
+
+    /// Replace the input with the previous line of the history
+    pub fn history_prev(&mut self) {
+        let idx = match self.recalled {
+            Some(idx) => idx.saturating_sub(1),
+            None if self.history.is_empty() => return,
+            None => self.history.len() - 1,
+        };
+        self.recalled = Some(idx);
+        self.input = self.history[idx].clone();
+    }
+
+    /// Replace the input with the next line of the history, or clear it past the last line
+    pub fn history_next(&mut self) {
+        match self.recalled {
+            Some(idx) if idx + 1 < self.history.len() => {
+                self.recalled = Some(idx + 1);
+                self.input = self.history[idx + 1].clone();
+            }
+            Some(_) => {
+                self.recalled = None;
+                self.input.clear();
+            }
+            None => (),
+        }
+    }
+
+    /// Complete the word being typed, a command name for the first word and one of `symbols`
+    /// for the others. Lists the candidates if there are several of them.
+    pub fn complete(&mut self, symbols: &[String]) {
+        let start = self.input.rfind(' ').map_or(0, |idx| idx + 1);
+        let word = self.input[start..].to_lowercase();
+        let candidates: Vec<String> = if start == 0 {
+            COMMANDS.iter().map(|name| name.to_string()).collect()
+        } else {
+            symbols.to_vec()
+        };
+        let matches: Vec<_> = candidates
+            .into_iter()
+            .filter(|name| name.to_lowercase().starts_with(&word))
+            .collect();
+        let Some(first) = matches.first() else {
+            return;
+        };
+        // Complete the prefix that all the candidates share
+        let common = matches.iter().fold(first.len(), |len, name| {
+            first
+                .chars()
+                .zip(name.chars())
+                .take(len)
+                .take_while(|(a, b)| a.eq_ignore_ascii_case(b))
+                .count()
+        });
+        self.input.truncate(start);
+        self.input.push_str(&first[..common]);
+        if matches.len() == 1 {
+            self.input.push(' ');
+        } else {
+            self.print(matches.join("  "));
+        }
+    }
+
+    /// Take the input line, adding it to the history and the output, and parse it. Returns
+    /// `None` for an empty line.
+    pub fn submit(&mut self) -> Option<Result<Command, String>> {
+        let line = std::mem::take(&mut self.input);
+        self.recalled = None;
+        if line.trim().is_empty() {
+            return None;
+        }
+        self.print(format!("> {}", line));
+        if self.history.last() != Some(&line) {
+            self.history.push(line.clone());
+        }
+        Some(line.parse())
+    }
+}
@@ -0,0 +1,317 @@
+//! The complete control configuration of the window in one shareable file: the bindings of host
+//! keys and gamepad buttons to keypad keys, see [`crate::keymap`], and the host keys of the
+//! hotkeys. A file written on one machine with `--export-controls` is loaded on others with
+//! `--controls`. Hotkeys that are not listed keep their default key.
+//!
+//! ```toml
+//! [keys]
+//! Up = "2"
+//!
+//! [gamepad]
+//! South = "5"
+//!
+//! [hotkeys]
+//! screenshot = "F12"
+//! rewind = "Back"
+//! ```
+//!
+//! A file is rejected if a host key is bound twice, to two hotkeys or to a hotkey and a keypad
+//! key, since only one of them would ever work.
+
+use crate::keymap::KeyMap;
+use crate::storage::Storage;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+/// Actions of the window triggered by a host key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Hotkey {
+    /// Pick a save state to restore
+    LoadState,
+    /// Pick a slot to save the state to
+    SaveState,
+    /// Show or hide the performance graphs
+    PerfGraphs,
+    /// Toggle a breakpoint at the program counter
+    Breakpoint,
+    /// Pause or resume the machine in the debugger
+    Pause,
+    /// Run a single instruction while paused
+    Step,
+    /// Show or hide the held keys
+    InputDisplay,
+    /// Write the program region of the memory to a ROM
+    ExportRom,
+    /// Print the state of the machine
+    Dump,
+    /// Print the statistics of the session
+    Stats,
+    /// Start or stop recording the screen
+    Recording,
+    /// Save a screenshot
+    Screenshot,
+    /// Rewind while the key is held
+    Rewind,
+    /// Play the next ROM of the playlist
+    NextRom,
+    /// Play the previous ROM of the playlist
+    PreviousRom,
+    /// Open the command console
+    Console,
+    /// Reset the machine and load the ROM again
+    Reset,
+    /// Switch to the next built-in palette
+    Theme,
+    /// Turn the CRT effect on or off
+    Crt,
+    /// Show or hide the memory viewer
+    Memory,
+}
+
+/// Every hotkey with its name in the files and its default host key
+const HOTKEYS: [(Hotkey, &str, &str); 20] = [
+    (Hotkey::LoadState, "load_state", "F1"),
+    (Hotkey::SaveState, "save_state", "F2"),
+    (Hotkey::PerfGraphs, "perf_graphs", "F3"),
+    (Hotkey::Breakpoint, "breakpoint", "F4"),
+    (Hotkey::Pause, "pause", "F5"),
+    (Hotkey::Step, "step", "F6"),
+    (Hotkey::InputDisplay, "input_display", "F7"),
+    (Hotkey::ExportRom, "export_rom", "F8"),
+    (Hotkey::Dump, "dump", "F9"),
+    (Hotkey::Stats, "stats", "F10"),
+    (Hotkey::Recording, "recording", "F11"),
+    (Hotkey::Screenshot, "screenshot", "F12"),
+    (Hotkey::Rewind, "rewind", "Back"),
+    (Hotkey::NextRom, "next_rom", "PageDown"),
+    (Hotkey::PreviousRom, "previous_rom", "PageUp"),
+    (Hotkey::Console, "console", "Grave"),
+    (Hotkey::Reset, "reset", "Home"),
+    (Hotkey::Theme, "theme", "Insert"),
+    (Hotkey::Crt, "crt", "End"),
+    (Hotkey::Memory, "memory", "Tab"),
+];
+
+impl Hotkey {
+    /// Name of the hotkey in the files
+    pub fn name(self) -> &'static str {
+        HOTKEYS
+            .iter()
+            .find(|(hotkey, ..)| *hotkey == self)
+            .unwrap()
+            .1
+    }
+
+    /// The hotkey with a name
+    pub fn from_name(name: &str) -> Option<Self> {
+        HOTKEYS
+            .iter()
+            .find(|(_, hotkey_name, _)| *hotkey_name == name)
+            .map(|(hotkey, ..)| *hotkey)
+    }
+}
+
+/// Host keys of the hotkeys, named as in ggez like the keys of [`KeyMap`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(
+    try_from = "BTreeMap<String, String>",
+    into = "BTreeMap<String, String>"
+)]
+pub struct Hotkeys {
+    bindings: BTreeMap<Hotkey, String>,
+}
+
+impl Default for Hotkeys {
+    fn default() -> Self {
+        let bindings = HOTKEYS
+            .iter()
+            .map(|(hotkey, _, host)| (*hotkey, host.to_string()))
+            .collect();
+        Self { bindings }
+    }
+}
+
+impl Hotkeys {
+    /// Bind a hotkey to a host key instead of its current one
+    pub fn bind(&mut self, hotkey: Hotkey, host: &str) {
+        self.bindings.insert(hotkey, host.to_string());
+    }
+
+    /// The host key of a hotkey
+    pub fn get(&self, hotkey: Hotkey) -> &str {
+        &self.bindings[&hotkey]
+    }
+
+    /// The hotkey bound to a host key
+    pub fn action(&self, host: &str) -> Option<Hotkey> {
+        self.bindings
+            .iter()
+            .find(|(_, bound)| *bound == host)
+            .map(|(hotkey, _)| *hotkey)
+    }
+}
+
+impl TryFrom<BTreeMap<String, String>> for Hotkeys {
+    type Error = String;
+
+    fn try_from(bindings: BTreeMap<String, String>) -> Result<Self, Self::Error> {
+        let mut hotkeys = Self::default();
+        for (name, host) in bindings {
+            let hotkey = Hotkey::from_name(&name).ok_or(format!("unknown hotkey {}", name))?;
+            hotkeys.bind(hotkey, &host);
+        }
+        Ok(hotkeys)
+    }
+}
+
+impl From<Hotkeys> for BTreeMap<String, String> {
+    fn from(hotkeys: Hotkeys) -> Self {
+        hotkeys
+            .bindings
+            .into_iter()
+            .map(|(hotkey, host)| (hotkey.name().to_string(), host))
+            .collect()
+    }
+}
+
+/// Every control of the window
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Controls {
+    /// Bindings of host keys to keypad keys, on top of the default layout
+    #[serde(default)]
+    pub keys: KeyMap,
+    /// Bindings of gamepad buttons to keypad keys, on top of [`KeyMap::gamepad`]
+    #[serde(default)]
+    pub gamepad: KeyMap,
+    /// Host keys of the hotkeys
+    #[serde(default)]
+    pub hotkeys: Hotkeys,
+}
+
+impl Controls {
+    /// Read the controls from a TOML file, rejecting them if they have conflicts
+    pub fn from_file(storage: &dyn Storage, path: &Path) -> Result<Self, ControlsError> {
+        let text = storage.read_to_string(path).map_err(ControlsError::Io)?;
+        let controls: Self = toml::from_str(&text).map_err(ControlsError::Parse)?;
+        let conflicts = controls.conflicts();
+        if !conflicts.is_empty() {
+            return Err(ControlsError::Conflicts(conflicts));
+        }
+        Ok(controls)
+    }
+
+    /// Write the controls to a TOML file
+    pub fn write(&self, storage: &dyn Storage, path: &Path) -> Result<(), ControlsError> {
+        let text = toml::to_string(self).map_err(ControlsError::Serialize)?;
+        storage
+            .write(path, text.as_bytes())
+            .map_err(ControlsError::Io)
+    }
+
+    /// The host keys that are bound more than once
+    pub fn conflicts(&self) -> Vec<Conflict> {
+        let mut conflicts = Vec::new();
+        for (idx, (first, host)) in self.hotkeys.bindings.iter().enumerate() {
+            for (second, other) in self.hotkeys.bindings.iter().skip(idx + 1) {
+                if host == other {
+                    conflicts.push(Conflict::Hotkeys {
+                        host: host.clone(),
+                        first: *first,
+                        second: *second,
+                    });
+                }
+            }
+            if let Some(key) = self.keys.get(host) {
+                conflicts.push(Conflict::Keypad {
+                    host: host.clone(),
+                    hotkey: *first,
+                    key,
+                });
+            }
+        }
+        conflicts
+    }
+}
+
+/// A host key bound more than once
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Conflict {
+    /// The key triggers two hotkeys
+    Hotkeys {
+        /// The host key
+        host: String,
+        /// The hotkey that wins
+        first: Hotkey,
+        /// The hotkey that is never triggered
+        second: Hotkey,
+    },
+    /// The key triggers a hotkey and presses a keypad key
+    Keypad {
+        /// The host key
+        host: String,
+        /// The hotkey that wins
+        hotkey: Hotkey,
+        /// The keypad key that is never pressed
+        key: u8,
+    },
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Hotkeys {
+                host,
+                first,
+                second,
+            } => write!(
+                f,
+                "{} is bound to both {} and {}",
+                host,
+                first.name(),
+                second.name()
+            ),
+            Self::Keypad { host, hotkey, key } => write!(
+                f,
+                "{} is bound to both {} and the keypad key {:X}",
+                host,
+                hotkey.name(),
+                key
+            ),
+        }
+    }
+}
+
+/// Reasons for controls to be rejected
+#[derive(Debug)]
+pub enum ControlsError {
+    /// The file could not be read or written
+    Io(io::Error),
+    /// The file is not valid
+    Parse(toml::de::Error),
+    /// The controls could not be written
+    Serialize(toml::ser::Error),
+    /// Some host keys are bound more than once
+    Conflicts(Vec<Conflict>),
+}
+
+impl fmt::Display for ControlsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "could not access the controls: {}", err),
+            Self::Parse(err) => write!(f, "invalid controls: {}", err),
+            Self::Serialize(err) => write!(f, "could not write the controls: {}", err),
+            Self::Conflicts(conflicts) => {
+                let conflicts: Vec<_> = conflicts.iter().map(|c| c.to_string()).collect();
+                write!(f, "conflicting controls: {}", conflicts.join(", "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for ControlsError {}
@@ -0,0 +1,195 @@
+//! Per-opcode instruction costs used to pace the emulation. An instruction with a cost of N keeps
+//! the machine busy for N ticks, during which the timers keep running. Costs can be tuned per game
+//! in the `[costs]` section of a TOML file.
+//!
+//! ```toml
+//! [costs]
+//! base = "vip"
+//! DXYN = 16
+//! 00E0 = 20
+//! ```
+
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Names of all the opcodes that can be given a cost
+pub const OPCODES: [&str; 45] = [
+    "00E0", "00EE", "00CN", "00FB", "00FC", "00FD", "00FE", "00FF", "0NNN", "1NNN", "2NNN", "3XKK",
+    "4XKK", "5XY0", "6XKK", "7XKK", "8XY0", "8XY1", "8XY2", "8XY3", "8XY4", "8XY5", "8XY6", "8XY7",
+    "8XYE", "9XY0", "ANNN", "BNNN", "CXKK", "DXYN", "EX9E", "EXA1", "F000", "FX07", "FX0A", "FX15",
+    "FX18", "FX1E", "FX29", "FX30", "FX33", "FX55", "FX65", "FX75", "FX85",
+];
+
+/// The table that the costs of a file start from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Base {
+    /// Every instruction takes a single tick
+    #[default]
+    Uniform,
+    /// Approximate relative timings of the COSMAC VIP interpreter, where clearing the screen and
+    /// drawing sprites are much slower than arithmetic
+    Vip,
+}
+
+/// Number of ticks taken by each opcode
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CostTable {
+    costs: BTreeMap<&'static str, u32>,
+}
+
+impl Default for CostTable {
+    fn default() -> Self {
+        Self::new(Base::Uniform)
+    }
+}
+
+impl CostTable {
+    /// Create the table of a base
+    pub fn new(base: Base) -> Self {
+        let costs = OPCODES
+            .iter()
+            .map(|&opcode| {
+                let cost = match base {
+                    Base::Uniform => 1,
+                    Base::Vip => match opcode {
+                        "00E0" | "00CN" | "00FB" | "00FC" => 12,
+                        "DXYN" => 11,
+                        "FX33" => 4,
+                        "2NNN" | "00EE" | "FX55" | "FX65" => 2,
+                        _ => 1,
+                    },
+                };
+                (opcode, cost)
+            })
+            .collect();
+        Self { costs }
+    }
+
+    /// Number of ticks taken by an opcode, given by its name such as `8XY4`
+    pub fn cost(&self, opcode: &str) -> u32 {
+        self.costs.get(opcode).copied().unwrap_or(1)
+    }
+
+    /// Every opcode with its number of ticks
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, u32)> + '_ {
+        self.costs.iter().map(|(opcode, cost)| (*opcode, *cost))
+    }
+
+    /// Change the cost of an opcode, a cost of zero is treated as one tick
+    pub fn set_cost(&mut self, opcode: &str, cost: u32) -> Result<(), CostError> {
+        match self.costs.get_mut(opcode) {
+            Some(entry) => {
+                *entry = cost.max(1);
+                Ok(())
+            }
+            None => Err(CostError::UnknownOpcode(opcode.to_string())),
+        }
+    }
+
+    /// Read the `[costs]` section of a TOML file
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, CostError> {
+        let text = fs::read_to_string(path).map_err(CostError::Io)?;
+        text.parse()
+    }
+}
+
+impl std::str::FromStr for CostTable {
+    type Err = CostError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let file: CostFile = toml::from_str(s).map_err(CostError::Parse)?;
+        let mut table = Self::new(file.costs.base);
+        for (opcode, cost) in &file.costs.opcodes {
+            table.set_cost(&opcode.to_uppercase(), *cost)?;
+        }
+        Ok(table)
+    }
+}
+
+#[derive(Deserialize)]
+struct CostFile {
+    #[serde(default)]
+    costs: CostSection,
+}
+
+#[derive(Default, Deserialize)]
+struct CostSection {
+    #[serde(default)]
+    base: Base,
+    #[serde(flatten)]
+    opcodes: BTreeMap<String, u32>,
+}
+
+/// Reasons for a cost table to be rejected
+#[derive(Debug)]
+pub enum CostError {
+    /// The file could not be read
+    Io(io::Error),
+    /// The file is not valid
+    Parse(toml::de::Error),
+    /// The opcode does not exist
+    UnknownOpcode(String),
+}
+
+impl fmt::Display for CostError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "could not read the cost table: {}", err),
+            Self::Parse(err) => write!(f, "invalid cost table: {}", err),
+            Self::UnknownOpcode(opcode) => write!(f, "unknown opcode {}", opcode),
+        }
+    }
+}
+
+impl std::error::Error for CostError {}
+
+#[cfg(test)]
+mod tests {
+    use super::Base;
+    use super::CostError;
+    use super::CostTable;
+
+    #[test]
+    fn costs_start_from_their_base() {
+        let table: CostTable = "[costs]\nbase = \"vip\"\nDXYN = 16\n00e0 = 20"
+            .parse()
+            .unwrap();
+        assert_eq!(table.cost("DXYN"), 16);
+        assert_eq!(table.cost("00E0"), 20);
+        assert_eq!(table.cost("FX33"), 4);
+        assert_eq!(table.cost("8XY4"), 1);
+        assert_eq!(
+            "[costs]\nbase = \"vip\"".parse::<CostTable>().unwrap(),
+            CostTable::new(Base::Vip)
+        );
+        assert_eq!("".parse::<CostTable>().unwrap(), CostTable::default());
+        // A cost of zero still takes a tick
+        let table: CostTable = "[costs]\n8XY4 = 0".parse().unwrap();
+        assert_eq!(table.cost("8XY4"), 1);
+    }
+
+    #[test]
+    fn invalid_tables_are_rejected() {
+        match "[costs]\nDXYM = 3".parse::<CostTable>() {
+            Err(CostError::UnknownOpcode(opcode)) => assert_eq!(opcode, "DXYM"),
+            other => panic!("{:?}", other),
+        }
+        for text in &[
+            "[costs]\nbase = \"chip48\"",
+            "[costs]\nDXYN = -1",
+            "[costs]\nDXYN = \"slow\"",
+            "[costs",
+        ] {
+            assert!(
+                matches!(text.parse::<CostTable>(), Err(CostError::Parse(_))),
+                "{:?}",
+                text
+            );
+        }
+    }
+}
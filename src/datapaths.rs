@@ -0,0 +1,73 @@
+//! Where the files that belong to the ROMs are kept: their settings, save states, screenshots and
+//! recordings, and the list of recent ROMs. By default a ROM's files sit next to it
+//! (`pong.ch8` has `pong.toml` and `pong.state`) and the recent list is in the home directory.
+//!
+//! In portable mode everything goes into a `data` directory beside the executable instead, so a
+//! copy on a USB stick or a kiosk leaves nothing behind on the host. It is enabled with
+//! `--portable` or by a `portable.txt` file beside the executable.
+
+use std::env;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// File beside the executable that enables the portable mode
+pub const PORTABLE_MARKER: &str = "portable.txt";
+/// Directory beside the executable that holds the files in portable mode
+pub const PORTABLE_DIR: &str = "data";
+
+/// The places of the files kept for the ROMs
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum DataPaths {
+    /// Next to each ROM, with the recent list in the home directory
+    #[default]
+    NextToRom,
+    /// Everything in a single directory
+    Portable(PathBuf),
+}
+
+impl DataPaths {
+    /// Portable mode beside the executable if it is forced or if [`PORTABLE_MARKER`] exists
+    /// there, the data directory is created if needed
+    pub fn detect(portable: bool) -> io::Result<Self> {
+        let exe = env::current_exe()?;
+        let dir = exe.parent().unwrap_or_else(|| Path::new("."));
+        if !portable && !dir.join(PORTABLE_MARKER).exists() {
+            return Ok(Self::NextToRom);
+        }
+        let data = dir.join(PORTABLE_DIR);
+        std::fs::create_dir_all(&data)?;
+        Ok(Self::Portable(data))
+    }
+
+    /// The file of a ROM with another extension
+    pub fn file(&self, rom: &Path, extension: &str) -> PathBuf {
+        match self {
+            Self::NextToRom => rom.with_extension(extension),
+            Self::Portable(dir) => dir
+                .join(rom.file_name().unwrap_or_default())
+                .with_extension(extension),
+        }
+    }
+
+    /// The settings of a ROM, see [`crate::romconfig`]
+    pub fn rom_config(&self, rom: &Path) -> PathBuf {
+        self.file(rom, "toml")
+    }
+
+    /// The save state of a ROM, see [`crate::savestate`]. Screenshots and recordings are
+    /// numbered after it.
+    pub fn state(&self, rom: &Path) -> PathBuf {
+        self.file(rom, "state")
+    }
+
+    /// The list of recently opened ROMs, if there is a place for it
+    pub fn recent_file(&self) -> Option<PathBuf> {
+        match self {
+            Self::NextToRom => {
+                env::var_os("HOME").map(|home| PathBuf::from(home).join(".chip8-recent"))
+            }
+            Self::Portable(dir) => Some(dir.join("recent")),
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! An interactive debugger for developing and fixing ROMs. It pauses the machine, runs it one
+//! instruction at a time and stops before instructions at breakpoints. It holds no windowing
+//! state, frontends drive it with [`Debugger::tick`] instead of calling [`Chip8::step`].
+
+use crate::disasm;
+use crate::Chip8;
+use crate::Inst;
+use std::collections::BTreeSet;
+
+/// Pause state and breakpoints of a machine
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Debugger {
+    paused: bool,
+    breakpoints: BTreeSet<u16>,
+    /// The breakpoint that execution was resumed from, it does not pause the machine again
+    resumed_from: Option<u16>,
+}
+
+impl Debugger {
+    /// Create a debugger that lets the machine run
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the machine is paused
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Stop running the machine
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Run the machine again, starting with the instruction at the program counter even if it has
+    /// a breakpoint
+    pub fn resume(&mut self, chip8: &Chip8) {
+        self.paused = false;
+        self.resumed_from = Some(chip8.pc());
+    }
+
+    /// Pause a running machine or resume a paused one, returns true if it is now paused
+    pub fn toggle_pause(&mut self, chip8: &Chip8) -> bool {
+        if self.paused {
+            self.resume(chip8);
+        } else {
+            self.pause();
+        }
+        self.paused
+    }
+
+    /// The addresses where the machine pauses
+    pub fn breakpoints(&self) -> &BTreeSet<u16> {
+        &self.breakpoints
+    }
+
+    /// Pause the machine before it executes the instruction at an address
+    pub fn add_breakpoint(&mut self, addr: u16) {
+        self.breakpoints.insert(addr);
+    }
+
+    /// Set or remove the breakpoint at an address, returns true if it is now set
+    pub fn toggle_breakpoint(&mut self, addr: u16) -> bool {
+        if !self.breakpoints.remove(&addr) {
+            self.breakpoints.insert(addr);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Run a single tick of the machine unless it is paused, pausing it instead if the next
+    /// instruction has a breakpoint. Returns true if the machine ran.
+    pub fn tick(&mut self, chip8: &mut Chip8) -> bool {
+        if self.paused {
+            return false;
+        }
+        if chip8.ready() {
+            let pc = chip8.pc();
+            if self.resumed_from.take() != Some(pc) && self.breakpoints.contains(&pc) {
+                self.paused = true;
+                return false;
+            }
+        }
+        chip8.step();
+        true
+    }
+
+    /// Execute the next instruction and the ticks it keeps the machine busy for, whether the
+    /// machine is paused or not. A machine waiting for the next frame is moved to it.
+    pub fn step_instruction(&mut self, chip8: &mut Chip8) {
+        self.resumed_from = None;
+        Self::finish_instruction(chip8);
+        chip8.step();
+        Self::finish_instruction(chip8);
+    }
+
+    /// Run the ticks that the previous instruction keeps the machine busy for
+    fn finish_instruction(chip8: &mut Chip8) {
+        while !chip8.ready() && chip8.fault().is_none() {
+            if chip8.waiting_for_frame() {
+                chip8.next_frame();
+            } else {
+                chip8.step();
+            }
+        }
+    }
+
+    /// Disassemble `count` instructions starting at the program counter, one line per
+    /// instruction with markers for the program counter (`>`), breakpoints (`*`) and code that
+    /// the program rewrote after running it (`~`, followed by the address of the writer and
+    /// the cycle of the write)
+    pub fn listing(&self, chip8: &Chip8, count: usize) -> Vec<String> {
+        let mem = chip8.memory();
+        (0..count as u16)
+            .map(|idx| chip8.pc().wrapping_add(idx * 2))
+            .take_while(|&addr| (addr as usize) + 1 < mem.len())
+            .map(|addr| {
+                let opcode = (mem[addr as usize] as u16) << 8 | mem[addr as usize + 1] as u16;
+                let pc = if addr == chip8.pc() { '>' } else { ' ' };
+                let breakpoint = if self.breakpoints.contains(&addr) {
+                    '*'
+                } else {
+                    ' '
+                };
+                let modification = disasm::modification(chip8.code_modifications(), addr);
+                let (modified, writer) = match modification {
+                    Some(modification) => (
+                        '~',
+                        format!(
+                            "  ; written by {:#05X} at cycle {}",
+                            modification.pc, modification.cycle
+                        ),
+                    ),
+                    None => (' ', String::new()),
+                };
+                let inst = match Inst::decode(opcode) {
+                    Ok(inst) => inst.to_string(),
+                    Err(_) => "???".to_string(),
+                };
+                format!(
+                    "{}{}{} {:#05X}  {:04X}  {}{}",
+                    pc, breakpoint, modified, addr, opcode, inst, writer
+                )
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,109 @@
+//! Compatibility notes collected while a program runs: behaviours that are not errors but hint
+//! that the ROM was written for another interpreter, such as an instruction whose result depends
+//! on a quirk. Every kind of note is counted, and the frontends surface each kind once with a
+//! hint like "this ROM may need --quirk shift_in_place=off" instead of crashing or staying silent.
+
+use crate::quirks::Quirks;
+use std::collections::BTreeMap;
+
+/// A kind of compatibility note
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Diagnostic {
+    /// 8XY6 or 8XYE shifted with two registers of different values, the result depends on the
+    /// shift_in_place quirk
+    Shift,
+    /// I was used after FX55 or FX65 without being set again, its value depends on the
+    /// increment_index quirk
+    IndexAfterLoadStore,
+    /// BNNN jumped with V0 and VX holding different values, the target depends on the jump_vx
+    /// quirk
+    JumpOffset,
+    /// A sprite was drawn across the edge of the screen, it wraps around or is clipped depending
+    /// on the wrap_sprites quirk
+    SpriteAtEdge,
+    /// Three or more keys were held at once, on a matrix keypad like the original one this can
+    /// make a key that is not held look pressed
+    KeyGhost,
+}
+
+impl Diagnostic {
+    /// What the note means for a machine running with the given quirks
+    pub fn hint(self, quirks: &Quirks) -> String {
+        let toggle =
+            |name: &str, on: bool| format!("--quirk {}={}", name, if on { "off" } else { "on" });
+        match self {
+            Self::Shift => format!(
+                "8XY6/8XYE shift one register into another, if the ROM misbehaves it may need {}",
+                toggle("shift_in_place", quirks.shift_in_place)
+            ),
+            Self::IndexAfterLoadStore => format!(
+                "I is used right after FX55/FX65, if the ROM misbehaves it may need {}",
+                toggle("increment_index", quirks.increment_index)
+            ),
+            Self::JumpOffset => format!(
+                "BNNN jumps with an offset, if the ROM misbehaves it may need {}",
+                toggle("jump_vx", quirks.jump_vx)
+            ),
+            Self::SpriteAtEdge => format!(
+                "sprites are {} at the edge of the screen, if they look wrong the ROM may need {}",
+                if quirks.wrap_sprites {
+                    "wrapped"
+                } else {
+                    "clipped"
+                },
+                toggle("wrap_sprites", quirks.wrap_sprites)
+            ),
+            Self::KeyGhost => "three or more keys are held at once, the keypad of the original \
+                               hardware would have seen ghost keys"
+                .to_string(),
+        }
+    }
+}
+
+/// The notes of a session, with the number of times each kind was made
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Diagnostics {
+    counts: BTreeMap<Diagnostic, u64>,
+    /// Kinds made for the first time since the frontend last took them
+    new: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    /// Count a note
+    pub fn record(&mut self, diagnostic: Diagnostic) {
+        let count = self.counts.entry(diagnostic).or_insert(0);
+        if *count == 0 {
+            self.new.push(diagnostic);
+        }
+        *count += 1;
+    }
+
+    /// Number of times a kind of note was made
+    pub fn count(&self, diagnostic: Diagnostic) -> u64 {
+        self.counts.get(&diagnostic).copied().unwrap_or(0)
+    }
+
+    /// Every kind of note that was made, with its count
+    pub fn iter(&self) -> impl Iterator<Item = (Diagnostic, u64)> + '_ {
+        self.counts
+            .iter()
+            .map(|(diagnostic, count)| (*diagnostic, *count))
+    }
+
+    /// True if nothing was noted
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// The kinds noted for the first time since the last call, to be surfaced once
+    pub fn take_new(&mut self) -> Vec<Diagnostic> {
+        std::mem::take(&mut self.new)
+    }
+
+    /// One line per kind of note with its count and its hint
+    pub fn report(&self, quirks: &Quirks) -> String {
+        self.iter()
+            .map(|(diagnostic, count)| format!("{:>8}x  {}\n", count, diagnostic.hint(quirks)))
+            .collect()
+    }
+}
@@ -0,0 +1,202 @@
+//! Disassembler that turns a ROM, or any range of memory, into a listing of mnemonics. The
+//! mnemonics follow the usual CHIPPER syntax (`LD VA, 0x02`, `DRW V0, V1, 5`, ...), and the
+//! targets of jumps, calls and `LD I` inside the disassembled range are given labels.
+//!
+//! ```text
+//! L200:
+//! 0x200  A20A  LD I, L20A
+//! 0x202  D015  DRW V0, V1, 5
+//! 0x204  1204  JP L204
+//! ```
+//!
+//! Views of a running machine mark the instructions that the program rewrote after running
+//! them, see [`modification`].
+
+use crate::CodeModification;
+use crate::Inst;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fmt::Write;
+
+/// A disassembled instruction, or a byte or word that is not an instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Line {
+    /// Address of the first byte
+    pub addr: u16,
+    /// The opcode, or the lone byte at the end of an odd-sized range
+    pub opcode: u16,
+    /// The decoded instruction, `None` for data
+    pub inst: Option<Inst>,
+    /// True for a single trailing byte
+    pub byte: bool,
+}
+
+/// Decode the bytes of a memory range that starts at `origin`, two bytes at a time. The address
+/// that follows a long `F000 NNNN` is data.
+pub fn disassemble(bytes: &[u8], origin: u16) -> Vec<Line> {
+    let mut lines: Vec<Line> = bytes
+        .chunks(2)
+        .enumerate()
+        .map(|(idx, chunk)| {
+            let addr = origin.wrapping_add(idx as u16 * 2);
+            match *chunk {
+                [hi, lo] => {
+                    let opcode = (hi as u16) << 8 | lo as u16;
+                    Line {
+                        addr,
+                        opcode,
+                        inst: Inst::decode(opcode).ok(),
+                        byte: false,
+                    }
+                }
+                [byte] => Line {
+                    addr,
+                    opcode: byte as u16,
+                    inst: None,
+                    byte: true,
+                },
+                _ => unreachable!(),
+            }
+        })
+        .collect();
+    for idx in 1..lines.len() {
+        if lines[idx - 1].inst == Some(Inst::OpF000) {
+            lines[idx].inst = None;
+        }
+    }
+    lines
+}
+
+/// The addresses referred to by jumps, calls and `LD I` inside the range of the lines
+pub fn labels(lines: &[Line]) -> BTreeSet<u16> {
+    let range = match (lines.first(), lines.last()) {
+        (Some(first), Some(last)) => first.addr..=last.addr.saturating_add(1),
+        _ => return BTreeSet::new(),
+    };
+    lines
+        .iter()
+        .filter_map(|line| match line.inst? {
+            Inst::Op1NNN(addr) | Inst::Op2NNN(addr) | Inst::OpANNN(addr) => Some(addr),
+            _ => None,
+        })
+        .filter(|addr| range.contains(addr))
+        .collect()
+}
+
+/// Disassemble a memory range that starts at `origin` into a listing with an address, the
+/// opcode and a mnemonic on every line, and a label before every referred address
+pub fn listing(bytes: &[u8], origin: u16) -> String {
+    let lines = disassemble(bytes, origin);
+    let labels = labels(&lines);
+    let name = |addr: u16| {
+        if labels.contains(&addr) {
+            label(addr)
+        } else {
+            format!("{:#05X}", addr)
+        }
+    };
+    let mut out = String::new();
+    for line in &lines {
+        if labels.contains(&line.addr) {
+            writeln!(out, "{}:", label(line.addr)).unwrap();
+        }
+        match line.inst {
+            Some(inst) => writeln!(
+                out,
+                "{:#05X}  {:04X}  {}",
+                line.addr,
+                line.opcode,
+                mnemonic(inst, name)
+            ),
+            None if line.byte => writeln!(
+                out,
+                "{:#05X}  {:02X}    DB {:#04X}",
+                line.addr, line.opcode, line.opcode
+            ),
+            None => writeln!(
+                out,
+                "{:#05X}  {:04X}  DW {:#06X}",
+                line.addr, line.opcode, line.opcode
+            ),
+        }
+        .unwrap();
+    }
+    out
+}
+
+/// The latest write into either byte of the instruction at `addr` after it was executed, from
+/// [`crate::Chip8::code_modifications`]
+pub fn modification(
+    modifications: &BTreeMap<u16, CodeModification>,
+    addr: u16,
+) -> Option<CodeModification> {
+    [addr, addr.wrapping_add(1)]
+        .iter()
+        .filter_map(|addr| modifications.get(addr))
+        .max_by_key(|modification| modification.cycle)
+        .copied()
+}
+
+/// Name of the label of an address
+pub fn label(addr: u16) -> String {
+    format!("L{:03X}", addr)
+}
+
+/// The mnemonic of an instruction, addresses are written by `addr`
+pub fn mnemonic(inst: Inst, addr: impl Fn(u16) -> String) -> String {
+    match inst {
+        Inst::Op00E0 => "CLS".to_string(),
+        Inst::Op00EE => "RET".to_string(),
+        Inst::Op00CN(n) => format!("SCD {}", n),
+        Inst::Op00FB => "SCR".to_string(),
+        Inst::Op00FC => "SCL".to_string(),
+        Inst::Op00FD => "EXIT".to_string(),
+        Inst::Op00FE => "LOW".to_string(),
+        Inst::Op00FF => "HIGH".to_string(),
+        Inst::Op0NNN(nnn) => format!("SYS {}", addr(nnn)),
+        Inst::Op1NNN(nnn) => format!("JP {}", addr(nnn)),
+        Inst::Op2NNN(nnn) => format!("CALL {}", addr(nnn)),
+        Inst::Op3XKK(x, kk) => format!("SE V{:X}, {:#04X}", x, kk),
+        Inst::Op4XKK(x, kk) => format!("SNE V{:X}, {:#04X}", x, kk),
+        Inst::Op5XY0(x, y) => format!("SE V{:X}, V{:X}", x, y),
+        Inst::Op6XKK(x, kk) => format!("LD V{:X}, {:#04X}", x, kk),
+        Inst::Op7XKK(x, kk) => format!("ADD V{:X}, {:#04X}", x, kk),
+        Inst::Op8XY0(x, y) => format!("LD V{:X}, V{:X}", x, y),
+        Inst::Op8XY1(x, y) => format!("OR V{:X}, V{:X}", x, y),
+        Inst::Op8XY2(x, y) => format!("AND V{:X}, V{:X}", x, y),
+        Inst::Op8XY3(x, y) => format!("XOR V{:X}, V{:X}", x, y),
+        Inst::Op8XY4(x, y) => format!("ADD V{:X}, V{:X}", x, y),
+        Inst::Op8XY5(x, y) => format!("SUB V{:X}, V{:X}", x, y),
+        Inst::Op8XY6(x, y) => format!("SHR V{:X}, V{:X}", x, y),
+        Inst::Op8XY7(x, y) => format!("SUBN V{:X}, V{:X}", x, y),
+        Inst::Op8XYE(x, y) => format!("SHL V{:X}, V{:X}", x, y),
+        Inst::Op9XY0(x, y) => format!("SNE V{:X}, V{:X}", x, y),
+        Inst::OpANNN(nnn) => format!("LD I, {}", addr(nnn)),
+        Inst::OpBNNN(nnn) => format!("JP V0, {}", addr(nnn)),
+        Inst::OpCXKK(x, kk) => format!("RND V{:X}, {:#04X}", x, kk),
+        Inst::OpDXYN(x, y, n) => format!("DRW V{:X}, V{:X}, {}", x, y, n),
+        Inst::OpEX9E(x) => format!("SKP V{:X}", x),
+        Inst::OpEXA1(x) => format!("SKNP V{:X}", x),
+        Inst::OpF000 => "LD I, long".to_string(),
+        Inst::OpFX07(x) => format!("LD V{:X}, DT", x),
+        Inst::OpFX0A(x) => format!("LD V{:X}, K", x),
+        Inst::OpFX15(x) => format!("LD DT, V{:X}", x),
+        Inst::OpFX18(x) => format!("LD ST, V{:X}", x),
+        Inst::OpFX1E(x) => format!("ADD I, V{:X}", x),
+        Inst::OpFX29(x) => format!("LD F, V{:X}", x),
+        Inst::OpFX30(x) => format!("LD HF, V{:X}", x),
+        Inst::OpFX33(x) => format!("LD B, V{:X}", x),
+        Inst::OpFX55(x) => format!("LD [I], V{:X}", x),
+        Inst::OpFX65(x) => format!("LD V{:X}, [I]", x),
+        Inst::OpFX75(x) => format!("LD R, V{:X}", x),
+        Inst::OpFX85(x) => format!("LD V{:X}, R", x),
+    }
+}
+
+impl fmt::Display for Inst {
+    /// The mnemonic of the instruction
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&mnemonic(*self, |addr| format!("{:#05X}", addr)))
+    }
+}
@@ -0,0 +1,148 @@
+//! Irregularities in the execution of a program. They are tolerated by default so that old games
+//! keep running, and halt the machine in strict mode. Warnings are reported but never halt it.
+
+use std::fmt;
+
+/// An irregularity detected while executing a program
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// The opcode at the given address is not a known instruction, it is skipped
+    UnknownOpcode {
+        /// Address of the opcode
+        addr: u16,
+        /// The unknown opcode
+        opcode: u16,
+    },
+    /// A machine code routine (0NNN) was called that no interpreter hook handles, it is ignored
+    MachineCodeCall {
+        /// Address of the routine
+        addr: u16,
+    },
+    /// An instruction accessed memory past 0xFFF, the address wraps around
+    MemoryOutOfRange {
+        /// The accessed address
+        addr: usize,
+    },
+    /// A subroutine was called with all sixteen stack levels in use, the stack wraps around
+    StackOverflow,
+    /// A subroutine returned with an empty stack, the stack wraps around
+    StackUnderflow,
+    /// The program counter points to an odd address
+    OddProgramCounter {
+        /// The program counter
+        addr: u16,
+    },
+    /// EX9E or EXA1 checked a key past 0xF, only the low nibble of VX is used
+    KeyOutOfRange {
+        /// The checked key, the value of VX
+        key: u8,
+    },
+    /// An instruction wrote into the interpreter area below 0x200
+    WriteBelowProgram {
+        /// The written address
+        addr: usize,
+    },
+    /// An assertion trap found a register to be zero, see [`crate::trap::ASSERT`]. The machine
+    /// halts whether or not it is in strict mode.
+    AssertionFailed {
+        /// Address of the trap call
+        addr: u16,
+        /// The checked register
+        register: u8,
+    },
+    /// A test trap reported a failure, see [`crate::trap::Verdict`]. The machine halts whether or
+    /// not it is in strict mode.
+    TestFailed {
+        /// Address of the trap call
+        addr: u16,
+    },
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownOpcode { addr, opcode } => {
+                write!(f, "unknown opcode {:04X} at {:#05X}", opcode, addr)
+            }
+            Self::MachineCodeCall { addr } => {
+                write!(f, "unhandled machine code call to {:#05X}", addr)
+            }
+            Self::MemoryOutOfRange { addr } => {
+                write!(f, "memory access out of range at {:#05X}", addr)
+            }
+            Self::StackOverflow => write!(f, "stack overflow"),
+            Self::StackUnderflow => write!(f, "return with an empty stack"),
+            Self::OddProgramCounter { addr } => {
+                write!(f, "program counter at odd address {:#05X}", addr)
+            }
+            Self::KeyOutOfRange { key } => write!(f, "key {:#04X} out of range", key),
+            Self::WriteBelowProgram { addr } => {
+                write!(f, "write to the interpreter area at {:#05X}", addr)
+            }
+            Self::AssertionFailed { addr, register } => {
+                write!(
+                    f,
+                    "assertion failed at {:#05X}: V{:X} is zero",
+                    addr, register
+                )
+            }
+            Self::TestFailed { addr } => write!(f, "test failed at {:#05X}", addr),
+        }
+    }
+}
+
+/// Something worth telling the user about the running program that never halts the machine. The
+/// library only queues warnings, the frontends print them, see [`crate::Chip8::take_warnings`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Warning {
+    /// A loop polls a short delay, its length depends on the timer phase, see
+    /// [`crate::Chip8::set_warn_timer_polling`]
+    TimerPolling {
+        /// Start of the loop
+        addr: u16,
+        /// The polled delay
+        delay: u8,
+        /// Address of the FX15 that set the delay
+        set_addr: u16,
+    },
+    /// A byte was read that has never been written, see [`crate::Chip8::set_warn_uninit`]
+    UninitializedRead {
+        /// The read address
+        addr: usize,
+        /// The program counter
+        pc: u16,
+    },
+    /// The trace could not be written and was stopped, see [`crate::Chip8::set_trace`]
+    TraceStopped(String),
+    /// The launcher could not start a ROM, see [`crate::shell::launcher`]
+    LaunchFailed {
+        /// Path of the ROM
+        rom: String,
+        /// Why it could not be started
+        reason: String,
+    },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TimerPolling {
+                addr,
+                delay,
+                set_addr,
+            } => write!(
+                f,
+                "timer polling loop at {:#05X}: the delay of {} set at {:#05X} is polled in a \
+                 tight loop, its length depends on the timer phase",
+                addr, delay, set_addr
+            ),
+            Self::UninitializedRead { addr, pc } => write!(
+                f,
+                "reading uninitialized memory at {:#05X} (PC: {:#05X})",
+                addr, pc
+            ),
+            Self::TraceStopped(err) => write!(f, "stopped the trace: {}", err),
+            Self::LaunchFailed { rom, reason } => write!(f, "{}: {}", rom, reason),
+        }
+    }
+}
@@ -0,0 +1,250 @@
+//! A frontend that renders directly to the Linux framebuffer and reads keys from an evdev device,
+//! for dedicated consoles (e.g. a Raspberry Pi) that run without X or Wayland.
+//!
+//! The geometry of the framebuffer is read from `/sys/class/graphics/<fb>/`, and both 16-bit
+//! (RGB565) and 32-bit (XRGB8888) pixel formats are supported. DRM/KMS devices are not, a system
+//! with a KMS driver needs the fbdev emulation of the kernel.
+
+use super::input;
+use super::pacer::Pacer;
+use super::Chip8;
+use std::fs;
+use std::io;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+use std::time;
+
+/// Linux key code of the Escape key, which quits the emulator
+const KEY_ESC: u16 = 1;
+/// Event type of key presses and releases
+const EV_KEY: u16 = 1;
+/// Size of the `struct timeval` that starts `struct input_event`, two `long`s: 16 bytes on 64-bit
+/// systems and 8 on 32-bit ones such as the Raspberry Pi Zero
+const TIMEVAL_SIZE: usize = 2 * std::mem::size_of::<std::os::raw::c_long>();
+/// Size of `struct input_event`, the time followed by the type, the code and the value
+const INPUT_EVENT_SIZE: usize = TIMEVAL_SIZE + 8;
+
+/// Devices used by the frontend
+#[derive(Debug, Clone)]
+pub struct FbdevConfig {
+    /// The framebuffer device, e.g. `/dev/fb0`
+    pub framebuffer: PathBuf,
+    /// The evdev keyboard device, e.g. `/dev/input/event0`
+    pub keyboard: PathBuf,
+    /// Number of instructions executed per second
+    pub ticks_per_sec: u32,
+}
+
+/// Run the emulator until Escape is pressed
+pub fn run(chip8: &mut Chip8, config: &FbdevConfig) -> io::Result<()> {
+    let mut screen = Screen::open(&config.framebuffer)?;
+    let quit = Arc::new(AtomicBool::new(false));
+    let events = spawn_evdev(config.keyboard.clone(), quit.clone())?;
+    chip8.attach_input(Box::new(input::EventSource::new(events)));
+
+    let frame = time::Duration::from_secs(1) / 60;
+    let mut pacer = Pacer::default();
+    let mut presented = Vec::new();
+    while !quit.load(Ordering::Relaxed) {
+        let started = time::Instant::now();
+        chip8.poll_inputs();
+        pacer.run(chip8, config.ticks_per_sec);
+        if chip8.framebuffer() != presented.as_slice() {
+            presented = chip8.framebuffer().to_vec();
+            screen.present(&presented, chip8.screen_size())?;
+        }
+        if let Some(rest) = frame.checked_sub(started.elapsed()) {
+            thread::sleep(rest);
+        }
+    }
+    Ok(())
+}
+
+/// The Linux framebuffer device
+struct Screen {
+    device: fs::File,
+    width: usize,
+    height: usize,
+    bytes_per_pixel: usize,
+    stride: usize,
+}
+
+impl Screen {
+    fn open(path: &Path) -> io::Result<Self> {
+        let name = path
+            .file_name()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid framebuffer"))?;
+        let sysfs = Path::new("/sys/class/graphics").join(name);
+        let read = |attr: &str| fs::read_to_string(sysfs.join(attr));
+        let invalid = |attr: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid framebuffer attribute {}", attr),
+            )
+        };
+
+        let size = read("virtual_size")?;
+        let mut size = size.trim().split(',').map(|n| n.parse::<usize>());
+        let (width, height) = match (size.next(), size.next()) {
+            (Some(Ok(w)), Some(Ok(h))) => (w, h),
+            _ => return Err(invalid("virtual_size")),
+        };
+        let bytes_per_pixel = match read("bits_per_pixel")?.trim() {
+            "16" => 2,
+            "32" => 4,
+            _ => return Err(invalid("bits_per_pixel")),
+        };
+        let stride = read("stride")?
+            .trim()
+            .parse()
+            .map_err(|_| invalid("stride"))?;
+        let device = fs::OpenOptions::new().write(true).open(path)?;
+        Ok(Self {
+            device,
+            width,
+            height,
+            bytes_per_pixel,
+            stride,
+        })
+    }
+
+    /// Draw the CHIP-8 screen scaled to fit and centered
+    fn present(&mut self, gfx: &[bool], (width, height): (usize, usize)) -> io::Result<()> {
+        let scale = (self.width / width).min(self.height / height).max(1);
+        let left = self.width.saturating_sub(width * scale) / 2;
+        let top = self.height.saturating_sub(height * scale) / 2;
+
+        let mut row = vec![0u8; width * scale * self.bytes_per_pixel];
+        for y in 0..height {
+            for (x, pixel) in gfx[y * width..(y + 1) * width].iter().enumerate() {
+                let value = if *pixel { 0xFF } else { 0x00 };
+                let start = x * scale * self.bytes_per_pixel;
+                row[start..start + scale * self.bytes_per_pixel]
+                    .iter_mut()
+                    .for_each(|b| *b = value);
+            }
+            for line in 0..scale {
+                let offset = (top + y * scale + line) * self.stride + left * self.bytes_per_pixel;
+                self.device.seek(SeekFrom::Start(offset as u64))?;
+                self.device.write_all(&row)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Spawn a thread that reads key events from an evdev device, Escape sets the quit flag
+fn spawn_evdev(
+    path: PathBuf,
+    quit: Arc<AtomicBool>,
+) -> io::Result<mpsc::Receiver<input::KeyEvent>> {
+    let mut device = fs::File::open(path)?;
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut event = [0u8; INPUT_EVENT_SIZE];
+        while device.read_exact(&mut event).is_ok() {
+            let fields = &event[TIMEVAL_SIZE..];
+            let kind = u16::from_ne_bytes([fields[0], fields[1]]);
+            let code = u16::from_ne_bytes([fields[2], fields[3]]);
+            let value = i32::from_ne_bytes([fields[4], fields[5], fields[6], fields[7]]);
+            // Ignore auto-repeat, which is reported with a value of 2
+            if kind != EV_KEY || value > 1 {
+                continue;
+            }
+            if code == KEY_ESC {
+                quit.store(true, Ordering::Relaxed);
+                return;
+            }
+            if let Some(key) = keypad_index(code) {
+                let event = input::KeyEvent {
+                    key,
+                    pressed: value == 1,
+                };
+                if tx.send(event).is_err() {
+                    return;
+                }
+            }
+        }
+    });
+    Ok(rx)
+}
+
+/// The keypad key that a Linux key code is mapped to, using the same layout as the window
+fn keypad_index(code: u16) -> Option<u8> {
+    let key = match code {
+        2 => 0x1,  // 1
+        3 => 0x2,  // 2
+        4 => 0x3,  // 3
+        5 => 0xC,  // 4
+        16 => 0x4, // Q
+        17 => 0x5, // W
+        18 => 0x6, // E
+        19 => 0xD, // R
+        30 => 0x7, // A
+        31 => 0x8, // S
+        32 => 0x9, // D
+        33 => 0xE, // F
+        44 => 0xA, // Z
+        45 => 0x0, // X
+        46 => 0xB, // C
+        47 => 0xF, // V
+        _ => return None,
+    };
+    Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::keypad_index;
+    use super::Screen;
+    use std::fs;
+
+    #[test]
+    fn keys_follow_the_layout_of_the_window() {
+        let codes = [2, 3, 4, 5, 16, 17, 18, 19, 30, 31, 32, 33, 44, 45, 46, 47];
+        let layout = [1, 2, 3, 0xC, 4, 5, 6, 0xD, 7, 8, 9, 0xE, 0xA, 0, 0xB, 0xF];
+        assert_eq!(codes.map(keypad_index), layout.map(Some));
+        assert_eq!(keypad_index(1), None); // Escape
+    }
+
+    #[test]
+    fn the_screen_is_scaled_and_centered() {
+        // Room for a scale of 2 with a margin of one pixel on every side, two bytes per pixel
+        let (width, height, stride) = (130, 66, 260);
+        let path = std::env::temp_dir().join(format!("chip8-fb-{}", std::process::id()));
+        fs::write(&path, vec![0x11; height * stride]).unwrap();
+        let device = fs::OpenOptions::new().write(true).open(&path).unwrap();
+        let mut screen = Screen {
+            device,
+            width,
+            height,
+            bytes_per_pixel: 2,
+            stride,
+        };
+        let mut gfx = vec![false; 64 * 32];
+        gfx[0] = true;
+        screen.present(&gfx, (64, 32)).unwrap();
+        let fb = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let pixel = |x: usize, y: usize| &fb[y * stride + x * 2..y * stride + x * 2 + 2];
+        for (x, y) in [(1, 1), (2, 1), (1, 2), (2, 2)] {
+            assert_eq!(pixel(x, y), [0xFF, 0xFF], "{}, {}", x, y);
+        }
+        assert_eq!(pixel(3, 1), [0, 0]);
+        assert_eq!(pixel(128, 64), [0, 0]);
+        // The margin is left as it was
+        assert_eq!(pixel(0, 1), [0x11, 0x11]);
+        assert_eq!(pixel(1, 0), [0x11, 0x11]);
+        assert_eq!(pixel(129, 65), [0x11, 0x11]);
+    }
+}
@@ -0,0 +1,414 @@
+//! The messages shown to users by the frontends, in every supported language. Diagnostics meant
+//! for ROM developers, such as the state dump and the statistics, stay in English.
+
+use std::env;
+use std::fmt;
+use std::str::FromStr;
+
+/// A language of the user interface
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Lang {
+    /// English
+    #[default]
+    En,
+    /// Vietnamese
+    Vi,
+}
+
+impl Lang {
+    /// The language from the `CHIP8_LANG` or `LANG` environment variables, English if neither
+    /// names a supported language
+    pub fn from_env() -> Self {
+        ["CHIP8_LANG", "LANG"]
+            .iter()
+            .filter_map(|var| env::var(var).ok())
+            .find_map(|value| value.get(..2).and_then(|code| code.parse().ok()))
+            .unwrap_or_default()
+    }
+}
+
+impl FromStr for Lang {
+    type Err = String;
+
+    /// Parse a language code, `en` or `vi`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "en" => Ok(Self::En),
+            "vi" => Ok(Self::Vi),
+            _ => Err(format!("Unknown language {}", s)),
+        }
+    }
+}
+
+/// A message shown to the user
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    /// Turbo-fire was switched on or off for a key
+    TurboFire {
+        /// The keypad key
+        key: u8,
+        /// True if turbo-fire is now enabled
+        on: bool,
+    },
+    /// The program memory was exported to a file
+    Exported(String),
+    /// The controls were written to a file
+    ExportedControls(String),
+    /// A host key of the exported controls is bound more than once
+    ControlsConflict(String),
+    /// The program memory could not be exported
+    ExportFailed(String),
+    /// No audio could be played
+    ToneFailed(String),
+    /// No ROM was given on the command line
+    NoRom,
+    /// The seed is not a number
+    InvalidSeed,
+    /// The turbo-fire setting cannot be parsed
+    InvalidTurbo(String),
+    /// The quirk setting cannot be parsed
+    InvalidQuirk(String),
+    /// The screenshot settings cannot be parsed
+    InvalidScreenshots,
+    /// The GPIO pins cannot be parsed
+    InvalidGpioPins,
+    /// Screenshots were written to a directory
+    WroteScreenshots {
+        /// Number of screenshots
+        count: usize,
+        /// The directory
+        dir: String,
+    },
+    /// A reproduction bundle was written to a directory
+    WroteRepro(String),
+    /// The keys of the run were written to a movie file
+    WroteMovie(String),
+    /// A reproduction bundle ended with its recorded fault
+    Reproduced(String),
+    /// A reproduction bundle did not end with its recorded fault
+    NotReproduced(String),
+    /// The machine was halted by a fault
+    Halted(String),
+    /// How to leave the error screen when there is no other ROM
+    ErrorQuit,
+    /// How to choose a ROM on the error screen
+    ErrorChooseRom,
+    /// How to choose a ROM in the library
+    LibraryHelp,
+    /// The library directory has no ROM
+    LibraryEmpty(String),
+    /// A ROM of the library
+    LibraryEntry {
+        /// Title of the ROM
+        title: String,
+        /// Size of the ROM in bytes
+        size: u64,
+    },
+    /// Keys of the debugger while the machine is paused
+    DebuggerHelp,
+    /// How to scroll the memory viewer
+    MemoryHelp,
+    /// An address cannot be parsed
+    InvalidAddress(String),
+    /// A number of frames cannot be parsed
+    InvalidFrames(String),
+    /// A number of seconds cannot be parsed
+    InvalidSeconds(String),
+    /// The number of ticks per second cannot be parsed
+    InvalidSpeed(String),
+    /// The window scale cannot be parsed
+    InvalidScale(String),
+    /// A color cannot be parsed
+    InvalidColor(String),
+    /// A palette name is not one of the built-in palettes
+    InvalidTheme(String),
+    /// The ROM cannot be opened
+    UnreadableRom {
+        /// Path of the ROM
+        rom: String,
+        /// Why it cannot be opened
+        err: String,
+    },
+    /// The machine state was saved to a file
+    SavedState(String),
+    /// The machine was reset and the ROM loaded again
+    Reloaded,
+    /// The screen is drawn with another built-in palette
+    Theme(String),
+    /// The machine state was restored from a file
+    LoadedState(String),
+    /// The machine state could not be saved or restored
+    StateFailed(String),
+    /// A screenshot of the window was written to a file
+    SavedScreenshot(String),
+    /// The screenshot could not be written
+    ScreenshotFailed(String),
+    /// A recording of the screen was started into a file
+    StartedRecording(String),
+    /// The recording of the screen was written to a file
+    SavedRecording(String),
+    /// The recording of the screen failed and was stopped
+    RecordingFailed(String),
+    /// Keys of the save state slot picker, when saving or loading
+    SlotPickerHelp {
+        /// True if the chosen slot is overwritten
+        saving: bool,
+    },
+    /// Label of a save state slot without a state
+    EmptySlot,
+    /// Keys of the calibration mode
+    CalibrationHelp,
+    /// The settings of the ROM were saved to a file
+    SavedConfig(String),
+    /// The settings of the ROM could not be saved
+    ConfigFailed(String),
+    /// The machine was paused because nothing was pressed for a while
+    Idle,
+    /// A compatibility note made for the first time, with its hint
+    CompatibilityNote(String),
+    /// Heading of the compatibility notes printed on exit
+    CompatibilityNotes,
+    /// Another ROM of the playlist was loaded
+    NowPlaying {
+        /// Position in the playlist, starting at 1
+        position: usize,
+        /// Number of ROMs in the playlist
+        count: usize,
+        /// Path of the ROM
+        rom: String,
+    },
+}
+
+impl Message {
+    /// The message in the given language
+    pub fn text(&self, lang: Lang) -> String {
+        match lang {
+            Lang::En => self.en(),
+            Lang::Vi => self.vi(),
+        }
+    }
+
+    fn en(&self) -> String {
+        match self {
+            Self::TurboFire { key, on } => {
+                let state = if *on { "on" } else { "off" };
+                format!("Turbo-fire {} for key {:X}", state, key)
+            }
+            Self::Exported(path) => format!("Exported the program memory to {}", path),
+            Self::ExportedControls(path) => format!("Exported the controls to {}", path),
+            Self::ControlsConflict(conflict) => format!("Warning: {}", conflict),
+            Self::ExportFailed(err) => format!("Could not export the program memory: {}", err),
+            Self::ToneFailed(err) => {
+                format!("Could not create the tone, the sound is off: {}", err)
+            }
+            Self::NoRom => "No ROM path given, run with --help for the usage".to_string(),
+            Self::InvalidSeed => "The seed must be an unsigned integer".to_string(),
+            Self::InvalidTurbo(value) => format!(
+                "Invalid turbo-fire setting {}, expected K[:RATE] with a rate above 0 and at most {} presses per second",
+                value,
+                crate::input::MAX_TURBO_RATE
+            ),
+            Self::InvalidQuirk(value) => format!("Invalid quirk setting {}", value),
+            Self::InvalidScreenshots => "Screenshots are given as DIR EVERY FRAMES".to_string(),
+            Self::InvalidGpioPins => "GPIO pins must be given as R,R,R,R:C,C,C,C".to_string(),
+            Self::WroteScreenshots { count, dir } => {
+                format!("Wrote {} screenshots and a contact sheet to {}", count, dir)
+            }
+            Self::WroteRepro(dir) => format!("Wrote a reproduction bundle to {}", dir),
+            Self::WroteMovie(path) => format!("Wrote the keys of the run to {}", path),
+            Self::Reproduced(fault) => format!("Reproduced: {}", fault),
+            Self::NotReproduced(fault) => format!("Did not reproduce: {}", fault),
+            Self::Halted(fault) => format!("The program was halted: {}", fault),
+            Self::ErrorQuit => "Press Esc to quit".to_string(),
+            Self::ErrorChooseRom => {
+                "Choose another ROM with Up and Down and open it with Enter, or press Esc to quit"
+                    .to_string()
+            }
+            Self::LibraryHelp => {
+                "Choose a ROM with Up and Down and open it with Enter or a click, or press Esc to quit"
+                    .to_string()
+            }
+            Self::LibraryEmpty(dir) => {
+                format!("No ROMs in {}, choose a directory with --library", dir)
+            }
+            Self::LibraryEntry { title, size } => format!("{}  ({} bytes)", title, size),
+            Self::DebuggerHelp => {
+                "Paused. F5: continue, F6: step, F4: toggle a breakpoint".to_string()
+            }
+            Self::MemoryHelp => {
+                "Memory. Shift with Up, Down, Page Up or Page Down, or the wheel: scroll".to_string()
+            }
+            Self::InvalidAddress(value) => format!("Invalid address {}", value),
+            Self::InvalidFrames(value) => format!("Invalid number of frames {}", value),
+            Self::InvalidSeconds(value) => format!("Invalid number of seconds {}", value),
+            Self::InvalidSpeed(value) => format!("Invalid number of ticks per second {}", value),
+            Self::InvalidScale(value) => format!("Invalid window scale {}", value),
+            Self::InvalidColor(value) => format!("Invalid color {}, expected RRGGBB", value),
+            Self::InvalidTheme(value) => format!(
+                "Unknown palette {}, expected classic, green, amber, lcd or inverted",
+                value
+            ),
+            Self::UnreadableRom { rom, err } => format!("Cannot open the ROM {}: {}", rom, err),
+            Self::SavedState(path) => format!("Saved the state to {}", path),
+            Self::Reloaded => "Reset the machine and reloaded the ROM".to_string(),
+            Self::Theme(name) => format!("Switched to the {} palette", name),
+            Self::LoadedState(path) => format!("Restored the state from {}", path),
+            Self::StateFailed(err) => format!("Could not save or restore the state: {}", err),
+            Self::SavedScreenshot(path) => format!("Saved a screenshot to {}", path),
+            Self::ScreenshotFailed(err) => format!("Could not save the screenshot: {}", err),
+            Self::StartedRecording(path) => format!("Recording the screen to {}", path),
+            Self::SavedRecording(path) => format!("Saved the recording to {}", path),
+            Self::RecordingFailed(err) => format!("Could not record the screen: {}", err),
+            Self::SlotPickerHelp { saving: true } => {
+                "Save to slot - Left/Right: choose, Enter: save, Esc: cancel".to_string()
+            }
+            Self::SlotPickerHelp { saving: false } => {
+                "Load from slot - Left/Right: choose, Enter: load, Esc: cancel".to_string()
+            }
+            Self::EmptySlot => "empty".to_string(),
+            Self::CalibrationHelp => {
+                "Up/Down: speed, Left/Right: quirk, Space: toggle, Enter: save".to_string()
+            }
+            Self::SavedConfig(path) => format!("Saved the ROM settings to {}", path),
+            Self::ConfigFailed(err) => format!("Could not save the ROM settings: {}", err),
+            Self::Idle => "Paused while idle, press any key to resume".to_string(),
+            Self::CompatibilityNote(hint) => format!("Compatibility: {}", hint),
+            Self::CompatibilityNotes => "Compatibility notes:".to_string(),
+            Self::NowPlaying {
+                position,
+                count,
+                rom,
+            } => format!("Playing {}/{}: {}", position, count, rom),
+        }
+    }
+
+    fn vi(&self) -> String {
+        match self {
+            Self::TurboFire { key, on } => {
+                let state = if *on { "bật" } else { "tắt" };
+                format!("Đã {} bắn liên tục cho phím {:X}", state, key)
+            }
+            Self::Exported(path) => format!("Đã xuất bộ nhớ chương trình ra {}", path),
+            Self::ExportedControls(path) => format!("Đã xuất thiết lập điều khiển ra {}", path),
+            Self::ControlsConflict(conflict) => format!("Cảnh báo: {}", conflict),
+            Self::ExportFailed(err) => format!("Không thể xuất bộ nhớ chương trình: {}", err),
+            Self::ToneFailed(err) => format!("Không thể tạo âm thanh, đã tắt tiếng: {}", err),
+            Self::NoRom => {
+                "Chưa chỉ định đường dẫn ROM, chạy với --help để xem cách dùng".to_string()
+            }
+            Self::InvalidSeed => "Hạt giống phải là một số nguyên không âm".to_string(),
+            Self::InvalidTurbo(value) => format!(
+                "Thiết lập bắn liên tục không hợp lệ: {}, cần dạng K[:RATE] với tốc độ lớn hơn 0 và tối đa {} lần nhấn mỗi giây",
+                value,
+                crate::input::MAX_TURBO_RATE
+            ),
+            Self::InvalidQuirk(value) => format!("Thiết lập quirk không hợp lệ: {}", value),
+            Self::InvalidScreenshots => {
+                "Ảnh chụp màn hình phải được cho dưới dạng DIR EVERY FRAMES".to_string()
+            }
+            Self::InvalidGpioPins => {
+                "Các chân GPIO phải được cho dưới dạng R,R,R,R:C,C,C,C".to_string()
+            }
+            Self::WroteScreenshots { count, dir } => {
+                format!(
+                    "Đã ghi {} ảnh chụp màn hình và một bảng tổng hợp vào {}",
+                    count, dir
+                )
+            }
+            Self::WroteRepro(dir) => format!("Đã ghi gói tái hiện lỗi vào {}", dir),
+            Self::WroteMovie(path) => format!("Đã ghi các phím bấm của lượt chơi vào {}", path),
+            Self::Reproduced(fault) => format!("Đã tái hiện: {}", fault),
+            Self::NotReproduced(fault) => format!("Không tái hiện được: {}", fault),
+            Self::Halted(fault) => format!("Chương trình đã bị dừng: {}", fault),
+            Self::ErrorQuit => "Nhấn Esc để thoát".to_string(),
+            Self::ErrorChooseRom => {
+                "Chọn ROM khác bằng phím Lên và Xuống rồi mở bằng Enter, hoặc nhấn Esc để thoát"
+                    .to_string()
+            }
+            Self::LibraryHelp => {
+                "Chọn ROM bằng phím Lên và Xuống rồi mở bằng Enter hoặc nhấp chuột, hoặc nhấn Esc để thoát"
+                    .to_string()
+            }
+            Self::LibraryEmpty(dir) => {
+                format!("Không có ROM nào trong {}, chọn thư mục bằng --library", dir)
+            }
+            Self::LibraryEntry { title, size } => format!("{}  ({} byte)", title, size),
+            Self::DebuggerHelp => {
+                "Tạm dừng. F5: tiếp tục, F6: chạy từng lệnh, F4: bật/tắt điểm dừng".to_string()
+            }
+            Self::MemoryHelp => {
+                "Bộ nhớ. Shift cùng Lên, Xuống, Page Up hoặc Page Down, hoặc con lăn: cuộn"
+                    .to_string()
+            }
+            Self::InvalidAddress(value) => format!("Địa chỉ không hợp lệ: {}", value),
+            Self::InvalidFrames(value) => format!("Số khung hình không hợp lệ: {}", value),
+            Self::InvalidSeconds(value) => format!("Số giây không hợp lệ: {}", value),
+            Self::InvalidSpeed(value) => format!("Số nhịp mỗi giây không hợp lệ: {}", value),
+            Self::InvalidScale(value) => format!("Tỉ lệ cửa sổ không hợp lệ: {}", value),
+            Self::InvalidColor(value) => format!("Màu không hợp lệ: {}, cần dạng RRGGBB", value),
+            Self::InvalidTheme(value) => format!(
+                "Bảng màu không xác định: {}, cần classic, green, amber, lcd hoặc inverted",
+                value
+            ),
+            Self::UnreadableRom { rom, err } => format!("Không thể mở ROM {}: {}", rom, err),
+            Self::SavedState(path) => format!("Đã lưu trạng thái vào {}", path),
+            Self::Reloaded => "Đã khởi động lại máy và nạp lại ROM".to_string(),
+            Self::Theme(name) => format!("Đã chuyển sang bảng màu {}", name),
+            Self::LoadedState(path) => format!("Đã khôi phục trạng thái từ {}", path),
+            Self::StateFailed(err) => format!("Không thể lưu hoặc khôi phục trạng thái: {}", err),
+            Self::SavedScreenshot(path) => format!("Đã lưu ảnh chụp màn hình vào {}", path),
+            Self::ScreenshotFailed(err) => format!("Không thể lưu ảnh chụp màn hình: {}", err),
+            Self::StartedRecording(path) => format!("Đang ghi lại màn hình vào {}", path),
+            Self::SavedRecording(path) => format!("Đã lưu bản ghi vào {}", path),
+            Self::RecordingFailed(err) => format!("Không thể ghi lại màn hình: {}", err),
+            Self::SlotPickerHelp { saving: true } => {
+                "Lưu vào ô - Trái/Phải: chọn, Enter: lưu, Esc: hủy".to_string()
+            }
+            Self::SlotPickerHelp { saving: false } => {
+                "Khôi phục từ ô - Trái/Phải: chọn, Enter: khôi phục, Esc: hủy".to_string()
+            }
+            Self::EmptySlot => "trống".to_string(),
+            Self::CalibrationHelp => {
+                "Lên/Xuống: tốc độ, Trái/Phải: quirk, Space: bật/tắt, Enter: lưu".to_string()
+            }
+            Self::SavedConfig(path) => format!("Đã lưu thiết lập của ROM vào {}", path),
+            Self::ConfigFailed(err) => format!("Không thể lưu thiết lập của ROM: {}", err),
+            Self::Idle => "Tạm dừng do không hoạt động, nhấn phím bất kỳ để tiếp tục".to_string(),
+            Self::CompatibilityNote(hint) => format!("Tương thích: {}", hint),
+            Self::CompatibilityNotes => "Ghi chú về tương thích:".to_string(),
+            Self::NowPlaying {
+                position,
+                count,
+                rom,
+            } => format!("Đang chơi {}/{}: {}", position, count, rom),
+        }
+    }
+}
+
+impl fmt::Display for Message {
+    /// The message in English
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.en())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Lang;
+    use super::Message;
+
+    #[test]
+    fn language_codes_are_parsed() {
+        assert_eq!("en".parse(), Ok(Lang::En));
+        assert_eq!("vi".parse(), Ok(Lang::Vi));
+        assert!("fr".parse::<Lang>().is_err());
+    }
+
+    #[test]
+    fn messages_are_shown_in_the_language_of_the_user() {
+        let message = Message::TurboFire { key: 0xA, on: true };
+        assert_eq!(message.text(Lang::En), "Turbo-fire on for key A");
+        assert_eq!(message.text(Lang::Vi), "Đã bật bắn liên tục cho phím A");
+        assert_eq!(Message::NoRom.to_string(), Message::NoRom.text(Lang::En));
+    }
+}
@@ -0,0 +1,93 @@
+//! Fault injection, used to check that tooling built on top of the emulator (replays, save
+//! states, desync detection) copes with a machine that diverges from its expected behaviour
+
+/// Faults that are injected while the machine runs. Every fault is deterministic so that a run
+/// can be reproduced.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FaultInjection {
+    /// Flip one bit of the program memory every N executed instructions
+    pub flip_bit_every: Option<u64>,
+    /// Drop every Nth change of the keypad state
+    pub drop_key_every: Option<u64>,
+    /// Factor applied to the period of the timers, e.g. 1.1 makes them 10% slower
+    pub clock_skew: f32,
+    /// Seed that picks the flipped bits
+    pub seed: u64,
+}
+
+impl Default for FaultInjection {
+    fn default() -> Self {
+        Self {
+            flip_bit_every: None,
+            drop_key_every: None,
+            clock_skew: 1.0,
+            seed: 0,
+        }
+    }
+}
+
+/// Running state of the injected faults
+#[derive(Debug, Clone)]
+pub(crate) struct Injector {
+    pub(crate) config: FaultInjection,
+    state: u64,
+    cycles: u64,
+    key_changes: u64,
+    /// Timer counts owed by the frames so far, less than one
+    timer_counts: f32,
+}
+
+impl Injector {
+    pub(crate) fn new(config: FaultInjection) -> Self {
+        Self {
+            config,
+            state: config.seed | 1,
+            cycles: 0,
+            key_changes: 0,
+            timer_counts: 0.0,
+        }
+    }
+
+    /// Count an executed instruction, returns the address and bit to flip if it is time to
+    pub(crate) fn on_cycle(&mut self) -> Option<(usize, u8)> {
+        let every = self.config.flip_bit_every?;
+        self.cycles += 1;
+        if every == 0 || !self.cycles.is_multiple_of(every) {
+            return None;
+        }
+        // xorshift64
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        let addr = 0x200 + (self.state % 0xE00) as usize;
+        let bit = ((self.state >> 32) % 8) as u8;
+        Some((addr, bit))
+    }
+
+    /// Count a frame, returns how many times the timers count down in it
+    pub(crate) fn on_frame(&mut self) -> u32 {
+        if self.config.clock_skew <= 0.0 {
+            return 0;
+        }
+        self.timer_counts += 1.0 / self.config.clock_skew;
+        let counts = self.timer_counts.floor();
+        self.timer_counts -= counts;
+        counts as u32
+    }
+
+    /// Filter a new keypad state, dropping every Nth change of a key
+    pub(crate) fn on_keys(&mut self, old: &[bool; 16], new: &mut [bool; 16]) {
+        let every = match self.config.drop_key_every {
+            Some(every) if every > 0 => every,
+            _ => return,
+        };
+        for (old, new) in old.iter().zip(new.iter_mut()) {
+            if old != new {
+                self.key_changes += 1;
+                if self.key_changes.is_multiple_of(every) {
+                    *new = *old;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,371 @@
+//! Input handling. The keypad state is merged every frame from a set of pluggable input sources,
+//! such as the keyboard or external drivers that feed key events from outside the window.
+
+#[cfg(feature = "gpio")]
+mod gpio;
+#[cfg(feature = "midi")]
+mod midi;
+#[cfg(feature = "osc")]
+mod osc;
+mod serial;
+
+#[cfg(feature = "gpio")]
+pub use gpio::*;
+#[cfg(feature = "midi")]
+pub use midi::*;
+#[cfg(feature = "osc")]
+pub use osc::*;
+pub use serial::*;
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::io::BufRead;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::thread;
+
+/// State of the sixteen keys of the HEX-based keypad, true if a key is held down
+pub type Keys = [bool; 16];
+
+/// Determines how the state of an input source is merged with the others
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Precedence {
+    /// The keys held by the source are combined with the keys of all other normal sources
+    Normal,
+    /// While active, the source replaces the state of all normal sources, e.g. a replay
+    Override,
+}
+
+/// A device or program that can hold down keys of the keypad
+pub trait InputSource: Send {
+    /// Update the source and return the keys it currently holds down, or `None` if the source is
+    /// inactive and should not take part in the merge
+    fn poll(&mut self) -> Option<Keys>;
+
+    /// How the keys of this source are merged with the others
+    fn precedence(&self) -> Precedence {
+        Precedence::Normal
+    }
+}
+
+/// Default number of presses per second of a key with turbo-fire
+pub const DEFAULT_TURBO_RATE: f32 = 10.0;
+/// Highest number of presses per second of a key with turbo-fire, a press and a release each
+/// take at least one 60 Hz frame
+pub const MAX_TURBO_RATE: f32 = 30.0;
+
+/// Reasons for a turbo-fire setting to be rejected
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TurboError {
+    /// The key is past 0xF
+    KeyOutOfRange(u8),
+    /// The rate is not above zero and at most [`MAX_TURBO_RATE`]
+    RateOutOfRange(f32),
+}
+
+impl fmt::Display for TurboError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::KeyOutOfRange(key) => write!(f, "key {:#04X} out of range", key),
+            Self::RateOutOfRange(rate) => write!(
+                f,
+                "turbo-fire rate {} is not above 0 and at most {} presses per second",
+                rate, MAX_TURBO_RATE
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TurboError {}
+
+/// Check that a turbo-fire setting can be used, see [`Inputs::set_turbo`]
+pub fn check_turbo(key: u8, rate: f32) -> Result<(), TurboError> {
+    if key > 0xF {
+        return Err(TurboError::KeyOutOfRange(key));
+    }
+    if !(rate > 0.0 && rate <= MAX_TURBO_RATE) {
+        return Err(TurboError::RateOutOfRange(rate));
+    }
+    Ok(())
+}
+
+/// The set of input sources that are merged into the keypad state every frame
+#[derive(Default)]
+pub struct Inputs {
+    /// The host keyboard, it receives the window's key events
+    pub keyboard: Keyboard,
+    sources: Vec<Box<dyn InputSource>>,
+    /// Turbo-fire rate of each key in presses per second, if enabled
+    turbo: [Option<f32>; 16],
+}
+
+impl Inputs {
+    /// Enable turbo-fire for a key at the given rate in presses per second, or disable it. While
+    /// a key with turbo-fire is held by any source, it is repeatedly pressed and released. The
+    /// rate must be above zero and at most [`MAX_TURBO_RATE`].
+    pub fn set_turbo(&mut self, key: u8, rate: Option<f32>) -> Result<(), TurboError> {
+        // Only the key is checked when turbo-fire is disabled
+        check_turbo(key, rate.unwrap_or(DEFAULT_TURBO_RATE))?;
+        self.turbo[key as usize] = rate;
+        Ok(())
+    }
+
+    /// Switch turbo-fire of a key on or off, returns true if it is now enabled
+    pub fn toggle_turbo(&mut self, key: u8) -> bool {
+        let turbo = &mut self.turbo[key as usize & 0xF];
+        *turbo = match turbo {
+            Some(_) => None,
+            None => Some(DEFAULT_TURBO_RATE),
+        };
+        turbo.is_some()
+    }
+
+    /// Add an input source
+    pub fn attach(&mut self, source: Box<dyn InputSource>) {
+        self.sources.push(source);
+    }
+
+    /// Poll every source and merge their states. The keys of all active sources with the highest
+    /// precedence are combined, so a key is held if any of those sources holds it. The turbo-fire
+    /// pulses follow `frame`, the number of 60 Hz frames run by the machine, so that replays see
+    /// the same presses.
+    pub fn poll(&mut self, frame: u64) -> Keys {
+        let mut polled = vec![(Precedence::Normal, self.keyboard.poll())];
+        for source in &mut self.sources {
+            polled.push((source.precedence(), source.poll()));
+        }
+        let highest = polled
+            .iter()
+            .filter(|(_, keys)| keys.is_some())
+            .map(|(precedence, _)| *precedence)
+            .max()
+            .unwrap_or(Precedence::Normal);
+
+        let mut merged = [false; 16];
+        for (_, keys) in polled.iter().filter(|(p, _)| *p == highest) {
+            if let Some(keys) = keys {
+                merged.iter_mut().zip(keys).for_each(|(m, k)| *m |= *k);
+            }
+        }
+
+        for (held, turbo) in merged.iter_mut().zip(&self.turbo) {
+            if let Some(rate) = turbo {
+                // Pressed during the first half of each period, released during the second
+                *held &= (frame as f64 * *rate as f64 / 60.0).fract() < 0.5;
+            }
+        }
+        merged
+    }
+}
+
+/// Input source for the host keyboard, the frontend translates its key events to keypad keys
+#[derive(Default)]
+pub struct Keyboard {
+    keys: Keys,
+}
+
+impl Keyboard {
+    /// Press or release a key of the keypad
+    pub fn set_key(&mut self, key: u8, pressed: bool) {
+        self.keys[key as usize & 0xF] = pressed;
+    }
+}
+
+impl InputSource for Keyboard {
+    fn poll(&mut self) -> Option<Keys> {
+        Some(self.keys)
+    }
+}
+
+/// Input source that keeps track of the key events sent by an external driver
+pub struct EventSource {
+    events: mpsc::Receiver<KeyEvent>,
+    keys: Keys,
+    connected: bool,
+}
+
+impl EventSource {
+    /// Track the key events received from the channel
+    pub fn new(events: mpsc::Receiver<KeyEvent>) -> Self {
+        Self {
+            events,
+            keys: [false; 16],
+            connected: true,
+        }
+    }
+}
+
+impl InputSource for EventSource {
+    fn poll(&mut self) -> Option<Keys> {
+        while self.connected {
+            match self.events.try_recv() {
+                Ok(event) => self.keys[event.key as usize & 0xF] = event.pressed,
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => self.connected = false,
+            }
+        }
+        if self.connected {
+            Some(self.keys)
+        } else {
+            None
+        }
+    }
+}
+
+/// A change in the state of one key of the HEX-based keypad
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyEvent {
+    /// Index of the key on the keypad (0x0-0xF)
+    pub key: u8,
+    /// True if the key is pressed, false if it is released
+    pub pressed: bool,
+}
+
+impl KeyEvent {
+    /// Parse a line of the text protocol, `+K` presses and `-K` releases the hexadecimal key `K`.
+    /// Blank lines and lines starting with `#` are ignored.
+    pub fn parse(line: &str) -> Option<Self> {
+        let line = line.trim();
+        let pressed = match line.chars().next()? {
+            '+' => true,
+            '-' => false,
+            _ => return None,
+        };
+        let digit = line[1..].trim();
+        if digit.len() != 1 {
+            return None;
+        }
+        let key = u8::from_str_radix(digit, 16).ok()?;
+        Some(Self { key, pressed })
+    }
+}
+
+/// Spawn a thread that reads key events from the standard input
+pub fn spawn_stdin() -> mpsc::Receiver<KeyEvent> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let stdin = io::stdin();
+        read_events(stdin.lock(), &tx);
+    });
+    rx
+}
+
+/// Spawn a thread that reads key events from a named pipe. The pipe is reopened every time the
+/// writing end is closed so that external drivers can come and go. Fails if there is no pipe at the
+/// path, the source disconnects if it cannot be reopened later.
+pub fn spawn_fifo(path: PathBuf) -> io::Result<mpsc::Receiver<KeyEvent>> {
+    // Opening the pipe blocks until a driver opens the writing end, so only check that it exists
+    fs::metadata(&path)?;
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        while let Ok(file) = fs::File::open(&path) {
+            if !read_events(io::BufReader::new(file), &tx) {
+                return;
+            }
+        }
+    });
+    Ok(rx)
+}
+
+/// Forward every valid event of the reader to the channel, returns false if the receiving end has
+/// been dropped
+fn read_events<R: BufRead>(reader: R, tx: &mpsc::Sender<KeyEvent>) -> bool {
+    for line in reader.lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        if let Some(event) = KeyEvent::parse(&line) {
+            if tx.send(event).is_err() {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::read_events;
+    use super::Inputs;
+    use super::KeyEvent;
+    use super::TurboError;
+    use super::MAX_TURBO_RATE;
+    use std::sync::mpsc;
+
+    fn event(key: u8, pressed: bool) -> Option<KeyEvent> {
+        Some(KeyEvent { key, pressed })
+    }
+
+    #[test]
+    fn presses_and_releases_are_parsed() {
+        assert_eq!(KeyEvent::parse("+5"), event(0x5, true));
+        assert_eq!(KeyEvent::parse("-a"), event(0xA, false));
+        assert_eq!(KeyEvent::parse("+F"), event(0xF, true));
+        assert_eq!(KeyEvent::parse("  - 0 \r"), event(0x0, false));
+    }
+
+    #[test]
+    fn other_lines_are_ignored() {
+        for line in &["", "   ", "# +5", "5", "+", "+10", "+g", "++5", "+-1", "*5"] {
+            assert_eq!(KeyEvent::parse(line), None, "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn valid_events_of_a_reader_are_forwarded() {
+        let (tx, rx) = mpsc::channel();
+        assert!(read_events("+1\n# comment\nbad\n-1\n".as_bytes(), &tx));
+        assert_eq!(
+            rx.try_iter().collect::<Vec<_>>(),
+            [
+                KeyEvent {
+                    key: 1,
+                    pressed: true
+                },
+                KeyEvent {
+                    key: 1,
+                    pressed: false
+                },
+            ]
+        );
+        drop(rx);
+        assert!(!read_events("+1\n".as_bytes(), &tx));
+    }
+
+    #[test]
+    fn missing_pipes_are_reported() {
+        let err = super::spawn_fifo("/nonexistent/chip8-input".into()).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn turbo_fire_follows_the_frames() {
+        let mut inputs = Inputs::default();
+        inputs.keyboard.set_key(0x5, true);
+        inputs.keyboard.set_key(0x6, true);
+        inputs.set_turbo(0x5, Some(10.0)).unwrap();
+        let held: Vec<_> = (0..12).map(|frame| inputs.poll(frame)[0x5]).collect();
+        let pulse = [true, true, true, false, false, false];
+        assert_eq!(held, [pulse, pulse].concat());
+        assert!((0..12).all(|frame| inputs.poll(frame)[0x6]));
+    }
+
+    #[test]
+    fn turbo_rates_are_checked() {
+        let mut inputs = Inputs::default();
+        assert_eq!(inputs.set_turbo(0x5, Some(MAX_TURBO_RATE)), Ok(()));
+        assert_eq!(inputs.set_turbo(0x5, None), Ok(()));
+        for rate in &[0.0, -1.0, MAX_TURBO_RATE + 1.0, f32::NAN] {
+            assert!(matches!(
+                inputs.set_turbo(0x5, Some(*rate)),
+                Err(TurboError::RateOutOfRange(_))
+            ));
+        }
+        assert_eq!(
+            inputs.set_turbo(0x10, None),
+            Err(TurboError::KeyOutOfRange(0x10))
+        );
+    }
+}
@@ -0,0 +1,117 @@
+//! Key events from a 4x4 matrix keypad wired to GPIO pins, using the Linux sysfs GPIO interface
+//! (e.g. on a Raspberry Pi).
+//!
+//! The rows are driven high one at a time and the columns are read back, so the column pins need
+//! pull-down resistors. The matrix is laid out like the original COSMAC VIP keypad:
+//! ```text
+//! 1 2 3 C
+//! 4 5 6 D
+//! 7 8 9 E
+//! A 0 B F
+//! ```
+
+use super::KeyEvent;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::thread;
+use std::time;
+
+/// Key at each position of the matrix, indexed by row then column
+const LAYOUT: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
+/// Time between two scans of the matrix
+const SCAN_INTERVAL: time::Duration = time::Duration::from_millis(10);
+
+/// GPIO pin numbers that the keypad is connected to
+#[derive(Debug, Clone, Copy)]
+pub struct GpioPins {
+    /// Pins driving the rows, from top to bottom
+    pub rows: [u32; 4],
+    /// Pins reading the columns, from left to right
+    pub cols: [u32; 4],
+}
+
+impl GpioPins {
+    /// Parse the pins from `R,R,R,R:C,C,C,C`
+    pub fn parse(s: &str) -> Option<Self> {
+        fn pins(s: &str) -> Option<[u32; 4]> {
+            let pins: Vec<u32> = s
+                .split(',')
+                .map(|p| p.trim().parse().ok())
+                .collect::<Option<_>>()?;
+            if pins.len() != 4 {
+                return None;
+            }
+            Some([pins[0], pins[1], pins[2], pins[3]])
+        }
+        let mut parts = s.splitn(2, ':');
+        let rows = pins(parts.next()?)?;
+        let cols = pins(parts.next()?)?;
+        Some(Self { rows, cols })
+    }
+}
+
+/// Export the pins and spawn a thread that scans the matrix for changes
+pub fn spawn_gpio(pins: GpioPins) -> io::Result<mpsc::Receiver<KeyEvent>> {
+    for pin in &pins.rows {
+        export(*pin, "out")?;
+    }
+    for pin in &pins.cols {
+        export(*pin, "in")?;
+    }
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut held = [[false; 4]; 4];
+        loop {
+            for (row, row_pin) in pins.rows.iter().enumerate() {
+                if write_value(*row_pin, true).is_err() {
+                    return;
+                }
+                for (col, col_pin) in pins.cols.iter().enumerate() {
+                    let pressed = read_value(*col_pin).unwrap_or(false);
+                    if pressed != held[row][col] {
+                        held[row][col] = pressed;
+                        let event = KeyEvent {
+                            key: LAYOUT[row][col],
+                            pressed,
+                        };
+                        if tx.send(event).is_err() {
+                            return;
+                        }
+                    }
+                }
+                if write_value(*row_pin, false).is_err() {
+                    return;
+                }
+            }
+            thread::sleep(SCAN_INTERVAL);
+        }
+    });
+    Ok(rx)
+}
+
+fn pin_path(pin: u32) -> PathBuf {
+    PathBuf::from(format!("/sys/class/gpio/gpio{}", pin))
+}
+
+fn export(pin: u32, direction: &str) -> io::Result<()> {
+    if !pin_path(pin).exists() {
+        fs::write("/sys/class/gpio/export", pin.to_string())?;
+    }
+    fs::write(pin_path(pin).join("direction"), direction)
+}
+
+fn write_value(pin: u32, high: bool) -> io::Result<()> {
+    fs::write(pin_path(pin).join("value"), if high { "1" } else { "0" })
+}
+
+fn read_value(pin: u32) -> io::Result<bool> {
+    Ok(fs::read_to_string(pin_path(pin).join("value"))?.trim() == "1")
+}
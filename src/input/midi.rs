@@ -0,0 +1,114 @@
+//! Key events from MIDI note messages read from a raw MIDI device (e.g. `/dev/midi1` or
+//! `/dev/snd/midiC1D0` on Linux).
+//!
+//! Sixteen consecutive notes starting from a base note are mapped to the keys 0x0 through 0xF.
+
+use super::KeyEvent;
+use std::fs;
+use std::io::BufReader;
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::thread;
+
+/// Middle C, the default note mapped to key 0x0
+pub const DEFAULT_BASE_NOTE: u8 = 60;
+
+/// Spawn a thread that reads MIDI messages from the device at the given path
+pub fn spawn_midi(path: PathBuf, base_note: u8) -> std::io::Result<mpsc::Receiver<KeyEvent>> {
+    let device = fs::File::open(path)?;
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut parser = Parser::default();
+        for byte in BufReader::new(device).bytes() {
+            let byte = match byte {
+                Ok(byte) => byte,
+                Err(_) => return,
+            };
+            if let Some((note, pressed)) = parser.feed(byte) {
+                if note < base_note || note - base_note > 0xF {
+                    continue;
+                }
+                let event = KeyEvent {
+                    key: note - base_note,
+                    pressed,
+                };
+                if tx.send(event).is_err() {
+                    return;
+                }
+            }
+        }
+    });
+    Ok(rx)
+}
+
+/// Incremental parser for a MIDI byte stream that only cares about note on/off messages
+#[derive(Default)]
+struct Parser {
+    status: u8,
+    data: Vec<u8>,
+}
+
+impl Parser {
+    /// Consume a byte, returns the note and whether it was pressed once a note message is complete
+    fn feed(&mut self, byte: u8) -> Option<(u8, bool)> {
+        if byte >= 0xF8 {
+            // Real-time messages can appear anywhere and do not affect the running status
+            return None;
+        }
+        if byte & 0x80 != 0 {
+            self.status = byte;
+            self.data.clear();
+            return None;
+        }
+        self.data.push(byte);
+        if self.data.len() < 2 {
+            return None;
+        }
+        let (note, velocity) = (self.data[0], self.data[1]);
+        self.data.clear();
+        match self.status & 0xF0 {
+            // A note on with zero velocity is a note off
+            0x90 => Some((note, velocity > 0)),
+            0x80 => Some((note, false)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Parser;
+
+    fn feed(bytes: &[u8]) -> Vec<(u8, bool)> {
+        let mut parser = Parser::default();
+        bytes.iter().filter_map(|byte| parser.feed(*byte)).collect()
+    }
+
+    #[test]
+    fn note_on_and_off_are_parsed() {
+        assert_eq!(feed(&[0x90, 60, 100]), [(60, true)]);
+        assert_eq!(feed(&[0x80, 60, 0]), [(60, false)]);
+        // A note on with zero velocity is a note off, on any channel
+        assert_eq!(feed(&[0x9F, 61, 0]), [(61, false)]);
+    }
+
+    #[test]
+    fn running_status_repeats_the_last_message() {
+        assert_eq!(
+            feed(&[0x90, 60, 100, 62, 100, 60, 0]),
+            [(60, true), (62, true), (60, false)]
+        );
+    }
+
+    #[test]
+    fn other_messages_are_ignored() {
+        // Control change, a real-time clock in the middle of a note and a SysEx
+        assert_eq!(
+            feed(&[0xB0, 7, 100, 0x90, 60, 0xF8, 100, 0xF0, 1, 2, 0xF7]),
+            [(60, true)]
+        );
+        // A status byte in the middle of a message drops the incomplete message
+        assert_eq!(feed(&[0x90, 60, 0x80, 61, 0]), [(61, false)]);
+    }
+}
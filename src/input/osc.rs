@@ -0,0 +1,183 @@
+//! Key events from Open Sound Control messages received over UDP.
+//!
+//! Two address forms are understood:
+//! - `/chip8/key K S`: set key `K` to state `S`
+//! - `/chip8/key/K S`: set key `K` to state `S`
+//!
+//! `K` and `S` can be integers or floats, a state larger than zero presses the key.
+
+use super::KeyEvent;
+use std::net::ToSocketAddrs;
+use std::net::UdpSocket;
+use std::sync::mpsc;
+use std::thread;
+
+/// Address prefix of the messages that are mapped to the keypad
+const ADDRESS: &str = "/chip8/key";
+
+/// Spawn a thread that listens for OSC messages on the given address
+pub fn spawn_osc<A: ToSocketAddrs>(addr: A) -> std::io::Result<mpsc::Receiver<KeyEvent>> {
+    let socket = UdpSocket::bind(addr)?;
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut buf = [0u8; 1536];
+        while let Ok(len) = socket.recv(&mut buf) {
+            let mut events = Vec::new();
+            parse_packet(&buf[..len], &mut events);
+            for event in events {
+                if tx.send(event).is_err() {
+                    return;
+                }
+            }
+        }
+    });
+    Ok(rx)
+}
+
+/// Parse an OSC packet, which is either a single message or a bundle of packets
+fn parse_packet(packet: &[u8], events: &mut Vec<KeyEvent>) {
+    if packet.starts_with(b"#bundle\0") {
+        // Skip the bundle tag and the time tag
+        let mut rest = packet.get(16..).unwrap_or(&[]);
+        while rest.len() >= 4 {
+            let size = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+            match rest.get(4..4 + size) {
+                Some(element) => parse_packet(element, events),
+                None => return,
+            }
+            rest = &rest[4 + size..];
+        }
+    } else if let Some(event) = parse_message(packet) {
+        events.push(event);
+    }
+}
+
+/// Parse an OSC message into a key event
+fn parse_message(packet: &[u8]) -> Option<KeyEvent> {
+    let (address, rest) = read_string(packet)?;
+    let (tags, mut rest) = read_string(rest)?;
+    let tags = tags.strip_prefix(',')?;
+    let mut args = Vec::new();
+    for tag in tags.chars() {
+        let word = rest.get(..4)?;
+        let word = [word[0], word[1], word[2], word[3]];
+        rest = &rest[4..];
+        match tag {
+            'i' => args.push(i32::from_be_bytes(word) as f32),
+            'f' => args.push(f32::from_be_bytes(word)),
+            _ => return None,
+        }
+    }
+
+    let suffix = address.strip_prefix(ADDRESS)?;
+    let (key, state) = if suffix.is_empty() {
+        (*args.first()?, *args.get(1)?)
+    } else {
+        let key = u8::from_str_radix(suffix.strip_prefix('/')?, 16).ok()?;
+        (key as f32, *args.first()?)
+    };
+    if !(0.0..16.0).contains(&key) {
+        return None;
+    }
+    Some(KeyEvent {
+        key: key as u8,
+        pressed: state > 0.0,
+    })
+}
+
+/// Read a null-terminated string that is padded to a multiple of four bytes
+fn read_string(data: &[u8]) -> Option<(&str, &[u8])> {
+    let len = data.iter().position(|b| *b == 0)?;
+    let s = std::str::from_utf8(&data[..len]).ok()?;
+    let padded = (len + 4) & !3;
+    Some((s, data.get(padded..)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_packet;
+    use super::KeyEvent;
+
+    /// A null-terminated string padded to a multiple of four bytes
+    fn string(s: &str) -> Vec<u8> {
+        let mut data = s.as_bytes().to_vec();
+        data.resize((s.len() + 4) & !3, 0);
+        data
+    }
+
+    /// An OSC message with integer and float arguments
+    fn message(address: &str, ints: &[i32], floats: &[f32]) -> Vec<u8> {
+        let tags = format!(",{}{}", "i".repeat(ints.len()), "f".repeat(floats.len()));
+        let mut data = string(address);
+        data.extend(string(&tags));
+        for int in ints {
+            data.extend_from_slice(&int.to_be_bytes());
+        }
+        for float in floats {
+            data.extend_from_slice(&float.to_be_bytes());
+        }
+        data
+    }
+
+    fn parse(packet: &[u8]) -> Vec<KeyEvent> {
+        let mut events = Vec::new();
+        parse_packet(packet, &mut events);
+        events
+    }
+
+    fn event(key: u8, pressed: bool) -> KeyEvent {
+        KeyEvent { key, pressed }
+    }
+
+    #[test]
+    fn both_address_forms_are_parsed() {
+        assert_eq!(
+            parse(&message("/chip8/key", &[5, 1], &[])),
+            [event(5, true)]
+        );
+        assert_eq!(
+            parse(&message("/chip8/key", &[], &[10.0, 0.0])),
+            [event(0xA, false)]
+        );
+        assert_eq!(
+            parse(&message("/chip8/key/c", &[], &[0.5])),
+            [event(0xC, true)]
+        );
+        assert_eq!(
+            parse(&message("/chip8/key/F", &[0], &[])),
+            [event(0xF, false)]
+        );
+    }
+
+    #[test]
+    fn invalid_messages_are_ignored() {
+        for packet in &[
+            message("/chip8/key", &[16, 1], &[]),
+            message("/chip8/key", &[-1, 1], &[]),
+            message("/chip8/key", &[5], &[]),
+            message("/chip8/key/10", &[1], &[]),
+            message("/chip8/keys/1", &[1], &[]),
+            message("/other/key", &[1, 1], &[]),
+            string("/chip8/key"),
+            message("/chip8/key", &[5, 1], &[])[..20].to_vec(),
+            [string("/chip8/key/1"), string(",s"), string("on")].concat(),
+        ] {
+            assert_eq!(parse(packet), [], "{:?}", packet);
+        }
+    }
+
+    #[test]
+    fn bundles_are_parsed_in_order() {
+        let element = |packet: Vec<u8>| [(packet.len() as u32).to_be_bytes().to_vec(), packet];
+        let mut bundle = string("#bundle");
+        bundle.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1]);
+        bundle.extend(element(message("/chip8/key/1", &[1], &[])).concat());
+        bundle.extend(element(message("/chip8/key/2", &[1], &[])).concat());
+        assert_eq!(parse(&bundle), [event(1, true), event(2, true)]);
+
+        // An element larger than the rest of the bundle ends it
+        let mut truncated = bundle.clone();
+        truncated.extend_from_slice(&[0, 0, 1, 0, 0]);
+        assert_eq!(parse(&truncated), [event(1, true), event(2, true)]);
+    }
+}
@@ -0,0 +1,133 @@
+//! Key events from a hardware keypad connected through a serial adapter.
+//!
+//! The keypad sends one line per change using the same text protocol as the other drivers
+//! (`+K` / `-K`), and may send `=XXXX` with a 16-bit hexadecimal mask of the held keys (bit `K` for
+//! key `K`) to resynchronize the whole state. The port should be configured beforehand, e.g. with
+//! `stty -F /dev/ttyUSB0 115200 raw`.
+
+use super::KeyEvent;
+use std::fs;
+use std::io;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::thread;
+use std::time;
+
+/// Time to wait before trying to reopen a port that was disconnected
+const RECONNECT_DELAY: time::Duration = time::Duration::from_secs(1);
+
+/// Spawn a thread that reads key events from the serial port at the given path. Fails if the port
+/// cannot be opened. The port is reopened whenever it is unplugged, and all keys are released in
+/// the meantime.
+pub fn spawn_serial(path: PathBuf) -> io::Result<mpsc::Receiver<KeyEvent>> {
+    let mut port = fs::File::open(&path)?;
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || loop {
+        for line in BufReader::new(port).lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(_) => break,
+            };
+            for event in parse_line(&line) {
+                if tx.send(event).is_err() {
+                    return;
+                }
+            }
+        }
+        if release_all(&tx).is_err() {
+            return;
+        }
+        // Wait for the keypad to be plugged in again
+        port = loop {
+            thread::sleep(RECONNECT_DELAY);
+            if let Ok(port) = fs::File::open(&path) {
+                break port;
+            }
+        };
+    });
+    Ok(rx)
+}
+
+/// Parse a line of the serial protocol into the key events it describes
+fn parse_line(line: &str) -> Vec<KeyEvent> {
+    let line = line.trim();
+    match line.strip_prefix('=') {
+        Some(mask) if mask.starts_with('+') => Vec::new(),
+        Some(mask) => match u16::from_str_radix(mask, 16) {
+            Ok(mask) => (0..16)
+                .map(|key| KeyEvent {
+                    key,
+                    pressed: mask & (1 << key) != 0,
+                })
+                .collect(),
+            Err(_) => Vec::new(),
+        },
+        None => KeyEvent::parse(line).into_iter().collect(),
+    }
+}
+
+fn release_all(tx: &mpsc::Sender<KeyEvent>) -> Result<(), mpsc::SendError<KeyEvent>> {
+    for key in 0..16 {
+        tx.send(KeyEvent {
+            key,
+            pressed: false,
+        })?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_line;
+    use super::KeyEvent;
+
+    /// The keys pressed by the events of a line, or None if it has no event
+    fn held(line: &str) -> Option<Vec<u8>> {
+        let events = parse_line(line);
+        if events.is_empty() {
+            return None;
+        }
+        assert_eq!(events.len(), 16, "a mask sets every key");
+        Some(
+            events
+                .iter()
+                .enumerate()
+                .inspect(|(idx, event)| assert_eq!(event.key as usize, *idx))
+                .filter(|(_, event)| event.pressed)
+                .map(|(_, event)| event.key)
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn masks_set_the_state_of_every_key() {
+        assert_eq!(held("=0000"), Some(vec![]));
+        assert_eq!(held("=0021"), Some(vec![0x0, 0x5]));
+        assert_eq!(held("=8000"), Some(vec![0xF]));
+        assert_eq!(held("=ffff"), Some((0..16).collect()));
+        assert_eq!(held(" =1\r"), Some(vec![0x0]));
+    }
+
+    #[test]
+    fn invalid_masks_are_ignored() {
+        for line in &["=", "=10000", "=00g0", "=+FF", "=-1", "= 21"] {
+            assert_eq!(held(line), None, "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn single_keys_use_the_text_protocol() {
+        let event = |key, pressed| vec![KeyEvent { key, pressed }];
+        assert_eq!(parse_line("+a"), event(0xA, true));
+        assert_eq!(parse_line("-3"), event(0x3, false));
+        assert_eq!(parse_line("# =FFFF"), []);
+    }
+
+    #[test]
+    fn missing_ports_are_reported() {
+        let err = super::spawn_serial("/nonexistent/ttyUSB0".into()).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+    }
+}
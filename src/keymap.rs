@@ -0,0 +1,129 @@
+//! Bindings of host keys to keypad keys, on top of the default QWERTY layout of the frontends.
+//! Host keys are named as in ggez (`Up`, `Space`, `Key1`, `Numpad8`, ...) and keypad keys are
+//! given as a hexadecimal digit. A binding replaces the default meaning of its host key. Gamepad
+//! buttons are bound the same way in a map of their own (`DPadUp`, `South`, `Start`, ...).
+//!
+//! ```toml
+//! Up = "2"
+//! Left = "4"
+//! Right = "6"
+//! Down = "8"
+//! ```
+
+use crate::storage::Storage;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+/// Deflection of a stick past which it presses the D-pad
+pub const STICK_THRESHOLD: f32 = 0.5;
+
+/// Keypad keys bound to host keys
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(
+    try_from = "BTreeMap<String, String>",
+    into = "BTreeMap<String, String>"
+)]
+pub struct KeyMap {
+    bindings: BTreeMap<String, u8>,
+}
+
+impl KeyMap {
+    /// Create a map without bindings
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The default bindings of gamepad buttons: the D-pad is 2, 4, 6 and 8, which most games use
+    /// as arrows, and the face buttons are 5, 0, A and B
+    pub fn gamepad() -> Self {
+        let mut map = Self::new();
+        for (button, key) in [
+            ("DPadUp", 0x2),
+            ("DPadLeft", 0x4),
+            ("DPadRight", 0x6),
+            ("DPadDown", 0x8),
+            ("South", 0x5),
+            ("East", 0x0),
+            ("West", 0xA),
+            ("North", 0xB),
+        ] {
+            map.bind(button, key);
+        }
+        map
+    }
+
+    /// Read bindings from a TOML file
+    pub fn from_file(storage: &dyn Storage, path: &Path) -> Result<Self, KeyMapError> {
+        let text = storage.read_to_string(path).map_err(KeyMapError::Io)?;
+        toml::from_str(&text).map_err(KeyMapError::Parse)
+    }
+
+    /// Bind a host key to a keypad key
+    pub fn bind(&mut self, host: &str, key: u8) {
+        self.bindings.insert(host.to_string(), key & 0xF);
+    }
+
+    /// The keypad key bound to a host key
+    pub fn get(&self, host: &str) -> Option<u8> {
+        self.bindings.get(host).copied()
+    }
+
+    /// Add the bindings of another map, replacing those of the same host keys
+    pub fn extend(&mut self, other: &KeyMap) {
+        self.bindings.extend(
+            other
+                .bindings
+                .iter()
+                .map(|(host, key)| (host.clone(), *key)),
+        );
+    }
+}
+
+impl TryFrom<BTreeMap<String, String>> for KeyMap {
+    type Error = String;
+
+    fn try_from(bindings: BTreeMap<String, String>) -> Result<Self, Self::Error> {
+        let bindings = bindings
+            .into_iter()
+            .map(|(host, key)| match u8::from_str_radix(&key, 16) {
+                Ok(digit) if key.len() == 1 => Ok((host, digit)),
+                _ => Err(format!("invalid keypad key {} for {}", key, host)),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { bindings })
+    }
+}
+
+impl From<KeyMap> for BTreeMap<String, String> {
+    fn from(map: KeyMap) -> Self {
+        map.bindings
+            .into_iter()
+            .map(|(host, key)| (host, format!("{:X}", key)))
+            .collect()
+    }
+}
+
+/// Reasons for a key map to be rejected
+#[derive(Debug)]
+pub enum KeyMapError {
+    /// The file could not be read
+    Io(io::Error),
+    /// The file is not valid
+    Parse(toml::de::Error),
+}
+
+impl fmt::Display for KeyMapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "could not read the key map: {}", err),
+            Self::Parse(err) => write!(f, "invalid key map: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for KeyMapError {}
@@ -0,0 +1,155 @@
+//! The layout of the streamer mode, where the window is meant to be captured as a source of a
+//! streaming program like OBS. The game, the keypad and the statistics are drawn at fixed places
+//! on a solid background that is keyed out, so the capture needs no cropping. The layout is read
+//! from a TOML file with `--streamer-layout`, missing values keep their default:
+//!
+//! ```toml
+//! width = 1280
+//! height = 720
+//! background = "00FF00"
+//!
+//! [screen]
+//! x = 32
+//! y = 32
+//! size = 16
+//!
+//! [keypad]
+//! x = 1088
+//! y = 32
+//! size = 40
+//!
+//! [stats]
+//! x = 1088
+//! y = 248
+//! size = 24
+//! ```
+//!
+//! The size of the screen is the size of a CHIP-8 pixel, the size of the keypad the size of a key
+//! and the size of the statistics the height of their text. `show_keypad = false` and
+//! `show_stats = false` leave the keypad and the statistics out.
+
+use crate::console::parse_color;
+use serde::Deserialize;
+use std::convert::TryFrom;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// A color of the layout, as `0xRRGGBB`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Color(pub u32);
+
+impl TryFrom<String> for Color {
+    type Error = String;
+
+    fn try_from(color: String) -> Result<Self, Self::Error> {
+        parse_color(&color).map(Self)
+    }
+}
+
+/// Where an element is drawn, in pixels of the window
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Placement {
+    /// Left edge
+    pub x: f32,
+    /// Top edge
+    pub y: f32,
+    /// Size of the parts of the element
+    pub size: f32,
+}
+
+/// The layout of the window in the streamer mode
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Layout {
+    /// Width of the window
+    pub width: f32,
+    /// Height of the window
+    pub height: f32,
+    /// The chroma key, drawn everywhere but on the elements
+    pub background: Color,
+    /// The screen of the game
+    pub screen: Placement,
+    /// The keypad with the held keys highlighted
+    pub keypad: Placement,
+    /// The frame, instruction and draw counters
+    pub stats: Placement,
+    /// False if the keypad is left out
+    pub show_keypad: bool,
+    /// False if the statistics are left out
+    pub show_stats: bool,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self {
+            width: 1280.0,
+            height: 720.0,
+            background: Color(0x00FF00),
+            screen: Placement {
+                x: 32.0,
+                y: 32.0,
+                size: 16.0,
+            },
+            keypad: Placement {
+                x: 1088.0,
+                y: 32.0,
+                size: 40.0,
+            },
+            stats: Placement {
+                x: 1088.0,
+                y: 248.0,
+                size: 24.0,
+            },
+            show_keypad: true,
+            show_stats: true,
+        }
+    }
+}
+
+impl Layout {
+    /// Read a layout from a TOML file
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, LayoutError> {
+        let text = fs::read_to_string(path).map_err(LayoutError::Io)?;
+        let layout: Self = toml::from_str(&text).map_err(LayoutError::Parse)?;
+        let sizes = [
+            layout.width,
+            layout.height,
+            layout.screen.size,
+            layout.keypad.size,
+            layout.stats.size,
+        ];
+        if sizes.iter().any(|size| *size <= 0.0) {
+            return Err(LayoutError::Invalid(
+                "sizes must be greater than zero".to_string(),
+            ));
+        }
+        Ok(layout)
+    }
+}
+
+/// Reasons for a layout to be rejected
+#[derive(Debug)]
+pub enum LayoutError {
+    /// The file could not be read
+    Io(io::Error),
+    /// The file is not valid
+    Parse(toml::de::Error),
+    /// The layout cannot be drawn
+    Invalid(String),
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "could not read the layout: {}", err),
+            Self::Parse(err) => write!(f, "invalid layout: {}", err),
+            Self::Invalid(reason) => write!(f, "invalid layout: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for LayoutError {}
@@ -3,23 +3,109 @@
 
 #![deny(missing_docs)]
 
-use ggez::event::EventHandler;
-use ggez::event::KeyCode;
-use ggez::event::KeyMods;
-use ggez::graphics;
-use ggez::graphics::Rect;
-use ggez::timer;
-use ggez::Context;
-use ggez::GameResult;
-use rand::prelude::*;
+pub mod asm;
+pub mod audit;
+#[cfg(feature = "blit")]
+pub mod blit;
+pub mod bundled;
+pub mod clock;
+pub mod compliance;
+pub mod conformance;
+pub mod console;
+pub mod controls;
+pub mod cost;
+pub mod datapaths;
+pub mod debugger;
+pub mod diagnostics;
+pub mod disasm;
+pub mod fault;
+#[cfg(feature = "fbdev")]
+pub mod fbdev;
+pub mod i18n;
+#[cfg(feature = "fault-injection")]
+pub mod inject;
+pub mod input;
+pub mod keymap;
+pub mod layout;
+pub mod library;
+#[cfg(feature = "libretro")]
+pub mod libretro;
+pub mod memory;
+pub mod movie;
+pub mod pacer;
+pub mod palette;
+pub mod patch;
+pub mod peripheral;
+pub mod phosphor;
+pub mod playlist;
+pub mod pool;
+pub mod quirks;
+#[cfg(feature = "recording")]
+pub mod recording;
+pub mod repro;
+pub mod rewind;
+pub mod rng;
+pub mod rom;
+pub mod romconfig;
+pub mod runner;
+pub mod savestate;
+pub mod screenshot;
+pub mod shell;
+pub mod sonify;
+pub mod splash;
+pub mod stats;
+pub mod storage;
+pub mod symbols;
+pub mod testrom;
+pub mod text;
+pub mod trace;
+pub mod trap;
+#[cfg(feature = "tui")]
+pub mod tui;
+#[cfg(feature = "web")]
+pub mod web;
+#[cfg(feature = "window")]
+pub mod window;
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::hash::Hash;
+use std::hash::Hasher;
 use std::time;
 
 /// Screen width of chip-8
 pub const CHIP8_SCREEN_WIDTH: usize = 64;
 /// Screen height of chip-8
 pub const CHIP8_SCREEN_HEIGHT: usize = 32;
-/// Size of each pixel when render to the host machine
-pub const PIXEL_SIZE: i32 = 16;
+/// Screen width of the SUPER-CHIP high resolution mode
+pub const SCHIP_SCREEN_WIDTH: usize = 128;
+/// Screen height of the SUPER-CHIP high resolution mode
+pub const SCHIP_SCREEN_HEIGHT: usize = 64;
+/// Number of instructions executed per second
+pub const TICKS_PER_SEC: u32 = 500;
+/// Address where programs are loaded
+pub const PROGRAM_START: u16 = 0x200;
+/// Size of the memory available to programs, from [`PROGRAM_START`] to the end of the 4K
+pub const PROGRAM_SIZE: usize = 0x1000 - PROGRAM_START as usize;
+/// Period of the delay and the sound timers, which count down at 60 Hz
+pub const TIMER_PERIOD: time::Duration = time::Duration::from_nanos(1_000_000_000 / 60);
+/// Most timer periods counted down at once by [`Chip8::run_timers`]
+pub const MAX_TIMER_CATCH_UP: u32 = 4;
+/// A stand-in for the image of the COSMAC VIP interpreter, which is not distributed with the
+/// emulator, see [`Chip8::load_interpreter_image`]. Past the font, every instruction below 0x200
+/// is a return (00EE), so a program that calls into the interpreter area comes back instead of
+/// running through zeros, and reads of the area see code rather than an empty memory.
+pub const BUILTIN_INTERPRETER_IMAGE: [u8; 0x200] = {
+    let mut image = [0; 0x200];
+    let mut idx = 1;
+    while idx < image.len() {
+        image[idx] = 0xEE;
+        idx += 2;
+    }
+    image
+};
 
 /// This struct represents the CPU structure of CHIP-8 systems
 pub struct Chip8 {
@@ -44,12 +130,101 @@ pub struct Chip8 {
     /// Graphics system, one instruction is used the draw sprite to the
     /// screen; drawing is done in XOR mode, VF register is set if a
     /// pixel is turned off.
-    gfx: [bool; CHIP8_SCREEN_WIDTH * CHIP8_SCREEN_HEIGHT],
+    gfx: Vec<bool>,
+    /// True in the SUPER-CHIP high resolution mode
+    hires: bool,
+    /// The SUPER-CHIP user flags (HP-48 RPL flags), kept across resets
+    rpl: [u8; 8],
     /// Current state of the HEX-based keypad
     key: [bool; 16],
     /// True of the graphics memory is recently updated
     gfx_updated: bool,
-    timing: time::Instant,
+    /// Rows of the screen changed since they were last taken, bit N for row N
+    dirty_rows: u64,
+    /// When the timers were last run
+    timing: clock::Instant,
+    /// Time not yet counted down by the timers, less than a period unless it is caught up
+    timer_elapsed: time::Duration,
+    /// [`Chip8::run_timers`] does nothing, the timers count down only with
+    /// [`Chip8::next_frame`]
+    manual_timers: bool,
+    /// Seed of the random number generator, it is restored on reset
+    seed: u64,
+    /// Random number generator used by CXKK
+    rng: rng::Rng,
+    /// Source of the bytes of the CXKK instruction
+    rng_source: rng::RandomSource,
+    /// Input sources that are merged into the keypad state
+    inputs: input::Inputs,
+    /// Devices mapped into memory, updated with the inputs
+    peripherals: Vec<Box<dyn peripheral::Peripheral>>,
+    /// Statistics of the current session
+    stats: stats::Stats,
+    /// Compatibility notes collected since the emulator was created
+    diagnostics: diagnostics::Diagnostics,
+    /// True if I was last changed by FX55 or FX65, whose effect on it depends on a quirk
+    index_after_load_store: bool,
+    /// True while a sprite was drawn and the display_wait quirk holds the machine until the
+    /// next frame
+    waiting_for_frame: bool,
+    /// The key latched by a FX0A that waits for its release, see
+    /// [`quirks::Quirks::key_release`]
+    key_latched: Option<usize>,
+    /// Pattern that fills the memory on reset
+    mem_fill: memory::MemoryFill,
+    /// True for each byte of memory that has been written since the last reset
+    mem_written: [bool; 4096],
+    /// Report the first read of every byte that has never been written
+    warn_uninit: bool,
+    /// Warn about loops that poll the delay timer, see [`Chip8::set_warn_timer_polling`]
+    warn_timer_polling: bool,
+    /// Receives a line for every executed instruction, see [`Chip8::set_trace`]
+    trace: Option<trace::TraceWriter>,
+    /// The latest short delay and where it was read back
+    timer_poll: Option<TimerPoll>,
+    /// Addresses of the polling loops that have already been reported
+    timer_loops: BTreeSet<u16>,
+    /// Warnings made since the frontend last took them
+    warnings: Vec<fault::Warning>,
+    /// Halt on the first irregularity instead of tolerating it
+    strict: bool,
+    /// The irregularity that halted the machine in strict mode
+    fault: Option<fault::Fault>,
+    /// Length of the loaded program
+    prog_len: usize,
+    /// The program as it was loaded, kept for [`Chip8::reload`]
+    rom: Vec<u8>,
+    /// Patches applied since the program was loaded, kept for the reproduction bundles
+    patches: Vec<patch::Patch>,
+    /// The image loaded below 0x200, kept for [`Chip8::reload`]
+    interpreter_image: Vec<u8>,
+    /// True for each byte of memory that has been executed since the last reset
+    mem_executed: [bool; 4096],
+    /// Latest write into already executed code, keyed by the written address
+    code_modifications: BTreeMap<u16, CodeModification>,
+    /// Behaviours that differ between interpreters
+    quirks: quirks::Quirks,
+    /// Records the run for a reproduction bundle
+    recorder: Option<repro::Recorder>,
+    /// Sets the keys of a movie instead of the input sources
+    player: Option<movie::Player>,
+    /// Number of ticks taken by each opcode
+    costs: cost::CostTable,
+    /// Remaining ticks before the next instruction is fetched
+    busy: u32,
+    /// Emulates the routines of the original interpreter below 0x200
+    interpreter_hook: Option<InterpreterHook>,
+    /// Told about every sprite drawn
+    draw_hook: Option<DrawHook>,
+    /// Show the splash on reset
+    splash: bool,
+    /// Remaining ticks of the splash, the program starts once they run out
+    splash_ticks: u32,
+    /// Names of the addresses of the program, shown in the state dump
+    symbols: symbols::Symbols,
+    /// Faults that are deliberately injected
+    #[cfg(feature = "fault-injection")]
+    injector: Option<inject::Injector>,
 }
 
 impl Default for Chip8 {
@@ -63,255 +238,1116 @@ impl Default for Chip8 {
             v: [0; 16],
             mem: [0; 4096],
             stack: [0; 16],
-            gfx: [false; CHIP8_SCREEN_WIDTH * CHIP8_SCREEN_HEIGHT],
+            gfx: vec![false; CHIP8_SCREEN_WIDTH * CHIP8_SCREEN_HEIGHT],
+            hires: false,
+            rpl: [0; 8],
             key: [false; 16],
             gfx_updated: false,
-            timing: time::Instant::now(),
+            dirty_rows: 0,
+            timing: clock::Instant::now(),
+            timer_elapsed: time::Duration::ZERO,
+            manual_timers: false,
+            seed: 0,
+            rng: rng::Rng::new(0),
+            rng_source: rng::RandomSource::default(),
+            inputs: input::Inputs::default(),
+            peripherals: Vec::new(),
+            stats: stats::Stats::default(),
+            diagnostics: diagnostics::Diagnostics::default(),
+            index_after_load_store: false,
+            waiting_for_frame: false,
+            key_latched: None,
+            mem_fill: memory::MemoryFill::default(),
+            mem_written: [false; 4096],
+            warn_uninit: false,
+            warn_timer_polling: false,
+            trace: None,
+            timer_poll: None,
+            timer_loops: BTreeSet::new(),
+            warnings: Vec::new(),
+            strict: false,
+            fault: None,
+            prog_len: 0,
+            rom: Vec::new(),
+            patches: Vec::new(),
+            interpreter_image: Vec::new(),
+            mem_executed: [false; 4096],
+            code_modifications: BTreeMap::new(),
+            quirks: quirks::Quirks::default(),
+            recorder: None,
+            player: None,
+            costs: cost::CostTable::default(),
+            busy: 0,
+            interpreter_hook: None,
+            draw_hook: None,
+            splash: false,
+            splash_ticks: 0,
+            symbols: symbols::Symbols::default(),
+            #[cfg(feature = "fault-injection")]
+            injector: None,
         }
     }
 }
 
-impl EventHandler for Chip8 {
-    fn update(&mut self, ctx: &mut Context) -> GameResult<()> {
-        const TICKS_PER_SEC: u32 = 500;
-        while timer::check_update_time(ctx, TICKS_PER_SEC) {
-            self.tick();
+impl Chip8 {
+    /// Set the state of the system to the intial state
+    pub fn reset(&mut self) {
+        self.i = 0;
+        self.pc = 0x200; // program begins at 0x200
+        self.sp = 0;
+        self.dt = 0;
+        self.st = 0;
+        self.v = [0; 16];
+        self.mem_fill.fill(&mut self.mem, self.seed);
+        self.mem_written = [false; 4096];
+        self.mem_executed = [false; 4096];
+        self.code_modifications.clear();
+        self.timer_poll = None;
+        self.timer_loops.clear();
+        self.busy = 0;
+        self.waiting_for_frame = false;
+        self.index_after_load_store = false;
+        self.key_latched = None;
+        self.fault = None;
+        self.stack = [0; 16];
+        self.set_hires(false); // clear display
+        self.key = [false; 16]; // clear display
+        self.gfx_updated = false;
+        self.splash_ticks = 0;
+        if self.splash {
+            let (width, height) = self.screen_size();
+            splash::draw(&mut self.gfx, width, height);
+            self.splash_ticks = splash::SPLASH_TICKS;
+            self.gfx_updated = true;
         }
+        self.timing = clock::Instant::now();
+        self.timer_elapsed = time::Duration::ZERO;
+        self.rng = rng::Rng::with_source(self.rng_source.clone(), self.seed);
+        // Load font sprites to the first 80 bytes of the memory.
+        // The first four nibble is used to determine what the character is
+        FONT.iter()
+            .chain(BIG_FONT.iter())
+            .enumerate()
+            .for_each(|(i, b)| {
+                self.mem[i] = *b;
+                self.mem_written[i] = true;
+            });
+    }
+
+    /// Copy a program into memory at 0x200, fails if it does not fit below 0x1000
+    pub fn load(&mut self, program: &[u8]) -> Result<(), LoadError> {
+        if program.len() > PROGRAM_SIZE {
+            return Err(LoadError::TooLarge { len: program.len() });
+        }
+        let start = PROGRAM_START as usize;
+        self.mem[start..start + program.len()].copy_from_slice(program);
+        self.mark_written(start, program.len());
+        self.prog_len = program.len();
+        self.rom = program.to_vec();
+        self.patches.clear();
         Ok(())
     }
 
-    fn draw(&mut self, ctx: &mut ggez::Context) -> ggez::GameResult {
-        if self.gfx_updated {
-            self.gfx_updated = false;
-            graphics::clear(ctx, [0.0, 0.0, 0.0, 1.0].into());
-            for y in 0..CHIP8_SCREEN_HEIGHT {
-                for x in 0..CHIP8_SCREEN_WIDTH {
-                    if self.gfx[x + y * CHIP8_SCREEN_WIDTH] {
-                        let rect = graphics::Mesh::new_rectangle(
-                            ctx,
-                            graphics::DrawMode::fill(),
-                            Rect::new_i32(
-                                x as i32 * PIXEL_SIZE,
-                                y as i32 * PIXEL_SIZE,
-                                PIXEL_SIZE,
-                                PIXEL_SIZE,
-                            ),
-                            (1.0, 1.0, 1.0, 1.0).into(),
-                        )?;
-                        graphics::draw(ctx, &rect, (ggez::mint::Point2 { x: 0.0, y: 0.0 },))?;
+    /// The program as it was last loaded, without the changes made to the memory since
+    pub fn rom(&self) -> &[u8] {
+        &self.rom
+    }
+
+    /// Reset the machine and load the last program and interpreter image again, as if the
+    /// machine was switched off and on. The settings, the hooks and the input sources are kept.
+    pub fn reload(&mut self) {
+        let rom = std::mem::take(&mut self.rom);
+        let image = std::mem::take(&mut self.interpreter_image);
+        self.reset();
+        self.load_interpreter_image(&image);
+        // The program was loaded once already, so it fits
+        let _ = self.load(&rom);
+    }
+
+    /// Show the name and version of the emulator for [`splash::SPLASH_TICKS`] ticks, or until a
+    /// key is pressed, before the program starts. It takes effect on the next reset.
+    pub fn set_splash(&mut self, splash: bool) {
+        self.splash = splash;
+    }
+
+    /// Name the addresses of the program in the state dump, see [`Chip8::dump`]
+    pub fn set_symbols(&mut self, symbols: symbols::Symbols) {
+        self.symbols = symbols;
+    }
+
+    /// Write a line for every executed instruction with its address, its mnemonic and the
+    /// registers it changed, see [`trace`]. `None` stops tracing.
+    pub fn set_trace(&mut self, writer: Option<trace::TraceWriter>) {
+        self.trace = writer;
+    }
+
+    /// Set the seed of the random number generator, it takes effect on the next reset
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+
+    /// The seed of the random number generator
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Set the behaviours that differ between interpreters
+    pub fn set_quirks(&mut self, quirks: quirks::Quirks) {
+        self.quirks = quirks;
+    }
+
+    /// The behaviours that differ between interpreters
+    pub fn quirks(&self) -> quirks::Quirks {
+        self.quirks
+    }
+
+    /// Set the source of the bytes of the CXKK instruction, it takes effect on the next reset
+    pub fn set_random_source(&mut self, source: rng::RandomSource) {
+        self.rng_source = source;
+    }
+
+    /// The source of the bytes of the CXKK instruction
+    pub fn random_source(&self) -> &rng::RandomSource {
+        &self.rng_source
+    }
+
+    /// Set the number of ticks taken by each opcode, the default table takes one tick for every
+    /// instruction
+    pub fn set_costs(&mut self, costs: cost::CostTable) {
+        self.costs = costs;
+    }
+
+    /// The number of ticks taken by each opcode
+    pub fn costs(&self) -> &cost::CostTable {
+        &self.costs
+    }
+
+    /// The patches applied since the program was loaded, in order
+    pub fn patches(&self) -> &[patch::Patch] {
+        &self.patches
+    }
+
+    /// Apply a patch to the loaded program. The patch is validated as a whole before anything
+    /// is changed.
+    pub fn apply_patch(&mut self, patch: &patch::Patch) -> Result<(), patch::PatchError> {
+        for bytes in &patch.bytes {
+            if bytes.addr as usize + bytes.data.len() > self.mem.len() {
+                return Err(patch::PatchError::OutOfRange {
+                    addr: bytes.addr,
+                    len: bytes.data.len(),
+                });
+            }
+        }
+        for (name, value) in &patch.registers {
+            let max = match name.as_str() {
+                "i" | "pc" => 0xFFF,
+                "dt" | "st" => 0xFF,
+                _ if Self::register_index(name).is_some() => 0xFF,
+                _ => return Err(patch::PatchError::UnknownRegister(name.clone())),
+            };
+            if *value > max {
+                return Err(patch::PatchError::ValueTooLarge {
+                    register: name.clone(),
+                    value: *value,
+                });
+            }
+        }
+
+        for bytes in &patch.bytes {
+            let addr = bytes.addr as usize;
+            self.mem[addr..addr + bytes.data.len()].copy_from_slice(&bytes.data);
+            self.mark_written(addr, bytes.data.len());
+        }
+        for (name, value) in &patch.registers {
+            match name.as_str() {
+                "i" => self.i = *value,
+                "pc" => self.pc = *value,
+                "dt" => self.dt = *value as u8,
+                "st" => self.st = *value as u8,
+                _ => {
+                    if let Some(x) = Self::register_index(name) {
+                        self.v[x] = *value as u8;
                     }
                 }
             }
-            graphics::present(ctx)?;
         }
-        timer::yield_now();
+        self.patches.push(patch.clone());
         Ok(())
     }
 
-    fn key_down_event(
-        &mut self,
-        _ctx: &mut ggez::Context,
-        keycode: KeyCode,
-        _keymods: KeyMods,
-        _repeat: bool,
-    ) {
-        match keycode {
-            KeyCode::Key1 => {
-                self.key[0x1] = true;
-            }
-            KeyCode::Key2 => {
-                self.key[0x2] = true;
-            }
-            KeyCode::Key3 => {
-                self.key[0x3] = true;
-            }
-            KeyCode::Key4 => {
-                self.key[0xC] = true;
-            }
-            KeyCode::Q => {
-                self.key[0x4] = true;
-            }
-            KeyCode::W => {
-                self.key[0x5] = true;
-            }
-            KeyCode::E => {
-                self.key[0x6] = true;
-            }
-            KeyCode::R => {
-                self.key[0xD] = true;
-            }
-            KeyCode::A => {
-                self.key[0x7] = true;
-            }
-            KeyCode::S => {
-                self.key[0x8] = true;
-            }
-            KeyCode::D => {
-                self.key[0x9] = true;
-            }
-            KeyCode::F => {
-                self.key[0xE] = true;
-            }
-            KeyCode::Z => {
-                self.key[0xA] = true;
-            }
-            KeyCode::X => {
-                self.key[0x0] = true;
-            }
-            KeyCode::C => {
-                self.key[0xB] = true;
-            }
-            KeyCode::V => {
-                self.key[0xF] = true;
-            }
-            _ => (),
+    /// Index of a general purpose register named `v0` to `vf`
+    fn register_index(name: &str) -> Option<usize> {
+        let digit = name.strip_prefix('v')?;
+        if digit.len() != 1 {
+            return None;
         }
+        usize::from_str_radix(digit, 16).ok()
     }
 
-    fn key_up_event(&mut self, _ctx: &mut ggez::Context, keycode: KeyCode, _keymods: KeyMods) {
-        match keycode {
-            KeyCode::Key1 => {
-                self.key[0x1] = false;
-            }
-            KeyCode::Key2 => {
-                self.key[0x2] = false;
-            }
-            KeyCode::Key3 => {
-                self.key[0x3] = false;
-            }
-            KeyCode::Key4 => {
-                self.key[0xC] = false;
-            }
-            KeyCode::Q => {
-                self.key[0x4] = false;
-            }
-            KeyCode::W => {
-                self.key[0x5] = false;
-            }
-            KeyCode::E => {
-                self.key[0x6] = false;
+    /// The program region of the live memory, from 0x200 to the end of the loaded program. It
+    /// includes any change made since loading, such as self-modified code or patched bytes.
+    pub fn export_rom(&self) -> &[u8] {
+        &self.mem[0x200..0x200 + self.prog_len]
+    }
+
+    /// Set the pattern that fills the program memory on the next reset
+    pub fn set_memory_fill(&mut self, fill: memory::MemoryFill) {
+        self.mem_fill = fill;
+    }
+
+    /// The pattern that fills the program memory on reset
+    pub fn memory_fill(&self) -> memory::MemoryFill {
+        self.mem_fill
+    }
+
+    /// Warn the first time each byte of memory is read without being written first, see
+    /// [`Chip8::take_warnings`]
+    pub fn set_warn_uninit(&mut self, warn: bool) {
+        self.warn_uninit = warn;
+    }
+
+    /// Warn the first time a loop polls the delay timer right after it was set to a short delay,
+    /// see [`Chip8::take_warnings`]. How many times such a loop runs depends on the phase of the
+    /// 60Hz timer when the delay is set, which is the usual cause of a game running at different
+    /// speeds on different emulators.
+    pub fn set_warn_timer_polling(&mut self, warn: bool) {
+        self.warn_timer_polling = warn;
+    }
+
+    /// Reset VF after a logic instruction if the quirk is enabled
+    fn reset_vf(&mut self) {
+        if self.quirks.vf_reset {
+            self.v[0xF] = 0;
+        }
+    }
+
+    /// Called on a backward jump, report the loop if it polls a short delay
+    fn check_timer_loop(&mut self, target: u16) {
+        const MAX_LOOP_LEN: u16 = 8;
+        let jump_addr = self.pc.wrapping_sub(2);
+        let poll = match self.timer_poll {
+            Some(poll) if self.warn_timer_polling => poll,
+            _ => return,
+        };
+        let read_addr = match poll.read_addr {
+            Some(addr) => addr,
+            None => return,
+        };
+        if target <= read_addr
+            && read_addr < jump_addr
+            && jump_addr - target <= MAX_LOOP_LEN
+            && self.timer_loops.insert(target)
+        {
+            self.warnings.push(fault::Warning::TimerPolling {
+                addr: target,
+                delay: poll.value,
+                set_addr: poll.set_addr,
+            });
+        }
+    }
+
+    /// Halt on the first irregularity (unknown opcode, out-of-range memory access, stack misuse,
+    /// odd program counter, write below 0x200) with a report, instead of tolerating it
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// Whether the machine halts on the first irregularity
+    pub fn strict(&self) -> bool {
+        self.strict
+    }
+
+    /// Ignore [`Chip8::run_timers`] so that the timers count down only with
+    /// [`Chip8::next_frame`], for runs whose result must not depend on the speed of the host
+    pub fn set_manual_timers(&mut self, manual: bool) {
+        self.manual_timers = manual;
+    }
+
+    /// The irregularity that halted the machine in strict mode, if any
+    pub fn fault(&self) -> Option<fault::Fault> {
+        self.fault
+    }
+
+    /// The warnings made since the last call, for the frontends to print
+    pub fn take_warnings(&mut self) -> Vec<fault::Warning> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    /// Queue a warning for the frontend, for the hooks of the crate
+    pub(crate) fn warn(&mut self, warning: fault::Warning) {
+        self.warnings.push(warning);
+    }
+
+    /// Record an irregularity, the machine halts after the current instruction in strict mode
+    fn report(&mut self, fault: fault::Fault) {
+        if self.strict && self.fault.is_none() {
+            self.fault = Some(fault);
+        }
+    }
+
+    /// Halt the machine after the current instruction with a fault, whatever the strict mode
+    pub fn fail(&mut self, fault: fault::Fault) {
+        if self.fault.is_none() {
+            self.fault = Some(fault);
+        }
+    }
+
+    fn mark_written(&mut self, addr: usize, len: usize) {
+        self.mem_written[addr..addr + len]
+            .iter_mut()
+            .for_each(|w| *w = true);
+    }
+
+    /// Check that an address is within memory and wrap it around if it is not
+    fn mem_addr(&mut self, addr: usize) -> usize {
+        if addr > 0xFFF {
+            self.report(fault::Fault::MemoryOutOfRange { addr });
+        }
+        addr & 0xFFF
+    }
+
+    /// Whether a key checked by EX9E or EXA1 is pressed, keys past 0xF wrap around
+    fn key_pressed(&mut self, key: u8) -> bool {
+        if key > 0xF {
+            self.report(fault::Fault::KeyOutOfRange { key });
+        }
+        self.key[(key & 0xF) as usize]
+    }
+
+    fn read_mem(&mut self, addr: usize) -> u8 {
+        let addr = self.mem_addr(addr);
+        if self.warn_uninit && !self.mem_written[addr] {
+            self.warnings
+                .push(fault::Warning::UninitializedRead { addr, pc: self.pc });
+            // Only warn once for each byte
+            self.mem_written[addr] = true;
+        }
+        self.mem[addr]
+    }
+
+    fn write_mem(&mut self, addr: usize, value: u8) {
+        let addr = self.mem_addr(addr);
+        if addr < 0x200 {
+            self.report(fault::Fault::WriteBelowProgram { addr });
+        }
+        if self.mem_executed[addr] && self.mem[addr] != value {
+            self.code_modifications.insert(
+                addr as u16,
+                CodeModification {
+                    cycle: self.stats.instructions,
+                    pc: self.pc.wrapping_sub(2),
+                    old: self.mem[addr],
+                    new: value,
+                },
+            );
+        }
+        self.mem[addr] = value;
+        self.mem_written[addr] = true;
+    }
+
+    /// Writes into memory that had already been executed, i.e. self-modifying code, keyed by the
+    /// written address. Only the latest write to each address is kept.
+    pub fn code_modifications(&self) -> &BTreeMap<u16, CodeModification> {
+        &self.code_modifications
+    }
+
+    /// Enable or disable turbo-fire for a key, see [`input::Inputs::set_turbo`]
+    pub fn set_turbo(&mut self, key: u8, rate: Option<f32>) -> Result<(), input::TurboError> {
+        self.inputs.set_turbo(key, rate)
+    }
+
+    /// Switch turbo-fire of a key on or off, returns true if it is now enabled
+    pub fn toggle_turbo(&mut self, key: u8) -> bool {
+        self.inputs.toggle_turbo(key)
+    }
+
+    /// Press or release a key of the host keyboard input source, by its index on the keypad
+    pub fn set_keyboard_key(&mut self, key: u8, pressed: bool) {
+        self.inputs.keyboard.set_key(key, pressed);
+    }
+
+    /// Add an input source, its keys are merged with the keyboard's every frame
+    pub fn attach_input(&mut self, source: Box<dyn input::InputSource>) {
+        self.inputs.attach(source);
+    }
+
+    /// Map a device into memory, it is updated every frame along with the inputs
+    pub fn attach_peripheral(&mut self, peripheral: Box<dyn peripheral::Peripheral>) {
+        self.peripherals.push(peripheral);
+    }
+
+    /// A readable report of the machine state: registers, timers, stack, the instruction at the
+    /// program counter and the screen. The program counter and the return addresses are named
+    /// after the closest label before them if symbols are set, see [`Chip8::set_symbols`].
+    pub fn dump(&self) -> String {
+        let named = |addr: u16| match self.symbols.name(addr) {
+            Some(name) => format!("{:#05X} ({})", addr, name),
+            None => format!("{:#05X}", addr),
+        };
+        let mut out = String::new();
+        writeln!(
+            out,
+            "PC: {}  I: {:#05X}  SP: {:#04X}",
+            named(self.pc),
+            self.i,
+            self.sp
+        )
+        .unwrap();
+        writeln!(out, "DT: {:#04X}  ST: {:#04X}", self.dt, self.st).unwrap();
+        for (row, regs) in self.v.chunks(8).enumerate() {
+            for (col, v) in regs.iter().enumerate() {
+                write!(out, "V{:X}: {:#04X}  ", row * 8 + col, v).unwrap();
             }
-            KeyCode::R => {
-                self.key[0xD] = false;
+            out.push('\n');
+        }
+
+        write!(out, "Stack:").unwrap();
+        if self.sp == 0 {
+            write!(out, " (empty)").unwrap();
+        }
+        for addr in self.stack[..self.sp as usize].iter().rev() {
+            write!(out, " {}", named(*addr)).unwrap();
+        }
+        out.push('\n');
+
+        let pc = self.pc as usize;
+        match self.mem.get(pc..pc + 2) {
+            Some(bytes) => {
+                let opcode = (bytes[0] as u16) << 8 | bytes[1] as u16;
+                match Inst::decode(opcode) {
+                    Ok(inst) => writeln!(out, "Next: {:04X} {}", opcode, inst).unwrap(),
+                    Err(_) => writeln!(out, "Next: {:04X} (unknown opcode)", opcode).unwrap(),
+                }
             }
-            KeyCode::A => {
-                self.key[0x7] = false;
+            None => writeln!(out, "Next: (out of memory)").unwrap(),
+        }
+
+        if !self.code_modifications.is_empty() {
+            writeln!(out, "Modified code:").unwrap();
+            for (addr, m) in &self.code_modifications {
+                writeln!(
+                    out,
+                    "  {:#05X}: {:02X} -> {:02X} at cycle {} by PC={:#05X}",
+                    addr, m.old, m.new, m.cycle, m.pc
+                )
+                .unwrap();
             }
-            KeyCode::S => {
-                self.key[0x8] = false;
+        }
+
+        let (width, _) = self.screen_size();
+        let border = "-".repeat(width);
+        writeln!(out, "+{}+", border).unwrap();
+        for row in self.gfx.chunks(width) {
+            let line: String = row.iter().map(|p| if *p { '#' } else { ' ' }).collect();
+            writeln!(out, "|{}|", line).unwrap();
+        }
+        writeln!(out, "+{}+", border).unwrap();
+        out
+    }
+
+    /// Statistics collected since the emulator was created
+    pub fn stats(&self) -> &stats::Stats {
+        &self.stats
+    }
+
+    /// Compatibility notes collected since the emulator was created, see [`diagnostics`]
+    pub fn diagnostics(&self) -> &diagnostics::Diagnostics {
+        &self.diagnostics
+    }
+
+    /// The kinds of compatibility notes made for the first time since the last call, for the
+    /// frontends to show once
+    pub fn take_new_diagnostics(&mut self) -> Vec<diagnostics::Diagnostic> {
+        self.diagnostics.take_new()
+    }
+
+    /// Run a single tick, executing an instruction unless the previous one is still busy. The
+    /// timers are not updated, see [`Chip8::run_timers`] and [`Chip8::next_frame`].
+    pub fn step(&mut self) {
+        if self.splash_ticks > 0 {
+            self.splash_ticks -= 1;
+            if self.splash_ticks == 0 {
+                self.set_hires(false);
             }
-            KeyCode::D => {
-                self.key[0x9] = false;
+            return;
+        }
+        if let Some(mut player) = self.player.take() {
+            if let Some(keys) = player.step() {
+                self.set_keys(keys);
             }
-            KeyCode::F => {
-                self.key[0xE] = false;
+            if !player.finished() {
+                self.player = Some(player);
             }
-            KeyCode::Z => {
-                self.key[0xA] = false;
+        }
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.step();
+        }
+        self.tick();
+    }
+
+    /// Capture the complete state of the machine. The settings, the statistics and the input
+    /// sources are not part of it.
+    pub fn save_state(&self) -> savestate::SaveState {
+        let (width, height) = self.screen_size();
+        savestate::SaveState {
+            version: savestate::VERSION,
+            registers: self.v,
+            index: self.i,
+            pc: self.pc,
+            sp: self.sp,
+            stack: self.stack,
+            delay_timer: self.dt,
+            sound_timer: self.st,
+            busy: self.busy,
+            memory: self.mem.to_vec(),
+            hires: self.hires,
+            screen: savestate::pack_pixels(&self.gfx),
+            rpl: self.rpl,
+            keys: repro::to_mask(&self.key),
+            rng: self.rng.state().to_string(),
+            thumbnail: Some(savestate::Thumbnail::of(&self.gfx, width, height)),
+            movie_step: (self.recorder.as_ref().map(repro::Recorder::steps))
+                .or_else(|| self.player.as_ref().map(movie::Player::position)),
+        }
+    }
+
+    /// Restore a state captured by [`Chip8::save_state`], the machine is left unchanged if the
+    /// state is invalid. A state saved while recording the inputs rewinds the recording to the
+    /// step it was saved at, and the movie being played back, if any, stops so that the
+    /// recording resumes with live input. Without a recording, the movie seeks to that step.
+    pub fn load_state(
+        &mut self,
+        state: &savestate::SaveState,
+    ) -> Result<(), savestate::SaveStateError> {
+        let invalid = |reason: &str| savestate::SaveStateError::Invalid(reason.to_string());
+        if state.memory.len() != self.mem.len() {
+            return Err(invalid("the memory is not 4K"));
+        }
+        if state.sp as usize > self.stack.len() {
+            return Err(invalid("the stack pointer is out of range"));
+        }
+        let rng = state
+            .rng
+            .parse()
+            .map_err(|_| invalid("the random number generator state is not a number"))?;
+        self.v = state.registers;
+        self.i = state.index;
+        self.pc = state.pc;
+        self.sp = state.sp;
+        self.stack = state.stack;
+        self.dt = state.delay_timer;
+        self.st = state.sound_timer;
+        self.busy = state.busy;
+        self.waiting_for_frame = false;
+        self.key_latched = None;
+        self.mem.copy_from_slice(&state.memory);
+        // Everything in a restored memory counts as written, nothing as executed
+        self.mem_written = [true; 4096];
+        self.mem_executed = [false; 4096];
+        self.code_modifications.clear();
+        self.set_hires(state.hires);
+        let len = self.gfx.len();
+        self.gfx = savestate::unpack_pixels(&state.screen, len);
+        self.rpl = state.rpl;
+        self.key = repro::from_mask(state.keys);
+        self.rng.set_state(rng);
+        if let Some(step) = state.movie_step {
+            if let Some(recorder) = self.recorder.as_mut() {
+                recorder.rewind(step, self.key);
+                self.player = None;
+            } else if let Some(player) = self.player.as_mut() {
+                player.seek(step);
             }
-            KeyCode::X => {
-                self.key[0x0] = false;
+        }
+        self.fault = None;
+        self.timer_poll = None;
+        self.timing = clock::Instant::now();
+        self.timer_elapsed = time::Duration::ZERO;
+        Ok(())
+    }
+
+    /// Hashes of the machine state, compared by the determinism audit
+    pub fn state_digest(&self) -> audit::StateDigest {
+        fn hash(value: impl Hash) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            value.hash(&mut hasher);
+            hasher.finish()
+        }
+        audit::StateDigest {
+            cpu: hash((self.v, self.i, self.pc, self.sp, self.stack, self.busy)),
+            memory: hash(&self.mem[..]),
+            timers: hash((self.dt, self.st)),
+            screen: hash((&self.gfx, self.hires)),
+            keys: hash(self.key),
+        }
+    }
+
+    /// Returns true if the next step executes a new instruction, false while the previous one
+    /// keeps the machine busy
+    pub fn ready(&self) -> bool {
+        self.busy == 0 && !self.waiting_for_frame
+    }
+
+    /// Returns true while the display_wait quirk holds the machine after a sprite was drawn,
+    /// until the next 60 Hz frame
+    pub fn waiting_for_frame(&self) -> bool {
+        self.waiting_for_frame
+    }
+
+    /// Start recording the keys, steps and frames of the run from the current program and
+    /// settings, so that [`Chip8::repro_bundle`] can reproduce it. Call it right after loading
+    /// and patching.
+    pub fn record(&mut self) {
+        self.recorder = Some(repro::Recorder::new(self));
+    }
+
+    /// Set the keys of a movie before each step instead of those of the input sources, until
+    /// the movie ends. The machine should be reset with the seed and memory fill of the movie.
+    pub fn play(&mut self, movie: &movie::Movie) {
+        self.player = Some(movie::Player::new(movie));
+    }
+
+    /// Whether a movie is being played back
+    pub fn playing(&self) -> bool {
+        self.player.is_some()
+    }
+
+    /// The reproduction bundle of the recorded run, with the fault that halted it if any
+    pub fn repro_bundle(&self) -> Option<repro::Bundle> {
+        let fault = self.fault.map(|fault| fault.to_string());
+        self.recorder
+            .as_ref()
+            .map(|recorder| recorder.finish(fault))
+    }
+
+    /// The screen, row by row, true if a pixel is lit. Its size is given by
+    /// [`Chip8::screen_size`].
+    pub fn framebuffer(&self) -> &[bool] {
+        &self.gfx
+    }
+
+    /// Width and height of the screen, 128x64 in the SUPER-CHIP high resolution mode and 64x32
+    /// otherwise
+    pub fn screen_size(&self) -> (usize, usize) {
+        if self.hires {
+            (SCHIP_SCREEN_WIDTH, SCHIP_SCREEN_HEIGHT)
+        } else {
+            (CHIP8_SCREEN_WIDTH, CHIP8_SCREEN_HEIGHT)
+        }
+    }
+
+    /// Switch the screen resolution, the screen is cleared
+    fn set_hires(&mut self, hires: bool) {
+        self.hires = hires;
+        let (width, height) = self.screen_size();
+        self.gfx.clear();
+        self.gfx.resize(width * height, false);
+        self.gfx_updated = true;
+        self.dirty_rows = u64::MAX;
+    }
+
+    /// Shift the screen by the given number of pixels, pixels shifted in are unlit. With the
+    /// half_pixel_scroll quirk the distance is halved in low resolution, the screen has no half
+    /// pixels so odd distances round down.
+    fn scroll(&mut self, dx: isize, dy: isize) {
+        let (dx, dy) = if self.quirks.half_pixel_scroll && !self.hires {
+            (dx / 2, dy / 2)
+        } else {
+            (dx, dy)
+        };
+        let (width, height) = self.screen_size();
+        let old = self.gfx.clone();
+        for y in 0..height {
+            for x in 0..width {
+                let (src_x, src_y) = (x as isize - dx, y as isize - dy);
+                let inside =
+                    (0..width as isize).contains(&src_x) && (0..height as isize).contains(&src_y);
+                self.gfx[x + y * width] = inside && old[src_x as usize + src_y as usize * width];
             }
-            KeyCode::C => {
-                self.key[0xB] = false;
+        }
+        self.gfx_updated = true;
+        self.dirty_rows = u64::MAX;
+    }
+
+    /// Merge the state of all input sources into the keypad and update the peripherals, the
+    /// frontends call it once per frame
+    pub fn poll_inputs(&mut self) {
+        let keys = self.inputs.poll(self.stats.frames);
+        // A movie being played back owns the keypad
+        if self.player.is_none() {
+            self.set_keys(keys);
+        }
+        if self.splash_ticks > 0 && self.key.iter().any(|&pressed| pressed) {
+            self.splash_ticks = 0;
+            self.set_hires(false);
+        }
+        for peripheral in &mut self.peripherals {
+            let range = peripheral.range();
+            let range = range.start as usize..(range.end as usize).min(self.mem.len());
+            if !range.is_empty() {
+                peripheral.update(&mut self.mem[range.clone()]);
+                self.mem_written[range].fill(true);
             }
-            KeyCode::V => {
-                self.key[0xF] = false;
+        }
+    }
+
+    /// Returns true while the sound timer is running, the frontend plays a tone during that time
+    pub fn sound_active(&self) -> bool {
+        self.st > 0
+    }
+
+    /// The keys of the keypad that are currently held down
+    pub fn keys(&self) -> &input::Keys {
+        &self.key
+    }
+
+    /// Draw a line of text onto the screen with its top-left corner at `x`, `y`, see
+    /// [`text::draw_text_to_framebuffer`]. Returns the column right after the text.
+    pub fn draw_text(&mut self, x: usize, y: usize, text: &str, font: text::Font) -> usize {
+        let (width, height) = self.screen_size();
+        self.gfx_updated = true;
+        for row in y..(y + text::GLYPH_HEIGHT).min(height) {
+            self.dirty_rows |= 1 << row;
+        }
+        text::draw_text_to_framebuffer(&mut self.gfx, width, x, y, text, font)
+    }
+
+    /// Returns true if the screen has changed since the last call
+    pub fn take_framebuffer_updated(&mut self) -> bool {
+        std::mem::replace(&mut self.gfx_updated, false)
+    }
+
+    /// The rows of the screen changed since the last call, bit N is set if row N may have
+    /// changed. Frontends that keep what they drew only have to redraw those rows, a change of
+    /// resolution sets every bit.
+    pub fn take_dirty_rows(&mut self) -> u64 {
+        std::mem::replace(&mut self.dirty_rows, 0)
+    }
+
+    /// Set the state of the whole keypad
+    pub fn set_keys(&mut self, keys: input::Keys) {
+        #[cfg(feature = "fault-injection")]
+        let keys = {
+            let mut keys = keys;
+            if let Some(injector) = &mut self.injector {
+                injector.on_keys(&self.key, &mut keys);
             }
-            _ => (),
+            keys
+        };
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.keys(keys);
         }
+        if keys.iter().filter(|&&pressed| pressed).count() >= 3 {
+            self.diagnostics.record(diagnostics::Diagnostic::KeyGhost);
+        }
+        self.key = keys;
     }
-}
 
-impl Chip8 {
-    /// Set the state of the system to the intial state
-    pub fn reset(&mut self) {
-        self.i = 0;
-        self.pc = 0x200; // program begins at 0x200
-        self.sp = 0;
-        self.dt = 0;
-        self.st = 0;
-        self.v = [0; 16];
-        self.mem = [0; 4096];
-        self.stack = [0; 16];
-        self.gfx = [false; CHIP8_SCREEN_WIDTH * CHIP8_SCREEN_HEIGHT]; // clear display
-        self.key = [false; 16]; // clear display
-        self.gfx_updated = false;
-        self.timing = time::Instant::now();
-        // Load font sprites to the first 80 bytes of the memory.
-        // The first four nibble is used to determine what the character is
-        [
-            0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
-            0x20, 0x60, 0x20, 0x20, 0x70, // 1
-            0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
-            0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
-            0x90, 0x90, 0xF0, 0x10, 0x10, // 4
-            0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
-            0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
-            0xF0, 0x10, 0x20, 0x40, 0x40, // 7
-            0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
-            0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
-            0xF0, 0x90, 0xF0, 0x90, 0x90, // A
-            0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
-            0xF0, 0x80, 0x80, 0x80, 0xF0, // C
-            0xE0, 0x90, 0x90, 0x90, 0xE0, // D
-            0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
-            0xF0, 0x80, 0xF0, 0x80, 0x80, // F
-        ]
-        .iter()
-        .enumerate()
-        .for_each(|(i, b)| self.mem[i] = *b);
-    }
-
-    /// Load the bytes from the file at the given path into memory
-    pub fn load(&mut self, prog_mem: &[u8; 0xDFF], prog_len: usize) {
-        self.mem[0x200..0x200 + prog_len].copy_from_slice(&prog_mem[..prog_len]);
+    /// Create a builder for a machine with non-default settings
+    pub fn builder() -> Chip8Builder {
+        Chip8Builder::default()
+    }
+
+    /// The image loaded below 0x200, empty if there is none
+    pub fn interpreter_image(&self) -> &[u8] {
+        &self.interpreter_image
+    }
+
+    /// Load an image of the original interpreter into the area below 0x200, for the few
+    /// programs that read from it. The font at the start of memory is kept.
+    pub fn load_interpreter_image(&mut self, image: &[u8]) {
+        const FONT_END: usize = 80 + BIG_FONT.len();
+        self.interpreter_image = image.to_vec();
+        let end = image.len().min(0x200);
+        if end > FONT_END {
+            self.mem[FONT_END..end].copy_from_slice(&image[FONT_END..end]);
+            self.mark_written(FONT_END, end - FONT_END);
+        }
+    }
+
+    /// Set the handler that emulates the routines of the original interpreter. It is called with
+    /// the target address of every machine code call (0NNN) and whenever the program counter
+    /// enters the area below 0x200, and returns true if it handled the address. When entering
+    /// the area below 0x200, the handler is responsible for moving the program counter, e.g.
+    /// with [`Chip8::return_from_subroutine`].
+    pub fn set_interpreter_hook(&mut self, hook: Option<InterpreterHook>) {
+        self.interpreter_hook = hook;
+    }
+
+    /// Set the handler that is told about every sprite drawn by DXYN, after it was drawn
+    pub fn set_draw_hook(&mut self, hook: Option<DrawHook>) {
+        self.draw_hook = hook;
+    }
+
+    /// The general purpose registers V0-VF
+    pub fn registers(&self) -> &[u8; 16] {
+        &self.v
+    }
+
+    /// The general purpose registers V0-VF
+    pub fn registers_mut(&mut self) -> &mut [u8; 16] {
+        &mut self.v
+    }
+
+    /// The index register
+    pub fn index(&self) -> u16 {
+        self.i
+    }
+
+    /// Set the index register
+    pub fn set_index(&mut self, i: u16) {
+        self.i = i & 0xFFF;
+    }
+
+    /// The program counter
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    /// Set the program counter
+    pub fn set_pc(&mut self, pc: u16) {
+        self.pc = pc & 0xFFF;
+    }
+
+    /// The SUPER-CHIP user flags saved by FX75 and restored by FX85
+    pub fn rpl_flags(&self) -> [u8; 8] {
+        self.rpl
+    }
+
+    /// Set the SUPER-CHIP user flags, e.g. to those kept from an earlier session
+    pub fn set_rpl_flags(&mut self, flags: [u8; 8]) {
+        self.rpl = flags;
+    }
+
+    /// The 4K memory
+    pub fn memory(&self) -> &[u8] {
+        &self.mem
+    }
+
+    /// The 4K memory
+    pub fn memory_mut(&mut self) -> &mut [u8] {
+        &mut self.mem
+    }
+
+    /// Return from the current subroutine as 00EE does
+    pub fn return_from_subroutine(&mut self) {
+        if let Flow::Jump(addr) = self.exec(Inst::Op00EE) {
+            self.pc = addr;
+        }
+    }
+
+    /// Run the interpreter hook for an address, returns true if the hook handled it
+    fn call_interpreter_hook(&mut self, addr: u16) -> bool {
+        match self.interpreter_hook.take() {
+            Some(mut hook) => {
+                let handled = hook(self, addr);
+                // The hook may have replaced itself
+                if self.interpreter_hook.is_none() {
+                    self.interpreter_hook = Some(hook);
+                }
+                handled
+            }
+            None => false,
+        }
     }
 
     fn tick(&mut self) {
+        if self.fault.is_some() {
+            return;
+        }
+        if self.waiting_for_frame {
+            return;
+        }
+        if self.busy > 0 {
+            self.busy -= 1;
+            return;
+        }
+        let addr = self.pc;
+        if addr < 0x200 && self.call_interpreter_hook(addr) {
+            return;
+        }
         // Get and process the opcode
+        if addr & 1 != 0 {
+            self.report(fault::Fault::OddProgramCounter { addr });
+        }
         let opcode = self.fetch();
-        self.pc = match self.exec(Inst::from(opcode)) {
-            Flow::Halt => self.pc - 2,
-            Flow::Next => self.pc,
-            Flow::Skip => self.pc + 2,
-            Flow::Jump(addr) => addr,
+        self.pc = match Inst::decode(opcode) {
+            Ok(inst) => {
+                self.stats.instructions += 1;
+                *self.stats.opcodes.entry(inst.pattern()).or_insert(0) += 1;
+                self.busy = self.costs.cost(inst.pattern()) - 1;
+                let before = self.trace.as_ref().map(|_| self.traced_registers());
+                self.check_index_use(inst);
+                let flow = self.exec(inst);
+                if let Some(before) = before {
+                    let line = trace::line(addr, opcode, inst, &before, &self.traced_registers());
+                    self.write_trace(&line);
+                }
+                match flow {
+                    Flow::Halt => self.pc - 2,
+                    Flow::Next => self.pc,
+                    Flow::Skip => self.pc + self.inst_len(self.pc),
+                    Flow::Jump(addr) => addr,
+                }
+            }
+            Err(UnknownOpcode(opcode)) => {
+                self.report(fault::Fault::UnknownOpcode { addr, opcode });
+                self.pc
+            }
         };
-        // Update timers
-        // The two timers count down to zero if they have been set to a
-        // value larger than zero (counting at 50Hz).
-        if self.timing.elapsed() >= time::Duration::from_millis(20) {
-            self.timing = time::Instant::now();
+        #[cfg(feature = "fault-injection")]
+        if let Some((addr, bit)) = self.injector.as_mut().and_then(|i| i.on_cycle()) {
+            self.mem[addr] ^= 1 << bit;
+        }
+        if self.fault.is_some() {
+            self.pc = addr;
+        }
+    }
+
+    fn traced_registers(&self) -> trace::Registers {
+        trace::Registers {
+            v: self.v,
+            i: self.i,
+            dt: self.dt,
+            st: self.st,
+        }
+    }
+
+    /// Write a line of the trace, the trace is stopped if it cannot be written
+    fn write_trace(&mut self, line: &str) {
+        if let Some(writer) = self.trace.as_mut() {
+            if let Err(err) = writeln!(writer, "{}", line) {
+                self.warnings
+                    .push(fault::Warning::TraceStopped(err.to_string()));
+                self.trace = None;
+            }
+        }
+    }
+
+    /// Count the delay and sound timers down once for every 1/60 s since the last call, the
+    /// remainder is kept for the next call so the rate does not drift. The interactive frontends
+    /// call it once per frame so that the timers follow the clock of the host, whatever the speed
+    /// of the machine, see [`crate::pacer`]. A machine that was not run for a while catches up at
+    /// most [`MAX_TIMER_CATCH_UP`] periods.
+    pub fn run_timers(&mut self) {
+        if self.manual_timers {
+            return;
+        }
+        let period = TIMER_PERIOD;
+        let now = clock::Instant::now();
+        self.timer_elapsed += now.duration_since(self.timing);
+        self.timing = now;
+        self.timer_elapsed = self.timer_elapsed.min(period * MAX_TIMER_CATCH_UP);
+        while self.timer_elapsed >= period {
+            self.timer_elapsed -= period;
+            self.next_frame();
+        }
+    }
+
+    /// Leave the time since the last call to [`Chip8::run_timers`] out of the timers, the frontends
+    /// call it instead while the machine is paused
+    pub fn pause_timers(&mut self) {
+        self.timing = clock::Instant::now();
+    }
+
+    /// Count the delay and sound timers down once, as at the start of a 1/60 s frame. Headless and
+    /// replayed runs call it after a fixed number of ticks, so the timers follow the ticks that
+    /// were run and not the speed of the host.
+    pub fn next_frame(&mut self) {
+        self.stats.frames += 1;
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.frame();
+        }
+        self.waiting_for_frame = false;
+        #[cfg(not(feature = "fault-injection"))]
+        let counts = 1;
+        #[cfg(feature = "fault-injection")]
+        let counts = self.injector.as_mut().map_or(1, |i| i.on_frame());
+        for _ in 0..counts {
             if self.dt > 0 {
                 self.dt -= 1;
             }
             if self.st > 0 {
                 if self.st == 1 {
-                    println!("BEEP");
+                    self.stats.beeps += 1;
                 }
                 self.st -= 1;
             }
         }
     }
 
+    /// Note the instructions that use I after FX55 or FX65 changed it
+    fn check_index_use(&mut self, inst: Inst) {
+        let uses_index = matches!(
+            inst,
+            Inst::OpDXYN(..)
+                | Inst::OpFX1E(_)
+                | Inst::OpFX33(_)
+                | Inst::OpFX55(_)
+                | Inst::OpFX65(_)
+        );
+        if uses_index && self.index_after_load_store {
+            self.diagnostics
+                .record(diagnostics::Diagnostic::IndexAfterLoadStore);
+        }
+        self.index_after_load_store = match inst {
+            Inst::OpFX55(_) | Inst::OpFX65(_) => true,
+            Inst::OpANNN(_) | Inst::OpF000 | Inst::OpFX29(_) | Inst::OpFX30(_) => false,
+            _ => self.index_after_load_store,
+        };
+    }
+
+    /// Length of the instruction at an address, four bytes for the long F000 NNNN
+    fn inst_len(&self, addr: u16) -> u16 {
+        let addr = addr as usize;
+        match (self.mem[addr & 0xFFF], self.mem[(addr + 1) & 0xFFF]) {
+            (0xF0, 0x00) => 4,
+            _ => 2,
+        }
+    }
+
     fn fetch(&mut self) -> u16 {
         let pc = self.pc as usize;
+        self.mem_executed[pc & 0xFFF] = true;
+        self.mem_executed[(pc + 1) & 0xFFF] = true;
         self.pc += 2;
-        (self.mem[pc] as u16) << 8 | self.mem[pc + 1] as u16
+        (self.read_mem(pc) as u16) << 8 | self.read_mem(pc + 1) as u16
     }
 
     fn exec(&mut self, inst: Inst) -> Flow {
         match inst {
             Inst::Op00E0 => {
                 self.gfx_updated = true;
+                self.dirty_rows = u64::MAX;
                 self.gfx.iter_mut().for_each(|pixel| *pixel = false);
             }
             Inst::Op00EE => {
-                self.sp -= 1;
+                if self.sp == 0 {
+                    self.report(fault::Fault::StackUnderflow);
+                }
+                self.sp = self.sp.wrapping_sub(1) % 16;
                 return Flow::Jump(self.stack[self.sp as usize]);
             }
-            Inst::Op1NNN(nnn) => return Flow::Jump(nnn),
+            Inst::Op00CN(n) => self.scroll(0, n as isize),
+            Inst::Op00FB => self.scroll(4, 0),
+            Inst::Op00FC => self.scroll(-4, 0),
+            Inst::Op00FD => return Flow::Halt,
+            Inst::Op00FE => self.set_hires(false),
+            Inst::Op00FF => self.set_hires(true),
+            Inst::Op0NNN(nnn) => {
+                if !self.call_interpreter_hook(nnn) {
+                    self.report(fault::Fault::MachineCodeCall { addr: nnn });
+                }
+            }
+            Inst::Op1NNN(nnn) => {
+                self.check_timer_loop(nnn);
+                return Flow::Jump(nnn);
+            }
             Inst::Op2NNN(nnn) => {
+                if self.sp as usize >= self.stack.len() {
+                    self.report(fault::Fault::StackOverflow);
+                }
+                self.sp %= 16;
                 self.stack[self.sp as usize] = self.pc;
                 self.sp += 1;
                 return Flow::Jump(nnn);
@@ -334,9 +1370,18 @@ impl Chip8 {
             Inst::Op6XKK(x, kk) => self.v[x] = kk,
             Inst::Op7XKK(x, kk) => self.v[x] = self.v[x].wrapping_add(kk),
             Inst::Op8XY0(x, y) => self.v[x] = self.v[y],
-            Inst::Op8XY1(x, y) => self.v[x] |= self.v[y],
-            Inst::Op8XY2(x, y) => self.v[x] &= self.v[y],
-            Inst::Op8XY3(x, y) => self.v[x] ^= self.v[y],
+            Inst::Op8XY1(x, y) => {
+                self.v[x] |= self.v[y];
+                self.reset_vf();
+            }
+            Inst::Op8XY2(x, y) => {
+                self.v[x] &= self.v[y];
+                self.reset_vf();
+            }
+            Inst::Op8XY3(x, y) => {
+                self.v[x] ^= self.v[y];
+                self.reset_vf();
+            }
             Inst::Op8XY4(x, y) => {
                 let (res, overflow) = self.v[x].overflowing_add(self.v[y]);
                 self.v[0xF] = if overflow { 1 } else { 0 };
@@ -347,18 +1392,34 @@ impl Chip8 {
                 self.v[0xF] = if overflow { 0 } else { 1 };
                 self.v[x] = res;
             }
-            Inst::Op8XY6(x, _y) => {
-                self.v[0xF] = self.v[x] & 0x01;
-                self.v[x] >>= 1;
+            Inst::Op8XY6(x, y) => {
+                if self.v[x] != self.v[y] {
+                    self.diagnostics.record(diagnostics::Diagnostic::Shift);
+                }
+                let value = if self.quirks.shift_in_place {
+                    self.v[x]
+                } else {
+                    self.v[y]
+                };
+                self.v[0xF] = value & 0x01;
+                self.v[x] = value >> 1;
             }
             Inst::Op8XY7(x, y) => {
                 let (res, overflow) = self.v[y].overflowing_sub(self.v[x]);
                 self.v[0xF] = if overflow { 0 } else { 1 };
                 self.v[x] = res;
             }
-            Inst::Op8XYE(x, _y) => {
-                self.v[0xF] = (self.v[x] & 0x80) >> 7;
-                self.v[x] <<= 1;
+            Inst::Op8XYE(x, y) => {
+                if self.v[x] != self.v[y] {
+                    self.diagnostics.record(diagnostics::Diagnostic::Shift);
+                }
+                let value = if self.quirks.shift_in_place {
+                    self.v[x]
+                } else {
+                    self.v[y]
+                };
+                self.v[0xF] = (value & 0x80) >> 7;
+                self.v[x] = value << 1;
             }
             Inst::Op9XY0(x, y) => {
                 if self.v[x] != self.v[y] {
@@ -366,38 +1427,118 @@ impl Chip8 {
                 }
             }
             Inst::OpANNN(nnn) => self.i = nnn,
-            Inst::OpBNNN(nnn) => return Flow::Jump(self.v[0] as u16 + nnn),
-            Inst::OpCXKK(x, kk) => self.v[x] = random::<u8>() & kk,
+            Inst::OpBNNN(nnn) => {
+                if self.v[(nnn >> 8) as usize] != self.v[0] {
+                    self.diagnostics.record(diagnostics::Diagnostic::JumpOffset);
+                }
+                let offset = if self.quirks.jump_vx {
+                    self.v[(nnn >> 8) as usize]
+                } else {
+                    self.v[0]
+                };
+                return Flow::Jump((offset as u16 + nnn) & 0xFFF);
+            }
+            Inst::OpCXKK(x, kk) => self.v[x] = self.rng.next_u8() & kk,
             Inst::OpDXYN(x, y, n) => {
                 self.gfx_updated = true;
-                self.v[0xF] = 0;
-                for (y_offset, sprite) in self.mem[self.i as usize..(self.i + n) as usize]
-                    .iter()
+                let (width, height) = self.screen_size();
+                // DXY0 draws a 16x16 sprite in high resolution and an 8x16 sprite otherwise
+                let (rows, cols) = match n {
+                    0 if self.hires => (16, 16),
+                    0 => (16, 8),
+                    n => (n as usize, 8),
+                };
+                let bytes_per_row = cols / 8;
+                let mut sprites = [0u8; 32];
+                for (offset, sprite) in sprites[..rows * bytes_per_row].iter_mut().enumerate() {
+                    *sprite = self.read_mem(self.i as usize + offset);
+                }
+                let (mut collided_rows, mut clipped_rows) = (0, 0);
+                // The sprite always starts on the screen, the rest is wrapped or clipped
+                let (x_start, y_start) = (self.v[x] as usize % width, self.v[y] as usize % height);
+                if x_start + cols > width || y_start + rows > height {
+                    self.diagnostics
+                        .record(diagnostics::Diagnostic::SpriteAtEdge);
+                }
+                let wrap = self.quirks.wrap_sprites;
+                for (y_offset, row) in sprites[..rows * bytes_per_row]
+                    .chunks(bytes_per_row)
                     .enumerate()
                 {
-                    let y_screen = (self.v[y] as usize + y_offset) % CHIP8_SCREEN_HEIGHT;
-                    for x_offset in 0..8 {
-                        let x_screen = (self.v[x] as usize + x_offset) % CHIP8_SCREEN_WIDTH;
-                        if (sprite & (0x80 >> x_offset)) != 0 {
-                            if self.gfx[x_screen + y_screen * CHIP8_SCREEN_WIDTH] {
-                                self.v[0xF] = 1;
-                            }
-                            self.gfx[x_screen + y_screen * CHIP8_SCREEN_WIDTH] ^= true;
+                    if !wrap && y_start + y_offset >= height {
+                        clipped_rows = (rows - y_offset) as u8;
+                        break;
+                    }
+                    let y_screen = (y_start + y_offset) % height;
+                    self.dirty_rows |= 1 << y_screen;
+                    let mut collided = false;
+                    for x_offset in 0..cols {
+                        if !wrap && x_start + x_offset >= width {
+                            break;
+                        }
+                        let x_screen = (x_start + x_offset) % width;
+                        if (row[x_offset / 8] & (0x80 >> (x_offset % 8))) != 0 {
+                            collided |= self.gfx[x_screen + y_screen * width];
+                            self.gfx[x_screen + y_screen * width] ^= true;
                         }
                     }
+                    collided_rows += collided as u8;
+                }
+                // SUPER-CHIP counts the rows that collided or were clipped in high resolution
+                self.v[0xF] = if self.hires && self.quirks.collision_rows {
+                    collided_rows + clipped_rows
+                } else {
+                    (collided_rows > 0) as u8
+                };
+                self.stats.draws += 1;
+                self.stats.collisions += (collided_rows > 0) as u64;
+                self.waiting_for_frame = self.quirks.display_wait;
+                if let Some(hook) = self.draw_hook.as_mut() {
+                    hook(&Draw {
+                        x: x_start,
+                        y: y_start,
+                        width: cols,
+                        height: rows,
+                        screen_width: width,
+                        screen_height: height,
+                        collided: collided_rows > 0,
+                    });
                 }
             }
             Inst::OpEX9E(x) => {
-                if self.key[self.v[x] as usize] {
+                if self.key_pressed(self.v[x]) {
                     return Flow::Skip;
                 }
             }
             Inst::OpEXA1(x) => {
-                if !self.key[self.v[x] as usize] {
+                if !self.key_pressed(self.v[x]) {
                     return Flow::Skip;
                 }
             }
-            Inst::OpFX07(x) => self.v[x] = self.dt,
+            Inst::OpF000 => {
+                let pc = self.pc as usize;
+                self.mem_executed[pc & 0xFFF] = true;
+                self.mem_executed[(pc + 1) & 0xFFF] = true;
+                self.i = (self.read_mem(pc) as u16) << 8 | self.read_mem(pc + 1) as u16;
+                return Flow::Jump(self.pc + 2);
+            }
+            Inst::OpFX07(x) => {
+                if let Some(poll) = self.timer_poll.as_mut() {
+                    poll.read_addr = Some(self.pc - 2);
+                }
+                self.v[x] = self.dt;
+            }
+            Inst::OpFX0A(x) if self.quirks.key_release => match self.key_latched {
+                Some(key) if !self.key[key] => {
+                    self.v[x] = key as u8;
+                    self.key_latched = None;
+                }
+                Some(_) => return Flow::Halt,
+                None => {
+                    self.key_latched = self.key.iter().position(|pressed| *pressed);
+                    return Flow::Halt;
+                }
+            },
             Inst::OpFX0A(x) => {
                 let mut pressed = false;
                 for (key_idx, key_pressed) in self.key.iter().enumerate() {
@@ -411,28 +1552,246 @@ impl Chip8 {
                     return Flow::Halt;
                 }
             }
-            Inst::OpFX15(x) => self.dt = self.v[x],
+            Inst::OpFX15(x) => {
+                // Delays of a few frames are the ones where the phase matters the most
+                const SHORT_DELAY: u8 = 4;
+                self.timer_poll = Some(TimerPoll {
+                    set_addr: self.pc - 2,
+                    value: self.v[x],
+                    read_addr: None,
+                })
+                .filter(|poll| poll.value <= SHORT_DELAY);
+                self.dt = self.v[x];
+            }
             Inst::OpFX18(x) => self.st = self.v[x],
             Inst::OpFX1E(x) => self.i = self.i.wrapping_add(self.v[x] as u16),
             Inst::OpFX29(x) => self.i = self.v[x] as u16 * 5,
+            Inst::OpFX30(x) => self.i = 80 + self.v[x] as u16 * 10,
             Inst::OpFX33(x) => {
-                self.mem[self.i as usize] = self.v[x] / 100;
-                self.mem[self.i as usize + 1] = (self.v[x] / 10) % 10;
-                self.mem[self.i as usize + 2] = (self.v[x] % 100) % 10;
+                self.write_mem(self.i as usize, self.v[x] / 100);
+                self.write_mem(self.i as usize + 1, (self.v[x] / 10) % 10);
+                self.write_mem(self.i as usize + 2, (self.v[x] % 100) % 10);
             }
             Inst::OpFX55(x) => {
-                self.mem[self.i as usize..=self.i as usize + x].copy_from_slice(&self.v[0..=x]);
-                self.i += x as u16 + 1;
+                for reg in 0..=x {
+                    self.write_mem(self.i as usize + reg, self.v[reg]);
+                }
+                if self.quirks.increment_index {
+                    self.i = self.i.wrapping_add(x as u16 + 1);
+                }
             }
             Inst::OpFX65(x) => {
-                self.v[0..=x].copy_from_slice(&self.mem[self.i as usize..=self.i as usize + x]);
-                self.i += x as u16 + 1;
+                for reg in 0..=x {
+                    self.v[reg] = self.read_mem(self.i as usize + reg);
+                }
+                if self.quirks.increment_index {
+                    self.i = self.i.wrapping_add(x as u16 + 1);
+                }
+            }
+            Inst::OpFX75(x) => {
+                let x = x.min(7);
+                self.rpl[..=x].copy_from_slice(&self.v[..=x]);
+            }
+            Inst::OpFX85(x) => {
+                let x = x.min(7);
+                self.v[..=x].copy_from_slice(&self.rpl[..=x]);
             }
         }
         Flow::Next
     }
 }
 
+/// A write into memory that had already been executed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CodeModification {
+    /// Number of instructions executed when the write happened
+    pub cycle: u64,
+    /// Address of the instruction that made the write
+    pub pc: u16,
+    /// The byte before the write
+    pub old: u8,
+    /// The written byte
+    pub new: u8,
+}
+
+/// A sprite drawn by DXYN, see [`Chip8::set_draw_hook`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Draw {
+    /// Column of the left edge of the sprite on the screen
+    pub x: usize,
+    /// Row of the top edge of the sprite on the screen
+    pub y: usize,
+    /// Width of the sprite in pixels
+    pub width: usize,
+    /// Height of the sprite in pixels
+    pub height: usize,
+    /// Width of the screen it was drawn on
+    pub screen_width: usize,
+    /// Height of the screen it was drawn on
+    pub screen_height: usize,
+    /// True if the sprite erased a lit pixel
+    pub collided: bool,
+}
+
+/// A short delay that was set in the delay timer
+#[derive(Debug, Clone, Copy)]
+struct TimerPoll {
+    /// Address of the FX15 instruction
+    set_addr: u16,
+    /// The delay in frames
+    value: u8,
+    /// Address of the latest FX07 instruction that read the timer since
+    read_addr: Option<u16>,
+}
+
+/// Handler that emulates the routines of the original interpreter, see
+/// [`Chip8::set_interpreter_hook`]
+pub type InterpreterHook = Box<dyn FnMut(&mut Chip8, u16) -> bool + Send>;
+
+/// Handler of the drawn sprites, see [`Chip8::set_draw_hook`]
+pub type DrawHook = Box<dyn FnMut(&Draw) + Send>;
+
+/// Builder for a [`Chip8`] with non-default settings, the built machine is already reset
+#[derive(Default)]
+pub struct Chip8Builder {
+    mem_fill: memory::MemoryFill,
+    warn_uninit: bool,
+    warn_timer_polling: bool,
+    strict: bool,
+    manual_timers: bool,
+    seed: Option<u64>,
+    random_source: rng::RandomSource,
+    quirks: quirks::Quirks,
+    costs: cost::CostTable,
+    splash: bool,
+    #[cfg(feature = "fault-injection")]
+    faults: Option<inject::FaultInjection>,
+}
+
+impl Chip8Builder {
+    /// Pattern that fills the program memory on reset, see [`Chip8::set_memory_fill`]
+    pub fn memory_fill(mut self, fill: memory::MemoryFill) -> Self {
+        self.mem_fill = fill;
+        self
+    }
+
+    /// Warn about reads of uninitialized memory, see [`Chip8::set_warn_uninit`]
+    pub fn warn_uninit(mut self, warn: bool) -> Self {
+        self.warn_uninit = warn;
+        self
+    }
+
+    /// Warn about loops that poll a short delay, see [`Chip8::set_warn_timer_polling`]
+    pub fn warn_timer_polling(mut self, warn: bool) -> Self {
+        self.warn_timer_polling = warn;
+        self
+    }
+
+    /// Halt on irregularities, see [`Chip8::set_strict`]
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Count the timers down only when told to, see [`Chip8::set_manual_timers`]
+    pub fn manual_timers(mut self, manual: bool) -> Self {
+        self.manual_timers = manual;
+        self
+    }
+
+    /// Seed of the random number generator, a seed from the host's entropy is used if not set
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Behaviours that differ between interpreters, see [`Chip8::set_quirks`]
+    pub fn quirks(mut self, quirks: quirks::Quirks) -> Self {
+        self.quirks = quirks;
+        self
+    }
+
+    /// Source of the bytes of the CXKK instruction, see [`Chip8::set_random_source`]
+    pub fn random_source(mut self, source: rng::RandomSource) -> Self {
+        self.random_source = source;
+        self
+    }
+
+    /// Number of ticks taken by each opcode, see [`Chip8::set_costs`]
+    pub fn costs(mut self, costs: cost::CostTable) -> Self {
+        self.costs = costs;
+        self
+    }
+
+    /// Show the splash before the program starts, see [`Chip8::set_splash`]
+    pub fn splash(mut self, splash: bool) -> Self {
+        self.splash = splash;
+        self
+    }
+
+    /// Deliberately inject faults while the machine runs
+    #[cfg(feature = "fault-injection")]
+    pub fn fault_injection(mut self, faults: inject::FaultInjection) -> Self {
+        self.faults = Some(faults);
+        self
+    }
+
+    /// Create the machine and reset it
+    pub fn build(self) -> Chip8 {
+        let mut chip8 = Chip8::default();
+        chip8.set_memory_fill(self.mem_fill);
+        chip8.set_warn_uninit(self.warn_uninit);
+        chip8.set_warn_timer_polling(self.warn_timer_polling);
+        chip8.set_strict(self.strict);
+        chip8.set_manual_timers(self.manual_timers);
+        chip8.set_seed(self.seed.unwrap_or_else(rng::entropy_seed));
+        chip8.set_random_source(self.random_source);
+        chip8.set_quirks(self.quirks);
+        chip8.set_costs(self.costs);
+        chip8.set_splash(self.splash);
+        #[cfg(feature = "fault-injection")]
+        {
+            chip8.injector = self.faults.map(inject::Injector::new);
+        }
+        chip8.reset();
+        chip8
+    }
+}
+
+/// The 4x5 hex digit sprites, stored at the start of the memory
+pub(crate) const FONT: [u8; 80] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+    0x20, 0x60, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+];
+
+/// The SUPER-CHIP 8x10 digit sprites, stored right after the 4x5 font
+const BIG_FONT: [u8; 100] = [
+    0x3C, 0x7E, 0xE7, 0xC3, 0xC3, 0xC3, 0xC3, 0xE7, 0x7E, 0x3C, // 0
+    0x18, 0x38, 0x58, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C, // 1
+    0x3E, 0x7F, 0xC3, 0x06, 0x0C, 0x18, 0x30, 0x60, 0xFF, 0xFF, // 2
+    0x3C, 0x7E, 0xC3, 0x03, 0x0E, 0x0E, 0x03, 0xC3, 0x7E, 0x3C, // 3
+    0x06, 0x0E, 0x1E, 0x36, 0x66, 0xC6, 0xFF, 0xFF, 0x06, 0x06, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFE, 0x03, 0xC3, 0x7E, 0x3C, // 5
+    0x3E, 0x7C, 0xE0, 0xC0, 0xFC, 0xFE, 0xC3, 0xC3, 0x7E, 0x3C, // 6
+    0xFF, 0xFF, 0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x60, 0x60, // 7
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7E, 0x7E, 0xC3, 0xC3, 0x7E, 0x3C, // 8
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7F, 0x3F, 0x03, 0x03, 0x3E, 0x7C, // 9
+];
+
 enum Flow {
     Halt,
     Next,
@@ -440,8 +1799,9 @@ enum Flow {
     Jump(u16),
 }
 
-#[derive(Debug)]
-enum Inst {
+/// A decoded instruction, named after its opcode pattern
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Inst {
     /// 00E0 - CLS
     /// Clear the display.
     Op00E0,
@@ -449,6 +1809,30 @@ enum Inst {
     /// Return from a subroutine.
     /// The interpreter sets the program counter to the address at the top of the stack, then subtracts 1 from the stack pointer.
     Op00EE,
+    /// 00CN - SCD nibble (SUPER-CHIP)
+    /// Scroll the display down by n pixels.
+    Op00CN(u16),
+    /// 00FB - SCR (SUPER-CHIP)
+    /// Scroll the display right by 4 pixels.
+    Op00FB,
+    /// 00FC - SCL (SUPER-CHIP)
+    /// Scroll the display left by 4 pixels.
+    Op00FC,
+    /// 00FD - EXIT (SUPER-CHIP)
+    /// Exit the interpreter, the program counter stays on this instruction.
+    Op00FD,
+    /// 00FE - LOW (SUPER-CHIP)
+    /// Switch to the 64x32 low resolution mode and clear the display.
+    Op00FE,
+    /// 00FF - HIGH (SUPER-CHIP)
+    /// Switch to the 128x64 high resolution mode and clear the display.
+    Op00FF,
+    /// 0NNN - SYS addr
+    /// Jump to a machine code routine at nnn.
+    /// This instruction is only used on the old computers on which Chip-8 was originally implemented. It is ignored by
+    /// modern interpreters, unless an interpreter hook handles the address. The SUPER-CHIP opcodes 00CN and 00FB-00FF
+    /// are decoded as their own instructions instead.
+    Op0NNN(u16),
     /// 1NNN - JP addr
     /// Jump to location nnn.
     /// The interpreter sets the program counter to nnn.
@@ -551,6 +1935,10 @@ enum Inst {
     /// Skip next instruction if key with the value of Vx is not pressed.
     /// Checks the keyboard, and if the key corresponding to the value of Vx is currently in the up position, PC is increased by 2.
     OpEXA1(usize),
+    /// F000 NNNN - LD I, long NNNN (XO-CHIP)
+    /// Set I = NNNN, the 16-bit address in the two bytes that follow the opcode.
+    /// The instruction is four bytes long, so the skip instructions skip over all of it.
+    OpF000,
     /// Fx07 - LD Vx, DT
     /// Set Vx = delay timer value.
     /// The value of DT is placed into Vx.
@@ -576,6 +1964,9 @@ enum Inst {
     /// The value of I is set to the location for the hexadecimal sprite corresponding to the value of Vx. See section 2.4, Display,
     /// for more information on the Chip-8 hexadecimal font.
     OpFX29(usize),
+    /// FX30 - LD HF, Vx (SUPER-CHIP)
+    /// Set I = location of the 8x10 sprite for digit Vx.
+    OpFX30(usize),
     /// Fx33 - LD B, Vx
     /// Store BCD representation of Vx in memory locations I, I+1, and I+2.
     /// The interpreter takes the decimal value of Vx, and places the hundreds digit in memory at location in I, the tens digit
@@ -589,10 +1980,114 @@ enum Inst {
     /// Read registers V0 through Vx from memory starting at location I.
     /// The interpreter reads values from memory starting at location I into registers V0 through Vx.
     OpFX65(usize),
+    /// FX75 - LD R, Vx (SUPER-CHIP)
+    /// Store registers V0 through Vx in the user flags, x is at most 7.
+    OpFX75(usize),
+    /// FX85 - LD Vx, R (SUPER-CHIP)
+    /// Read registers V0 through Vx from the user flags, x is at most 7.
+    OpFX85(usize),
+}
+
+impl Inst {
+    /// The opcode pattern of the instruction, e.g. `8XY4`
+    pub fn pattern(&self) -> &'static str {
+        match self {
+            Self::Op00E0 => "00E0",
+            Self::Op00EE => "00EE",
+            Self::Op00CN(..) => "00CN",
+            Self::Op00FB => "00FB",
+            Self::Op00FC => "00FC",
+            Self::Op00FD => "00FD",
+            Self::Op00FE => "00FE",
+            Self::Op00FF => "00FF",
+            Self::Op0NNN(..) => "0NNN",
+            Self::Op1NNN(..) => "1NNN",
+            Self::Op2NNN(..) => "2NNN",
+            Self::Op3XKK(..) => "3XKK",
+            Self::Op4XKK(..) => "4XKK",
+            Self::Op5XY0(..) => "5XY0",
+            Self::Op6XKK(..) => "6XKK",
+            Self::Op7XKK(..) => "7XKK",
+            Self::Op8XY0(..) => "8XY0",
+            Self::Op8XY1(..) => "8XY1",
+            Self::Op8XY2(..) => "8XY2",
+            Self::Op8XY3(..) => "8XY3",
+            Self::Op8XY4(..) => "8XY4",
+            Self::Op8XY5(..) => "8XY5",
+            Self::Op8XY6(..) => "8XY6",
+            Self::Op8XY7(..) => "8XY7",
+            Self::Op8XYE(..) => "8XYE",
+            Self::Op9XY0(..) => "9XY0",
+            Self::OpANNN(..) => "ANNN",
+            Self::OpBNNN(..) => "BNNN",
+            Self::OpCXKK(..) => "CXKK",
+            Self::OpDXYN(..) => "DXYN",
+            Self::OpEX9E(..) => "EX9E",
+            Self::OpEXA1(..) => "EXA1",
+            Self::OpF000 => "F000",
+            Self::OpFX07(..) => "FX07",
+            Self::OpFX0A(..) => "FX0A",
+            Self::OpFX15(..) => "FX15",
+            Self::OpFX18(..) => "FX18",
+            Self::OpFX1E(..) => "FX1E",
+            Self::OpFX29(..) => "FX29",
+            Self::OpFX30(..) => "FX30",
+            Self::OpFX33(..) => "FX33",
+            Self::OpFX55(..) => "FX55",
+            Self::OpFX65(..) => "FX65",
+            Self::OpFX75(..) => "FX75",
+            Self::OpFX85(..) => "FX85",
+        }
+    }
+}
+
+impl std::convert::TryFrom<u16> for Inst {
+    type Error = UnknownOpcode;
+
+    fn try_from(opcode: u16) -> Result<Self, Self::Error> {
+        Self::decode(opcode)
+    }
+}
+
+/// An opcode that does not decode to any instruction, e.g. data in the execution path
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnknownOpcode(pub u16);
+
+impl std::fmt::Display for UnknownOpcode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown opcode {:04X}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownOpcode {}
+
+/// A program rejected by [`Chip8::load`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadError {
+    /// The program does not fit between 0x200 and 0x1000
+    TooLarge {
+        /// Size of the program in bytes
+        len: usize,
+    },
 }
 
-impl From<u16> for Inst {
-    fn from(opcode: u16) -> Self {
+impl std::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TooLarge { len } => write!(
+                f,
+                "the program is {} bytes, only {} fit between 0x200 and 0x1000",
+                len, PROGRAM_SIZE
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl Inst {
+    /// Decode an opcode, fails if it is not a known instruction
+    pub fn decode(opcode: u16) -> Result<Self, UnknownOpcode> {
         let nibbles = (
             (opcode & 0xF000) >> 12,
             (opcode & 0x0F00) >> 8,
@@ -604,9 +2099,16 @@ impl From<u16> for Inst {
         let n = nibbles.3;
         let kk = (opcode & 0x00FF) as u8;
         let nnn = opcode & 0x0FFF;
-        match nibbles {
+        let inst = match nibbles {
             (0x0, 0x0, 0xE, 0x0) => Self::Op00E0,
             (0x0, 0x0, 0xE, 0xE) => Self::Op00EE,
+            (0x0, 0x0, 0xC, _) => Self::Op00CN(n),
+            (0x0, 0x0, 0xF, 0xB) => Self::Op00FB,
+            (0x0, 0x0, 0xF, 0xC) => Self::Op00FC,
+            (0x0, 0x0, 0xF, 0xD) => Self::Op00FD,
+            (0x0, 0x0, 0xF, 0xE) => Self::Op00FE,
+            (0x0, 0x0, 0xF, 0xF) => Self::Op00FF,
+            (0x0, _, _, _) => Self::Op0NNN(nnn),
             (0x1, _, _, _) => Self::Op1NNN(nnn),
             (0x2, _, _, _) => Self::Op2NNN(nnn),
             (0x3, _, _, _) => Self::Op3XKK(x, kk),
@@ -631,15 +2133,146 @@ impl From<u16> for Inst {
             (0xE, _, 0x9, 0xE) => Self::OpEX9E(x),
             (0xE, _, 0xA, 0x1) => Self::OpEXA1(x),
             (0xF, _, 0x0, 0x7) => Self::OpFX07(x),
+            (0xF, 0x0, 0x0, 0x0) => Self::OpF000,
             (0xF, _, 0x0, 0xA) => Self::OpFX0A(x),
             (0xF, _, 0x1, 0x5) => Self::OpFX15(x),
             (0xF, _, 0x1, 0x8) => Self::OpFX18(x),
             (0xF, _, 0x1, 0xE) => Self::OpFX1E(x),
             (0xF, _, 0x2, 0x9) => Self::OpFX29(x),
+            (0xF, _, 0x3, 0x0) => Self::OpFX30(x),
             (0xF, _, 0x3, 0x3) => Self::OpFX33(x),
             (0xF, _, 0x5, 0x5) => Self::OpFX55(x),
             (0xF, _, 0x6, 0x5) => Self::OpFX65(x),
-            (_, _, _, _) => panic!("Opcode is not supported {:#04X}", opcode),
+            (0xF, _, 0x7, 0x5) => Self::OpFX75(x),
+            (0xF, _, 0x8, 0x5) => Self::OpFX85(x),
+            (_, _, _, _) => return Err(UnknownOpcode(opcode)),
+        };
+        Ok(inst)
+    }
+
+    /// The opcode of the instruction, registers and operands are truncated to their field width
+    pub fn encode(&self) -> u16 {
+        let xy = |op: u16, x: usize, y: usize, n: u16| {
+            op << 12 | (x as u16 & 0xF) << 8 | (y as u16 & 0xF) << 4 | (n & 0xF)
+        };
+        let xkk = |op: u16, x: usize, kk: u8| op << 12 | (x as u16 & 0xF) << 8 | kk as u16;
+        let nnn = |op: u16, nnn: u16| op << 12 | (nnn & 0xFFF);
+        match *self {
+            Self::Op00E0 => 0x00E0,
+            Self::Op00EE => 0x00EE,
+            Self::Op00CN(n) => 0x00C0 | (n & 0xF),
+            Self::Op00FB => 0x00FB,
+            Self::Op00FC => 0x00FC,
+            Self::Op00FD => 0x00FD,
+            Self::Op00FE => 0x00FE,
+            Self::Op00FF => 0x00FF,
+            Self::Op0NNN(addr) => nnn(0x0, addr),
+            Self::Op1NNN(addr) => nnn(0x1, addr),
+            Self::Op2NNN(addr) => nnn(0x2, addr),
+            Self::Op3XKK(x, kk) => xkk(0x3, x, kk),
+            Self::Op4XKK(x, kk) => xkk(0x4, x, kk),
+            Self::Op5XY0(x, y) => xy(0x5, x, y, 0x0),
+            Self::Op6XKK(x, kk) => xkk(0x6, x, kk),
+            Self::Op7XKK(x, kk) => xkk(0x7, x, kk),
+            Self::Op8XY0(x, y) => xy(0x8, x, y, 0x0),
+            Self::Op8XY1(x, y) => xy(0x8, x, y, 0x1),
+            Self::Op8XY2(x, y) => xy(0x8, x, y, 0x2),
+            Self::Op8XY3(x, y) => xy(0x8, x, y, 0x3),
+            Self::Op8XY4(x, y) => xy(0x8, x, y, 0x4),
+            Self::Op8XY5(x, y) => xy(0x8, x, y, 0x5),
+            Self::Op8XY6(x, y) => xy(0x8, x, y, 0x6),
+            Self::Op8XY7(x, y) => xy(0x8, x, y, 0x7),
+            Self::Op8XYE(x, y) => xy(0x8, x, y, 0xE),
+            Self::Op9XY0(x, y) => xy(0x9, x, y, 0x0),
+            Self::OpANNN(addr) => nnn(0xA, addr),
+            Self::OpBNNN(addr) => nnn(0xB, addr),
+            Self::OpCXKK(x, kk) => xkk(0xC, x, kk),
+            Self::OpDXYN(x, y, n) => xy(0xD, x, y, n),
+            Self::OpEX9E(x) => xkk(0xE, x, 0x9E),
+            Self::OpEXA1(x) => xkk(0xE, x, 0xA1),
+            Self::OpF000 => 0xF000,
+            Self::OpFX07(x) => xkk(0xF, x, 0x07),
+            Self::OpFX0A(x) => xkk(0xF, x, 0x0A),
+            Self::OpFX15(x) => xkk(0xF, x, 0x15),
+            Self::OpFX18(x) => xkk(0xF, x, 0x18),
+            Self::OpFX1E(x) => xkk(0xF, x, 0x1E),
+            Self::OpFX29(x) => xkk(0xF, x, 0x29),
+            Self::OpFX30(x) => xkk(0xF, x, 0x30),
+            Self::OpFX33(x) => xkk(0xF, x, 0x33),
+            Self::OpFX55(x) => xkk(0xF, x, 0x55),
+            Self::OpFX65(x) => xkk(0xF, x, 0x65),
+            Self::OpFX75(x) => xkk(0xF, x, 0x75),
+            Self::OpFX85(x) => xkk(0xF, x, 0x85),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fault::Warning;
+    use super::Chip8;
+
+    /// The addresses of the reported polling loops after a few dozen steps of `program`
+    fn timer_loops(program: &[u8], warn: bool) -> Vec<u16> {
+        let mut chip8 = Chip8::builder().warn_timer_polling(warn).build();
+        chip8.mem[0x200..0x200 + program.len()].copy_from_slice(program);
+        for _ in 0..40 {
+            chip8.step();
         }
+        chip8.timer_loops.iter().copied().collect()
+    }
+
+    /// Set the delay to `delay`, then poll it in a loop with `padding` instructions before the
+    /// jump back
+    fn polling_loop(delay: u8, padding: usize) -> Vec<u8> {
+        let mut program = vec![
+            0x60, delay, // LD V0, delay
+            0xF0, 0x15, // LD DT, V0
+            0xF1, 0x07, // LD V1, DT
+            0x31, 0x00, // SE V1, 0
+        ];
+        for _ in 0..padding {
+            program.extend_from_slice(&[0x72, 0x01]); // ADD V2, 1
+        }
+        program.extend_from_slice(&[0x12, 0x04]); // JP 0x204
+        program
+    }
+
+    #[test]
+    fn loops_polling_a_short_delay_are_reported_once() {
+        assert_eq!(timer_loops(&polling_loop(3, 0), true), [0x204]);
+        assert_eq!(timer_loops(&polling_loop(3, 2), true), [0x204]);
+    }
+
+    #[test]
+    fn reported_loops_are_queued_as_warnings() {
+        let mut chip8 = Chip8::builder().warn_timer_polling(true).build();
+        chip8.load(&polling_loop(3, 0)).unwrap();
+        for _ in 0..40 {
+            chip8.step();
+        }
+        let warning = Warning::TimerPolling {
+            addr: 0x204,
+            delay: 3,
+            set_addr: 0x202,
+        };
+        assert_eq!(chip8.take_warnings(), [warning]);
+        assert_eq!(chip8.take_warnings(), []);
+    }
+
+    #[test]
+    fn other_loops_are_not_reported() {
+        // The warning is off
+        assert_eq!(timer_loops(&polling_loop(3, 0), false), []);
+        // A long delay does not depend much on the phase of the timer
+        assert_eq!(timer_loops(&polling_loop(5, 0), true), []);
+        // The loop is too long to be a polling loop
+        assert_eq!(timer_loops(&polling_loop(3, 3), true), []);
+        // The loop does not read the timer
+        let program = [0x60, 0x03, 0xF0, 0x15, 0x71, 0x01, 0x12, 0x04];
+        assert_eq!(timer_loops(&program, true), []);
+        // The timer is read before the loop
+        let program = [0x60, 0x03, 0xF0, 0x15, 0xF1, 0x07, 0x71, 0x01, 0x12, 0x06];
+        assert_eq!(timer_loops(&program, true), []);
     }
 }
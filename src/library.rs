@@ -0,0 +1,78 @@
+//! The ROM library, the list of ROMs a frontend shows when it is started without one. Each ROM
+//! of a directory is listed with its title, its size and a thumbnail of the first frame it
+//! draws, found by running it for a moment without a window.
+
+use crate::savestate::Thumbnail;
+use crate::shell;
+use crate::Chip8;
+use crate::TICKS_PER_SEC;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Frames a ROM may run for before it draws, it has no thumbnail if it draws nothing by then
+pub const THUMBNAIL_FRAMES: u64 = 120;
+
+/// A ROM of the library
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// Where the ROM is
+    pub path: PathBuf,
+    /// Name of the ROM, its file name without the extension
+    pub title: String,
+    /// Size of the ROM in bytes
+    pub size: u64,
+    /// The first frame with something drawn on it, if there is one
+    pub thumbnail: Option<Thumbnail>,
+}
+
+impl Entry {
+    /// The entry of a ROM file
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let program = fs::read(path)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            title: shell::name(path),
+            size: program.len() as u64,
+            thumbnail: thumbnail(&program),
+        })
+    }
+}
+
+/// The ROMs of a directory with one of the given extensions, by title. Files that cannot be read
+/// are left out.
+pub fn scan(dir: &Path, extensions: &[&str]) -> io::Result<Vec<Entry>> {
+    let mut entries: Vec<_> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| extensions.contains(&ext.to_lowercase().as_str()))
+        })
+        .filter_map(|path| Entry::open(&path).ok())
+        .collect();
+    entries.sort_by_key(|entry| entry.title.to_lowercase());
+    Ok(entries)
+}
+
+/// The first frame a program draws something on within [`THUMBNAIL_FRAMES`] frames, with the
+/// default quirks
+pub fn thumbnail(program: &[u8]) -> Option<Thumbnail> {
+    let mut chip8 = Chip8::builder().build();
+    chip8.load(program).ok()?;
+    for _ in 0..THUMBNAIL_FRAMES {
+        for _ in 0..TICKS_PER_SEC / 60 {
+            chip8.step();
+        }
+        chip8.next_frame();
+        if chip8.fault().is_some() {
+            return None;
+        }
+        if chip8.framebuffer().contains(&true) {
+            let (width, height) = chip8.screen_size();
+            return Some(Thumbnail::of(chip8.framebuffer(), width, height));
+        }
+    }
+    None
+}
@@ -0,0 +1,525 @@
+//! A libretro core, built as a shared library with the `libretro` feature and without the default
+//! `window` feature, so that the emulator can be loaded in RetroArch and other libretro frontends
+//! with their save states, shaders and input remapping. The libretro API is small enough that its
+//! types are declared here instead of pulling in bindings.
+//!
+//! The RetroPad is mapped like the gamepads of the window, see [`KeyMap::gamepad`], and the
+//! keyboard uses the same layout as the window. The ROM settings of [`RomConfig`] apply when the
+//! frontend passes the path of the ROM.
+
+use crate::datapaths::DataPaths;
+use crate::keymap::KeyMap;
+use crate::pacer::Pacer;
+use crate::palette::Palette;
+use crate::romconfig::RomConfig;
+use crate::savestate::SaveState;
+use crate::storage::FileStorage;
+use crate::Chip8;
+use crate::CHIP8_SCREEN_HEIGHT;
+use crate::CHIP8_SCREEN_WIDTH;
+use crate::SCHIP_SCREEN_HEIGHT;
+use crate::SCHIP_SCREEN_WIDTH;
+use crate::TICKS_PER_SEC;
+use crate::TIMER_PERIOD;
+use std::cell::RefCell;
+use std::ffi::c_char;
+use std::ffi::c_void;
+use std::ffi::CStr;
+use std::path::Path;
+
+/// Frames run per second
+pub const FRAMES_PER_SEC: u32 = 60;
+/// Sample rate of the audio sent to the frontend
+pub const SAMPLE_RATE: u32 = 44100;
+/// Frequency of the beep, in Hz
+pub const TONE_FREQUENCY: u32 = 440;
+/// Size of the buffer that holds a save state, which is written as TOML and padded with zeros
+pub const SAVE_STATE_SIZE: usize = 0x4000;
+
+const API_VERSION: u32 = 1;
+const ENVIRONMENT_SET_PIXEL_FORMAT: u32 = 10;
+const PIXEL_FORMAT_XRGB8888: u32 = 1;
+const DEVICE_JOYPAD: u32 = 1;
+const DEVICE_KEYBOARD: u32 = 3;
+const MEMORY_SYSTEM_RAM: u32 = 2;
+const REGION_NTSC: u32 = 0;
+const AMPLITUDE: i16 = 0x1000;
+
+/// RetroPad buttons and the gamepad buttons of [`KeyMap`] they stand for
+const JOYPAD: [(u32, &str); 8] = [
+    (4, "DPadUp"),
+    (5, "DPadDown"),
+    (6, "DPadLeft"),
+    (7, "DPadRight"),
+    (0, "South"),
+    (8, "East"),
+    (1, "West"),
+    (9, "North"),
+];
+
+/// Keyboard keys, by their libretro key codes, and the keypad keys they are mapped to
+const KEYBOARD: [(u8, u8); 16] = [
+    (b'1', 0x1),
+    (b'2', 0x2),
+    (b'3', 0x3),
+    (b'4', 0xC),
+    (b'q', 0x4),
+    (b'w', 0x5),
+    (b'e', 0x6),
+    (b'r', 0xD),
+    (b'a', 0x7),
+    (b's', 0x8),
+    (b'd', 0x9),
+    (b'f', 0xE),
+    (b'z', 0xA),
+    (b'x', 0x0),
+    (b'c', 0xB),
+    (b'v', 0xF),
+];
+
+/// `retro_system_info`
+#[repr(C)]
+pub struct SystemInfo {
+    library_name: *const c_char,
+    library_version: *const c_char,
+    valid_extensions: *const c_char,
+    need_fullpath: bool,
+    block_extract: bool,
+}
+
+/// `retro_game_geometry`
+#[repr(C)]
+pub struct GameGeometry {
+    base_width: u32,
+    base_height: u32,
+    max_width: u32,
+    max_height: u32,
+    aspect_ratio: f32,
+}
+
+/// `retro_system_timing`
+#[repr(C)]
+pub struct SystemTiming {
+    fps: f64,
+    sample_rate: f64,
+}
+
+/// `retro_system_av_info`
+#[repr(C)]
+pub struct SystemAvInfo {
+    geometry: GameGeometry,
+    timing: SystemTiming,
+}
+
+/// `retro_game_info`
+#[repr(C)]
+pub struct GameInfo {
+    path: *const c_char,
+    data: *const c_void,
+    size: usize,
+    meta: *const c_char,
+}
+
+type EnvironmentFn = unsafe extern "C" fn(cmd: u32, data: *mut c_void) -> bool;
+type VideoRefreshFn =
+    unsafe extern "C" fn(data: *const c_void, width: u32, height: u32, pitch: usize);
+type AudioSampleFn = unsafe extern "C" fn(left: i16, right: i16);
+type AudioSampleBatchFn = unsafe extern "C" fn(data: *const i16, frames: usize) -> usize;
+type InputPollFn = unsafe extern "C" fn();
+type InputStateFn = unsafe extern "C" fn(port: u32, device: u32, index: u32, id: u32) -> i16;
+
+/// The machine run by the frontend and the callbacks it registered
+struct Core {
+    chip8: Chip8,
+    ticks_per_sec: u32,
+    pacer: Pacer,
+    gamepad: KeyMap,
+    palette: Palette,
+    screen: Vec<u32>,
+    audio: Vec<i16>,
+    /// Samples played since the start, for the phase of the beep
+    samples: u32,
+    environment: Option<EnvironmentFn>,
+    video_refresh: Option<VideoRefreshFn>,
+    audio_sample_batch: Option<AudioSampleBatchFn>,
+    input_poll: Option<InputPollFn>,
+    input_state: Option<InputStateFn>,
+}
+
+impl Default for Core {
+    fn default() -> Self {
+        Self {
+            chip8: Chip8::builder().build(),
+            ticks_per_sec: TICKS_PER_SEC,
+            pacer: Pacer::default(),
+            gamepad: KeyMap::gamepad(),
+            palette: Palette::default(),
+            screen: Vec::new(),
+            audio: Vec::new(),
+            samples: 0,
+            environment: None,
+            video_refresh: None,
+            audio_sample_batch: None,
+            input_poll: None,
+            input_state: None,
+        }
+    }
+}
+
+impl Core {
+    /// Read the RetroPad and the keyboard into the keyboard input source
+    fn read_inputs(&mut self) {
+        let Some(input_state) = self.input_state else {
+            return;
+        };
+        let mut keys = [false; 16];
+        for (id, button) in JOYPAD {
+            if let Some(key) = self.gamepad.get(button) {
+                keys[key as usize] |= unsafe { input_state(0, DEVICE_JOYPAD, 0, id) } != 0;
+            }
+        }
+        for (code, key) in KEYBOARD {
+            keys[key as usize] |= unsafe { input_state(0, DEVICE_KEYBOARD, 0, code as u32) } != 0;
+        }
+        for (key, pressed) in keys.iter().enumerate() {
+            self.chip8.set_keyboard_key(key as u8, *pressed);
+        }
+    }
+
+    /// Send the screen to the frontend
+    fn present(&mut self) {
+        let (width, height) = self.chip8.screen_size();
+        let palette = self.palette;
+        self.screen.clear();
+        self.screen.extend(
+            self.chip8
+                .framebuffer()
+                .iter()
+                .map(|lit| palette.color(*lit as usize)),
+        );
+        if let Some(video_refresh) = self.video_refresh {
+            let data = self.screen.as_ptr() as *const c_void;
+            unsafe { video_refresh(data, width as u32, height as u32, width * 4) };
+        }
+    }
+
+    /// Send a frame of audio to the frontend, a square wave while the sound timer runs
+    fn play(&mut self) {
+        self.audio.clear();
+        for _ in 0..SAMPLE_RATE / FRAMES_PER_SEC {
+            let high = (self.samples * TONE_FREQUENCY * 2 / SAMPLE_RATE).is_multiple_of(2);
+            let sample = match (self.chip8.sound_active(), high) {
+                (false, _) => 0,
+                (true, true) => AMPLITUDE,
+                (true, false) => -AMPLITUDE,
+            };
+            self.audio.extend([sample, sample]);
+            self.samples = (self.samples + 1) % SAMPLE_RATE;
+        }
+        if let Some(audio_sample_batch) = self.audio_sample_batch {
+            unsafe { audio_sample_batch(self.audio.as_ptr(), self.audio.len() / 2) };
+        }
+    }
+}
+
+thread_local! {
+    static CORE: RefCell<Core> = RefCell::new(Core::default());
+}
+
+/// Version of the libretro API implemented by the core
+#[no_mangle]
+pub extern "C" fn retro_api_version() -> u32 {
+    API_VERSION
+}
+
+/// Register the callback through which the frontend is configured
+#[no_mangle]
+pub extern "C" fn retro_set_environment(callback: EnvironmentFn) {
+    CORE.with(|core| core.borrow_mut().environment = Some(callback))
+}
+
+/// Register the callback that receives the screen
+#[no_mangle]
+pub extern "C" fn retro_set_video_refresh(callback: VideoRefreshFn) {
+    CORE.with(|core| core.borrow_mut().video_refresh = Some(callback))
+}
+
+/// Unused, the audio is sent a frame at a time
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample(_callback: AudioSampleFn) {}
+
+/// Register the callback that receives the audio of a frame
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample_batch(callback: AudioSampleBatchFn) {
+    CORE.with(|core| core.borrow_mut().audio_sample_batch = Some(callback))
+}
+
+/// Register the callback that updates the inputs
+#[no_mangle]
+pub extern "C" fn retro_set_input_poll(callback: InputPollFn) {
+    CORE.with(|core| core.borrow_mut().input_poll = Some(callback))
+}
+
+/// Register the callback that reads the inputs
+#[no_mangle]
+pub extern "C" fn retro_set_input_state(callback: InputStateFn) {
+    CORE.with(|core| core.borrow_mut().input_state = Some(callback))
+}
+
+/// Nothing to set up, the machine is created when a game is loaded
+#[no_mangle]
+pub extern "C" fn retro_init() {}
+
+/// Drop the machine and the callbacks
+#[no_mangle]
+pub extern "C" fn retro_deinit() {
+    CORE.with(|core| *core.borrow_mut() = Core::default())
+}
+
+/// Name, version and file extensions of the core
+///
+/// # Safety
+///
+/// `info` must point to a `retro_system_info`.
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_info(info: *mut SystemInfo) {
+    *info = SystemInfo {
+        library_name: b"CHIP-8\0".as_ptr() as *const c_char,
+        library_version: concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char,
+        valid_extensions: b"ch8|sc8|c8\0".as_ptr() as *const c_char,
+        need_fullpath: false,
+        block_extract: false,
+    };
+}
+
+/// Size of the screen, frame rate and sample rate
+///
+/// # Safety
+///
+/// `info` must point to a `retro_system_av_info`.
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_av_info(info: *mut SystemAvInfo) {
+    *info = SystemAvInfo {
+        geometry: GameGeometry {
+            base_width: CHIP8_SCREEN_WIDTH as u32,
+            base_height: CHIP8_SCREEN_HEIGHT as u32,
+            max_width: SCHIP_SCREEN_WIDTH as u32,
+            max_height: SCHIP_SCREEN_HEIGHT as u32,
+            aspect_ratio: 2.0,
+        },
+        timing: SystemTiming {
+            fps: FRAMES_PER_SEC as f64,
+            sample_rate: SAMPLE_RATE as f64,
+        },
+    };
+}
+
+/// Every device is read as the RetroPad and the keyboard
+#[no_mangle]
+pub extern "C" fn retro_set_controller_port_device(_port: u32, _device: u32) {}
+
+/// Reset the machine and load the ROM again
+#[no_mangle]
+pub extern "C" fn retro_reset() {
+    CORE.with(|core| core.borrow_mut().chip8.reload())
+}
+
+/// Run the machine for a frame, then send its screen and audio to the frontend
+#[no_mangle]
+pub extern "C" fn retro_run() {
+    CORE.with(|core| {
+        let core = &mut *core.borrow_mut();
+        if let Some(input_poll) = core.input_poll {
+            unsafe { input_poll() };
+        }
+        core.read_inputs();
+        core.chip8.poll_inputs();
+        // The frontend runs the frames at 60 Hz, so the timers count down once per frame
+        for _ in 0..core.pacer.ticks(TIMER_PERIOD, core.ticks_per_sec) {
+            core.chip8.step();
+        }
+        core.chip8.next_frame();
+        core.present();
+        core.play();
+    })
+}
+
+/// Size of the buffer a save state is written to, see [`SAVE_STATE_SIZE`]
+#[no_mangle]
+pub extern "C" fn retro_serialize_size() -> usize {
+    SAVE_STATE_SIZE
+}
+
+/// Write a save state, as TOML padded with zeros
+///
+/// # Safety
+///
+/// `data` must point to `size` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_serialize(data: *mut c_void, size: usize) -> bool {
+    let Ok(text) = CORE.with(|core| core.borrow().chip8.save_state().to_toml()) else {
+        return false;
+    };
+    if text.len() > size {
+        return false;
+    }
+    let buffer = std::slice::from_raw_parts_mut(data as *mut u8, size);
+    buffer[..text.len()].copy_from_slice(text.as_bytes());
+    buffer[text.len()..].fill(0);
+    true
+}
+
+/// Restore a save state written by [`retro_serialize`]
+///
+/// # Safety
+///
+/// `data` must point to `size` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_unserialize(data: *const c_void, size: usize) -> bool {
+    let buffer = std::slice::from_raw_parts(data as *const u8, size);
+    let len = buffer.iter().position(|byte| *byte == 0).unwrap_or(size);
+    let Ok(text) = std::str::from_utf8(&buffer[..len]) else {
+        return false;
+    };
+    let Ok(state) = SaveState::from_toml(text) else {
+        return false;
+    };
+    CORE.with(|core| core.borrow_mut().chip8.load_state(&state).is_ok())
+}
+
+/// Cheats are not supported
+#[no_mangle]
+pub extern "C" fn retro_cheat_reset() {}
+
+/// Cheats are not supported
+#[no_mangle]
+pub extern "C" fn retro_cheat_set(_index: u32, _enabled: bool, _code: *const c_char) {}
+
+/// Load a ROM, with its settings if the frontend passes its path
+///
+/// # Safety
+///
+/// `game` must be null or point to a `retro_game_info` whose data holds the ROM.
+#[no_mangle]
+pub unsafe extern "C" fn retro_load_game(game: *const GameInfo) -> bool {
+    let Some(game) = game.as_ref() else {
+        return false;
+    };
+    if game.data.is_null() {
+        return false;
+    }
+    let program = std::slice::from_raw_parts(game.data as *const u8, game.size);
+    let rom_config = if game.path.is_null() {
+        RomConfig::default()
+    } else {
+        let path = CStr::from_ptr(game.path).to_string_lossy().into_owned();
+        // The frontend keeps the saves, the settings stay next to the ROM
+        RomConfig::load(&FileStorage, &DataPaths::NextToRom, Path::new(&path)).unwrap_or_default()
+    };
+    CORE.with(|core| {
+        let core = &mut *core.borrow_mut();
+        if let Some(environment) = core.environment {
+            let mut format = PIXEL_FORMAT_XRGB8888;
+            let format = &mut format as *mut u32 as *mut c_void;
+            if !environment(ENVIRONMENT_SET_PIXEL_FORMAT, format) {
+                return false;
+            }
+        }
+        core.ticks_per_sec = rom_config.speed.unwrap_or(TICKS_PER_SEC);
+        core.gamepad = KeyMap::gamepad();
+        core.palette = rom_config.palette.unwrap_or_default();
+        core.chip8 = Chip8::builder()
+            .quirks(rom_config.quirks.unwrap_or_default())
+            .build();
+        core.chip8.load(program).is_ok()
+    })
+}
+
+/// Special game types are not supported
+#[no_mangle]
+pub extern "C" fn retro_load_game_special(
+    _game_type: u32,
+    _info: *const GameInfo,
+    _num_info: usize,
+) -> bool {
+    false
+}
+
+/// Drop the machine that runs the ROM
+#[no_mangle]
+pub extern "C" fn retro_unload_game() {
+    CORE.with(|core| core.borrow_mut().chip8 = Chip8::builder().build())
+}
+
+/// The core runs at 60 frames per second like NTSC machines
+#[no_mangle]
+pub extern "C" fn retro_get_region() -> u32 {
+    REGION_NTSC
+}
+
+/// The 4K of memory, for cheat searches and achievements
+#[no_mangle]
+pub extern "C" fn retro_get_memory_data(id: u32) -> *mut c_void {
+    if id != MEMORY_SYSTEM_RAM {
+        return std::ptr::null_mut();
+    }
+    CORE.with(|core| core.borrow_mut().chip8.memory_mut().as_mut_ptr() as *mut c_void)
+}
+
+/// Size of the memory returned by [`retro_get_memory_data`]
+#[no_mangle]
+pub extern "C" fn retro_get_memory_size(id: u32) -> usize {
+    if id != MEMORY_SYSTEM_RAM {
+        return 0;
+    }
+    CORE.with(|core| core.borrow().chip8.memory().len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Core;
+    use super::AMPLITUDE;
+    use super::DEVICE_JOYPAD;
+    use super::DEVICE_KEYBOARD;
+    use super::FRAMES_PER_SEC;
+    use super::SAMPLE_RATE;
+
+    /// Holds V on the keyboard and South on the RetroPad
+    unsafe extern "C" fn input_state(_port: u32, device: u32, _index: u32, id: u32) -> i16 {
+        match (device, id) {
+            (DEVICE_KEYBOARD, id) if id == b'v' as u32 => 1,
+            (DEVICE_JOYPAD, 0) => 1,
+            _ => 0,
+        }
+    }
+
+    #[test]
+    fn keyboard_and_retropad_hold_keypad_keys() {
+        let mut core = Core {
+            input_state: Some(input_state),
+            ..Core::default()
+        };
+        core.read_inputs();
+        core.chip8.poll_inputs();
+        let south = core.gamepad.get("South").unwrap();
+        let held: Vec<_> = (0..16).filter(|key| core.chip8.keys()[*key]).collect();
+        assert_eq!(held, [south as usize, 0xF]);
+    }
+
+    #[test]
+    fn a_square_wave_plays_while_the_sound_timer_runs() {
+        let mut core = Core::default();
+        core.play();
+        assert_eq!(
+            core.audio.len(),
+            2 * (SAMPLE_RATE / FRAMES_PER_SEC) as usize
+        );
+        assert!(core.audio.iter().all(|sample| *sample == 0));
+
+        core.chip8.st = 2;
+        core.play();
+        assert_eq!(core.audio[..2], [AMPLITUDE, AMPLITUDE]);
+        assert!(core.audio.contains(&-AMPLITUDE));
+        assert!(core.audio.iter().all(|sample| sample.abs() == AMPLITUDE));
+    }
+}
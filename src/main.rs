@@ -1,26 +1,1214 @@
+use chip8::i18n::Lang;
+use chip8::i18n::Message;
 use chip8::*;
-use ggez::event;
+use clap::Args;
+use clap::Parser;
+use clap::Subcommand;
 use ggez::ContextBuilder;
+use ggez::GameError;
 use ggez::GameResult;
-use std::io::Read;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
 
-const WINDOW_WIDTH: f32 = CHIP8_SCREEN_WIDTH as f32 * PIXEL_SIZE as f32;
-const WINDOW_HEIGHT: f32 = CHIP8_SCREEN_HEIGHT as f32 * PIXEL_SIZE as f32;
+/// A CHIP-8 and SUPER-CHIP emulator
+#[derive(Parser)]
+#[command(version, about, args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// ROMs to play in turn, directories to open in the launcher, or playlists
+    #[arg(value_name = "ROM")]
+    roms: Vec<PathBuf>,
+    #[command(flatten)]
+    display: DisplayArgs,
+    #[command(flatten)]
+    emulation: EmulationArgs,
+    #[command(flatten)]
+    input: InputArgs,
+    #[command(flatten)]
+    session: SessionArgs,
+    #[command(flatten)]
+    debug: DebugArgs,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Assemble a CHIPPER-style source into a ROM
+    Asm {
+        /// The source
+        source: String,
+        /// The ROM to write
+        output: String,
+        /// Also write the addresses of the labels to a symbol file, see --symbols
+        #[arg(long, value_name = "FILE")]
+        symbols: Option<String>,
+    },
+    /// Print the listing of a ROM
+    Disasm {
+        /// The ROM
+        rom: String,
+    },
+    /// Replay a bundle written by --repro-on-fault and open the state where it failed in the
+    /// debugger
+    Repro {
+        /// Directory of the bundle
+        dir: String,
+        /// Print the state instead of opening it
+        #[arg(long)]
+        print: bool,
+    },
+    /// Run the compliance suite and print its summary as TOML
+    Compliance {
+        #[command(flatten)]
+        quirks: QuirkArgs,
+    },
+    /// Run test ROMs without a window until they report a result through the test traps
+    Test {
+        /// The ROMs
+        #[arg(required = true)]
+        roms: Vec<PathBuf>,
+        /// Frames a ROM may run for before it times out
+        #[arg(long, value_name = "FRAMES", value_parser = parse_frames, default_value_t = runner::DEFAULT_FRAMES)]
+        frames: u64,
+        #[command(flatten)]
+        quirks: QuirkArgs,
+    },
+    /// Run a conformance suite without a window and compare the screens with its snapshots
+    Conformance {
+        /// Directory of the suite, with its conformance.toml
+        dir: PathBuf,
+        /// Write the snapshots from this run instead of comparing the screens with them
+        #[arg(long)]
+        bless: bool,
+    },
+    /// Edit a movie written by --record-input
+    Movie {
+        #[command(subcommand)]
+        edit: MovieEdit,
+    },
+}
+
+#[derive(Subcommand)]
+enum MovieEdit {
+    /// Keep the first steps of a movie
+    Trim {
+        /// The movie
+        movie: String,
+        /// The movie to write
+        output: String,
+        /// Number of steps to keep
+        #[arg(long)]
+        steps: u64,
+    },
+    /// Join the steps of a movie before STEP to those of another recording from STEP on
+    Splice {
+        /// The movie to take the start from
+        first: String,
+        /// The movie to take the rest from, recorded with the same seed and memory fill
+        second: String,
+        /// The movie to write
+        output: String,
+        /// Step where the second movie takes over
+        #[arg(long, value_name = "STEP")]
+        at: u64,
+    },
+    /// Move the steps of a movie for a run at another speed
+    Retime {
+        /// The movie
+        movie: String,
+        /// The movie to write
+        output: String,
+        /// Instructions per second of the recorded run
+        #[arg(long, value_name = "N", value_parser = parse_speed)]
+        from: u32,
+        /// Instructions per second of the run to play it in
+        #[arg(long, value_name = "N", value_parser = parse_speed)]
+        to: u32,
+    },
+    /// Convert a reproduction bundle to a movie, or a movie to a reproduction bundle of a ROM
+    Convert {
+        /// A bundle directory or a movie
+        input: String,
+        /// The movie, or the bundle directory with --rom
+        output: String,
+        /// The ROM of the bundle to write
+        #[arg(long)]
+        rom: Option<String>,
+    },
+}
+
+#[derive(Args)]
+#[command(next_help_heading = "Display")]
+struct DisplayArgs {
+    /// Size of a CHIP-8 pixel in host pixels
+    #[arg(long, value_name = "N", value_parser = parse_scale, default_value_t = window::PIXEL_SIZE as f32)]
+    scale: f32,
+    /// Colors of the lit and unlit pixels, then of the XO-CHIP second plane and of both planes
+    #[arg(long, num_args = 2..=4, value_name = "RRGGBB", value_parser = parse_color)]
+    palette: Option<Vec<u32>>,
+    /// Colors of a built-in palette: classic, green, amber, lcd or inverted
+    #[arg(long, value_name = "NAME", value_parser = parse_theme, conflicts_with = "palette")]
+    theme: Option<palette::Palette>,
+    /// Fill the screen
+    #[arg(long)]
+    fullscreen: bool,
+    /// Never play the tone
+    #[arg(long)]
+    mute: bool,
+    /// Play a panned cue for the drawing in each region of the screen (experimental)
+    #[arg(long)]
+    sonify: bool,
+    /// Draw the game, the keypad and the statistics on a chroma key, for capturing the window
+    #[arg(long)]
+    streamer: bool,
+    /// Place the elements of the streamer mode as in a layout file, implies --streamer
+    #[arg(long, value_name = "FILE")]
+    streamer_layout: Option<String>,
+    /// Draw the screen with scanlines, a vignette and rounded corners like a CRT
+    #[arg(long)]
+    crt: bool,
+    /// Fade out the pixels that go off over FRAMES frames instead of clearing them, against flicker
+    #[arg(long, value_name = "FRAMES", num_args = 0..=1, default_missing_value = "4")]
+    phosphor: Option<u8>,
+    /// Show the held keys
+    #[arg(long)]
+    input_display: bool,
+    /// Show the performance graphs
+    #[arg(long)]
+    perf_graphs: bool,
+    /// Start the ROM right away, without the splash
+    #[arg(long)]
+    no_splash: bool,
+    /// Language of the messages, en or vi
+    #[arg(long)]
+    lang: Option<Lang>,
+}
+
+#[derive(Args)]
+#[command(next_help_heading = "Emulation")]
+struct EmulationArgs {
+    /// Instructions per second, instead of the settings of the ROM
+    #[arg(long, value_name = "TICKS", value_parser = parse_speed)]
+    speed: Option<u32>,
+    #[command(flatten)]
+    quirks: QuirkArgs,
+    /// List the quirks and their settings in each profile
+    #[arg(long)]
+    list_quirks: bool,
+    /// Seed of the random number generator
+    #[arg(long, value_parser = parse_seed)]
+    seed: Option<u64>,
+    /// Source of the random numbers of CXKK, or replay:FILE
+    #[arg(long, value_name = "SOURCE")]
+    random: Option<String>,
+    /// Fill the program memory on reset: zero, ff or random[:SEED]
+    #[arg(long, value_name = "FILL")]
+    mem_fill: Option<memory::MemoryFill>,
+    /// Load an interpreter image into 0x000-0x1FF, or builtin for a stand-in that returns from
+    /// calls into it
+    #[arg(long, value_name = "FILE")]
+    interpreter_image: Option<String>,
+    /// Handle the print and assert traps (SYS 0x100-0x1FF), printed lines also go to the console
+    #[arg(long)]
+    host_traps: bool,
+    /// Map the local date and time into the eight bytes at ADDR, updated every frame
+    #[cfg(feature = "rtc")]
+    #[arg(long, value_name = "ADDR", value_parser = parse_address)]
+    rtc: Option<u16>,
+    /// Ticks taken by every instruction
+    #[arg(long, value_name = "FILE")]
+    costs: Option<String>,
+    /// Apply a patch to the ROM
+    #[arg(long = "patch", value_name = "FILE")]
+    patches: Vec<String>,
+}
+
+#[derive(Args)]
+struct QuirkArgs {
+    /// Quirk profile: chip8, schip or xochip
+    #[arg(long = "quirks", value_name = "PROFILE")]
+    profile: Option<quirks::Profile>,
+    /// Override a quirk, see --list-quirks
+    #[arg(long = "quirk", value_name = "NAME=on|off", value_parser = parse_quirk)]
+    overrides: Vec<(String, bool)>,
+}
+
+#[derive(Args)]
+#[command(next_help_heading = "Input")]
+struct InputArgs {
+    /// Bind host keys to keypad keys
+    #[arg(long, value_name = "FILE")]
+    keymap: Option<String>,
+    /// Bind gamepad buttons to keypad keys
+    #[arg(long, value_name = "FILE")]
+    gamepad: Option<String>,
+    /// Read the keypad bindings, the gamepad bindings and the hotkeys from a controls file
+    #[arg(long, value_name = "FILE")]
+    controls: Option<String>,
+    /// Write the controls in effect to a file that --controls reads, and exit
+    #[arg(long, value_name = "FILE")]
+    export_controls: Option<String>,
+    /// Mark a key as turbo-fire, RATE in presses per second
+    #[arg(long, value_name = "K[:RATE]", value_parser = parse_turbo)]
+    turbo: Vec<(u8, f32)>,
+    /// Read key events from the standard input
+    #[arg(long)]
+    input_stdin: bool,
+    /// Read key events from a named pipe
+    #[arg(long, value_name = "PATH")]
+    input_fifo: Vec<PathBuf>,
+    /// Read key events from a serial port
+    #[arg(long, value_name = "PORT")]
+    input_serial: Vec<PathBuf>,
+    /// Receive OSC key messages over UDP
+    #[cfg(feature = "osc")]
+    #[arg(long, value_name = "ADDR")]
+    input_osc: Vec<String>,
+    /// Receive notes from a raw MIDI device
+    #[cfg(feature = "midi")]
+    #[arg(long, value_name = "DEVICE")]
+    input_midi: Vec<PathBuf>,
+    /// Scan a keypad matrix wired to GPIO pins
+    #[cfg(feature = "gpio")]
+    #[arg(long, value_name = "R,R,R,R:C,C,C,C", value_parser = parse_gpio_pins)]
+    input_gpio: Vec<input::GpioPins>,
+}
+
+#[derive(Args)]
+#[command(next_help_heading = "Session")]
+struct SessionArgs {
+    /// Length of the rewind history, up to ten minutes
+    #[arg(long, value_name = "SECONDS", value_parser = parse_rewind)]
+    rewind: Option<usize>,
+    /// Pause after a time without input
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds)]
+    idle_pause: Option<u64>,
+    /// Tune the speed and quirks of the ROM and save them next to it
+    #[arg(long)]
+    calibrate: bool,
+    /// Break at an address
+    #[arg(long = "break", value_name = "ADDR", value_parser = parse_address)]
+    breakpoints: Vec<u16>,
+    /// Run on a framebuffer device with keys from an evdev device, without a window
+    #[cfg(feature = "fbdev")]
+    #[arg(long, num_args = 2, value_names = ["FRAMEBUFFER", "KEYBOARD"])]
+    fbdev: Option<Vec<PathBuf>>,
+    /// Run in the terminal, without a window
+    #[cfg(feature = "tui")]
+    #[arg(long)]
+    tui: bool,
+    /// Write every change of the keypad to a movie file on exit
+    #[arg(long, value_name = "FILE")]
+    record_input: Option<String>,
+    /// Play the keys of a movie file back, with its seed and memory fill
+    #[arg(long, value_name = "FILE", conflicts_with = "record_input")]
+    play_input: Option<String>,
+    /// Keep the settings, saves and captures of the ROMs in a directory beside the executable
+    #[arg(long)]
+    portable: bool,
+    /// Directory of the ROMs listed by the library when no ROM is given
+    #[arg(long, value_name = "DIR", default_value = ".")]
+    library: PathBuf,
+}
+
+#[derive(Args)]
+#[command(next_help_heading = "Debugging")]
+struct DebugArgs {
+    /// Print a summary of the session on exit
+    #[arg(long)]
+    stats: bool,
+    /// Print the machine state on exit
+    #[arg(long)]
+    dump_on_exit: bool,
+    /// Name the addresses in the machine state after the labels of a symbol file
+    #[arg(long, value_name = "FILE")]
+    symbols: Option<String>,
+    /// Warn on reads of memory that was never written
+    #[arg(long)]
+    warn_uninit: bool,
+    /// Warn on loops that poll the delay timer
+    #[arg(long)]
+    warn_timer_polling: bool,
+    /// Halt on the first irregularity
+    #[arg(long)]
+    strict: bool,
+    /// Log every executed instruction and the registers it changed, to FILE or to stderr
+    #[arg(long, value_name = "FILE", num_args = 0..=1, default_missing_value = "-")]
+    trace: Option<String>,
+    /// Write a bundle to replay the run when a fault halts it
+    #[arg(long, value_name = "DIR")]
+    repro_on_fault: Option<String>,
+    /// Save a screenshot every EVERY frames for FRAMES frames, without a window
+    #[arg(long, num_args = 3, value_names = ["DIR", "EVERY", "FRAMES"])]
+    screenshots: Option<Vec<String>>,
+    /// Run the ROM twice for FRAMES frames and check that both runs match
+    #[arg(long, value_name = "FRAMES", value_parser = parse_frames)]
+    audit: Option<u64>,
+}
 
 fn main() -> GameResult<()> {
-    let fpath: String = std::env::args().skip(1).take(1).collect();
-    let mut prog = std::fs::File::open(fpath)?;
+    let cli = Cli::parse();
+    let lang = cli.display.lang.unwrap_or_else(Lang::from_env);
+    match cli.command {
+        Some(Command::Asm {
+            source,
+            output,
+            symbols,
+        }) => {
+            let text = std::fs::read_to_string(&source)
+                .map_err(|err| GameError::ResourceLoadError(format!("{}: {}", source, err)))?;
+            let (rom, labels) = asm::assemble_with_symbols(&text)
+                .map_err(|err| GameError::ResourceLoadError(format!("{}: {}", source, err)))?;
+            std::fs::write(&output, rom)
+                .map_err(|err| GameError::FilesystemError(format!("{}: {}", output, err)))?;
+            if let Some(path) = symbols {
+                std::fs::write(&path, labels.to_text())
+                    .map_err(|err| GameError::FilesystemError(format!("{}: {}", path, err)))?;
+            }
+            return Ok(());
+        }
+        Some(Command::Disasm { rom }) => {
+            let rom = std::fs::read(&rom)
+                .map_err(|err| GameError::ResourceLoadError(format!("{}: {}", rom, err)))?;
+            print!("{}", disasm::listing(&rom, 0x200));
+            return Ok(());
+        }
+        Some(Command::Repro { dir, print }) => return replay(&dir, print, lang),
+        Some(Command::Movie { edit }) => return edit_movie(edit),
+        Some(Command::Compliance { quirks }) => {
+            let base = quirks.profile.map(|profile| profile.quirks());
+            let summary =
+                compliance::run(override_quirks(base.unwrap_or_default(), &quirks.overrides));
+            let text = summary
+                .to_toml()
+                .map_err(|err| GameError::ConfigError(err.to_string()))?;
+            print!("{}", text);
+            if !summary.passed() {
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        Some(Command::Test {
+            roms,
+            frames,
+            quirks,
+        }) => {
+            let paths = data_paths(cli.session.portable)?;
+            let mut passed = true;
+            for rom in &roms {
+                let name = rom.display().to_string();
+                let error = |err: &dyn std::fmt::Display| {
+                    GameError::ResourceLoadError(format!("{}: {}", name, err))
+                };
+                let program = rom::Rom::open(rom).map_err(|err| error(&err))?;
+                print_rom_warnings(rom, &program);
+                let rom_config = romconfig::RomConfig::load(&storage::FileStorage, &paths, rom)
+                    .map_err(|err| error(&err))?;
+                let base = quirks.profile.map(|profile| profile.quirks());
+                let base = base.or(rom_config.quirks).unwrap_or_default();
+                let result = runner::run(
+                    &name,
+                    program.data(),
+                    override_quirks(base, &quirks.overrides),
+                    frames,
+                );
+                match &result.message {
+                    Some(message) => println!("FAIL {}: {}", name, message),
+                    None => println!("PASS {}", name),
+                }
+                passed &= result.passed;
+            }
+            if !passed {
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        Some(Command::Conformance { dir, bless }) => {
+            let suite = conformance::Suite::from_dir(&dir).map_err(|err| {
+                GameError::ResourceLoadError(format!("{}: {}", dir.display(), err))
+            })?;
+            let results = suite.run(&dir, bless);
+            for result in &results {
+                match &result.message {
+                    Some(message) => println!("FAIL {}: {}", result.name, message),
+                    None => println!("PASS {}", result.name),
+                }
+            }
+            if results.iter().any(|result| !result.passed) {
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        None => {}
+    }
+    if cli.emulation.list_quirks {
+        list_quirks();
+        return Ok(());
+    }
+
+    let DisplayArgs {
+        scale,
+        palette,
+        theme,
+        fullscreen,
+        mute: muted,
+        sonify,
+        streamer,
+        streamer_layout,
+        crt,
+        phosphor,
+        input_display,
+        perf_graphs,
+        no_splash,
+        ..
+    } = cli.display;
+    let palette = palette
+        .as_deref()
+        .and_then(palette::Palette::from_list)
+        .or(theme);
+    let EmulationArgs {
+        speed: ticks_per_sec,
+        quirks,
+        seed,
+        random,
+        mem_fill,
+        interpreter_image,
+        host_traps,
+        #[cfg(feature = "rtc")]
+        rtc,
+        costs,
+        patches,
+        ..
+    } = cli.emulation;
+    let SessionArgs {
+        rewind,
+        idle_pause,
+        calibrate,
+        breakpoints,
+        #[cfg(feature = "fbdev")]
+        fbdev,
+        #[cfg(feature = "tui")]
+        tui,
+        record_input,
+        play_input,
+        portable,
+        library,
+    } = cli.session;
+    let paths = data_paths(portable)?;
+    let DebugArgs {
+        stats: print_stats,
+        dump_on_exit,
+        symbols,
+        warn_uninit,
+        warn_timer_polling,
+        strict,
+        trace,
+        repro_on_fault: repro_dir,
+        screenshots,
+        audit: audit_frames,
+    } = cli.debug;
+    let profile = quirks.profile.map(|profile| profile.quirks());
+    let quirk_overrides = quirks.overrides;
+    let mem_fill = mem_fill.unwrap_or_default();
+    let turbo = cli.input.turbo;
+    let rewind_frames = rewind.unwrap_or(rewind::DEFAULT_FRAMES);
+    let idle_timeout = idle_pause.map(std::time::Duration::from_secs);
+    let load_keymap = |path: Option<String>| -> GameResult<Option<keymap::KeyMap>> {
+        path.map(|path| {
+            keymap::KeyMap::from_file(&storage::FileStorage, Path::new(&path))
+                .map_err(|err| GameError::ConfigError(format!("{}: {}", path, err)))
+        })
+        .transpose()
+    };
+    // The bindings of --keymap and --gamepad go on top of those of --controls
+    let mut controls = match cli.input.controls {
+        Some(path) => controls::Controls::from_file(&storage::FileStorage, Path::new(&path))
+            .map_err(|err| GameError::ConfigError(format!("{}: {}", path, err)))?,
+        None => controls::Controls::default(),
+    };
+    if let Some(keymap) = load_keymap(cli.input.keymap)? {
+        controls.keys.extend(&keymap);
+    }
+    if let Some(gamepad) = load_keymap(cli.input.gamepad)? {
+        controls.gamepad.extend(&gamepad);
+    }
+    if let Some(path) = cli.input.export_controls {
+        return export_controls(&controls, &path, lang);
+    }
+    let keymap = controls.keys.clone();
+    let gamepad = controls.gamepad.clone();
+    let symbols = match symbols {
+        Some(path) => symbols::Symbols::from_file(&path)
+            .map_err(|err| GameError::ResourceLoadError(format!("{}: {}", path, err)))?,
+        None => symbols::Symbols::default(),
+    };
+    let costs = match costs {
+        Some(path) => cost::CostTable::from_file(&path)
+            .map_err(|err| GameError::ResourceLoadError(format!("{}: {}", path, err)))?,
+        None => cost::CostTable::default(),
+    };
+    let random_source = match random {
+        Some(value) => match value.strip_prefix("replay:") {
+            Some(path) => rng::RandomSource::Replay(std::fs::read(path)?),
+            None => value.parse().map_err(GameError::ConfigError)?,
+        },
+        None => rng::RandomSource::default(),
+    };
+    // A movie is played back with the settings it was recorded with
+    let movie = play_input
+        .map(|path| {
+            movie::Movie::read(&storage::FileStorage, Path::new(&path))
+                .map_err(|err| GameError::ResourceLoadError(format!("{}: {}", path, err)))
+        })
+        .transpose()?;
+    let (seed, mem_fill) = match &movie {
+        Some(movie) => (
+            Some(movie.seed),
+            movie.mem_fill.parse().map_err(GameError::ConfigError)?,
+        ),
+        None => (seed, mem_fill),
+    };
+    let screenshots = match screenshots.as_deref() {
+        Some([dir, every, frames]) => {
+            let count = |value: &str| {
+                value
+                    .parse::<u64>()
+                    .map_err(|_| GameError::ConfigError(Message::InvalidScreenshots.text(lang)))
+            };
+            Some((dir.clone(), count(every)?, count(frames)?))
+        }
+        _ => None,
+    };
+    #[cfg(feature = "fbdev")]
+    let fbdev = fbdev.map(|paths| fbdev::FbdevConfig {
+        framebuffer: paths[0].clone(),
+        keyboard: paths[1].clone(),
+        ticks_per_sec: TICKS_PER_SEC,
+    });
+
+    let mut drivers = Vec::new();
+    if cli.input.input_stdin {
+        drivers.push(input::spawn_stdin());
+    }
+    let driver_error = |path: &Path, err: std::io::Error| {
+        GameError::ResourceLoadError(format!("{}: {}", path.display(), err))
+    };
+    for path in cli.input.input_fifo {
+        let events = input::spawn_fifo(path.clone()).map_err(|err| driver_error(&path, err))?;
+        drivers.push(events);
+    }
+    for path in cli.input.input_serial {
+        let events = input::spawn_serial(path.clone()).map_err(|err| driver_error(&path, err))?;
+        drivers.push(events);
+    }
+    #[cfg(feature = "osc")]
+    for addr in cli.input.input_osc {
+        drivers.push(input::spawn_osc(addr)?);
+    }
+    #[cfg(feature = "midi")]
+    for path in cli.input.input_midi {
+        drivers.push(input::spawn_midi(path, input::DEFAULT_BASE_NOTE)?);
+    }
+    #[cfg(feature = "gpio")]
+    for pins in cli.input.input_gpio {
+        drivers.push(input::spawn_gpio(pins)?);
+    }
 
-    let mut prog_mem = [0u8; 0xDFF];
-    let prog_len = prog.read(&mut prog_mem)?;
+    // Playlists are replaced by the ROMs they list
+    let mut playlist = Vec::new();
+    for path in cli.roms {
+        if playlist::is_playlist(&path) {
+            let roms = playlist::read(&path).map_err(|err| {
+                GameError::ResourceLoadError(format!("{}: {}", path.display(), err))
+            })?;
+            playlist.extend(roms);
+        } else {
+            playlist.push(path);
+        }
+    }
+    // Without a ROM the window shows the library, the other frontends need one
+    let first_rom = playlist.first().cloned();
+    let fpath = first_rom.clone().unwrap_or_else(|| library.clone());
+    let no_rom = || match &first_rom {
+        Some(_) => Ok(()),
+        None => Err(GameError::ConfigError(Message::NoRom.text(lang))),
+    };
+    // Fail before opening a window when the first ROM cannot be read, later ones are reported
+    // in the window
+    let readable = if fpath.is_dir() {
+        std::fs::read_dir(&fpath).map(drop)
+    } else {
+        std::fs::File::open(&fpath).map(drop)
+    };
+    if let Err(err) = readable {
+        let message = Message::UnreadableRom {
+            rom: fpath.display().to_string(),
+            err: err.to_string(),
+        };
+        return Err(GameError::ResourceLoadError(message.text(lang)));
+    }
 
-    let mut chip8 = Chip8::default();
-    chip8.reset();
-    chip8.load(&prog_mem, prog_len);
+    // Both runs of an audit must see the same inputs
+    if audit_frames.is_some() {
+        drivers.clear();
+    }
+    // Headless runs capture the program from its first frame
+    let splash = !no_splash && audit_frames.is_none() && screenshots.is_none();
 
+    // Also used by the window to open another ROM, the input drivers are attached to the first
+    // machine that loads. The settings of the ROM replace the defaults, not the flags.
+    let record = repro_dir.is_some() || record_input.is_some();
+    let (debug_tx, debug_rx) = std::sync::mpsc::channel();
+    // The terminal frontend owns the standard output, the lines of the traps are printed on exit
+    #[cfg(feature = "tui")]
+    let print_traps = !tui;
+    #[cfg(not(feature = "tui"))]
+    let print_traps = true;
+    let load_paths = paths.clone();
+    let storage: Arc<dyn storage::Storage> = Arc::new(storage::FileStorage);
+    let load_storage = storage.clone();
+    let mut load = move |path: &Path| -> Result<(Chip8, window::RomSettings), String> {
+        let error = |err: &dyn std::fmt::Display| format!("{}: {}", path.display(), err);
+        let rom_config = romconfig::RomConfig::load(load_storage.as_ref(), &load_paths, path)
+            .map_err(|err| {
+                let path = romconfig::RomConfig::path(&load_paths, path);
+                format!("{}: {}", path.display(), err)
+            })?;
+        let base = profile.or(rom_config.quirks).unwrap_or_default();
+        let quirks = override_quirks(base, &quirk_overrides);
+        let mut settings = window::RomSettings {
+            ticks_per_sec: ticks_per_sec.or(rom_config.speed).unwrap_or(TICKS_PER_SEC),
+            keymap: rom_config.keys.unwrap_or_default(),
+            gamepad: rom_config.gamepad.unwrap_or_default(),
+            palette: palette.or(rom_config.palette).unwrap_or_default(),
+        };
+        settings.keymap.extend(&keymap);
+        settings.gamepad.extend(&gamepad);
+
+        // A directory is opened with the launcher listing its ROMs
+        let mut launcher = None;
+        let program = if path.is_dir() {
+            let recent_file = load_paths.recent_file();
+            let recent = recent_file
+                .as_deref()
+                .map(|file| shell::recent(load_storage.as_ref(), file))
+                .unwrap_or_default();
+            let roms =
+                shell::entries(path, &window::ROM_EXTENSIONS, recent).map_err(|err| error(&err))?;
+            let names: Vec<_> = roms.iter().map(|rom| shell::name(rom)).collect();
+            launcher = Some(shell::launcher(
+                roms,
+                load_paths.clone(),
+                load_storage.clone(),
+            ));
+            shell::program(&names)
+        } else {
+            let rom = rom::Rom::open(path).map_err(|err| error(&err))?;
+            print_rom_warnings(path, &rom);
+            rom.data().to_vec()
+        };
+
+        let mut builder = Chip8::builder()
+            .memory_fill(mem_fill)
+            .warn_uninit(warn_uninit)
+            .warn_timer_polling(warn_timer_polling)
+            .strict(strict)
+            .costs(costs.clone())
+            .random_source(random_source.clone())
+            .quirks(quirks)
+            .splash(splash);
+        if let Some(seed) = seed {
+            builder = builder.seed(seed);
+        }
+        let mut chip8 = builder.build();
+        chip8.load(&program).map_err(|err| error(&err))?;
+        chip8.set_symbols(symbols.clone());
+        if let Some(movie) = &movie {
+            chip8.play(movie);
+        }
+        if host_traps {
+            let debug_tx = debug_tx.clone();
+            let traps = trap::Traps::builtin(move |line: String| {
+                if print_traps {
+                    println!("{}", line);
+                }
+                let _ = debug_tx.send(line);
+            });
+            launcher = Some(traps.fallback(launcher).into_hook());
+        }
+        chip8.set_interpreter_hook(launcher);
+        if let Some(path) = &trace {
+            let writer: trace::TraceWriter = match path.as_str() {
+                "-" => Box::new(std::io::stderr()),
+                path => {
+                    let file =
+                        std::fs::File::create(path).map_err(|err| format!("{}: {}", path, err))?;
+                    Box::new(std::io::BufWriter::new(file))
+                }
+            };
+            chip8.set_trace(Some(writer));
+        }
+        #[cfg(feature = "rtc")]
+        if let Some(addr) = rtc {
+            chip8.attach_peripheral(Box::new(peripheral::Rtc::new(addr)));
+        }
+        match interpreter_image.as_deref() {
+            Some("builtin") => chip8.load_interpreter_image(&BUILTIN_INTERPRETER_IMAGE),
+            Some(image) => {
+                let image = std::fs::read(image).map_err(|err| format!("{}: {}", image, err))?;
+                chip8.load_interpreter_image(&image);
+            }
+            None => {}
+        }
+        for path in &patches {
+            patch::Patch::from_file(path)
+                .and_then(|patch| chip8.apply_patch(&patch))
+                .map_err(|err| format!("{}: {}", path, err))?;
+        }
+        for (key, rate) in &turbo {
+            chip8
+                .set_turbo(*key, Some(*rate))
+                .map_err(|err| err.to_string())?;
+        }
+        for events in drivers.drain(..) {
+            chip8.attach_input(Box::new(input::EventSource::new(events)));
+        }
+        if record {
+            chip8.record();
+        }
+        Ok((chip8, settings))
+    };
+
+    if let Some(frames) = audit_frames {
+        no_rom()?;
+        let (first, _) = load(&fpath).map_err(GameError::ResourceLoadError)?;
+        let (second, _) = load(&fpath).map_err(GameError::ResourceLoadError)?;
+        let report = audit::audit(first, second, &[], frames);
+        print!("{}", report);
+        if report.divergence.is_some() {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if let Some((dir, every, frames)) = screenshots {
+        no_rom()?;
+        let (mut chip8, _) = load(&fpath).map_err(GameError::ResourceLoadError)?;
+        std::fs::create_dir_all(&dir)?;
+        let paths = screenshot::capture(
+            &storage::FileStorage,
+            &mut chip8,
+            Path::new(&dir),
+            every,
+            frames,
+        )?;
+        print_warnings(&mut chip8, lang);
+        let message = Message::WroteScreenshots {
+            count: paths.len(),
+            dir: dir.clone(),
+        };
+        println!("{}", message.text(lang));
+        return write_repro(&chip8, repro_dir, lang);
+    }
+
+    #[cfg(feature = "fbdev")]
+    if let Some(mut config) = fbdev {
+        no_rom()?;
+        let (mut chip8, settings) = load(&fpath).map_err(GameError::ResourceLoadError)?;
+        config.ticks_per_sec = settings.ticks_per_sec;
+        fbdev::run(&mut chip8, &config)?;
+        print_warnings(&mut chip8, lang);
+        print_diagnostics(&chip8, lang);
+        write_movie(&chip8, record_input, lang)?;
+        return write_repro(&chip8, repro_dir, lang);
+    }
+
+    #[cfg(feature = "tui")]
+    if tui {
+        no_rom()?;
+        let (mut chip8, settings) = load(&fpath).map_err(GameError::ResourceLoadError)?;
+        tui::run(&mut chip8, settings.ticks_per_sec)?;
+        debug_rx.try_iter().for_each(|line| println!("{}", line));
+        print_warnings(&mut chip8, lang);
+        print_diagnostics(&chip8, lang);
+        write_movie(&chip8, record_input, lang)?;
+        return write_repro(&chip8, repro_dir, lang);
+    }
+
+    let layout = match streamer_layout {
+        Some(path) => Some(
+            layout::Layout::from_file(&path)
+                .map_err(|err| GameError::ConfigError(format!("{}: {}", path, err)))?,
+        ),
+        None if streamer => Some(layout::Layout::default()),
+        None => None,
+    };
+    // The streamer mode opens at the size of its layout
+    let (window_width, window_height) = match &layout {
+        Some(layout) => (layout.width, layout.height),
+        None => (
+            CHIP8_SCREEN_WIDTH as f32 * scale,
+            CHIP8_SCREEN_HEIGHT as f32 * scale,
+        ),
+    };
     let (ctx, event_loop) = &mut ContextBuilder::new("CHIP-8", "Tung L. Vo")
         .window_setup(ggez::conf::WindowSetup::default().title("CHIP-8"))
-        .window_mode(ggez::conf::WindowMode::default().dimensions(WINDOW_WIDTH, WINDOW_HEIGHT))
+        .window_mode(
+            ggez::conf::WindowMode::default()
+                .dimensions(window_width, window_height)
+                .resizable(true)
+                .fullscreen_type(if fullscreen {
+                    ggez::conf::FullscreenType::Desktop
+                } else {
+                    ggez::conf::FullscreenType::Windowed
+                }),
+        )
         .build()?;
-    event::run(ctx, event_loop, &mut chip8)
+    // A ROM that cannot be loaded is reported in the window, on top of an empty machine
+    let mut window = window::Window::new(Chip8::builder().build());
+    window.set_input_display(input_display);
+    window.set_crt(crt);
+    window.set_phosphor(phosphor.unwrap_or_default());
+    window.set_perf_graphs(perf_graphs);
+    window.set_rewind_frames(rewind_frames);
+    window.set_idle_timeout(idle_timeout);
+    window.set_muted(muted);
+    window.set_sonify(sonify);
+    window.set_layout(layout);
+    // The window is drawn at the default scale and fitted to its actual size
+    let (drawable_width, drawable_height) = ggez::graphics::drawable_size(ctx);
+    window.fit_to_window(ctx, drawable_width, drawable_height)?;
+    window.set_debug_output(debug_rx);
+    window.set_lang(lang);
+    window.set_data_paths(paths);
+    window.set_storage(storage);
+    window.set_hotkeys(controls.hotkeys);
+    window.set_loader(&fpath, Box::new(load));
+    if calibrate {
+        window.calibrate(&fpath);
+    }
+    for addr in breakpoints {
+        window.debugger_mut().add_breakpoint(addr);
+    }
+    window.set_playlist(playlist);
+    if first_rom.is_some() {
+        window.load_rom(&fpath);
+    } else {
+        let entries = library::scan(&library, &window::ROM_EXTENSIONS).map_err(|err| {
+            GameError::ResourceLoadError(format!("{}: {}", library.display(), err))
+        })?;
+        window.show_library(&library, entries);
+    }
+    window::run(ctx, event_loop, &mut window)?;
+    let chip8 = window.into_inner();
+    if dump_on_exit {
+        print!("{}", chip8.dump());
+    }
+    if print_stats {
+        print!("{}", chip8.stats());
+    }
+    print_diagnostics(&chip8, lang);
+    write_movie(&chip8, record_input, lang)?;
+    write_repro(&chip8, repro_dir, lang)
+}
+
+/// Apply the parsed quirk settings of the command line on top of a configuration
+fn override_quirks(mut quirks: quirks::Quirks, overrides: &[(String, bool)]) -> quirks::Quirks {
+    for (name, on) in overrides {
+        quirks.set(name, *on);
+    }
+    quirks
+}
+
+/// Print every quirk with its setting in each profile
+fn list_quirks() {
+    print!("{:<16} {:>7}", "QUIRK", "DEFAULT");
+    for profile in quirks::Profile::ALL.iter() {
+        print!(" {:>7}", profile.name());
+    }
+    println!("  OPCODES");
+    let on_off = |on| if on { "on" } else { "off" };
+    for info in quirks::QUIRKS.iter() {
+        print!(
+            "{:<16} {:>7}",
+            info.name,
+            on_off((info.get)(&quirks::Quirks::default()))
+        );
+        for profile in quirks::Profile::ALL.iter() {
+            print!(" {:>7}", on_off((info.get)(&profile.quirks())));
+        }
+        println!("  {}", info.opcodes.join(", "));
+        println!("    {}", info.description);
+    }
+}
+
+/// Print the problems of a ROM that did not prevent it from loading
+fn print_rom_warnings(path: &Path, rom: &rom::Rom) {
+    for problem in rom.warnings() {
+        eprintln!("{}: {}, {}", path.display(), problem, problem.hint());
+    }
+}
+
+/// Print the warnings of a headless session and the state where a fault halted it
+fn print_warnings(chip8: &mut Chip8, lang: Lang) {
+    for warning in chip8.take_warnings() {
+        eprintln!("{}", warning);
+    }
+    if let Some(fault) = chip8.fault() {
+        eprintln!("{}", Message::Halted(fault.to_string()).text(lang));
+        eprint!("{}", chip8.dump());
+    }
+}
+
+/// Print the compatibility notes of a session with their counts, if there are any
+fn print_diagnostics(chip8: &Chip8, lang: Lang) {
+    if !chip8.diagnostics().is_empty() {
+        eprintln!("{}", Message::CompatibilityNotes.text(lang));
+        eprint!("{}", chip8.diagnostics().report(&chip8.quirks()));
+    }
+}
+
+/// Write the controls with the default gamepad bindings spelled out, so that the file shows
+/// every binding. Conflicts are reported but do not stop the export.
+fn export_controls(controls: &controls::Controls, path: &str, lang: Lang) -> GameResult<()> {
+    let mut exported = controls.clone();
+    exported.gamepad = keymap::KeyMap::gamepad();
+    exported.gamepad.extend(&controls.gamepad);
+    for conflict in exported.conflicts() {
+        eprintln!(
+            "{}",
+            Message::ControlsConflict(conflict.to_string()).text(lang)
+        );
+    }
+    exported
+        .write(&storage::FileStorage, Path::new(path))
+        .map_err(|err| GameError::FilesystemError(format!("{}: {}", path, err)))?;
+    println!("{}", Message::ExportedControls(path.to_string()).text(lang));
+    Ok(())
+}
+
+/// Where the files of the ROMs are kept, creating the data directory of the portable mode
+fn data_paths(portable: bool) -> GameResult<datapaths::DataPaths> {
+    datapaths::DataPaths::detect(portable)
+        .map_err(|err| GameError::FilesystemError(err.to_string()))
+}
+
+/// Write the reproduction bundle of the run if it was halted by a fault
+fn write_repro(chip8: &Chip8, dir: Option<String>, lang: Lang) -> GameResult<()> {
+    if let (Some(dir), Some(bundle)) = (dir, chip8.repro_bundle()) {
+        if chip8.fault().is_some() {
+            bundle
+                .write(&dir)
+                .map_err(|err| GameError::FilesystemError(format!("{}: {}", dir, err)))?;
+            println!("{}", Message::WroteRepro(dir).text(lang));
+        }
+    }
+    Ok(())
+}
+
+/// Run a `chip8 movie` command
+fn edit_movie(edit: MovieEdit) -> GameResult<()> {
+    let read = |path: &str| {
+        movie::Movie::read(&storage::FileStorage, Path::new(path))
+            .map_err(|err| GameError::ResourceLoadError(format!("{}: {}", path, err)))
+    };
+    let write = |movie: &movie::Movie, path: &str| {
+        movie
+            .write(&storage::FileStorage, Path::new(path))
+            .map_err(|err| GameError::FilesystemError(format!("{}: {}", path, err)))
+    };
+    match edit {
+        MovieEdit::Trim {
+            movie,
+            output,
+            steps,
+        } => {
+            let mut movie = read(&movie)?;
+            movie.trim(steps);
+            write(&movie, &output)
+        }
+        MovieEdit::Splice {
+            first,
+            second,
+            output,
+            at,
+        } => {
+            let movie = read(&first)?
+                .splice(&read(&second)?, at)
+                .map_err(|err| GameError::ConfigError(format!("{}: {}", second, err)))?;
+            write(&movie, &output)
+        }
+        MovieEdit::Retime {
+            movie,
+            output,
+            from,
+            to,
+        } => {
+            let mut movie = read(&movie)?;
+            movie.retime(from, to);
+            write(&movie, &output)
+        }
+        MovieEdit::Convert { input, output, rom } => {
+            let movie = if Path::new(&input).is_dir() {
+                repro::Bundle::read(&input)
+                    .map(movie::Movie::from)
+                    .map_err(|err| GameError::ResourceLoadError(format!("{}: {}", input, err)))?
+            } else {
+                read(&input)?
+            };
+            match rom {
+                Some(rom) => {
+                    let rom = std::fs::read(&rom)
+                        .map_err(|err| GameError::ResourceLoadError(format!("{}: {}", rom, err)))?;
+                    movie
+                        .to_bundle(&rom)
+                        .write(&output)
+                        .map_err(|err| GameError::FilesystemError(format!("{}: {}", output, err)))
+                }
+                None => write(&movie, &output),
+            }
+        }
+    }
+}
+
+/// Write the keys of the recorded run to a movie file
+fn write_movie(chip8: &Chip8, path: Option<String>, lang: Lang) -> GameResult<()> {
+    if let (Some(path), Some(bundle)) = (path, chip8.repro_bundle()) {
+        movie::Movie::from(bundle)
+            .write(&storage::FileStorage, Path::new(&path))
+            .map_err(|err| GameError::FilesystemError(format!("{}: {}", path, err)))?;
+        println!("{}", Message::WroteMovie(path).text(lang));
+    }
+    Ok(())
+}
+
+/// Replay a reproduction bundle and open the state where it failed in the debugger of a window,
+/// or print it
+fn replay(dir: &str, print: bool, lang: Lang) -> GameResult<()> {
+    let bundle = repro::Bundle::read(dir)
+        .map_err(|err| GameError::ResourceLoadError(format!("{}: {}", dir, err)))?;
+    let mut chip8 = bundle
+        .replay()
+        .map_err(|err| GameError::ResourceLoadError(format!("{}: {}", dir, err)))?;
+    if let Some(fault) = chip8.fault() {
+        println!("{}", Message::Reproduced(fault.to_string()).text(lang));
+    } else if let Some(fault) = &bundle.manifest.fault {
+        println!("{}", Message::NotReproduced(fault.clone()).text(lang));
+    }
+    if print {
+        print!("{}", chip8.dump());
+        return Ok(());
+    }
+    let (ctx, event_loop) = &mut ContextBuilder::new("CHIP-8", "Tung L. Vo")
+        .window_setup(ggez::conf::WindowSetup::default().title("CHIP-8"))
+        .window_mode(
+            ggez::conf::WindowMode::default()
+                .dimensions(
+                    CHIP8_SCREEN_WIDTH as f32 * window::PIXEL_SIZE as f32,
+                    CHIP8_SCREEN_HEIGHT as f32 * window::PIXEL_SIZE as f32,
+                )
+                .resizable(true),
+        )
+        .build()?;
+    // The replay counted the recorded frames, the window counts them with the clock from here
+    chip8.set_manual_timers(false);
+    let mut window = window::Window::new(chip8);
+    let (drawable_width, drawable_height) = ggez::graphics::drawable_size(ctx);
+    window.fit_to_window(ctx, drawable_width, drawable_height)?;
+    window.set_lang(lang);
+    window.debugger_mut().pause();
+    window::run(ctx, event_loop, &mut window)
+}
+
+/// Parse a `NAME=on|off` quirk setting. Values are parsed before `--lang` is known, so their
+/// errors are in the language of the environment.
+fn parse_quirk(setting: &str) -> Result<(String, bool), String> {
+    let invalid = || Message::InvalidQuirk(setting.to_string()).text(Lang::from_env());
+    let (name, value) = setting.split_once('=').ok_or_else(invalid)?;
+    let on = match value {
+        "on" | "true" | "1" => true,
+        "off" | "false" | "0" => false,
+        _ => return Err(invalid()),
+    };
+    if !quirks::Quirks::default().set(name, on) {
+        return Err(invalid());
+    }
+    Ok((name.to_string(), on))
+}
+
+/// Parse a turbo-fire setting given as `K` or `K:RATE`
+fn parse_turbo(value: &str) -> Result<(u8, f32), String> {
+    let invalid = || Message::InvalidTurbo(value.to_string()).text(Lang::from_env());
+    let mut parts = value.splitn(2, ':');
+    let key = u8::from_str_radix(parts.next().unwrap_or_default(), 16).map_err(|_| invalid())?;
+    let rate = match parts.next() {
+        Some(rate) => rate.parse().map_err(|_| invalid())?,
+        None => input::DEFAULT_TURBO_RATE,
+    };
+    input::check_turbo(key, rate).map_err(|_| invalid())?;
+    Ok((key, rate))
+}
+
+#[cfg(feature = "gpio")]
+fn parse_gpio_pins(value: &str) -> Result<input::GpioPins, String> {
+    input::GpioPins::parse(value).ok_or_else(|| Message::InvalidGpioPins.text(Lang::from_env()))
+}
+
+fn parse_speed(value: &str) -> Result<u32, String> {
+    value
+        .parse()
+        .ok()
+        .filter(|ticks| *ticks > 0)
+        .ok_or_else(|| Message::InvalidSpeed(value.to_string()).text(Lang::from_env()))
+}
+
+fn parse_scale(value: &str) -> Result<f32, String> {
+    value
+        .parse()
+        .ok()
+        .filter(|scale| (1.0..=64.0).contains(scale))
+        .ok_or_else(|| Message::InvalidScale(value.to_string()).text(Lang::from_env()))
+}
+
+fn parse_color(value: &str) -> Result<u32, String> {
+    console::parse_color(value)
+        .map_err(|_| Message::InvalidColor(value.to_string()).text(Lang::from_env()))
+}
+
+fn parse_theme(value: &str) -> Result<palette::Palette, String> {
+    palette::Palette::theme(value)
+        .ok_or_else(|| Message::InvalidTheme(value.to_string()).text(Lang::from_env()))
+}
+
+fn parse_seconds(value: &str) -> Result<u64, String> {
+    value
+        .parse()
+        .map_err(|_| Message::InvalidSeconds(value.to_string()).text(Lang::from_env()))
+}
+
+/// Parse the length of the rewind history into a number of frames
+fn parse_rewind(value: &str) -> Result<usize, String> {
+    value
+        .parse()
+        .ok()
+        .and_then(rewind::frames)
+        .ok_or_else(|| Message::InvalidSeconds(value.to_string()).text(Lang::from_env()))
+}
+
+fn parse_frames(value: &str) -> Result<u64, String> {
+    value
+        .parse()
+        .map_err(|_| Message::InvalidFrames(value.to_string()).text(Lang::from_env()))
+}
+
+fn parse_seed(value: &str) -> Result<u64, String> {
+    value
+        .parse()
+        .map_err(|_| Message::InvalidSeed.text(Lang::from_env()))
+}
+
+fn parse_address(value: &str) -> Result<u16, String> {
+    u16::from_str_radix(value.trim_start_matches("0x"), 16)
+        .map_err(|_| Message::InvalidAddress(value.to_string()).text(Lang::from_env()))
 }
@@ -0,0 +1,120 @@
+//! Initial contents of the program memory
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Pattern used to fill the program memory on reset, before a ROM is loaded. Filling it with
+/// something other than zeros flushes out programs that read memory they have never written.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryFill {
+    /// Every byte is 0x00
+    #[default]
+    Zero,
+    /// Every byte is 0xFF
+    Ones,
+    /// Pseudo-random bytes generated from the given seed
+    Random(u64),
+    /// Pseudo-random bytes generated from the seed of the machine, see
+    /// [`crate::Chip8Builder::seed`]
+    RandomFromSeed,
+}
+
+impl MemoryFill {
+    /// Fill the memory with the pattern, `seed` is the seed of the machine
+    pub fn fill(&self, mem: &mut [u8], seed: u64) {
+        match self {
+            Self::Zero => mem.iter_mut().for_each(|b| *b = 0x00),
+            Self::Ones => mem.iter_mut().for_each(|b| *b = 0xFF),
+            Self::RandomFromSeed => Self::Random(seed).fill(mem, seed),
+            Self::Random(seed) => {
+                // xorshift64*, the state must never be zero
+                let mut state = seed ^ 0x9E37_79B9_7F4A_7C15;
+                if state == 0 {
+                    state = 1;
+                }
+                for b in mem.iter_mut() {
+                    state ^= state >> 12;
+                    state ^= state << 25;
+                    state ^= state >> 27;
+                    *b = (state.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 56) as u8;
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for MemoryFill {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Zero => write!(f, "zero"),
+            Self::Ones => write!(f, "ff"),
+            Self::Random(seed) => write!(f, "random:{}", seed),
+            Self::RandomFromSeed => write!(f, "random"),
+        }
+    }
+}
+
+impl FromStr for MemoryFill {
+    type Err = String;
+
+    /// Parse `zero`, `ff`, `random` (from the seed of the machine) or `random:SEED`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "zero" | "00" => Ok(Self::Zero),
+            "ff" | "ones" => Ok(Self::Ones),
+            "random" => Ok(Self::RandomFromSeed),
+            _ => s
+                .strip_prefix("random:")
+                .and_then(|seed| seed.parse().ok())
+                .map(Self::Random)
+                .ok_or_else(|| format!("Unknown memory fill pattern {}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MemoryFill;
+    use crate::Chip8;
+
+    #[test]
+    fn patterns_are_parsed_and_written_back() {
+        assert_eq!("zero".parse(), Ok(MemoryFill::Zero));
+        assert_eq!("00".parse(), Ok(MemoryFill::Zero));
+        assert_eq!("ff".parse(), Ok(MemoryFill::Ones));
+        assert_eq!("ones".parse(), Ok(MemoryFill::Ones));
+        assert_eq!("random".parse(), Ok(MemoryFill::RandomFromSeed));
+        assert_eq!("random:42".parse(), Ok(MemoryFill::Random(42)));
+        assert!("random:".parse::<MemoryFill>().is_err());
+        assert!("random:-1".parse::<MemoryFill>().is_err());
+        assert!("noise".parse::<MemoryFill>().is_err());
+        for fill in [
+            MemoryFill::Zero,
+            MemoryFill::Ones,
+            MemoryFill::Random(u64::MAX),
+            MemoryFill::RandomFromSeed,
+        ] {
+            assert_eq!(fill.to_string().parse(), Ok(fill));
+        }
+    }
+
+    #[test]
+    fn bare_random_is_seeded_by_the_machine() {
+        let memory = |fill, seed| {
+            let chip8 = Chip8::builder().memory_fill(fill).seed(seed).build();
+            chip8.memory()[0x200..0x280].to_vec()
+        };
+        assert_eq!(
+            memory(MemoryFill::RandomFromSeed, 1),
+            memory(MemoryFill::RandomFromSeed, 1)
+        );
+        assert_ne!(
+            memory(MemoryFill::RandomFromSeed, 1),
+            memory(MemoryFill::RandomFromSeed, 2)
+        );
+        assert_eq!(
+            memory(MemoryFill::RandomFromSeed, 7),
+            memory(MemoryFill::Random(7), 8)
+        );
+    }
+}
@@ -0,0 +1,232 @@
+//! Input movies for tool-assisted runs: every change of the keypad with the step it happened at,
+//! along with the seed and the memory fill of the run. Playing a movie back sets the same keys
+//! before the same steps, so whole games can be replayed and checked as regression tests.
+//!
+//! A movie can be edited without playing it again: trimmed to its first steps, spliced with
+//! another recording of the same start, re-timed for another speed, or converted from and to a
+//! reproduction bundle. A state saved while recording remembers the step it was saved at, so
+//! loading it rewinds the recording and it resumes from there.
+//!
+//! ```toml
+//! seed = 42
+//! mem_fill = "zero"
+//! steps = 5400
+//!
+//! [[inputs]]
+//! step = 100
+//! keys = 0x0010
+//! ```
+
+use crate::input;
+use crate::repro;
+use crate::repro::InputChange;
+use crate::storage::Storage;
+use serde::Deserialize;
+use serde::Serialize;
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+/// The inputs of a recorded run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Movie {
+    /// Seed of the random number generator
+    #[serde(with = "repro::seed")]
+    pub seed: u64,
+    /// Pattern that filled the memory, see [`crate::memory::MemoryFill`]
+    pub mem_fill: String,
+    /// Number of steps covered by the movie, live input takes over after them
+    pub steps: u64,
+    /// Changes of the keypad state
+    #[serde(default)]
+    pub inputs: Vec<InputChange>,
+}
+
+impl Movie {
+    /// Read a movie from a file
+    pub fn read(storage: &dyn Storage, path: &Path) -> Result<Self, MovieError> {
+        let text = storage.read_to_string(path).map_err(MovieError::Io)?;
+        toml::from_str(&text).map_err(MovieError::Parse)
+    }
+
+    /// Write the movie to a file
+    pub fn write(&self, storage: &dyn Storage, path: &Path) -> Result<(), MovieError> {
+        let text = toml::to_string(self).map_err(MovieError::Serialize)?;
+        storage.write(path, text.as_bytes()).map_err(MovieError::Io)
+    }
+
+    /// Keep only the first steps of the movie, live input takes over after them
+    pub fn trim(&mut self, steps: u64) {
+        self.inputs.retain(|change| change.step < steps);
+        self.steps = self.steps.min(steps);
+    }
+
+    /// The steps of this movie before `at` followed by those of another recording of the same
+    /// start from `at` on, e.g. to redo the end of a run
+    pub fn splice(&self, other: &Movie, at: u64) -> Result<Movie, MovieError> {
+        if (self.seed, &self.mem_fill) != (other.seed, &other.mem_fill) {
+            return Err(MovieError::Mismatch);
+        }
+        let mut movie = Movie {
+            inputs: Vec::new(),
+            steps: other.steps.max(at),
+            ..self.clone()
+        };
+        let before = self.inputs.iter().filter(|change| change.step < at);
+        let held = other.inputs.iter().rfind(|change| change.step <= at);
+        let after = other.inputs.iter().filter(|change| change.step > at);
+        let held = InputChange {
+            step: at,
+            keys: held.map_or(0, |change| change.keys),
+        };
+        for change in before.chain(Some(&held)).chain(after) {
+            movie.push(*change);
+        }
+        Ok(movie)
+    }
+
+    /// Move every change for a run at another speed, from `from` to `to` instructions per
+    /// second, so that it happens at the same time
+    pub fn retime(&mut self, from: u32, to: u32) {
+        let scale = |step: u64| (step as u128 * to as u128 / from.max(1) as u128) as u64;
+        let inputs = std::mem::take(&mut self.inputs);
+        for change in inputs {
+            self.push(InputChange {
+                step: scale(change.step),
+                keys: change.keys,
+            });
+        }
+        self.steps = scale(self.steps);
+    }
+
+    /// The reproduction bundle of the run of a program with the inputs of the movie, with the
+    /// frames of a frontend running at the default speed
+    pub fn to_bundle(&self, rom: &[u8]) -> repro::Bundle {
+        let ticks_per_frame = (crate::TICKS_PER_SEC / 60) as u64;
+        repro::Bundle {
+            rom: rom.to_vec(),
+            interpreter_image: Vec::new(),
+            manifest: repro::Manifest {
+                seed: self.seed,
+                mem_fill: self.mem_fill.clone(),
+                strict: false,
+                random_source: crate::rng::RandomSource::default().to_string(),
+                random_bytes: Vec::new(),
+                steps: self.steps,
+                fault: None,
+                frames: (1..=self.steps / ticks_per_frame)
+                    .map(|frame| frame * ticks_per_frame)
+                    .collect(),
+                quirks: Default::default(),
+                costs: Default::default(),
+                patches: Vec::new(),
+                inputs: self.inputs.clone(),
+            },
+        }
+    }
+
+    /// Append a change, replacing a change at the same step and dropping one that keeps the
+    /// keys as they are
+    fn push(&mut self, change: InputChange) {
+        if let Some(last) = self.inputs.last() {
+            if last.step == change.step {
+                self.inputs.pop();
+            }
+        }
+        let held = self.inputs.last().map_or(0, |last| last.keys);
+        if change.keys != held {
+            self.inputs.push(change);
+        }
+    }
+}
+
+impl From<repro::Bundle> for Movie {
+    /// The inputs of a run recorded with [`crate::Chip8::record`]
+    fn from(bundle: repro::Bundle) -> Self {
+        Self {
+            seed: bundle.manifest.seed,
+            mem_fill: bundle.manifest.mem_fill,
+            steps: bundle.manifest.steps,
+            inputs: bundle.manifest.inputs,
+        }
+    }
+}
+
+/// Plays a movie back one step at a time
+#[derive(Debug, Clone)]
+pub(crate) struct Player {
+    inputs: Vec<InputChange>,
+    /// Index of the next change to apply
+    next: usize,
+    /// Number of steps played so far
+    step: u64,
+    steps: u64,
+}
+
+impl Player {
+    pub(crate) fn new(movie: &Movie) -> Self {
+        Self {
+            inputs: movie.inputs.clone(),
+            next: 0,
+            step: 0,
+            steps: movie.steps,
+        }
+    }
+
+    /// The keypad state to set before the next step, if it changes
+    pub(crate) fn step(&mut self) -> Option<input::Keys> {
+        let mut keys = None;
+        while let Some(change) = self.inputs.get(self.next) {
+            if change.step > self.step {
+                break;
+            }
+            keys = Some(repro::from_mask(change.keys));
+            self.next += 1;
+        }
+        self.step += 1;
+        keys
+    }
+
+    /// Whether every step of the movie has been played
+    pub(crate) fn finished(&self) -> bool {
+        self.step >= self.steps
+    }
+
+    /// Number of steps played so far
+    pub(crate) fn position(&self) -> u64 {
+        self.step
+    }
+
+    /// Continue from a step, as when a state saved at that step is loaded
+    pub(crate) fn seek(&mut self, step: u64) {
+        self.next = self.inputs.partition_point(|change| change.step < step);
+        self.step = step;
+    }
+}
+
+/// Reasons for a movie to be rejected
+#[derive(Debug)]
+pub enum MovieError {
+    /// The file could not be read or written
+    Io(io::Error),
+    /// The file is not valid
+    Parse(toml::de::Error),
+    /// The movie could not be written
+    Serialize(toml::ser::Error),
+    /// Two movies do not start from the same seed and memory fill
+    Mismatch,
+}
+
+impl fmt::Display for MovieError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "could not access the movie: {}", err),
+            Self::Parse(err) => write!(f, "invalid movie: {}", err),
+            Self::Serialize(err) => write!(f, "could not write the movie: {}", err),
+            Self::Mismatch => write!(f, "the movies do not start with the same seed and memory"),
+        }
+    }
+}
+
+impl std::error::Error for MovieError {}
@@ -0,0 +1,75 @@
+//! Pacing of the frontends that run their own frame loop. The ticks are spread over the frames so
+//! that exactly `ticks_per_sec` run every second, and the timers count down with the clock of the
+//! host, see [`Chip8::run_timers`], instead of after a number of ticks.
+
+use crate::clock;
+use crate::Chip8;
+use crate::MAX_TIMER_CATCH_UP;
+use crate::TIMER_PERIOD;
+use std::time;
+
+/// Nanoseconds per second
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+
+/// Runs a machine at a number of ticks per second
+#[derive(Debug, Clone, Default)]
+pub struct Pacer {
+    /// Billionths of a tick owed to the machine
+    owed: u128,
+    /// When the ticks were last counted, None until the first call to [`Pacer::run`]
+    last: Option<clock::Instant>,
+}
+
+impl Pacer {
+    /// Number of ticks in the given time at a speed, the fraction of a tick that is left over is
+    /// owed to the next call. A frontend that stalled runs at most [`MAX_TIMER_CATCH_UP`] frames
+    /// of ticks. Frontends whose frames are paced by their host, e.g. a libretro frontend, pass
+    /// [`TIMER_PERIOD`] for every frame.
+    pub fn ticks(&mut self, elapsed: time::Duration, ticks_per_sec: u32) -> u32 {
+        let elapsed = elapsed.min(TIMER_PERIOD * MAX_TIMER_CATCH_UP);
+        self.owed += elapsed.as_nanos() * ticks_per_sec as u128;
+        let ticks = self.owed / NANOS_PER_SEC;
+        self.owed %= NANOS_PER_SEC;
+        ticks as u32
+    }
+
+    /// Count the timers down with the clock and run the ticks that are due since the last call
+    pub fn run(&mut self, chip8: &mut Chip8, ticks_per_sec: u32) {
+        let now = clock::Instant::now();
+        let elapsed = self
+            .last
+            .map_or(time::Duration::ZERO, |last| now.duration_since(last));
+        self.last = Some(now);
+        chip8.run_timers();
+        for _ in 0..self.ticks(elapsed, ticks_per_sec) {
+            chip8.step();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Pacer;
+    use crate::MAX_TIMER_CATCH_UP;
+    use crate::TIMER_PERIOD;
+    use std::time::Duration;
+
+    #[test]
+    fn fractions_of_a_tick_are_owed() {
+        let mut pacer = Pacer::default();
+        let ticks: u32 = (0..1000)
+            .map(|_| pacer.ticks(Duration::from_millis(1), 500))
+            .sum();
+        assert_eq!(ticks, 500);
+        assert_eq!(pacer.ticks(Duration::from_micros(1500), 500), 0);
+        assert_eq!(pacer.ticks(Duration::from_micros(500), 500), 1);
+    }
+
+    #[test]
+    fn stalls_are_not_caught_up() {
+        let mut pacer = Pacer::default();
+        let most = pacer.ticks(TIMER_PERIOD * MAX_TIMER_CATCH_UP, 600);
+        let mut pacer = Pacer::default();
+        assert_eq!(pacer.ticks(Duration::from_secs(10), 600), most);
+    }
+}
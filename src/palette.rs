@@ -0,0 +1,148 @@
+//! Colors of the screen. XO-CHIP draws on two bit planes, so a pixel takes one of four colors
+//! picked by the planes it is lit in: none, the first, the second or both, like the
+//! `backgroundColor`, `fillColor`, `fillColor2` and `blendColor` of Octo. Only the first plane is
+//! emulated so far, the colors of the second plane are kept for when it is.
+//!
+//! Palettes are written as the lit and unlit colors, optionally followed by the colors of the
+//! second plane and of both planes, e.g. in the settings of a ROM:
+//!
+//! ```toml
+//! palette = ["FFCC00", "996600", "FF6600", "662200"]
+//! ```
+//!
+//! or as the name of one of the built-in [`THEMES`], such as `palette = "amber"`.
+
+use crate::console::parse_color;
+use serde::Deserialize;
+use serde::Serialize;
+use std::convert::TryFrom;
+
+/// Number of colors of a palette, one per combination of planes
+pub const PLANE_COLORS: usize = 4;
+
+/// Built-in palettes by name, with their colors in the order of [`Palette::from_list`]. The
+/// first one is the default.
+pub const THEMES: [(&str, [u32; PLANE_COLORS]); 5] = [
+    ("classic", [0xFFFFFF, 0x000000, 0xAAAAAA, 0x555555]),
+    ("green", [0x33FF66, 0x0A1A0F, 0x1F9940, 0x66FF99]),
+    ("amber", [0xFFB000, 0x1A1200, 0x996A00, 0xFFD066]),
+    ("lcd", [0x0F380F, 0x9BBC0F, 0x306230, 0x8BAC0F]),
+    ("inverted", [0x000000, 0xFFFFFF, 0x555555, 0xAAAAAA]),
+];
+
+/// How a palette is written in the settings, the name of a theme or a list of colors
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PaletteSpec {
+    Theme(String),
+    Colors(Vec<String>),
+}
+
+/// The color of every combination of planes, as `0xRRGGBB`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "PaletteSpec", into = "Vec<String>")]
+pub struct Palette {
+    /// Indexed by the planes, bit 0 for the first plane and bit 1 for the second
+    colors: [u32; PLANE_COLORS],
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self {
+            colors: [0x000000, 0xFFFFFF, 0xAAAAAA, 0x555555],
+        }
+    }
+}
+
+impl Palette {
+    /// A palette from the lit and unlit colors, optionally followed by the colors of the second
+    /// plane and of both planes. Missing colors keep their default, returns `None` unless there
+    /// are two to four colors.
+    pub fn from_list(list: &[u32]) -> Option<Self> {
+        if !(2..=PLANE_COLORS).contains(&list.len()) {
+            return None;
+        }
+        let mut palette = Self::default();
+        for (planes, color) in [1, 0, 2, 3].iter().zip(list) {
+            palette.colors[*planes] = *color;
+        }
+        Some(palette)
+    }
+
+    /// The built-in palette with a name, see [`THEMES`]
+    pub fn theme(name: &str) -> Option<Self> {
+        THEMES
+            .iter()
+            .find(|(theme, _)| theme.eq_ignore_ascii_case(name))
+            .and_then(|(_, colors)| Self::from_list(colors))
+    }
+
+    /// The name of the built-in palette with the same colors, if there is one
+    pub fn theme_name(&self) -> Option<&'static str> {
+        THEMES
+            .iter()
+            .find(|(_, colors)| *colors == self.to_list())
+            .map(|(name, _)| *name)
+    }
+
+    /// The built-in palette after this one, the first one after the last one or after a palette
+    /// that is not built in
+    pub fn next_theme(&self) -> Self {
+        let next = match self.theme_name() {
+            Some(name) => THEMES.iter().position(|(theme, _)| *theme == name).unwrap() + 1,
+            None => 0,
+        };
+        Self::from_list(&THEMES[next % THEMES.len()].1).unwrap()
+    }
+
+    /// The colors in the order of [`Palette::from_list`]
+    pub fn to_list(&self) -> [u32; PLANE_COLORS] {
+        let [background, first, second, both] = self.colors;
+        [first, background, second, both]
+    }
+
+    /// The color of a pixel lit in the given planes
+    pub fn color(&self, planes: usize) -> u32 {
+        self.colors[planes % PLANE_COLORS]
+    }
+
+    /// Change the color of a pixel lit in the given planes
+    pub fn set_color(&mut self, planes: usize, color: u32) {
+        self.colors[planes % PLANE_COLORS] = color;
+    }
+}
+
+impl TryFrom<PaletteSpec> for Palette {
+    type Error = String;
+
+    fn try_from(spec: PaletteSpec) -> Result<Self, Self::Error> {
+        match spec {
+            PaletteSpec::Theme(name) => {
+                Self::theme(&name).ok_or_else(|| format!("unknown palette {}", name))
+            }
+            PaletteSpec::Colors(list) => Self::try_from(list),
+        }
+    }
+}
+
+impl TryFrom<Vec<String>> for Palette {
+    type Error = String;
+
+    fn try_from(list: Vec<String>) -> Result<Self, Self::Error> {
+        let colors = list
+            .iter()
+            .map(|color| parse_color(color))
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::from_list(&colors).ok_or_else(|| "a palette has two to four colors".to_string())
+    }
+}
+
+impl From<Palette> for Vec<String> {
+    fn from(palette: Palette) -> Self {
+        palette
+            .to_list()
+            .iter()
+            .map(|color| format!("{:06X}", color))
+            .collect()
+    }
+}
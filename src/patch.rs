@@ -0,0 +1,110 @@
+//! Human-readable patch files that are applied to a program right after it is loaded, a kind of
+//! trainer that can be shared alongside a ROM and versioned in git.
+//!
+//! ```toml
+//! name = "Infinite lives"
+//!
+//! [[bytes]]
+//! addr = 0x2A4
+//! data = [0x60, 0x09]
+//!
+//! [registers]
+//! v3 = 9
+//! i = 0x300
+//! ```
+
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// A set of memory changes and register presets
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Patch {
+    /// Description of the patch
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Bytes written to memory
+    #[serde(default)]
+    pub bytes: Vec<Bytes>,
+    /// Values of the registers, keyed by `v0`-`vf`, `i`, `pc`, `dt` or `st`
+    #[serde(default)]
+    pub registers: BTreeMap<String, u16>,
+}
+
+/// Bytes written to memory starting from an address
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Bytes {
+    /// Address of the first byte
+    pub addr: u16,
+    /// The bytes to write
+    pub data: Vec<u8>,
+}
+
+/// Reasons for a patch to be rejected
+#[derive(Debug)]
+pub enum PatchError {
+    /// The patch file could not be read
+    Io(io::Error),
+    /// The patch file is not valid
+    Parse(toml::de::Error),
+    /// The bytes do not fit in memory
+    OutOfRange {
+        /// Address of the first byte
+        addr: u16,
+        /// Number of bytes
+        len: usize,
+    },
+    /// The register does not exist
+    UnknownRegister(String),
+    /// The value does not fit in the register
+    ValueTooLarge {
+        /// Name of the register
+        register: String,
+        /// The rejected value
+        value: u16,
+    },
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "could not read the patch: {}", err),
+            Self::Parse(err) => write!(f, "invalid patch: {}", err),
+            Self::OutOfRange { addr, len } => {
+                write!(f, "{} bytes at {:#05X} do not fit in memory", len, addr)
+            }
+            Self::UnknownRegister(name) => write!(f, "unknown register {}", name),
+            Self::ValueTooLarge { register, value } => {
+                write!(
+                    f,
+                    "value {:#X} does not fit in register {}",
+                    value, register
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for PatchError {}
+
+impl Patch {
+    /// Read a patch from a TOML file
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, PatchError> {
+        let text = fs::read_to_string(path).map_err(PatchError::Io)?;
+        text.parse()
+    }
+}
+
+impl std::str::FromStr for Patch {
+    type Err = PatchError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        toml::from_str(s).map_err(PatchError::Parse)
+    }
+}
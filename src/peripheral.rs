@@ -0,0 +1,19 @@
+//! Peripherals that expose host state to programs through a range of memory, which they update
+//! once per frame, e.g. a real-time clock for kiosk clocks and calendars.
+
+#[cfg(feature = "rtc")]
+mod rtc;
+
+#[cfg(feature = "rtc")]
+pub use rtc::*;
+
+use std::ops::Range;
+
+/// A device mapped into the memory of the machine
+pub trait Peripheral: Send {
+    /// The addresses the device is mapped to
+    fn range(&self) -> Range<u16>;
+
+    /// Write the state of the device into its range of memory, called once per frame
+    fn update(&mut self, memory: &mut [u8]);
+}
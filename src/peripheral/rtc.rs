@@ -0,0 +1,44 @@
+use super::Peripheral;
+use chrono::Datelike;
+use chrono::Local;
+use chrono::Timelike;
+use std::ops::Range;
+
+/// Number of bytes the clock is mapped to
+pub const RTC_SIZE: u16 = 8;
+
+/// A real-time clock showing the local date and time of the host. Its eight bytes are the year
+/// (big-endian), the month (1-12), the day of the month (1-31), the hour (0-23), the minute, the
+/// second and the day of the week (0 for Sunday).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rtc {
+    addr: u16,
+}
+
+impl Rtc {
+    /// A clock mapped to the eight bytes starting at `addr`
+    pub fn new(addr: u16) -> Self {
+        Self { addr }
+    }
+}
+
+impl Peripheral for Rtc {
+    fn range(&self) -> Range<u16> {
+        self.addr..self.addr + RTC_SIZE
+    }
+
+    fn update(&mut self, memory: &mut [u8]) {
+        let now = Local::now();
+        let [year_hi, year_lo] = (now.year().clamp(0, u16::MAX as i32) as u16).to_be_bytes();
+        memory.copy_from_slice(&[
+            year_hi,
+            year_lo,
+            now.month() as u8,
+            now.day() as u8,
+            now.hour() as u8,
+            now.minute() as u8,
+            now.second() as u8,
+            now.weekday().num_days_from_sunday() as u8,
+        ]);
+    }
+}
@@ -0,0 +1,57 @@
+//! The phosphor decay of the screen, an optional mode against the flicker of CHIP-8 games.
+//! Sprites are drawn with XOR, so a game erases a sprite before drawing it at its new place and
+//! the sprite is missing from some frames. With the decay, a pixel that goes off fades out over
+//! the next frames instead of vanishing, like the glow of the phosphor of a CRT, and the previous
+//! frames are blended into the one on the screen.
+
+/// Number of frames a pixel takes to fade out, unless another number is given
+pub const DEFAULT_FRAMES: u8 = 4;
+
+/// How long ago each pixel of the screen was last lit
+#[derive(Debug, Clone)]
+pub struct Phosphor {
+    frames: u8,
+    ages: Vec<u8>,
+}
+
+impl Phosphor {
+    /// Pixels that fade out over the given number of 60 Hz frames, at least one
+    pub fn new(frames: u8) -> Self {
+        Self {
+            frames: frames.max(1),
+            ages: Vec::new(),
+        }
+    }
+
+    /// Number of frames a pixel takes to fade out
+    pub fn frames(&self) -> u8 {
+        self.frames
+    }
+
+    /// Advance by a number of frames to a framebuffer, the lit pixels are as bright as they get
+    /// and the others fade. Returns true if the brightness of some pixel changed.
+    pub fn update(&mut self, framebuffer: &[bool], elapsed: u64) -> bool {
+        if self.ages.len() != framebuffer.len() {
+            self.ages = vec![self.frames; framebuffer.len()];
+        }
+        let elapsed = elapsed.min(self.frames as u64) as u8;
+        let mut changed = false;
+        for (age, lit) in self.ages.iter_mut().zip(framebuffer) {
+            let next = if *lit {
+                0
+            } else {
+                age.saturating_add(elapsed).min(self.frames)
+            };
+            changed |= next != *age;
+            *age = next;
+        }
+        changed
+    }
+
+    /// Brightness of a pixel, from 1.0 while it is lit to 0.0 once it has faded out
+    pub fn brightness(&self, pixel: usize) -> f32 {
+        self.ages
+            .get(pixel)
+            .map_or(0.0, |age| 1.0 - *age as f32 / self.frames as f32)
+    }
+}
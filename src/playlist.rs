@@ -0,0 +1,40 @@
+//! Playlists of ROMs, text files that list one ROM per line. Blank lines and lines starting with
+//! `#` are skipped, so M3U playlists can be used as they are, and relative paths are relative to
+//! the playlist.
+//!
+//! ```text
+//! # meetup demo
+//! games/pong.ch8
+//! games/brix.ch8
+//! ```
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Extensions of the files read as playlists instead of ROMs
+pub const EXTENSIONS: [&str; 2] = ["m3u", "txt"];
+
+/// Whether a file is read as a playlist
+pub fn is_playlist(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// The ROMs listed in a playlist
+pub fn parse(text: &str, dir: &Path) -> Vec<PathBuf> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| dir.join(line))
+        .collect()
+}
+
+/// Read the ROMs listed in a playlist file
+pub fn read<P: AsRef<Path>>(path: P) -> io::Result<Vec<PathBuf>> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path)?;
+    Ok(parse(&text, path.parent().unwrap_or_else(|| Path::new(""))))
+}
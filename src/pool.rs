@@ -0,0 +1,115 @@
+//! A pool of machines that are stepped together, for workloads that run many emulators at once
+//! such as reinforcement learning or the analysis of a corpus of ROMs. With the `parallel`
+//! feature the machines are stepped in parallel on a rayon thread pool, otherwise one after
+//! another.
+
+use super::savestate;
+use super::Chip8;
+use super::SCHIP_SCREEN_HEIGHT;
+use super::SCHIP_SCREEN_WIDTH;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Number of pixels of a framebuffer, at the SUPER-CHIP high resolution
+pub const FRAMEBUFFER_LEN: usize = SCHIP_SCREEN_WIDTH * SCHIP_SCREEN_HEIGHT;
+
+/// Owns a set of machines and steps them together
+pub struct Chip8Pool {
+    machines: Vec<Chip8>,
+    /// Threads that step the machines, the global rayon pool if there is none
+    #[cfg(feature = "parallel")]
+    threads: Option<rayon::ThreadPool>,
+}
+
+impl Chip8Pool {
+    /// Create a pool of `n` machines, each created by calling `init` with its index
+    pub fn new<F: FnMut(usize) -> Chip8>(n: usize, init: F) -> Self {
+        Self {
+            machines: (0..n).map(init).collect(),
+            #[cfg(feature = "parallel")]
+            threads: None,
+        }
+    }
+
+    /// Limit the number of threads used to step the machines, instead of the threads of the
+    /// global rayon pool
+    #[cfg(feature = "parallel")]
+    pub fn set_threads(&mut self, threads: usize) -> Result<(), rayon::ThreadPoolBuildError> {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads.max(1))
+            .build()?;
+        self.threads = Some(pool);
+        Ok(())
+    }
+
+    /// The machines of the pool
+    pub fn machines(&self) -> &[Chip8] {
+        &self.machines
+    }
+
+    /// The machines of the pool, e.g. to set their keys between steps
+    pub fn machines_mut(&mut self) -> &mut [Chip8] {
+        &mut self.machines
+    }
+
+    /// Run `steps` ticks on every machine. A machine halted by a fault stays halted, see
+    /// [`Chip8::fault`].
+    pub fn step(&mut self, steps: usize) {
+        let step = |chip8: &mut Chip8| {
+            for _ in 0..steps {
+                chip8.step();
+            }
+        };
+        #[cfg(feature = "parallel")]
+        {
+            let machines = &mut self.machines;
+            match &self.threads {
+                Some(pool) => pool.install(|| machines.par_iter_mut().for_each(step)),
+                None => machines.par_iter_mut().for_each(step),
+            }
+        }
+        #[cfg(not(feature = "parallel"))]
+        self.machines.iter_mut().for_each(step);
+    }
+
+    /// Start the next 1/60 s frame on every machine, counting their timers down, see
+    /// [`Chip8::next_frame`]
+    pub fn next_frame(&mut self) {
+        self.machines.iter_mut().for_each(Chip8::next_frame);
+    }
+
+    /// The complete state of every machine, in order
+    pub fn save_states(&self) -> Vec<savestate::SaveState> {
+        self.machines.iter().map(Chip8::save_state).collect()
+    }
+
+    /// Restore the state of every machine from states returned by [`Chip8Pool::save_states`].
+    /// `states` must hold one state per machine, the first state that cannot be loaded stops the
+    /// loading with its error.
+    pub fn load_states(
+        &mut self,
+        states: &[savestate::SaveState],
+    ) -> Result<(), savestate::SaveStateError> {
+        assert_eq!(states.len(), self.machines.len());
+        self.machines
+            .iter_mut()
+            .zip(states)
+            .try_for_each(|(chip8, state)| chip8.load_state(state))
+    }
+
+    /// Copy the framebuffers of all machines one after another into `out`, one byte per pixel
+    /// (0 or 1). `out` must hold `FRAMEBUFFER_LEN` bytes per machine, the screens of machines in
+    /// low resolution are scaled up by two.
+    pub fn copy_framebuffers(&self, out: &mut [u8]) {
+        assert_eq!(out.len(), self.machines.len() * FRAMEBUFFER_LEN);
+        for (chip8, out) in self.machines.iter().zip(out.chunks_mut(FRAMEBUFFER_LEN)) {
+            let (width, _) = chip8.screen_size();
+            let scale = SCHIP_SCREEN_WIDTH / width;
+            let gfx = chip8.framebuffer();
+            for (idx, out) in out.iter_mut().enumerate() {
+                let (x, y) = (idx % SCHIP_SCREEN_WIDTH, idx / SCHIP_SCREEN_WIDTH);
+                *out = gfx[x / scale + y / scale * width] as u8;
+            }
+        }
+    }
+}
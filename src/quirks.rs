@@ -0,0 +1,235 @@
+//! Behaviours that differ between CHIP-8 interpreters. Programs were written against a specific
+//! interpreter and often rely on its quirks, so they can be switched per ROM or as a whole with a
+//! profile. Every quirk is described in [`QUIRKS`] so that frontends can generate their settings
+//! from it.
+
+use serde::Deserialize;
+use serde::Serialize;
+use std::str::FromStr;
+
+/// The set of behaviours that differ between interpreters, see [`QUIRKS`] for a description
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Quirks {
+    /// 8XY6 and 8XYE shift Vx in place instead of shifting Vy into Vx
+    pub shift_in_place: bool,
+    /// FX55 and FX65 leave I pointing past the last register that was stored or loaded
+    pub increment_index: bool,
+    /// BNNN jumps to XNN plus VX instead of NNN plus V0
+    pub jump_vx: bool,
+    /// Sprites drawn past the edge of the screen wrap around instead of being clipped
+    pub wrap_sprites: bool,
+    /// 8XY1, 8XY2 and 8XY3 reset VF to zero
+    pub vf_reset: bool,
+    /// 00CN, 00FB and 00FC scroll by half the distance in low resolution, as SUPER-CHIP 1.1 which
+    /// scrolls by high resolution pixels
+    pub half_pixel_scroll: bool,
+    /// DXYN in high resolution sets VF to the number of rows that collided or were clipped off
+    /// the bottom of the screen instead of 1 on any collision
+    pub collision_rows: bool,
+    /// DXYN waits for the next 60 Hz frame after drawing, as on the COSMAC VIP where drawing
+    /// waited for the display interrupt, which limits programs to one sprite per frame
+    pub display_wait: bool,
+    /// FX0A latches the first key that is pressed and only stores it once the key is released,
+    /// as the COSMAC VIP did, instead of storing any key that is held
+    pub key_release: bool,
+}
+
+impl Default for Quirks {
+    /// The behaviour of this emulator before quirks could be selected, a mix of the profiles,
+    /// except that FX0A waits for the release of the key as in all of them
+    fn default() -> Self {
+        Self {
+            shift_in_place: true,
+            increment_index: true,
+            jump_vx: false,
+            wrap_sprites: true,
+            vf_reset: false,
+            half_pixel_scroll: false,
+            collision_rows: true,
+            display_wait: false,
+            key_release: true,
+        }
+    }
+}
+
+/// Description of a quirk, for settings UIs and `--list-quirks`
+#[derive(Debug, Clone, Copy)]
+pub struct QuirkInfo {
+    /// Short name, as used on the command line
+    pub name: &'static str,
+    /// What the quirk does when enabled
+    pub description: &'static str,
+    /// Opcodes whose behaviour changes
+    pub opcodes: &'static [&'static str],
+    /// Whether the quirk is enabled
+    pub get: fn(&Quirks) -> bool,
+    /// Enable or disable the quirk
+    pub set: fn(&mut Quirks, bool),
+}
+
+/// Every supported quirk
+pub const QUIRKS: [QuirkInfo; 9] = [
+    QuirkInfo {
+        name: "shift_in_place",
+        description: "8XY6 and 8XYE shift Vx in place instead of shifting Vy into Vx",
+        opcodes: &["8XY6", "8XYE"],
+        get: |quirks| quirks.shift_in_place,
+        set: |quirks, on| quirks.shift_in_place = on,
+    },
+    QuirkInfo {
+        name: "increment_index",
+        description: "FX55 and FX65 leave I pointing past the last register",
+        opcodes: &["FX55", "FX65"],
+        get: |quirks| quirks.increment_index,
+        set: |quirks, on| quirks.increment_index = on,
+    },
+    QuirkInfo {
+        name: "jump_vx",
+        description: "BNNN jumps to XNN plus VX instead of NNN plus V0",
+        opcodes: &["BNNN"],
+        get: |quirks| quirks.jump_vx,
+        set: |quirks, on| quirks.jump_vx = on,
+    },
+    QuirkInfo {
+        name: "wrap_sprites",
+        description: "Sprites wrap around the edges of the screen instead of being clipped",
+        opcodes: &["DXYN"],
+        get: |quirks| quirks.wrap_sprites,
+        set: |quirks, on| quirks.wrap_sprites = on,
+    },
+    QuirkInfo {
+        name: "vf_reset",
+        description: "8XY1, 8XY2 and 8XY3 reset VF to zero",
+        opcodes: &["8XY1", "8XY2", "8XY3"],
+        get: |quirks| quirks.vf_reset,
+        set: |quirks, on| quirks.vf_reset = on,
+    },
+    QuirkInfo {
+        name: "half_pixel_scroll",
+        description: "Scrolling in low resolution moves by half the distance",
+        opcodes: &["00CN", "00FB", "00FC"],
+        get: |quirks| quirks.half_pixel_scroll,
+        set: |quirks, on| quirks.half_pixel_scroll = on,
+    },
+    QuirkInfo {
+        name: "collision_rows",
+        description: "VF counts the rows that collided or were clipped in high resolution",
+        opcodes: &["DXYN"],
+        get: |quirks| quirks.collision_rows,
+        set: |quirks, on| quirks.collision_rows = on,
+    },
+    QuirkInfo {
+        name: "display_wait",
+        description: "DXYN waits for the next 60 Hz frame after drawing",
+        opcodes: &["DXYN"],
+        get: |quirks| quirks.display_wait,
+        set: |quirks, on| quirks.display_wait = on,
+    },
+    QuirkInfo {
+        name: "key_release",
+        description: "FX0A stores a pressed key once it is released",
+        opcodes: &["FX0A"],
+        get: |quirks| quirks.key_release,
+        set: |quirks, on| quirks.key_release = on,
+    },
+];
+
+impl Quirks {
+    /// Whether the quirk with the given name is enabled
+    pub fn get(&self, name: &str) -> Option<bool> {
+        QUIRKS
+            .iter()
+            .find(|info| info.name == name)
+            .map(|info| (info.get)(self))
+    }
+
+    /// Enable or disable the quirk with the given name, returns false if there is no such quirk
+    pub fn set(&mut self, name: &str, on: bool) -> bool {
+        match QUIRKS.iter().find(|info| info.name == name) {
+            Some(info) => {
+                (info.set)(self, on);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// The quirks of a well-known interpreter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Profile {
+    /// The original COSMAC VIP interpreter
+    Chip8,
+    /// SUPER-CHIP 1.1 on the HP-48
+    Schip,
+    /// Octo and XO-CHIP
+    Xochip,
+}
+
+impl Profile {
+    /// Every profile
+    pub const ALL: [Profile; 3] = [Self::Chip8, Self::Schip, Self::Xochip];
+
+    /// Name of the profile, as used on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Chip8 => "chip8",
+            Self::Schip => "schip",
+            Self::Xochip => "xochip",
+        }
+    }
+
+    /// The quirks of the interpreter
+    pub fn quirks(self) -> Quirks {
+        match self {
+            Self::Chip8 => Quirks {
+                shift_in_place: false,
+                increment_index: true,
+                jump_vx: false,
+                wrap_sprites: false,
+                vf_reset: true,
+                half_pixel_scroll: false,
+                collision_rows: false,
+                display_wait: true,
+                key_release: true,
+            },
+            Self::Schip => Quirks {
+                shift_in_place: true,
+                increment_index: false,
+                jump_vx: true,
+                wrap_sprites: false,
+                vf_reset: false,
+                half_pixel_scroll: true,
+                collision_rows: true,
+                display_wait: false,
+                key_release: true,
+            },
+            Self::Xochip => Quirks {
+                shift_in_place: false,
+                increment_index: true,
+                jump_vx: false,
+                wrap_sprites: true,
+                vf_reset: false,
+                half_pixel_scroll: false,
+                collision_rows: false,
+                display_wait: false,
+                key_release: true,
+            },
+        }
+    }
+}
+
+impl FromStr for Profile {
+    type Err = String;
+
+    /// Parse `chip8` (or `cosmac`), `schip` or `xochip` (or `octo`)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "chip8" | "cosmac" => Ok(Self::Chip8),
+            "schip" => Ok(Self::Schip),
+            "xochip" | "octo" => Ok(Self::Xochip),
+            _ => Err(format!("Unknown quirk profile {}", s)),
+        }
+    }
+}
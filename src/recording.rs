@@ -0,0 +1,219 @@
+//! Recordings of the screen as animated GIFs, for sharing clips and attaching them to bug
+//! reports. The screen is captured at 60 frames per second and scaled up like the screenshots of
+//! [`crate::screenshot`], a screen that does not change is kept as a single longer frame. With
+//! the input display on, the held keys and the frame counter are drawn into the recording like
+//! in the window.
+
+use crate::palette::Palette;
+use crate::screenshot::Image;
+use crate::screenshot::SCREENSHOT_HEIGHT;
+use crate::screenshot::SCREENSHOT_WIDTH;
+use crate::storage::Storage;
+use crate::Chip8;
+use std::borrow::Cow;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Number of frames captured per second
+pub const FRAMES_PER_SEC: u64 = 60;
+/// Shortest delay of a GIF frame in hundredths of a second, viewers slow down shorter ones so
+/// screens shown for less time are dropped
+const MIN_DELAY: u64 = 2;
+/// Colors of the input display after the unlit and lit ones: its background, the keys that are
+/// not held, the held keys and the labels
+const OVERLAY_COLORS: [u32; 4] = [0x000000, 0x808080, 0xFFCC00, 0xFFFFFF];
+const BACKGROUND: u8 = 2;
+const KEY: u8 = 3;
+const HELD: u8 = 4;
+const LABEL: u8 = 5;
+
+/// Encodes the captured screens into an animated GIF, which is written into a storage when the
+/// recording ends or is dropped
+pub struct GifRecorder {
+    storage: Arc<dyn Storage>,
+    path: PathBuf,
+    encoder: Option<gif::Encoder<Vec<u8>>>,
+    /// The last captured screen as palette indices, written once a different one is captured
+    pending: Option<Vec<u8>>,
+    /// Number of captured frames
+    frames: u64,
+    /// Hundredths of a second covered by the written frames
+    written: u64,
+    /// Draw the held keys and the frame counter over the screen
+    input_display: bool,
+}
+
+impl GifRecorder {
+    /// Start recording into a file of a storage, in the lit and unlit colors of a palette
+    pub fn create(storage: Arc<dyn Storage>, path: &Path, palette: &Palette) -> io::Result<Self> {
+        let colors: Vec<u8> = (0..2)
+            .map(|planes| palette.color(planes))
+            .chain(OVERLAY_COLORS.iter().copied())
+            .flat_map(|color| color.to_be_bytes()[1..].to_vec())
+            .collect();
+        let (width, height) = (SCREENSHOT_WIDTH as u16, SCREENSHOT_HEIGHT as u16);
+        let mut encoder =
+            gif::Encoder::new(Vec::new(), width, height, &colors).map_err(encoding)?;
+        encoder
+            .set_repeat(gif::Repeat::Infinite)
+            .map_err(encoding)?;
+        Ok(Self {
+            storage,
+            path: path.to_path_buf(),
+            encoder: Some(encoder),
+            pending: None,
+            frames: 0,
+            written: 0,
+            input_display: false,
+        })
+    }
+
+    /// Draw the held keys and the frame counter over the captured screens
+    pub fn set_input_display(&mut self, enabled: bool) {
+        self.input_display = enabled;
+    }
+
+    /// Number of frames captured so far
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Capture the screen of a machine for one frame
+    pub fn capture(&mut self, chip8: &Chip8) -> io::Result<()> {
+        let image = Image::screenshot(chip8);
+        let mut pixels: Vec<u8> = image
+            .pixels
+            .iter()
+            .map(|&pixel| (pixel != 0) as u8)
+            .collect();
+        if self.input_display {
+            draw_input_display(&mut pixels, chip8);
+        }
+        if self.pending.as_ref() != Some(&pixels) {
+            self.flush(false)?;
+            self.pending = Some(pixels);
+        }
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// Write the last screen and end the recording
+    pub fn finish(mut self) -> io::Result<()> {
+        self.end()
+    }
+
+    /// Write the last screen and the GIF into the storage
+    fn end(&mut self) -> io::Result<()> {
+        self.flush(true)?;
+        match self.encoder.take() {
+            Some(encoder) => self.storage.write(&self.path, &encoder.into_inner()?),
+            None => Ok(()),
+        }
+    }
+
+    /// Write the pending screen, shown until the current frame. It is dropped if it was shown
+    /// too briefly, unless it is the last one.
+    fn flush(&mut self, last: bool) -> io::Result<()> {
+        let (Some(pixels), Some(encoder)) = (self.pending.take(), self.encoder.as_mut()) else {
+            return Ok(());
+        };
+        let now = self.frames * 100 / FRAMES_PER_SEC;
+        let delay = now.saturating_sub(self.written);
+        if delay < MIN_DELAY && !last {
+            return Ok(());
+        }
+        let delay = delay.max(MIN_DELAY);
+        let frame = gif::Frame {
+            width: SCREENSHOT_WIDTH as u16,
+            height: SCREENSHOT_HEIGHT as u16,
+            delay: delay.min(u16::MAX as u64) as u16,
+            buffer: Cow::Owned(pixels),
+            ..gif::Frame::default()
+        };
+        encoder.write_frame(&frame).map_err(encoding)?;
+        self.written += delay;
+        Ok(())
+    }
+}
+
+impl Drop for GifRecorder {
+    fn drop(&mut self) {
+        let _ = self.end();
+    }
+}
+
+/// Draw the keypad with the held keys highlighted and the frame counter below it in the top-right
+/// corner of a screen of palette indices, like the input display of the window
+fn draw_input_display(pixels: &mut [u8], chip8: &Chip8) {
+    const CELL: usize = 10;
+    // Keys in the order they appear on the original keypad
+    const LAYOUT: [usize; 16] = [
+        0x1, 0x2, 0x3, 0xC, 0x4, 0x5, 0x6, 0xD, 0x7, 0x8, 0x9, 0xE, 0xA, 0x0, 0xB, 0xF,
+    ];
+    let (left, top) = (SCREENSHOT_WIDTH - 4 - CELL * 4, 4);
+    fill(
+        pixels,
+        (left - 2, top - 2),
+        (CELL * 4 + 4, CELL * 4 + 11),
+        BACKGROUND,
+    );
+    let keys = chip8.keys();
+    for (idx, &key) in LAYOUT.iter().enumerate() {
+        let (x, y) = (left + idx % 4 * CELL, top + idx / 4 * CELL);
+        let label = if keys[key] {
+            fill(pixels, (x + 1, y + 1), (CELL - 2, CELL - 2), HELD);
+            BACKGROUND
+        } else {
+            fill(pixels, (x + 1, y + 1), (CELL - 2, 1), KEY);
+            fill(pixels, (x + 1, y + CELL - 2), (CELL - 2, 1), KEY);
+            fill(pixels, (x + 1, y + 1), (1, CELL - 2), KEY);
+            fill(pixels, (x + CELL - 2, y + 1), (1, CELL - 2), KEY);
+            LABEL
+        };
+        draw_digit(pixels, (x + 3, y + 3), key, label);
+    }
+    // The last digits of the counter, aligned to the right of the keypad
+    let frames = chip8.stats().frames.to_string();
+    for (idx, digit) in frames.bytes().rev().take(CELL * 4 / 5).enumerate() {
+        let x = left + CELL * 4 - (idx + 1) * 5 + 1;
+        draw_digit(
+            pixels,
+            (x, top + CELL * 4 + 2),
+            (digit - b'0') as usize,
+            LABEL,
+        );
+    }
+}
+
+/// Fill a rectangle of a screen of palette indices
+fn fill(
+    pixels: &mut [u8],
+    (left, top): (usize, usize),
+    (width, height): (usize, usize),
+    color: u8,
+) {
+    for y in top..top + height {
+        let start = y * SCREENSHOT_WIDTH + left;
+        pixels[start..start + width].fill(color);
+    }
+}
+
+/// Draw a hex digit of the CHIP-8 font with its top left pixel at a place
+fn draw_digit(pixels: &mut [u8], (left, top): (usize, usize), digit: usize, color: u8) {
+    for (y, row) in crate::FONT[digit * 5..digit * 5 + 5].iter().enumerate() {
+        for x in 0..4 {
+            if row & (0x80 >> x) != 0 {
+                pixels[(top + y) * SCREENSHOT_WIDTH + left + x] = color;
+            }
+        }
+    }
+}
+
+fn encoding(err: gif::EncodingError) -> io::Error {
+    match err {
+        gif::EncodingError::Io(err) => err,
+        err => io::Error::other(err),
+    }
+}
@@ -0,0 +1,369 @@
+//! Reproduction bundles that capture a failing run so that it can be replayed deterministically.
+//! A bundle is a directory holding the program (`rom.ch8`), the interpreter image if one was
+//! loaded (`interpreter.bin`) and a manifest (`repro.toml`) with the settings of the machine, the
+//! key changes, the frames and the number of steps that led to the failure. The settings that
+//! were left at their default can be omitted.
+//!
+//! ```toml
+//! seed = 42
+//! mem_fill = "zero"
+//! strict = true
+//! random_source = "xorshift"
+//! steps = 1234
+//! fault = "unknown opcode 0xFFFF at 0x2A4"
+//! frames = [8, 16, 24]
+//!
+//! [quirks]
+//! shift_in_place = false
+//!
+//! [costs]
+//! DXYN = 11
+//!
+//! [[inputs]]
+//! step = 100
+//! keys = 0x0010
+//! ```
+
+use crate::cost;
+use crate::input;
+use crate::patch;
+use crate::quirks;
+use crate::rng;
+use crate::Chip8;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Name of the program file inside a bundle
+pub const ROM_FILE: &str = "rom.ch8";
+/// Name of the manifest file inside a bundle
+pub const MANIFEST_FILE: &str = "repro.toml";
+/// Name of the interpreter image file inside a bundle, see [`Chip8::load_interpreter_image`]
+pub const INTERPRETER_FILE: &str = "interpreter.bin";
+
+/// Everything needed to replay a run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bundle {
+    /// The program, loaded at 0x200
+    pub rom: Vec<u8>,
+    /// The image loaded below 0x200, empty if there was none
+    pub interpreter_image: Vec<u8>,
+    /// How the program was run
+    pub manifest: Manifest,
+}
+
+/// Settings and inputs of a recorded run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    /// Seed of the random number generator
+    #[serde(with = "seed")]
+    pub seed: u64,
+    /// Pattern that filled the memory, see [`memory::MemoryFill`]
+    pub mem_fill: String,
+    /// Whether the machine halts on the first irregularity
+    pub strict: bool,
+    /// Source of the bytes of CXKK, see [`rng::RandomSource`]. Replayed bytes are given by
+    /// `random_bytes`.
+    #[serde(default = "default_random_source")]
+    pub random_source: String,
+    /// The bytes of the `replay` random source
+    #[serde(default)]
+    pub random_bytes: Vec<u8>,
+    /// Number of steps run before the failure
+    pub steps: u64,
+    /// Description of the failure, for the reader
+    #[serde(default)]
+    pub fault: Option<String>,
+    /// Number of steps run before each frame started, see [`Chip8::next_frame`]
+    #[serde(default)]
+    pub frames: Vec<u64>,
+    /// The behaviours that differ between interpreters
+    #[serde(default)]
+    pub quirks: quirks::Quirks,
+    /// Number of ticks taken by the opcodes that take more than one
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub costs: BTreeMap<String, u32>,
+    /// Patches applied after loading the program, in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub patches: Vec<patch::Patch>,
+    /// Changes of the keypad state
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inputs: Vec<InputChange>,
+}
+
+fn default_random_source() -> String {
+    rng::RandomSource::default().to_string()
+}
+
+impl Manifest {
+    /// The settings of a machine that is about to run, before any step
+    pub fn of(chip8: &Chip8) -> Self {
+        let random_bytes = match chip8.random_source() {
+            rng::RandomSource::Replay(bytes) => bytes.clone(),
+            _ => Vec::new(),
+        };
+        Self {
+            seed: chip8.seed(),
+            mem_fill: chip8.memory_fill().to_string(),
+            strict: chip8.strict(),
+            random_source: chip8.random_source().to_string(),
+            random_bytes,
+            steps: 0,
+            fault: None,
+            frames: Vec::new(),
+            quirks: chip8.quirks(),
+            costs: chip8
+                .costs()
+                .iter()
+                .filter(|(_, cost)| *cost > 1)
+                .map(|(opcode, cost)| (opcode.to_string(), cost))
+                .collect(),
+            patches: chip8.patches().to_vec(),
+            inputs: Vec::new(),
+        }
+    }
+
+    /// Create a machine with these settings, its timers count down only with
+    /// [`Chip8::next_frame`]
+    pub fn build(&self) -> Result<Chip8, ReproError> {
+        let mem_fill = self.mem_fill.parse().map_err(ReproError::Invalid)?;
+        let random_source = match self.random_source.as_str() {
+            "replay" => rng::RandomSource::Replay(self.random_bytes.clone()),
+            source => source.parse().map_err(ReproError::Invalid)?,
+        };
+        let mut costs = cost::CostTable::default();
+        for (opcode, cost) in &self.costs {
+            costs
+                .set_cost(opcode, *cost)
+                .map_err(|err| ReproError::Invalid(err.to_string()))?;
+        }
+        Ok(Chip8::builder()
+            .seed(self.seed)
+            .memory_fill(mem_fill)
+            .strict(self.strict)
+            .random_source(random_source)
+            .quirks(self.quirks)
+            .costs(costs)
+            .manual_timers(true)
+            .build())
+    }
+}
+
+/// The keypad state set before a step
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct InputChange {
+    /// Index of the step that first sees the keys
+    pub step: u64,
+    /// Held keys, bit K is set if key K is held
+    pub keys: u16,
+}
+
+/// Records a run as it happens, so that a bundle can be emitted when it fails
+#[derive(Debug, Clone)]
+pub struct Recorder {
+    rom: Vec<u8>,
+    interpreter_image: Vec<u8>,
+    /// The settings of the machine
+    manifest: Manifest,
+    steps: u64,
+    frames: Vec<u64>,
+    keys: input::Keys,
+    inputs: Vec<InputChange>,
+}
+
+impl Recorder {
+    /// Start recording a run of the loaded program of a machine with its current settings
+    pub fn new(chip8: &Chip8) -> Self {
+        Self {
+            rom: chip8.rom().to_vec(),
+            interpreter_image: chip8.interpreter_image().to_vec(),
+            manifest: Manifest::of(chip8),
+            steps: 0,
+            frames: Vec::new(),
+            keys: [false; 16],
+            inputs: Vec::new(),
+        }
+    }
+
+    /// Record the keypad state passed to [`Chip8::set_keys`] before the next step
+    pub fn keys(&mut self, keys: input::Keys) {
+        if keys != self.keys {
+            self.keys = keys;
+            self.inputs.push(InputChange {
+                step: self.steps,
+                keys: to_mask(&keys),
+            });
+        }
+    }
+
+    /// Record a call to [`Chip8::step`]
+    pub fn step(&mut self) {
+        self.steps += 1;
+    }
+
+    /// Record a call to [`Chip8::next_frame`]
+    pub fn frame(&mut self) {
+        self.frames.push(self.steps);
+    }
+
+    /// Number of steps recorded so far
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Forget the steps after `step`, as when a state saved at that step is loaded, so that the
+    /// recording goes on from there with the keys held in the state
+    pub fn rewind(&mut self, step: u64, keys: input::Keys) {
+        self.inputs.retain(|change| change.step < step);
+        self.frames.retain(|frame| *frame < step);
+        self.steps = step;
+        self.keys = match self.inputs.last() {
+            Some(change) => from_mask(change.keys),
+            None => [false; 16],
+        };
+        self.keys(keys);
+    }
+
+    /// Produce the bundle of the run so far
+    pub fn finish(&self, fault: Option<String>) -> Bundle {
+        Bundle {
+            rom: self.rom.clone(),
+            interpreter_image: self.interpreter_image.clone(),
+            manifest: Manifest {
+                steps: self.steps,
+                fault,
+                frames: self.frames.clone(),
+                inputs: self.inputs.clone(),
+                ..self.manifest.clone()
+            },
+        }
+    }
+}
+
+impl Bundle {
+    /// Read a bundle from its directory
+    pub fn read<P: AsRef<Path>>(dir: P) -> Result<Self, ReproError> {
+        let dir = dir.as_ref();
+        let rom = fs::read(dir.join(ROM_FILE)).map_err(ReproError::Io)?;
+        let interpreter_image = match fs::read(dir.join(INTERPRETER_FILE)) {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+            image => image.map_err(ReproError::Io)?,
+        };
+        let text = fs::read_to_string(dir.join(MANIFEST_FILE)).map_err(ReproError::Io)?;
+        let manifest = toml::from_str(&text).map_err(ReproError::Parse)?;
+        Ok(Self {
+            rom,
+            interpreter_image,
+            manifest,
+        })
+    }
+
+    /// Write the bundle to a directory, which is created if needed
+    pub fn write<P: AsRef<Path>>(&self, dir: P) -> Result<(), ReproError> {
+        let dir = dir.as_ref();
+        let text = toml::to_string(&self.manifest).map_err(ReproError::Serialize)?;
+        fs::create_dir_all(dir).map_err(ReproError::Io)?;
+        fs::write(dir.join(ROM_FILE), &self.rom).map_err(ReproError::Io)?;
+        if !self.interpreter_image.is_empty() {
+            fs::write(dir.join(INTERPRETER_FILE), &self.interpreter_image)
+                .map_err(ReproError::Io)?;
+        }
+        fs::write(dir.join(MANIFEST_FILE), text).map_err(ReproError::Io)
+    }
+
+    /// Create a machine with the recorded settings and run the recorded steps and frames, the
+    /// machine is left in the state where the run failed
+    pub fn replay(&self) -> Result<Chip8, ReproError> {
+        let mut chip8 = self.manifest.build()?;
+        if !self.interpreter_image.is_empty() {
+            chip8.load_interpreter_image(&self.interpreter_image);
+        }
+        chip8
+            .load(&self.rom)
+            .map_err(|err| ReproError::Invalid(err.to_string()))?;
+        for patch in &self.manifest.patches {
+            chip8
+                .apply_patch(patch)
+                .map_err(|err| ReproError::Invalid(err.to_string()))?;
+        }
+
+        let mut inputs = self.manifest.inputs.iter().peekable();
+        let mut frames = self.manifest.frames.iter().peekable();
+        for step in 0..self.manifest.steps {
+            while frames.next_if(|frame| **frame <= step).is_some() {
+                chip8.next_frame();
+            }
+            while let Some(change) = inputs.next_if(|change| change.step <= step) {
+                chip8.set_keys(from_mask(change.keys));
+            }
+            chip8.step();
+        }
+        // The frames that started after the last step
+        frames.for_each(|_| chip8.next_frame());
+        Ok(chip8)
+    }
+}
+
+/// The key mask of a keypad state, bit K is set if key K is held
+pub(crate) fn to_mask(keys: &input::Keys) -> u16 {
+    keys.iter()
+        .enumerate()
+        .fold(0, |mask, (key, held)| mask | (*held as u16) << key)
+}
+
+/// The keypad state of a key mask, bit K is set if key K is held
+pub(crate) fn from_mask(mask: u16) -> input::Keys {
+    let mut keys = [false; 16];
+    keys.iter_mut()
+        .enumerate()
+        .for_each(|(key, held)| *held = mask & (1 << key) != 0);
+    keys
+}
+
+/// Reasons for a bundle to be rejected
+#[derive(Debug)]
+pub enum ReproError {
+    /// The bundle could not be read or written
+    Io(io::Error),
+    /// The manifest is not valid
+    Parse(toml::de::Error),
+    /// The manifest could not be written
+    Serialize(toml::ser::Error),
+    /// The bundle cannot be replayed
+    Invalid(String),
+}
+
+impl fmt::Display for ReproError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "could not access the bundle: {}", err),
+            Self::Parse(err) => write!(f, "invalid manifest: {}", err),
+            Self::Serialize(err) => write!(f, "could not write the manifest: {}", err),
+            Self::Invalid(reason) => write!(f, "invalid bundle: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ReproError {}
+
+/// Seeds past `i64::MAX` are kept as negative TOML integers with the same bits, TOML has no
+/// larger integers
+pub(crate) mod seed {
+    use serde::Deserialize;
+    use serde::Deserializer;
+    use serde::Serializer;
+
+    pub fn serialize<S: Serializer>(seed: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i64(*seed as i64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        Ok(i64::deserialize(deserializer)? as u64)
+    }
+}
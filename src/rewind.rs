@@ -0,0 +1,83 @@
+//! Rewinding. A ring buffer keeps a snapshot of the machine for each recent frame, and the
+//! machine can be stepped backwards through them, one frame at a time.
+
+use crate::savestate::SaveState;
+use crate::Chip8;
+use std::collections::VecDeque;
+
+/// Number of frames kept by default, ten seconds at 60 frames per second
+pub const DEFAULT_FRAMES: usize = 600;
+
+/// Longest history that can be kept, in seconds
+pub const MAX_SECONDS: u64 = 600;
+
+/// Number of frames in a history of the given length, None if it is longer than [`MAX_SECONDS`]
+pub fn frames(seconds: u64) -> Option<usize> {
+    if seconds <= MAX_SECONDS {
+        Some(seconds as usize * 60)
+    } else {
+        None
+    }
+}
+
+/// The snapshots of recent frames, oldest first
+#[derive(Debug, Clone)]
+pub struct Rewind {
+    snapshots: VecDeque<SaveState>,
+    capacity: usize,
+}
+
+impl Default for Rewind {
+    fn default() -> Self {
+        Self::new(DEFAULT_FRAMES)
+    }
+}
+
+impl Rewind {
+    /// Keep the given number of frames, zero disables rewinding. The buffer grows as the
+    /// snapshots are taken.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            snapshots: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    /// Number of frames that can be rewound
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    /// True if there is nothing to rewind
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    /// Take a snapshot of the machine at the end of a frame, the oldest one is dropped when the
+    /// buffer is full
+    pub fn push(&mut self, chip8: &Chip8) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.snapshots.len() == self.capacity {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(chip8.save_state());
+    }
+
+    /// Restore the machine to the frame before the last snapshot, which is dropped. Returns false
+    /// if there is nothing to rewind.
+    pub fn step_back(&mut self, chip8: &mut Chip8) -> bool {
+        self.snapshots.pop_back();
+        match self.snapshots.back() {
+            // The snapshots were taken from a valid machine
+            Some(state) => chip8.load_state(state).is_ok(),
+            None => false,
+        }
+    }
+
+    /// Forget every snapshot, for example when another program is loaded
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+    }
+}
@@ -0,0 +1,146 @@
+//! The random number generator used by the CXKK instruction. It is seeded explicitly so that runs
+//! can be reproduced, and does not depend on the operating system so that it works in the browser.
+//! For research, the generator can be swapped for other sources of bytes.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Where the bytes of the CXKK instruction come from
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum RandomSource {
+    /// A xorshift64* generator
+    #[default]
+    Xorshift,
+    /// An 8-bit Galois linear-feedback shift register, a generator as small as those of the
+    /// 8-bit interpreters. It is not the routine of the COSMAC VIP interpreter, which mixes a
+    /// counter into the bytes of its own code.
+    Lfsr8,
+    /// Always the same byte
+    Constant(u8),
+    /// The given bytes in order, starting over once they run out
+    Replay(Vec<u8>),
+}
+
+impl FromStr for RandomSource {
+    type Err = String;
+
+    /// Parse `xorshift`, `lfsr8` or `constant:N`, replayed bytes are read by the caller
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "xorshift" => Ok(Self::Xorshift),
+            "lfsr8" => Ok(Self::Lfsr8),
+            _ => s
+                .strip_prefix("constant:")
+                .and_then(|value| value.parse().ok())
+                .map(Self::Constant)
+                .ok_or_else(|| format!("Unknown random source {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for RandomSource {
+    /// The name parsed by [`RandomSource::from_str`], replayed bytes are only named `replay`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Xorshift => write!(f, "xorshift"),
+            Self::Lfsr8 => write!(f, "lfsr8"),
+            Self::Constant(value) => write!(f, "constant:{}", value),
+            Self::Replay(_) => write!(f, "replay"),
+        }
+    }
+}
+
+/// A generator of random bytes
+#[derive(Debug, Clone)]
+pub struct Rng {
+    source: RandomSource,
+    state: u64,
+}
+
+impl Rng {
+    /// Create a xorshift64* generator from a seed, the same seed always produces the same
+    /// sequence
+    pub fn new(seed: u64) -> Self {
+        Self::with_source(RandomSource::Xorshift, seed)
+    }
+
+    /// Create a generator for a source, the seed is used by the sources that have a state
+    pub fn with_source(source: RandomSource, seed: u64) -> Self {
+        let state = match source {
+            // Scramble the seed so that small seeds do not start with a run of small numbers,
+            // the state must never be zero
+            RandomSource::Xorshift => {
+                let state = (seed ^ 0x9E37_79B9_7F4A_7C15).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+                if state == 0 {
+                    1
+                } else {
+                    state
+                }
+            }
+            RandomSource::Lfsr8 => match seed as u8 {
+                0 => 1,
+                state => state as u64,
+            },
+            RandomSource::Constant(_) | RandomSource::Replay(_) => 0,
+        };
+        Self { source, state }
+    }
+
+    /// The internal state, to be restored with [`Rng::set_state`]
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    /// Restore a state returned by [`Rng::state`]
+    pub fn set_state(&mut self, state: u64) {
+        self.state = state;
+    }
+
+    /// Generate the next random byte
+    pub fn next_u8(&mut self) -> u8 {
+        match &self.source {
+            RandomSource::Xorshift => {
+                self.state ^= self.state >> 12;
+                self.state ^= self.state << 25;
+                self.state ^= self.state >> 27;
+                (self.state.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 56) as u8
+            }
+            RandomSource::Lfsr8 => {
+                // Galois LFSR with the polynomial x^8 + x^6 + x^5 + x^4 + 1
+                let lsb = self.state & 1;
+                self.state >>= 1;
+                if lsb != 0 {
+                    self.state ^= 0xB8;
+                }
+                self.state as u8
+            }
+            RandomSource::Constant(value) => *value,
+            RandomSource::Replay(values) => {
+                let value = values
+                    .get(self.state as usize % values.len().max(1))
+                    .copied()
+                    .unwrap_or(0);
+                self.state += 1;
+                value
+            }
+        }
+    }
+}
+
+/// A seed from the entropy of the host
+#[cfg(not(target_arch = "wasm32"))]
+pub fn entropy_seed() -> u64 {
+    rand::random()
+}
+
+/// A seed from the entropy of the host, provided by the page through the `chip8.random` import,
+/// usually backed by `crypto.getRandomValues()` or `Math.random()`
+#[cfg(target_arch = "wasm32")]
+pub fn entropy_seed() -> u64 {
+    #[link(wasm_import_module = "chip8")]
+    extern "C" {
+        fn random() -> f64;
+    }
+    let (hi, lo) = unsafe { (random(), random()) };
+    ((hi * u32::MAX as f64) as u64) << 32 | (lo * u32::MAX as f64) as u64
+}
@@ -0,0 +1,195 @@
+//! Programs read from a file or any other source, checked to fit in memory before they are
+//! loaded with [`crate::Chip8::load`]. Files that are obviously not CHIP-8 programs, such as an
+//! empty download, an executable or a source file, are rejected with a hint instead of running
+//! whatever they hold, see [`inspect`].
+
+use crate::LoadError;
+use crate::PROGRAM_SIZE;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::path::Path;
+
+/// Signatures of the file formats that are mistaken for ROMs the most, with their name
+const SIGNATURES: [(&[u8], &str); 6] = [
+    (b"\x7FELF", "an ELF executable"),
+    (b"\x89PNG", "a PNG image"),
+    (b"GIF8", "a GIF image"),
+    (b"\xFF\xD8\xFF", "a JPEG image"),
+    (b"PK\x03\x04", "a ZIP archive"),
+    (b"%PDF", "a PDF document"),
+];
+
+/// Signatures of two bytes, which are also instructions a program can start with (`4D5A`,
+/// `1F8B`, `3C21`), so a file that starts with them is only warned about. HTML pages are still
+/// rejected as text.
+const SHORT_SIGNATURES: [(&[u8], &str); 3] = [
+    (b"MZ", "a Windows executable"),
+    (b"\x1F\x8B", "a gzip archive"),
+    (b"<!", "an HTML page"),
+];
+
+/// Smallest file that is taken for text if it is all printable, shorter programs can be
+/// printable by chance
+const MIN_TEXT_LEN: usize = 16;
+
+/// Something about the content of a file that makes it unlikely to be a working ROM
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Problem {
+    /// The file has no bytes at all
+    Empty,
+    /// The file starts with the signature of another format
+    Format(&'static str),
+    /// The file starts with a short signature of another format, which is also a valid
+    /// instruction, so it is only a warning
+    MaybeFormat(&'static str),
+    /// The file is printable text, such as an assembly source
+    Text,
+    /// Instructions are two bytes but the file has an odd number of bytes. Programs that end
+    /// with sprite data can be odd, so it is only a warning.
+    OddLength {
+        /// Size of the file in bytes
+        len: usize,
+    },
+}
+
+impl Problem {
+    /// True if the file is rejected, false if it is loaded with a warning
+    pub fn is_fatal(self) -> bool {
+        !matches!(self, Self::OddLength { .. } | Self::MaybeFormat(_))
+    }
+
+    /// What the user can do about it
+    pub fn hint(self) -> &'static str {
+        match self {
+            Self::Empty => "check that the file was downloaded or copied completely",
+            Self::Format(_) => "check that the right file was picked",
+            Self::MaybeFormat(_) => {
+                "check that the right file was picked if the program misbehaves"
+            }
+            Self::Text => "if it is an assembly source, build it first with `chip8 asm`",
+            Self::OddLength { .. } => "the file may be truncated if the program misbehaves",
+        }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "the ROM is empty"),
+            Self::Format(format) => write!(f, "the file is {}, not a CHIP-8 ROM", format),
+            Self::MaybeFormat(format) => write!(f, "the file starts like {}", format),
+            Self::Text => write!(f, "the file is text, not a CHIP-8 ROM"),
+            Self::OddLength { len } => write!(f, "the ROM has an odd size of {} bytes", len),
+        }
+    }
+}
+
+/// The problems of the content of a file, the most specific first
+pub fn inspect(data: &[u8]) -> Vec<Problem> {
+    if data.is_empty() {
+        return vec![Problem::Empty];
+    }
+    let mut problems = Vec::new();
+    let signature = |signatures: &[(&[u8], &'static str)]| {
+        signatures
+            .iter()
+            .find(|(signature, _)| data.starts_with(signature))
+            .map(|(_, format)| *format)
+    };
+    if let Some(format) = signature(&SIGNATURES) {
+        problems.push(Problem::Format(format));
+    } else if let Some(format) = signature(&SHORT_SIGNATURES) {
+        problems.push(Problem::MaybeFormat(format));
+    }
+    let printable = |byte: &u8| byte.is_ascii_graphic() || byte.is_ascii_whitespace();
+    if data.len() >= MIN_TEXT_LEN && data.contains(&b'\n') && data.iter().all(printable) {
+        problems.push(Problem::Text);
+    }
+    if data.len() % 2 == 1 {
+        problems.push(Problem::OddLength { len: data.len() });
+    }
+    problems
+}
+
+/// The bytes of a program that fits between 0x200 and 0x1000
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rom {
+    data: Vec<u8>,
+    /// Problems that do not prevent the ROM from being loaded
+    warnings: Vec<Problem>,
+}
+
+impl Rom {
+    /// A ROM holding the given bytes, rejected if [`inspect`] finds a fatal problem or if it
+    /// does not fit in memory
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self, RomError> {
+        let (fatal, warnings): (Vec<_>, Vec<_>) = inspect(&data)
+            .into_iter()
+            .partition(|problem| problem.is_fatal());
+        if let Some(problem) = fatal.first() {
+            return Err(RomError::Invalid(*problem));
+        }
+        if data.len() > PROGRAM_SIZE {
+            return Err(RomError::Load(LoadError::TooLarge { len: data.len() }));
+        }
+        Ok(Self { data, warnings })
+    }
+
+    /// A ROM holding everything the reader returns until the end of its input
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<Self, RomError> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data).map_err(RomError::Io)?;
+        Self::from_bytes(data)
+    }
+
+    /// A ROM holding the content of a file
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, RomError> {
+        Self::from_reader(File::open(path).map_err(RomError::Io)?)
+    }
+
+    /// The bytes of the program
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Size of the program in bytes
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Whether the program has no instructions at all
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Problems of the content that were not serious enough to reject the ROM, the frontends
+    /// print them before running it
+    pub fn warnings(&self) -> &[Problem] {
+        &self.warnings
+    }
+}
+
+/// Reasons for a ROM to be rejected
+#[derive(Debug)]
+pub enum RomError {
+    /// The ROM could not be read
+    Io(io::Error),
+    /// The ROM does not fit in memory
+    Load(LoadError),
+    /// The file is not a CHIP-8 program
+    Invalid(Problem),
+}
+
+impl fmt::Display for RomError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "could not read the ROM: {}", err),
+            Self::Load(err) => write!(f, "{}", err),
+            Self::Invalid(problem) => write!(f, "{}, {}", problem, problem.hint()),
+        }
+    }
+}
+
+impl std::error::Error for RomError {}
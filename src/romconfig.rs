@@ -0,0 +1,187 @@
+//! Settings of a single ROM, kept in a TOML file next to it (`pong.ch8` reads `pong.toml`) or in
+//! the data directory of the portable mode, see [`crate::datapaths`]. They are the defaults of
+//! the ROM, flags given on the command line take precedence.
+//!
+//! ```toml
+//! speed = 700
+//! palette = ["FFCC00", "996600"]
+//!
+//! [quirks]
+//! shift_in_place = false
+//!
+//! [keys]
+//! Up = "2"
+//!
+//! [gamepad]
+//! South = "A"
+//! ```
+//!
+//! The web frontend also takes settings from the parameters of its URL, see
+//! [`RomConfig::from_query`].
+
+use crate::console::parse_color;
+use crate::datapaths::DataPaths;
+use crate::keymap::KeyMap;
+use crate::palette::Palette;
+use crate::quirks::Profile;
+use crate::quirks::Quirks;
+use crate::storage::Storage;
+use serde::Deserialize;
+use serde::Serialize;
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Settings of a ROM, unset values fall back to the global defaults
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RomConfig {
+    /// Number of ticks per second
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speed: Option<u32>,
+    /// Colors of the screen, see [`crate::palette`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub palette: Option<Palette>,
+    /// Behaviours that the ROM relies on, missing quirks take their default value
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quirks: Option<Quirks>,
+    /// Bindings of host keys to keypad keys, see [`crate::keymap`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keys: Option<KeyMap>,
+    /// Bindings of gamepad buttons to keypad keys, on top of [`KeyMap::gamepad`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gamepad: Option<KeyMap>,
+}
+
+impl RomConfig {
+    /// The file holding the settings of a ROM
+    pub fn path(paths: &DataPaths, rom: &Path) -> PathBuf {
+        paths.rom_config(rom)
+    }
+
+    /// Read the settings of a ROM, a ROM without a file has no settings
+    pub fn load(
+        storage: &dyn Storage,
+        paths: &DataPaths,
+        rom: &Path,
+    ) -> Result<Self, RomConfigError> {
+        let path = Self::path(paths, rom);
+        if !storage.exists(&path) {
+            return Ok(Self::default());
+        }
+        let text = storage.read_to_string(&path).map_err(RomConfigError::Io)?;
+        toml::from_str(&text).map_err(RomConfigError::Parse)
+    }
+
+    /// Settings given as the parameters of a URL, such as
+    /// `speed=700&quirks=schip&quirk=shift_in_place:off&palette=amber`. `quirk` can be repeated
+    /// and changes the quirks of the `quirks` profile, or the default ones. `palette` is a theme
+    /// or two to four colors separated by commas. Other parameters are left to the page.
+    pub fn from_query(query: &str) -> Result<Self, RomConfigError> {
+        let mut config = Self::default();
+        let mut overrides = Vec::new();
+        for param in query.trim_start_matches('?').split('&') {
+            let (name, value) = param.split_once('=').unwrap_or((param, ""));
+            let value = percent_decode(value);
+            let invalid = || RomConfigError::Param(format!("{}={}", name, value));
+            match name {
+                "speed" => {
+                    let speed = value.parse().ok().filter(|speed| *speed > 0);
+                    config.speed = Some(speed.ok_or_else(invalid)?);
+                }
+                "quirks" => {
+                    let profile: Profile = value.parse().map_err(|_| invalid())?;
+                    config.quirks = Some(profile.quirks());
+                }
+                "quirk" => match value.split_once(':') {
+                    Some((quirk, "on")) => overrides.push((quirk.to_string(), true, invalid())),
+                    Some((quirk, "off")) => overrides.push((quirk.to_string(), false, invalid())),
+                    _ => return Err(invalid()),
+                },
+                "palette" => {
+                    let colors = value
+                        .split(',')
+                        .map(parse_color)
+                        .collect::<Result<Vec<_>, _>>();
+                    let palette = Palette::theme(&value)
+                        .or_else(|| colors.ok().and_then(|colors| Palette::from_list(&colors)));
+                    config.palette = Some(palette.ok_or_else(invalid)?);
+                }
+                _ => {}
+            }
+        }
+        if !overrides.is_empty() {
+            let mut quirks = config.quirks.unwrap_or_default();
+            for (quirk, on, invalid) in overrides {
+                if !quirks.set(&quirk, on) {
+                    return Err(invalid);
+                }
+            }
+            config.quirks = Some(quirks);
+        }
+        Ok(config)
+    }
+
+    /// Write the settings of a ROM
+    pub fn save(
+        &self,
+        storage: &dyn Storage,
+        paths: &DataPaths,
+        rom: &Path,
+    ) -> Result<(), RomConfigError> {
+        let text = toml::to_string(self).map_err(RomConfigError::Serialize)?;
+        storage
+            .write(&Self::path(paths, rom), text.as_bytes())
+            .map_err(RomConfigError::Io)
+    }
+}
+
+/// Decode the `%XX` escapes and the `+` spaces of a URL parameter
+fn percent_decode(value: &str) -> String {
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut rest = value.as_bytes();
+    while let Some((byte, tail)) = rest.split_first() {
+        let escaped = tail
+            .get(..2)
+            .filter(|_| *byte == b'%')
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(escaped) => {
+                bytes.push(escaped);
+                rest = &tail[2..];
+            }
+            None => {
+                bytes.push(if *byte == b'+' { b' ' } else { *byte });
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Reasons for the settings of a ROM to be rejected
+#[derive(Debug)]
+pub enum RomConfigError {
+    /// The file could not be read or written
+    Io(io::Error),
+    /// The file is not valid
+    Parse(toml::de::Error),
+    /// The settings could not be written
+    Serialize(toml::ser::Error),
+    /// A parameter of a URL is not a valid setting
+    Param(String),
+}
+
+impl fmt::Display for RomConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "could not access the ROM settings: {}", err),
+            Self::Parse(err) => write!(f, "invalid ROM settings: {}", err),
+            Self::Serialize(err) => write!(f, "could not write the ROM settings: {}", err),
+            Self::Param(param) => write!(f, "invalid setting {}", param),
+        }
+    }
+}
+
+impl std::error::Error for RomConfigError {}
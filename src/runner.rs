@@ -0,0 +1,53 @@
+//! Headless runs of test ROMs that report their results through the test traps of
+//! [`crate::trap`], so that compliance and homebrew tests can be checked without a window:
+//!
+//! ```text
+//! chip8 test tests/*.ch8 --frames 1200
+//! ```
+//!
+//! A ROM passes once it calls [`trap::PASS`], and fails when it calls [`trap::FAIL`], when one of
+//! its assertions fails or when the machine faults. A ROM that reports nothing within the frame
+//! budget times out and does not pass.
+
+use crate::compliance::TestResult;
+use crate::quirks::Quirks;
+use crate::trap;
+use crate::trap::Verdict;
+use crate::Chip8;
+use crate::TICKS_PER_SEC;
+
+/// Frames a ROM may run for before it times out, 10 seconds at 60 frames per second
+pub const DEFAULT_FRAMES: u64 = 600;
+
+/// Run a ROM until it reports a verdict, faults, or has run for `frames` frames
+pub fn run(name: &str, program: &[u8], quirks: Quirks, frames: u64) -> TestResult {
+    let result = |passed, message| TestResult {
+        name: name.to_string(),
+        passed,
+        message,
+    };
+    let mut chip8 = Chip8::builder().quirks(quirks).build();
+    if let Err(err) = chip8.load(program) {
+        return result(false, Some(err.to_string()));
+    }
+    let traps = trap::Traps::builtin(|_| ());
+    let verdict = traps.verdict();
+    chip8.set_interpreter_hook(Some(traps.into_hook()));
+
+    for _ in 0..frames {
+        for _ in 0..TICKS_PER_SEC / 60 {
+            chip8.step();
+            if let Some(verdict) = verdict.lock().unwrap().clone() {
+                return match verdict {
+                    Verdict::Passed => result(true, None),
+                    Verdict::Failed(message) => result(false, Some(message)),
+                };
+            }
+            if let Some(fault) = chip8.fault() {
+                return result(false, Some(fault.to_string()));
+            }
+        }
+        chip8.next_frame();
+    }
+    result(false, Some(format!("timed out after {} frames", frames)))
+}
@@ -0,0 +1,233 @@
+//! Save states that capture the complete machine state so that a session can be restored later.
+//! They are written as TOML, with the memory and the screen encoded in hexadecimal.
+//!
+//! ```toml
+//! version = 1
+//! pc = 676
+//! registers = [0, 3, ...]
+//! memory = "f0909090f0..."
+//!
+//! [thumbnail]
+//! width = 32
+//! height = 16
+//! pixels = "0000ff00..."
+//! ```
+//!
+//! The thumbnail is optional, states written before it existed still load.
+
+use crate::storage::Storage;
+use serde::Deserialize;
+use serde::Serialize;
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+/// Version of the save state format
+pub const VERSION: u32 = 1;
+/// Width of the thumbnail of a state
+pub const THUMBNAIL_WIDTH: usize = 32;
+/// Height of the thumbnail of a state
+pub const THUMBNAIL_HEIGHT: usize = 16;
+
+/// The state of a machine, see [`crate::Chip8::save_state`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SaveState {
+    /// Version of the format, see [`VERSION`]
+    pub version: u32,
+    /// Registers V0-VF
+    pub registers: [u8; 16],
+    /// Index register
+    pub index: u16,
+    /// Program counter
+    pub pc: u16,
+    /// Stack pointer
+    pub sp: u8,
+    /// The stack
+    pub stack: [u16; 16],
+    /// Delay timer
+    pub delay_timer: u8,
+    /// Sound timer
+    pub sound_timer: u8,
+    /// Remaining ticks of the current instruction
+    pub busy: u32,
+    /// The 4K of memory
+    #[serde(with = "hex")]
+    pub memory: Vec<u8>,
+    /// True in the SUPER-CHIP high resolution mode
+    pub hires: bool,
+    /// The screen, row by row, eight pixels per byte
+    #[serde(with = "hex")]
+    pub screen: Vec<u8>,
+    /// The SUPER-CHIP user flags
+    pub rpl: [u8; 8],
+    /// Held keys, bit K is set if key K is held
+    pub keys: u16,
+    /// State of the random number generator, as a string since it may not fit in a TOML integer
+    pub rng: String,
+    /// A small picture of the screen, for choosing between states
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thumbnail: Option<Thumbnail>,
+    /// Number of steps of the input recording or of the movie being played back when the state
+    /// was saved, they are rewound to it when the state is loaded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub movie_step: Option<u64>,
+}
+
+/// The screen scaled down to [`THUMBNAIL_WIDTH`]x[`THUMBNAIL_HEIGHT`], a pixel is lit if any of
+/// the pixels it covers is lit
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Thumbnail {
+    /// Width in pixels
+    pub width: usize,
+    /// Height in pixels
+    pub height: usize,
+    /// The pixels, row by row, eight pixels per byte
+    #[serde(with = "hex")]
+    pub pixels: Vec<u8>,
+}
+
+impl Thumbnail {
+    /// Scale down a screen of the given size, row by row with true for lit pixels
+    pub fn of(screen: &[bool], width: usize, height: usize) -> Self {
+        let (scale_x, scale_y) = (width / THUMBNAIL_WIDTH, height / THUMBNAIL_HEIGHT);
+        let pixels: Vec<_> = (0..THUMBNAIL_WIDTH * THUMBNAIL_HEIGHT)
+            .map(|idx| {
+                let (x, y) = (
+                    idx % THUMBNAIL_WIDTH * scale_x,
+                    idx / THUMBNAIL_WIDTH * scale_y,
+                );
+                (y..y + scale_y)
+                    .any(|y| screen[y * width + x..y * width + x + scale_x].contains(&true))
+            })
+            .collect();
+        Self {
+            width: THUMBNAIL_WIDTH,
+            height: THUMBNAIL_HEIGHT,
+            pixels: pack_pixels(&pixels),
+        }
+    }
+
+    /// Whether the pixel at `x`, `y` is lit
+    pub fn lit(&self, x: usize, y: usize) -> bool {
+        let idx = y * self.width + x;
+        x < self.width
+            && self
+                .pixels
+                .get(idx / 8)
+                .is_some_and(|byte| byte & (0x80 >> (idx % 8)) != 0)
+    }
+}
+
+impl SaveState {
+    /// Read a save state from a file
+    pub fn read(storage: &dyn Storage, path: &Path) -> Result<Self, SaveStateError> {
+        let text = storage.read_to_string(path).map_err(SaveStateError::Io)?;
+        Self::from_toml(&text)
+    }
+
+    /// Write the save state to a file
+    pub fn write(&self, storage: &dyn Storage, path: &Path) -> Result<(), SaveStateError> {
+        storage
+            .write(path, self.to_toml()?.as_bytes())
+            .map_err(SaveStateError::Io)
+    }
+
+    /// Parse a save state written by [`SaveState::to_toml`]
+    pub fn from_toml(text: &str) -> Result<Self, SaveStateError> {
+        let state: Self = toml::from_str(text).map_err(SaveStateError::Parse)?;
+        if state.version != VERSION {
+            return Err(SaveStateError::Invalid(format!(
+                "unsupported version {}",
+                state.version
+            )));
+        }
+        Ok(state)
+    }
+
+    /// The save state as TOML
+    pub fn to_toml(&self) -> Result<String, SaveStateError> {
+        toml::to_string(self).map_err(SaveStateError::Serialize)
+    }
+}
+
+/// Pack pixels eight to a byte, the first pixel in the most significant bit
+pub(crate) fn pack_pixels(pixels: &[bool]) -> Vec<u8> {
+    pixels
+        .chunks(8)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .enumerate()
+                .fold(0, |byte, (bit, lit)| byte | (*lit as u8) << (7 - bit))
+        })
+        .collect()
+}
+
+/// Unpack `count` pixels packed by [`pack_pixels`]
+pub(crate) fn unpack_pixels(bytes: &[u8], count: usize) -> Vec<bool> {
+    (0..count)
+        .map(|idx| {
+            bytes
+                .get(idx / 8)
+                .is_some_and(|byte| byte & (0x80 >> (idx % 8)) != 0)
+        })
+        .collect()
+}
+
+mod hex {
+    use serde::Deserialize;
+    use serde::Deserializer;
+    use serde::Serializer;
+    use std::fmt::Write;
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        let mut text = String::with_capacity(bytes.len() * 2);
+        for byte in bytes {
+            write!(text, "{:02x}", byte).unwrap();
+        }
+        serializer.serialize_str(&text)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        if !text.len().is_multiple_of(2) {
+            return Err(serde::de::Error::custom("odd number of hexadecimal digits"));
+        }
+        (0..text.len())
+            .step_by(2)
+            .map(|idx| {
+                text.get(idx..idx + 2)
+                    .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+                    .ok_or_else(|| serde::de::Error::custom("invalid hexadecimal digits"))
+            })
+            .collect()
+    }
+}
+
+/// Reasons for a save state to be rejected
+#[derive(Debug)]
+pub enum SaveStateError {
+    /// The file could not be read or written
+    Io(io::Error),
+    /// The file is not valid
+    Parse(toml::de::Error),
+    /// The state could not be written
+    Serialize(toml::ser::Error),
+    /// The state does not describe a machine
+    Invalid(String),
+}
+
+impl fmt::Display for SaveStateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "could not access the save state: {}", err),
+            Self::Parse(err) => write!(f, "invalid save state: {}", err),
+            Self::Serialize(err) => write!(f, "could not write the save state: {}", err),
+            Self::Invalid(reason) => write!(f, "invalid save state: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for SaveStateError {}
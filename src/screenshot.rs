@@ -0,0 +1,154 @@
+//! Screenshots of the CHIP-8 screen as PNG images, and headless runs that capture a screenshot
+//! every few frames and assemble them into a contact sheet, for visually diffing emulator
+//! versions across a corpus of ROMs.
+
+use crate::palette::Palette;
+use crate::storage::Storage;
+use crate::Chip8;
+use crate::SCHIP_SCREEN_HEIGHT;
+use crate::SCHIP_SCREEN_WIDTH;
+use crate::TICKS_PER_SEC;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Number of steps in an emulated frame
+pub const STEPS_PER_FRAME: u32 = TICKS_PER_SEC / 60;
+/// Width of a screenshot, low resolution screens are scaled up to the same size
+pub const SCREENSHOT_WIDTH: usize = SCHIP_SCREEN_WIDTH * 2;
+/// Height of a screenshot
+pub const SCREENSHOT_HEIGHT: usize = SCHIP_SCREEN_HEIGHT * 2;
+/// Number of screenshots in a row of a contact sheet
+pub const SHEET_COLUMNS: usize = 8;
+/// Name of the contact sheet written by [`capture`]
+pub const SHEET_FILE: &str = "contact-sheet.png";
+
+/// A grayscale image, one byte per pixel
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    /// Width in pixels
+    pub width: usize,
+    /// Height in pixels
+    pub height: usize,
+    /// Pixels row by row
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    /// Render the screen of a machine at [`SCREENSHOT_WIDTH`]x[`SCREENSHOT_HEIGHT`]
+    pub fn screenshot(chip8: &Chip8) -> Self {
+        let (width, _) = chip8.screen_size();
+        let scale = SCREENSHOT_WIDTH / width;
+        let gfx = chip8.framebuffer();
+        let pixels = (0..SCREENSHOT_WIDTH * SCREENSHOT_HEIGHT)
+            .map(|idx| {
+                let (x, y) = (idx % SCREENSHOT_WIDTH, idx / SCREENSHOT_WIDTH);
+                if gfx[x / scale + y / scale * width] {
+                    0xFF
+                } else {
+                    0x00
+                }
+            })
+            .collect();
+        Self {
+            width: SCREENSHOT_WIDTH,
+            height: SCREENSHOT_HEIGHT,
+            pixels,
+        }
+    }
+
+    /// Lay out images of the same size in a grid with a gray border around each of them
+    pub fn contact_sheet(images: &[Image], columns: usize) -> Self {
+        const BORDER: usize = 4;
+        const BORDER_COLOR: u8 = 0x60;
+        let (tile_width, tile_height) = images
+            .first()
+            .map_or((0, 0), |image| (image.width, image.height));
+        let columns = columns.clamp(1, images.len().max(1));
+        let rows = images.len().div_ceil(columns);
+        let width = columns * (tile_width + BORDER) + BORDER;
+        let height = rows * (tile_height + BORDER) + BORDER;
+        let mut pixels = vec![BORDER_COLOR; width * height];
+        for (idx, image) in images.iter().enumerate() {
+            let left = BORDER + (idx % columns) * (tile_width + BORDER);
+            let top = BORDER + (idx / columns) * (tile_height + BORDER);
+            for (y, row) in image.pixels.chunks(image.width).enumerate() {
+                let start = left + (top + y) * width;
+                pixels[start..start + row.len()].copy_from_slice(row);
+            }
+        }
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Write the image as a grayscale PNG file
+    pub fn save_png(&self, storage: &dyn Storage, path: &Path) -> io::Result<()> {
+        let mut png = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&self.pixels)?;
+        storage.write(path, &png)
+    }
+}
+
+/// Write the screen of a machine as a PNG file of the given size, in the colors of a palette, as
+/// it is shown in a window of that size
+pub fn save_screen(
+    storage: &dyn Storage,
+    chip8: &Chip8,
+    palette: &Palette,
+    (width, height): (usize, usize),
+    path: &Path,
+) -> io::Result<()> {
+    let (screen_width, screen_height) = chip8.screen_size();
+    let gfx = chip8.framebuffer();
+    let mut pixels = Vec::with_capacity(width * height * 3);
+    for y in 0..height {
+        for x in 0..width {
+            let lit = gfx[x * screen_width / width + y * screen_height / height * screen_width];
+            let color = palette.color(lit as usize);
+            pixels.extend_from_slice(&color.to_be_bytes()[1..]);
+        }
+    }
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, width as u32, height as u32);
+    encoder.set_color(png::ColorType::RGB);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&pixels)?;
+    storage.write(path, &png)
+}
+
+/// Run a machine without a window for the given number of frames, writing a screenshot into
+/// `dir` every `every` frames and a contact sheet of all of them at the end. Returns the paths
+/// of the screenshots.
+pub fn capture(
+    storage: &dyn Storage,
+    chip8: &mut Chip8,
+    dir: &Path,
+    every: u64,
+    frames: u64,
+) -> io::Result<Vec<PathBuf>> {
+    let every = every.max(1);
+    let mut paths = Vec::new();
+    let mut images = Vec::new();
+    for frame in 1..=frames {
+        chip8.poll_inputs();
+        for _ in 0..STEPS_PER_FRAME {
+            chip8.step();
+        }
+        chip8.next_frame();
+        if frame.is_multiple_of(every) {
+            let image = Image::screenshot(chip8);
+            let path = dir.join(format!("frame-{:06}.png", frame));
+            image.save_png(storage, &path)?;
+            paths.push(path);
+            images.push(image);
+        }
+    }
+    Image::contact_sheet(&images, SHEET_COLUMNS).save_png(storage, &dir.join(SHEET_FILE))?;
+    Ok(paths)
+}
@@ -0,0 +1,296 @@
+//! A launcher that runs on the machine itself: a CHIP-8 menu program listing ROMs, drawn with a
+//! small font of its own, that asks the host to start the chosen one through a trap. The menu is
+//! generated from CHIPPER-style source by [`crate::asm`], and the trap is a machine code call
+//! (`SYS 0x1F0`, see [`crate::trap`]) handled by the interpreter hook, so it works in every frontend.
+//!
+//! Keys 2 and 8 move the cursor, 5 or 6 launch the selected ROM.
+
+use crate::asm;
+use crate::datapaths::DataPaths;
+use crate::fault::Warning;
+use crate::playlist;
+use crate::rom::Rom;
+use crate::romconfig::RomConfig;
+use crate::storage::Storage;
+use crate::text::Font;
+use crate::trap;
+use crate::InterpreterHook;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Machine code address that launches the ROM whose index is in V0
+pub const LAUNCH_TRAP: u16 = trap::LAUNCH;
+/// Number of ROMs listed by the menu, the names are indexed with a single register
+pub const MAX_ENTRIES: usize = 16;
+/// Number of characters shown of a name
+pub const NAME_LEN: usize = 15;
+/// Number of names on the screen at once
+pub const ROWS: usize = 5;
+/// Number of ROMs remembered in the recent list
+pub const RECENT_ROMS: usize = 8;
+
+/// Characters of the font of the menu, a subset of [`Font::Mini`] that can be indexed with a
+/// byte. Names are shown in upper case and other characters as `?`.
+const CHARSET: &str = " ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789-_.?>";
+
+/// Byte that fills the names up to [`NAME_LEN`] characters, it is not a glyph
+const PADDING: usize = 0xFF;
+
+/// The menu program, registers: VA selected entry, VB first entry on the screen, V8 entry being
+/// drawn, V3 and V4 position, V5 character, V6 offset of the character in the names, V7 row
+const MENU: &str = "
+start:  LD VA, 0
+        LD VB, 0
+draw:   CLS
+        LD V8, VB
+        LD V4, 1
+        LD V7, 0
+row:    SNE V8, {count}
+        JP cursor
+        CALL name
+        ADD V8, 1
+        ADD V4, 6
+        ADD V7, 1
+        SE V7, {rows}
+        JP row
+cursor: CALL arrow
+key:    LD V0, K
+release: SKNP V0          ; act once the key is released
+        JP release
+        SE V0, 2
+        JP down
+        SNE VA, 0
+        JP key
+        CALL arrow        ; erase the cursor
+        SE VA, VB
+        JP up
+        ADD VA, 0xFF      ; scroll up
+        ADD VB, 0xFF
+        JP draw
+up:     ADD VA, 0xFF
+        CALL arrow
+        JP key
+down:   SE V0, 8
+        JP launch
+        LD V1, VA
+        ADD V1, 1
+        SNE V1, {count}
+        JP key
+        CALL arrow
+        ADD VA, 1
+        LD V1, VA
+        SUB V1, VB
+        SE V1, {rows}
+        JP moved
+        ADD VB, 1         ; scroll down
+        JP draw
+moved:  CALL arrow
+        JP key
+launch: SNE V0, 5
+        JP go
+        SE V0, 6
+        JP key
+go:     LD V0, VA
+        SYS {trap}
+        JP key            ; the ROM could not be launched
+
+arrow:  LD V1, VA         ; draw or erase the cursor, at y = (VA - VB) * 6 + 1
+        SUB V1, VB
+        LD V4, 1
+        ADD V4, V1
+        ADD V4, V1
+        ADD V4, V1
+        ADD V4, V1
+        ADD V4, V1
+        ADD V4, V1
+        LD V3, 0
+        LD I, font
+        LD V0, {arrow}
+        ADD I, V0
+        DRW V3, V4, 5
+        RET
+
+name:   LD V3, 4          ; V6 = V8 * {len}
+        LD V6, 0
+        LD V1, V8
+mul:    SNE V1, 0
+        JP chars
+        ADD V6, {len}
+        ADD V1, 0xFF
+        JP mul
+chars:  LD V5, 0
+char:   LD I, names
+        ADD I, V6
+        LD V0, [I]
+        SNE V0, {end}     ; the rest of the name is padding
+        RET
+        LD I, font
+        ADD I, V0
+        DRW V3, V4, 5
+        ADD V3, 4
+        ADD V6, 1
+        ADD V5, 1
+        SE V5, {len}
+        JP char
+        RET
+";
+
+/// Offset of the glyph of a character in the font
+fn glyph(ch: char) -> usize {
+    let ch = ch.to_ascii_uppercase();
+    let idx = CHARSET
+        .find(ch)
+        .or_else(|| CHARSET.find('?'))
+        .unwrap_or_default();
+    idx * 5
+}
+
+/// `DB` lines holding bytes, sixteen to a line
+fn data(bytes: &[usize]) -> String {
+    bytes
+        .chunks(16)
+        .map(|chunk| {
+            let bytes: Vec<_> = chunk.iter().map(|byte| format!("{:#04X}", byte)).collect();
+            format!("        DB {}\n", bytes.join(", "))
+        })
+        .collect()
+}
+
+/// The menu program listing the given names, of which only the first [`MAX_ENTRIES`] are shown
+pub fn program(names: &[String]) -> Vec<u8> {
+    let names = &names[..names.len().min(MAX_ENTRIES)];
+    let mut source = MENU
+        .replace("{count}", &names.len().to_string())
+        .replace("{rows}", &ROWS.to_string())
+        .replace("{len}", &NAME_LEN.to_string())
+        .replace("{arrow}", &glyph('>').to_string())
+        .replace("{end}", &PADDING.to_string())
+        .replace("{trap}", &format!("{:#05X}", LAUNCH_TRAP));
+    let font: Vec<_> = CHARSET
+        .chars()
+        .flat_map(|ch| Font::Mini.glyph(ch))
+        .map(usize::from)
+        .collect();
+    source.push_str("font:\n");
+    source.push_str(&data(&font));
+    let chars: Vec<_> = names
+        .iter()
+        .flat_map(|name| {
+            let mut chars: Vec<_> = name.chars().take(NAME_LEN).map(glyph).collect();
+            chars.resize(NAME_LEN, PADDING);
+            chars
+        })
+        .collect();
+    source.push_str("names:\n");
+    source.push_str(&data(&chars));
+    // The source is generated here, so it always assembles
+    asm::assemble(&source).expect("the menu assembles")
+}
+
+/// The name of a ROM in the menu
+pub fn name(rom: &Path) -> String {
+    rom.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// The warning of a ROM that could not be started
+fn launch_failed(rom: &Path, err: &dyn std::fmt::Display) -> Warning {
+    Warning::LaunchFailed {
+        rom: rom.display().to_string(),
+        reason: err.to_string(),
+    }
+}
+
+/// The interpreter hook that handles [`LAUNCH_TRAP`] by resetting the machine and loading the
+/// ROM with the index in V0, with the quirks of its settings. The settings and the recent list
+/// are found through `paths`.
+pub fn launcher(
+    roms: Vec<PathBuf>,
+    paths: DataPaths,
+    storage: Arc<dyn Storage>,
+) -> InterpreterHook {
+    let recent_file = paths.recent_file();
+    Box::new(move |chip8, addr| {
+        if addr != LAUNCH_TRAP {
+            return false;
+        }
+        let Some(rom) = roms.get(chip8.registers()[0] as usize) else {
+            return true;
+        };
+        let program = match Rom::open(rom) {
+            Ok(program) => program,
+            Err(err) => {
+                chip8.warn(launch_failed(rom, &err));
+                return true;
+            }
+        };
+        if let Ok(RomConfig {
+            quirks: Some(quirks),
+            ..
+        }) = RomConfig::load(storage.as_ref(), &paths, rom)
+        {
+            chip8.set_quirks(quirks);
+        }
+        chip8.reset();
+        if let Err(err) = chip8.load(program.data()) {
+            chip8.warn(launch_failed(rom, &err));
+            return true;
+        }
+        if let Some(file) = &recent_file {
+            remember(storage.as_ref(), file, rom);
+        }
+        true
+    })
+}
+
+/// The ROMs to list for a directory: the given recent ones first, then those in the directory
+pub fn entries(dir: &Path, extensions: &[&str], recent: Vec<PathBuf>) -> io::Result<Vec<PathBuf>> {
+    let mut roms: Vec<_> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| extensions.contains(&ext.to_lowercase().as_str()))
+        })
+        .collect();
+    roms.sort();
+    let mut entries = recent;
+    for rom in roms {
+        let rom = fs::canonicalize(&rom).unwrap_or(rom);
+        if !entries.contains(&rom) {
+            entries.push(rom);
+        }
+    }
+    entries.truncate(MAX_ENTRIES);
+    Ok(entries)
+}
+
+/// The recently opened ROMs listed in a file, latest first
+pub fn recent(storage: &dyn Storage, file: &Path) -> Vec<PathBuf> {
+    let Ok(text) = storage.read_to_string(file) else {
+        return Vec::new();
+    };
+    playlist::parse(&text, file.parent().unwrap_or_else(|| Path::new("")))
+        .into_iter()
+        .filter(|rom| rom.is_file())
+        .collect()
+}
+
+/// Add a ROM to the recent list in a file, failures are ignored since the list is only a
+/// convenience
+pub fn remember(storage: &dyn Storage, file: &Path, rom: &Path) {
+    let rom = fs::canonicalize(rom).unwrap_or_else(|_| rom.to_path_buf());
+    let mut roms = recent(storage, file);
+    roms.retain(|recent| *recent != rom);
+    roms.insert(0, rom);
+    roms.truncate(RECENT_ROMS);
+    let text: String = roms
+        .iter()
+        .map(|rom| format!("{}\n", rom.display()))
+        .collect();
+    let _ = storage.write(file, text.as_bytes());
+}
@@ -0,0 +1,113 @@
+//! An experimental accessibility mode that turns drawing into sound, so that players with low
+//! vision can follow simple games like Pong. The screen is split into regions, and a sprite drawn
+//! in a region makes a short cue: panned to the left or right by the column of the region and
+//! pitched by its row, higher at the top of the screen.
+//!
+//! The sprites are received through the draw hook of the machine, see
+//! [`crate::Chip8::set_draw_hook`], and the cues are played by the frontend.
+
+use crate::Draw;
+use crate::DrawHook;
+use std::sync::mpsc;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::Sender;
+
+/// Number of regions across the screen, left, centre and right
+pub const REGION_COLUMNS: usize = 3;
+/// Number of regions down the screen, each one a note higher than the one below
+pub const REGION_ROWS: usize = 6;
+
+/// Semitones above the lowest note of each row of regions from the bottom, a pentatonic scale
+/// so that cues played together do not clash
+const SCALE: [f32; REGION_ROWS] = [0.0, 2.0, 4.0, 7.0, 9.0, 12.0];
+
+/// A sound to play for drawing in a region
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cue {
+    /// Column of the region, from the left
+    pub column: usize,
+    /// Row of the region, from the top
+    pub row: usize,
+    /// Position between the left (-1.0) and the right (1.0) speaker
+    pub pan: f32,
+    /// Ratio of the pitch to the lowest note, from 1.0 at the bottom to 2.0 at the top
+    pub pitch: f32,
+}
+
+impl Cue {
+    /// The cue of a region
+    pub fn new(column: usize, row: usize) -> Self {
+        let pan = if REGION_COLUMNS > 1 {
+            column as f32 * 2.0 / (REGION_COLUMNS - 1) as f32 - 1.0
+        } else {
+            0.0
+        };
+        let semitones = SCALE[REGION_ROWS - 1 - row];
+        Self {
+            column,
+            row,
+            pan,
+            pitch: 2f32.powf(semitones / 12.0),
+        }
+    }
+
+    /// The cue of the region that holds the centre of a sprite
+    pub fn of_draw(draw: &Draw) -> Self {
+        let x = (draw.x + draw.width / 2) % draw.screen_width;
+        let y = (draw.y + draw.height / 2) % draw.screen_height;
+        Self::new(
+            x * REGION_COLUMNS / draw.screen_width,
+            y * REGION_ROWS / draw.screen_height,
+        )
+    }
+}
+
+/// Collects the sprites drawn by a machine and turns them into cues
+#[derive(Debug)]
+pub struct Sonifier {
+    sender: Sender<Draw>,
+    draws: Receiver<Draw>,
+}
+
+impl Default for Sonifier {
+    fn default() -> Self {
+        let (sender, draws) = mpsc::channel();
+        Self { sender, draws }
+    }
+}
+
+impl Sonifier {
+    /// A sonifier that is not told about any sprite yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A draw hook that passes the sprites of a machine to this sonifier, it is installed again
+    /// on every machine that replaces the previous one
+    pub fn hook(&self) -> DrawHook {
+        let sender = self.sender.clone();
+        Box::new(move |draw| {
+            let _ = sender.send(*draw);
+        })
+    }
+
+    /// The cues of the regions drawn in since the last call, once per region, from the top
+    /// left. A game redraws its sprites every frame, so frontends play a cue only when the
+    /// previous one in its column has finished.
+    pub fn cues(&self) -> Vec<Cue> {
+        let mut drawn = [[false; REGION_COLUMNS]; REGION_ROWS];
+        for draw in self.draws.try_iter() {
+            let cue = Cue::of_draw(&draw);
+            drawn[cue.row][cue.column] = true;
+        }
+        let mut cues = Vec::new();
+        for (row, columns) in drawn.iter().enumerate() {
+            for (column, drawn) in columns.iter().enumerate() {
+                if *drawn {
+                    cues.push(Cue::new(column, row));
+                }
+            }
+        }
+        cues
+    }
+}
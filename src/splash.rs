@@ -0,0 +1,21 @@
+//! The screen shown for a moment before a ROM starts: the name and version of the emulator,
+//! drawn by the machine into its own screen so that every frontend shows it. Any key skips it,
+//! see [`crate::Chip8::set_splash`].
+
+use crate::text;
+use crate::text::Font;
+use crate::TICKS_PER_SEC;
+
+/// Number of ticks the splash is shown for, a second at the default speed
+pub const SPLASH_TICKS: u32 = TICKS_PER_SEC;
+
+/// Draw the splash onto a cleared screen
+pub(crate) fn draw(gfx: &mut [bool], width: usize, height: usize) {
+    let name = env!("CARGO_PKG_NAME").to_ascii_uppercase();
+    let version = format!("v{}", env!("CARGO_PKG_VERSION"));
+    let top = height / 2 - 6;
+    for (row, line) in [name, version].iter().enumerate() {
+        let left = width.saturating_sub(text::text_width(line, Font::Mini)) / 2;
+        text::draw_text_to_framebuffer(gfx, width, left, top + row * 7, line, Font::Mini);
+    }
+}
@@ -0,0 +1,74 @@
+//! Statistics collected over an emulation session
+
+use crate::clock;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time;
+
+/// Counters that are updated while the emulator runs
+pub struct Stats {
+    started: clock::Instant,
+    /// Number of frames of the delay and sound timers
+    pub frames: u64,
+    /// Number of executed instructions
+    pub instructions: u64,
+    /// Number of executed sprite draws
+    pub draws: u64,
+    /// Number of sprite draws that erased a pixel
+    pub collisions: u64,
+    /// Number of times the sound timer made a beep
+    pub beeps: u64,
+    /// Number of executed instructions of each kind, keyed by the opcode pattern
+    pub opcodes: BTreeMap<&'static str, u64>,
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Self {
+            started: clock::Instant::now(),
+            frames: 0,
+            instructions: 0,
+            draws: 0,
+            collisions: 0,
+            beeps: 0,
+            opcodes: BTreeMap::new(),
+        }
+    }
+}
+
+impl Stats {
+    /// Time elapsed since the session started
+    pub fn wall_time(&self) -> time::Duration {
+        self.started.elapsed()
+    }
+
+    /// Average number of instructions executed per second of wall time
+    pub fn average_ips(&self) -> f64 {
+        let secs = self.wall_time().as_secs_f64();
+        if secs > 0.0 {
+            self.instructions as f64 / secs
+        } else {
+            0.0
+        }
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Wall time:    {:.2?}", self.wall_time())?;
+        writeln!(f, "Frames:       {}", self.frames)?;
+        writeln!(f, "Instructions: {}", self.instructions)?;
+        writeln!(f, "Average IPS:  {:.1}", self.average_ips())?;
+        writeln!(f, "Draws:        {}", self.draws)?;
+        writeln!(f, "Collisions:   {}", self.collisions)?;
+        writeln!(f, "Beeps:        {}", self.beeps)?;
+        writeln!(f, "Opcodes:")?;
+        let mut opcodes: Vec<_> = self.opcodes.iter().collect();
+        opcodes.sort_by(|a, b| b.1.cmp(a.1));
+        for (pattern, count) in opcodes {
+            let share = *count as f64 * 100.0 / self.instructions.max(1) as f64;
+            writeln!(f, "  {} {:>12} {:>6.2}%", pattern, count, share)?;
+        }
+        Ok(())
+    }
+}
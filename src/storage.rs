@@ -0,0 +1,90 @@
+//! Where the files kept for the ROMs are read and written: their settings, save states and
+//! screenshots, and the list of recent ROMs. [`crate::datapaths`] decides the path of each file
+//! and a [`Storage`] holds its bytes. The native frontends use the file system with
+//! [`FileStorage`]. Builds without a file system, such as the browser or a libretro frontend, and
+//! tests supply their own, e.g. a [`MemoryStorage`].
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// A place that keeps files by path. It is shared by the window and the hooks of the machine, so
+/// it is used through a shared reference.
+pub trait Storage: Send + Sync {
+    /// The content of a file
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+
+    /// Replace the content of a file, it is created if it does not exist
+    fn write(&self, path: &Path, data: &[u8]) -> io::Result<()>;
+
+    /// Whether a file exists
+    fn exists(&self, path: &Path) -> bool;
+
+    /// The content of a text file
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        String::from_utf8(self.read(path)?)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
+
+/// The files of the host
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FileStorage;
+
+impl Storage for FileStorage {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(path)
+    }
+
+    fn write(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+        fs::write(path, data)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        path.is_file()
+    }
+}
+
+/// Files kept in memory, lost when it is dropped
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    files: Mutex<BTreeMap<PathBuf, Vec<u8>>>,
+}
+
+impl MemoryStorage {
+    /// A storage without any file
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The paths of the files that were written, in order
+    pub fn paths(&self) -> Vec<PathBuf> {
+        self.files.lock().unwrap().keys().cloned().collect()
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.files
+            .lock()
+            .unwrap()
+            .get(path)
+            .cloned()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no such file"))
+    }
+
+    fn write(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+        self.files
+            .lock()
+            .unwrap()
+            .insert(path.to_path_buf(), data.to_vec());
+        Ok(())
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.files.lock().unwrap().contains_key(path)
+    }
+}
@@ -0,0 +1,114 @@
+//! Names of the addresses of a program, read from a symbol file so that the state dump can show
+//! the routines a return address belongs to. A symbol file has a line per label with its address
+//! and its name, as written by `chip8 asm --symbols`:
+//!
+//! ```text
+//! 0x200 start
+//! 0x20A loop   ; comments start with a semicolon
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Labels of a program by address
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Symbols {
+    labels: BTreeMap<u16, String>,
+}
+
+impl Symbols {
+    /// Symbols without any label
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Name an address, a later label for the same address replaces the earlier one
+    pub fn insert(&mut self, addr: u16, name: &str) {
+        self.labels.insert(addr, name.to_string());
+    }
+
+    /// Parse the text of a symbol file
+    pub fn parse(text: &str) -> Result<Self, SymbolsError> {
+        let mut symbols = Self::new();
+        for (idx, line) in text.lines().enumerate() {
+            let line = line.split(';').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let invalid = || SymbolsError::Invalid {
+                line: idx + 1,
+                text: line.to_string(),
+            };
+            let (addr, name) = line.split_once(char::is_whitespace).ok_or_else(invalid)?;
+            let addr = u16::from_str_radix(addr.trim_start_matches("0x"), 16)
+                .ok()
+                .filter(|addr| *addr <= 0xFFF)
+                .ok_or_else(invalid)?;
+            symbols.insert(addr, name.trim());
+        }
+        Ok(symbols)
+    }
+
+    /// Read a symbol file
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, SymbolsError> {
+        let text = fs::read_to_string(path).map_err(SymbolsError::Io)?;
+        Self::parse(&text)
+    }
+
+    /// The text of a symbol file with every label
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for (addr, name) in &self.labels {
+            writeln!(text, "{:#05X} {}", addr, name).unwrap();
+        }
+        text
+    }
+
+    /// Name of an address: the label at it, or the closest label before it with the offset,
+    /// e.g. `loop+4`. `None` if no label comes before the address.
+    pub fn name(&self, addr: u16) -> Option<String> {
+        let (label_addr, name) = self.labels.range(..=addr).next_back()?;
+        Some(match addr - label_addr {
+            0 => name.clone(),
+            offset => format!("{}+{}", name, offset),
+        })
+    }
+
+    /// Returns true if there is no label
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+}
+
+/// Reasons for a symbol file to be rejected
+#[derive(Debug)]
+pub enum SymbolsError {
+    /// The file could not be read
+    Io(io::Error),
+    /// A line is not an address followed by a name
+    Invalid {
+        /// Line number, starting at 1
+        line: usize,
+        /// Text of the line
+        text: String,
+    },
+}
+
+impl fmt::Display for SymbolsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "could not read the symbols: {}", err),
+            Self::Invalid { line, text } => write!(
+                f,
+                "line {}: expected an address and a name, found {}",
+                line, text
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SymbolsError {}
@@ -0,0 +1,121 @@
+//! Helpers for generating test ROMs programmatically, for opcode tests and compliance suites.
+//!
+//! ```
+//! use chip8::testrom::RomBuilder;
+//! use chip8::Inst;
+//!
+//! let mut rom = RomBuilder::new();
+//! rom.inst(Inst::OpANNN(0)).patch("data");
+//! rom.inst(Inst::OpFX65(0));
+//! let halt = rom.addr();
+//! rom.inst(Inst::Op1NNN(halt));
+//! rom.define("data").bytes(&[0x2A]);
+//! let mut chip8 = rom.load();
+//! for _ in 0..3 {
+//!     chip8.step();
+//! }
+//! assert_eq!(chip8.registers()[0], 0x2A);
+//! ```
+
+use crate::Chip8;
+use crate::Inst;
+use std::collections::BTreeMap;
+
+pub use crate::PROGRAM_SIZE;
+pub use crate::PROGRAM_START;
+
+/// Assembles instructions and data into a program image starting at 0x200
+#[derive(Debug, Clone, Default)]
+pub struct RomBuilder {
+    bytes: Vec<u8>,
+    /// Named addresses
+    labels: BTreeMap<String, u16>,
+    /// Offsets of the instructions whose address operand refers to a label
+    patches: Vec<(usize, String)>,
+}
+
+impl RomBuilder {
+    /// Start an empty program
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Address of the next byte to be appended
+    pub fn addr(&self) -> u16 {
+        PROGRAM_START + self.bytes.len() as u16
+    }
+
+    /// Append an instruction
+    pub fn inst(&mut self, inst: Inst) -> &mut Self {
+        self.bytes.extend_from_slice(&inst.encode().to_be_bytes());
+        self
+    }
+
+    /// Append a sequence of instructions
+    pub fn insts(&mut self, insts: &[Inst]) -> &mut Self {
+        insts.iter().for_each(|inst| {
+            self.inst(*inst);
+        });
+        self
+    }
+
+    /// Append raw bytes, such as sprites
+    pub fn bytes(&mut self, bytes: &[u8]) -> &mut Self {
+        self.bytes.extend_from_slice(bytes);
+        self
+    }
+
+    /// Define a label at the current address
+    pub fn define(&mut self, name: &str) -> &mut Self {
+        let addr = self.addr();
+        self.labels.insert(name.to_string(), addr);
+        self
+    }
+
+    /// Make the address operand of the last instruction refer to a label, which may be defined
+    /// later
+    pub fn patch(&mut self, name: &str) -> &mut Self {
+        self.patches.push((self.bytes.len() - 2, name.to_string()));
+        self
+    }
+
+    /// Write bytes at a fixed address, growing the program as needed
+    pub fn place(&mut self, addr: u16, data: &[u8]) -> &mut Self {
+        let start = (addr - PROGRAM_START) as usize;
+        if self.bytes.len() < start + data.len() {
+            self.bytes.resize(start + data.len(), 0);
+        }
+        self.bytes[start..start + data.len()].copy_from_slice(data);
+        self
+    }
+
+    /// Produce the program image
+    ///
+    /// # Panics
+    ///
+    /// Panics if a label is referred to but never defined
+    pub fn build(&self) -> Vec<u8> {
+        let mut bytes = self.bytes.clone();
+        for (offset, name) in &self.patches {
+            let addr = *self
+                .labels
+                .get(name)
+                .unwrap_or_else(|| panic!("Label {} is never defined", name));
+            let opcode = u16::from_be_bytes([bytes[*offset], bytes[offset + 1]]);
+            let opcode = (opcode & 0xF000) | (addr & 0x0FFF);
+            bytes[*offset..offset + 2].copy_from_slice(&opcode.to_be_bytes());
+        }
+        bytes
+    }
+
+    /// Build the program and load it into a machine with a fixed seed
+    ///
+    /// # Panics
+    ///
+    /// Panics if the program does not fit in memory
+    pub fn load(&self) -> Chip8 {
+        let mut chip8 = Chip8::builder().seed(0).build();
+        chip8.load(&self.build()).expect("The program does not fit");
+        chip8
+    }
+}
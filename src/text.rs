@@ -0,0 +1,179 @@
+//! Text drawn straight into a framebuffer, for the splash screen, the launcher and labeled golden
+//! images in tests. It needs no program running on the machine, so headless frontends can use it
+//! as well.
+//!
+//! ```
+//! use chip8::text::{draw_text_to_framebuffer, Font};
+//!
+//! let mut screen = vec![false; 64 * 32];
+//! let end = draw_text_to_framebuffer(&mut screen, 64, 1, 1, "Score 42", Font::Mini);
+//! assert_eq!(end, 33);
+//! ```
+
+use crate::FONT;
+
+/// Height of the glyphs of every font
+pub const GLYPH_HEIGHT: usize = 5;
+
+/// The fonts text can be drawn in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Font {
+    /// The 4x5 hex digits built into the machine, other characters are left blank
+    Hex,
+    /// A 3x5 font of the printable ASCII characters, lower case letters are drawn in upper case
+    /// and characters without a glyph as `?`
+    Mini,
+}
+
+impl Font {
+    /// Width of a glyph in pixels
+    pub fn glyph_width(self) -> usize {
+        match self {
+            Self::Hex => 4,
+            Self::Mini => 3,
+        }
+    }
+
+    /// Distance between the left edges of two characters, glyphs are a pixel apart
+    pub fn advance(self) -> usize {
+        self.glyph_width() + 1
+    }
+
+    /// The rows of the glyph of a character, top to bottom with the leftmost pixel in the high bit
+    pub fn glyph(self, ch: char) -> [u8; GLYPH_HEIGHT] {
+        let mut rows = [0; GLYPH_HEIGHT];
+        match self {
+            Self::Hex => {
+                if let Some(digit) = ch.to_digit(16) {
+                    let start = digit as usize * GLYPH_HEIGHT;
+                    rows.copy_from_slice(&FONT[start..start + GLYPH_HEIGHT]);
+                }
+            }
+            Self::Mini => {
+                let glyph = &MINI_GLYPHS[mini_index(ch).unwrap_or('?' as usize - ' ' as usize)];
+                for (byte, row) in rows.iter_mut().zip(glyph) {
+                    *byte = row
+                        .chars()
+                        .enumerate()
+                        .filter(|(_, pixel)| *pixel == '#')
+                        .fold(0, |byte, (bit, _)| byte | 0x80 >> bit);
+                }
+            }
+        }
+        rows
+    }
+}
+
+/// Width of a line of text in pixels, without the space after the last character
+pub fn text_width(text: &str, font: Font) -> usize {
+    (text.chars().count() * font.advance()).saturating_sub(1)
+}
+
+/// Light the pixels of a line of text with its top-left corner at `x`, `y`, in a framebuffer
+/// `width` pixels wide. Pixels past the edges are clipped, pixels that are already lit stay lit.
+/// Returns the column right after the text, where more text can follow.
+pub fn draw_text_to_framebuffer(
+    framebuffer: &mut [bool],
+    width: usize,
+    x: usize,
+    y: usize,
+    text: &str,
+    font: Font,
+) -> usize {
+    let height = framebuffer.len() / width.max(1);
+    let mut left = x;
+    for ch in text.chars() {
+        for (row, bits) in font.glyph(ch).iter().enumerate() {
+            for col in 0..font.glyph_width() {
+                let (px, py) = (left + col, y + row);
+                if bits & (0x80 >> col) != 0 && px < width && py < height {
+                    framebuffer[px + py * width] = true;
+                }
+            }
+        }
+        left += font.advance();
+    }
+    left
+}
+
+/// Index of the glyph of a character in [`MINI_GLYPHS`]
+fn mini_index(ch: char) -> Option<usize> {
+    match ch.to_ascii_uppercase() {
+        ch @ ' '..='`' => Some(ch as usize - ' ' as usize),
+        ch @ '{'..='~' => Some(ch as usize - '{' as usize + '`' as usize - ' ' as usize + 1),
+        _ => None,
+    }
+}
+
+/// Glyphs of the printable ASCII characters from the space to the backquote, then `{|}~`
+const MINI_GLYPHS: [[&str; GLYPH_HEIGHT]; 69] = [
+    ["...", "...", "...", "...", "..."],
+    [".#.", ".#.", ".#.", "...", ".#."],
+    ["#.#", "#.#", "...", "...", "..."],
+    ["#.#", "###", "#.#", "###", "#.#"],
+    [".##", "##.", ".#.", ".##", "##."],
+    ["#.#", "..#", ".#.", "#..", "#.#"],
+    [".#.", "#.#", ".#.", "#.#", ".##"],
+    [".#.", ".#.", "...", "...", "..."],
+    ["..#", ".#.", ".#.", ".#.", "..#"],
+    ["#..", ".#.", ".#.", ".#.", "#.."],
+    ["...", "#.#", ".#.", "#.#", "..."],
+    ["...", ".#.", "###", ".#.", "..."],
+    ["...", "...", "...", ".#.", "#.."],
+    ["...", "...", "###", "...", "..."],
+    ["...", "...", "...", "...", ".#."],
+    ["..#", "..#", ".#.", "#..", "#.."],
+    ["###", "#.#", "#.#", "#.#", "###"],
+    [".#.", "##.", ".#.", ".#.", "###"],
+    ["##.", "..#", ".#.", "#..", "###"],
+    ["##.", "..#", ".#.", "..#", "##."],
+    ["#.#", "#.#", "###", "..#", "..#"],
+    ["###", "#..", "##.", "..#", "##."],
+    [".##", "#..", "###", "#.#", "###"],
+    ["###", "..#", ".#.", ".#.", ".#."],
+    ["###", "#.#", "###", "#.#", "###"],
+    ["###", "#.#", "###", "..#", "##."],
+    ["...", ".#.", "...", ".#.", "..."],
+    ["...", ".#.", "...", ".#.", "#.."],
+    ["..#", ".#.", "#..", ".#.", "..#"],
+    ["...", "###", "...", "###", "..."],
+    ["#..", ".#.", "..#", ".#.", "#.."],
+    ["##.", "..#", ".#.", "...", ".#."],
+    [".#.", "#.#", "###", "#..", ".##"],
+    [".#.", "#.#", "###", "#.#", "#.#"],
+    ["##.", "#.#", "##.", "#.#", "##."],
+    [".##", "#..", "#..", "#..", ".##"],
+    ["##.", "#.#", "#.#", "#.#", "##."],
+    ["###", "#..", "##.", "#..", "###"],
+    ["###", "#..", "##.", "#..", "#.."],
+    [".##", "#..", "#.#", "#.#", ".##"],
+    ["#.#", "#.#", "###", "#.#", "#.#"],
+    ["###", ".#.", ".#.", ".#.", "###"],
+    ["..#", "..#", "..#", "#.#", ".#."],
+    ["#.#", "#.#", "##.", "#.#", "#.#"],
+    ["#..", "#..", "#..", "#..", "###"],
+    ["#.#", "###", "###", "#.#", "#.#"],
+    ["##.", "#.#", "#.#", "#.#", "#.#"],
+    [".#.", "#.#", "#.#", "#.#", ".#."],
+    ["##.", "#.#", "##.", "#..", "#.."],
+    [".#.", "#.#", "#.#", "##.", ".##"],
+    ["##.", "#.#", "##.", "#.#", "#.#"],
+    [".##", "#..", ".#.", "..#", "##."],
+    ["###", ".#.", ".#.", ".#.", ".#."],
+    ["#.#", "#.#", "#.#", "#.#", "###"],
+    ["#.#", "#.#", "#.#", "#.#", ".#."],
+    ["#.#", "#.#", "###", "###", "#.#"],
+    ["#.#", "#.#", ".#.", "#.#", "#.#"],
+    ["#.#", "#.#", ".#.", ".#.", ".#."],
+    ["###", "..#", ".#.", "#..", "###"],
+    ["##.", "#..", "#..", "#..", "##."],
+    ["#..", "#..", ".#.", "..#", "..#"],
+    [".##", "..#", "..#", "..#", ".##"],
+    [".#.", "#.#", "...", "...", "..."],
+    ["...", "...", "...", "...", "###"],
+    ["#..", ".#.", "...", "...", "..."],
+    [".##", ".#.", "##.", ".#.", ".##"],
+    [".#.", ".#.", ".#.", ".#.", ".#."],
+    ["##.", ".#.", ".##", ".#.", "##."],
+    ["...", "##.", ".##", "...", "..."],
+];
@@ -0,0 +1,49 @@
+//! Traces of the executed instructions, one line per instruction with its address, its opcode,
+//! its mnemonic and the registers it changed, for comparing a run against another emulator:
+//!
+//! ```text
+//! 0x200  6005  LD V0, 0x05          V0=05
+//! 0x202  A300  LD I, 0x300          I=300
+//! 0x204  F015  LD DT, V0            DT=05
+//! ```
+
+use crate::Inst;
+use std::fmt::Write;
+
+/// Where the trace is written, e.g. `Box::new(std::io::stderr())`
+pub type TraceWriter = Box<dyn std::io::Write + Send>;
+
+/// The registers an instruction can change
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Registers {
+    pub(crate) v: [u8; 16],
+    pub(crate) i: u16,
+    pub(crate) dt: u8,
+    pub(crate) st: u8,
+}
+
+/// The line of an instruction executed at `addr`, with the registers before and after it
+pub(crate) fn line(
+    addr: u16,
+    opcode: u16,
+    inst: Inst,
+    before: &Registers,
+    after: &Registers,
+) -> String {
+    let mut line = format!("{:#05X}  {:04X}  {:<20}", addr, opcode, inst.to_string());
+    for (x, (old, new)) in before.v.iter().zip(&after.v).enumerate() {
+        if old != new {
+            let _ = write!(line, " V{:X}={:02X}", x, new);
+        }
+    }
+    if before.i != after.i {
+        let _ = write!(line, " I={:03X}", after.i);
+    }
+    if before.dt != after.dt {
+        let _ = write!(line, " DT={:02X}", after.dt);
+    }
+    if before.st != after.st {
+        let _ = write!(line, " ST={:02X}", after.st);
+    }
+    line.trim_end().to_string()
+}
@@ -0,0 +1,294 @@
+//! Host traps, machine code calls (0NNN) to a reserved range of addresses that call back into the
+//! host. They let a ROM print a register while it is being debugged, or check its results when
+//! it is a test. Real ROMs may call machine code at these addresses, so the traps are only
+//! handled when a [`Traps`] hook is installed.
+//!
+//! ```text
+//! SYS 0x103   ; print V3
+//! SYS 0x113   ; halt unless V3 is not zero
+//! SYS 0x120   ; print every register
+//! SYS 0x131   ; print V1 as a character, a line is printed at a newline
+//! PRINT "score %3"    ; SYS 0x121 followed by the text, see crate::asm
+//! SYS 0x143           ; halt unless V3 is 7
+//! DB 7, 0
+//! SYS 0x150           ; halt unless the pixel at 10,4 is lit
+//! DB 10, 4, 1, 0
+//! SYS 0x160           ; the test passed
+//! ```
+//!
+//! Test ROMs report their [`Verdict`] through the last traps, which makes them usable by the
+//! headless runner of [`crate::runner`] without looking at the screen.
+
+use crate::fault::Fault;
+use crate::Chip8;
+use crate::InterpreterHook;
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+/// Addresses reserved for host traps
+pub const RANGE: RangeInclusive<u16> = 0x100..=0x1FF;
+/// Print VX, the trap for VX is at `PRINT + X`
+pub const PRINT: u16 = 0x100;
+/// Halt with [`Fault::AssertionFailed`] if VX is zero, the trap for VX is at `ASSERT + X`
+pub const ASSERT: u16 = 0x110;
+/// Print the registers, the index register and the program counter
+pub const PRINT_REGISTERS: u16 = 0x120;
+/// Print the zero-terminated text that follows the call and continue after it, at the next even
+/// address. `%X` is replaced with the value of VX in decimal and `%%` with `%`.
+pub const PRINT_TEXT: u16 = 0x121;
+/// Add VX to the current line as a character, the trap for VX is at `PRINT_CHAR + X`. The line is
+/// printed when the character is a newline.
+pub const PRINT_CHAR: u16 = 0x130;
+/// Halt unless VX equals the byte that follows the call, the trap for VX is at `ASSERT_EQUAL + X`.
+/// The call is followed by `DB KK, 0`.
+pub const ASSERT_EQUAL: u16 = 0x140;
+/// Halt unless the pixel at the coordinates that follow the call is lit (1) or unlit (0). The call
+/// is followed by `DB X, Y, LIT, 0`.
+pub const ASSERT_PIXEL: u16 = 0x150;
+/// Report that the test passed
+pub const PASS: u16 = 0x160;
+/// Report that the test failed
+pub const FAIL: u16 = 0x161;
+/// Start a ROM from the launcher, see [`crate::shell`]
+pub const LAUNCH: u16 = 0x1F0;
+
+/// Handler of a trap, called with the machine and the address of the trap
+pub type TrapHandler = Box<dyn FnMut(&mut Chip8, u16) + Send>;
+
+/// Outcome of a test ROM, reported by the test traps
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    /// The ROM called [`PASS`]
+    Passed,
+    /// The ROM called [`FAIL`] or an assertion failed, with what went wrong
+    Failed(String),
+}
+
+/// The first verdict reported by a ROM, shared with the trap handlers
+pub type SharedVerdict = Arc<Mutex<Option<Verdict>>>;
+
+/// Handlers of the host traps, installed with [`Traps::into_hook`]
+#[derive(Default)]
+pub struct Traps {
+    handlers: BTreeMap<u16, TrapHandler>,
+    fallback: Option<InterpreterHook>,
+    verdict: SharedVerdict,
+}
+
+impl Traps {
+    /// No traps
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The print, assert and test traps. The printed lines are passed to `print`, which decides
+    /// where they go, e.g. the standard output or the console of a frontend.
+    pub fn builtin(print: impl Fn(String) + Clone + Send + 'static) -> Self {
+        let mut traps = Self::new();
+        let line = Arc::new(Mutex::new(String::new()));
+        for x in 0..16 {
+            let print_value = print.clone();
+            traps.register(
+                PRINT + x,
+                Box::new(move |chip8, addr| {
+                    let x = (addr - PRINT) as usize;
+                    let value = chip8.registers()[x];
+                    print_value(format!("V{:X} = {:#04X} ({})", x, value, value));
+                }),
+            );
+            let print_char = print.clone();
+            let line = line.clone();
+            traps.register(
+                PRINT_CHAR + x,
+                Box::new(move |chip8, addr| {
+                    let ch = chip8.registers()[(addr - PRINT_CHAR) as usize] as char;
+                    let mut line = line.lock().unwrap();
+                    if ch == '\n' {
+                        print_char(std::mem::take(&mut *line));
+                    } else {
+                        line.push(ch);
+                    }
+                }),
+            );
+            let verdict = traps.verdict.clone();
+            traps.register(
+                ASSERT + x,
+                Box::new(move |chip8, addr| {
+                    let register = (addr - ASSERT) as u8;
+                    if chip8.registers()[register as usize] == 0 {
+                        let addr = chip8.pc().wrapping_sub(2);
+                        let fault = Fault::AssertionFailed { addr, register };
+                        report(&verdict, Verdict::Failed(fault.to_string()));
+                        chip8.fail(fault);
+                    }
+                }),
+            );
+            let verdict = traps.verdict.clone();
+            traps.register(
+                ASSERT_EQUAL + x,
+                Box::new(move |chip8, addr| {
+                    let register = (addr - ASSERT_EQUAL) as u8;
+                    let start = chip8.pc();
+                    let expected = chip8.memory()[start as usize];
+                    let actual = chip8.registers()[register as usize];
+                    chip8.set_pc(start + 2);
+                    if actual != expected {
+                        let message = format!(
+                            "V{:X} is {:#04X} at {:#05X}, expected {:#04X}",
+                            register,
+                            actual,
+                            start - 2,
+                            expected
+                        );
+                        report(&verdict, Verdict::Failed(message));
+                        let addr = start - 2;
+                        chip8.fail(Fault::AssertionFailed { addr, register });
+                    }
+                }),
+            );
+        }
+        let verdict = traps.verdict.clone();
+        traps.register(
+            ASSERT_PIXEL,
+            Box::new(move |chip8, _| {
+                let start = chip8.pc();
+                let operands = &chip8.memory()[start as usize..start as usize + 3];
+                let (x, y, expected) = (operands[0] as usize, operands[1] as usize, operands[2]);
+                let (width, height) = chip8.screen_size();
+                let lit = chip8.framebuffer()[(y % height) * width + x % width];
+                chip8.set_pc(start + 4);
+                if lit != (expected != 0) {
+                    let state = |lit| if lit { "lit" } else { "unlit" };
+                    let message = format!(
+                        "pixel {},{} is {} at {:#05X}, expected {}",
+                        x,
+                        y,
+                        state(lit),
+                        start - 2,
+                        state(!lit)
+                    );
+                    report(&verdict, Verdict::Failed(message));
+                    chip8.fail(Fault::TestFailed { addr: start - 2 });
+                }
+            }),
+        );
+        let print_pass = print.clone();
+        let verdict = traps.verdict.clone();
+        traps.register(
+            PASS,
+            Box::new(move |_, _| {
+                print_pass("Test passed".to_string());
+                report(&verdict, Verdict::Passed);
+            }),
+        );
+        let print_fail = print.clone();
+        let verdict = traps.verdict.clone();
+        traps.register(
+            FAIL,
+            Box::new(move |chip8, _| {
+                let addr = chip8.pc().wrapping_sub(2);
+                print_fail("Test failed".to_string());
+                report(
+                    &verdict,
+                    Verdict::Failed(format!("failed at {:#05X}", addr)),
+                );
+                chip8.fail(Fault::TestFailed { addr });
+            }),
+        );
+        let print_registers = print.clone();
+        traps.register(
+            PRINT_REGISTERS,
+            Box::new(move |chip8, _| {
+                let registers: Vec<_> = chip8
+                    .registers()
+                    .iter()
+                    .enumerate()
+                    .map(|(x, value)| format!("V{:X}={:02X}", x, value))
+                    .collect();
+                print_registers(format!(
+                    "{} I={:03X} PC={:03X}",
+                    registers.join(" "),
+                    chip8.index(),
+                    chip8.pc()
+                ));
+            }),
+        );
+        traps.register(
+            PRINT_TEXT,
+            Box::new(move |chip8, _| {
+                let start = chip8.pc() as usize;
+                let text: Vec<_> = chip8.memory()[start..]
+                    .iter()
+                    .take_while(|byte| **byte != 0)
+                    .copied()
+                    .collect();
+                print(format_text(&text, chip8.registers()));
+                chip8.set_pc((start + text.len() + 1).next_multiple_of(2) as u16);
+            }),
+        );
+        traps
+    }
+
+    /// The verdict reported by the test traps, which can be read while the hook runs
+    pub fn verdict(&self) -> SharedVerdict {
+        self.verdict.clone()
+    }
+
+    /// Handle the trap at an address, replacing its previous handler. Returns false if the
+    /// address is outside of [`RANGE`].
+    pub fn register(&mut self, addr: u16, handler: TrapHandler) -> bool {
+        if !RANGE.contains(&addr) {
+            return false;
+        }
+        self.handlers.insert(addr, handler);
+        true
+    }
+
+    /// Pass the addresses without a trap handler to another interpreter hook
+    pub fn fallback(mut self, hook: Option<InterpreterHook>) -> Self {
+        self.fallback = hook;
+        self
+    }
+
+    /// The interpreter hook that runs the handlers, see [`Chip8::set_interpreter_hook`]
+    pub fn into_hook(mut self) -> InterpreterHook {
+        Box::new(move |chip8, addr| match self.handlers.get_mut(&addr) {
+            Some(handler) => {
+                handler(chip8, addr);
+                true
+            }
+            None => self
+                .fallback
+                .as_mut()
+                .is_some_and(|fallback| fallback(chip8, addr)),
+        })
+    }
+}
+
+/// Keep the first verdict of a run
+fn report(verdict: &SharedVerdict, reported: Verdict) {
+    verdict.lock().unwrap().get_or_insert(reported);
+}
+
+/// Replace the `%X` placeholders of a text with the values of the registers
+fn format_text(text: &[u8], registers: &[u8; 16]) -> String {
+    let mut formatted = String::new();
+    let mut chars = text.iter().map(|byte| *byte as char);
+    while let Some(ch) = chars.next() {
+        if ch != '%' {
+            formatted.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some(x) if x.is_ascii_hexdigit() => {
+                let x = x.to_digit(16).unwrap() as usize;
+                formatted.push_str(&registers[x].to_string());
+            }
+            Some(other) => formatted.push(other),
+            None => formatted.push('%'),
+        }
+    }
+    formatted
+}
@@ -0,0 +1,191 @@
+//! A frontend that runs in a terminal, for machines reached over SSH or without a GPU. Every
+//! character cell shows two pixels stacked with the half block characters, so the 64x32 screen
+//! fits in 64x16 cells.
+//!
+//! Most terminals only report key presses, so a key is held until it has not been repeated for
+//! [`RELEASE_DELAY`]. Terminals that support the kitty keyboard protocol also report releases,
+//! which are used instead.
+
+use super::pacer::Pacer;
+use super::Chip8;
+use crossterm::cursor;
+use crossterm::event;
+use crossterm::event::Event;
+use crossterm::event::KeyCode;
+use crossterm::event::KeyEventKind;
+use crossterm::event::KeyModifiers;
+use crossterm::event::KeyboardEnhancementFlags;
+use crossterm::event::PopKeyboardEnhancementFlags;
+use crossterm::event::PushKeyboardEnhancementFlags;
+use crossterm::execute;
+use crossterm::queue;
+use crossterm::style::Print;
+use crossterm::terminal;
+use std::io;
+use std::io::Write;
+use std::time;
+
+/// Time after the latest press of a key until it is released, when the terminal does not report
+/// releases. Longer than the delay before the keyboard starts repeating.
+pub const RELEASE_DELAY: time::Duration = time::Duration::from_millis(600);
+
+/// Run the emulator in the terminal until Escape or Ctrl+C is pressed
+pub fn run(chip8: &mut Chip8, ticks_per_sec: u32) -> io::Result<()> {
+    let mut out = io::stdout();
+    terminal::enable_raw_mode()?;
+    let releases = terminal::supports_keyboard_enhancement().unwrap_or(false);
+    execute!(out, terminal::EnterAlternateScreen, cursor::Hide)?;
+    if releases {
+        let flags = KeyboardEnhancementFlags::REPORT_EVENT_TYPES;
+        execute!(out, PushKeyboardEnhancementFlags(flags))?;
+    }
+    let result = run_loop(chip8, ticks_per_sec, releases, &mut out);
+    if releases {
+        execute!(out, PopKeyboardEnhancementFlags)?;
+    }
+    execute!(out, cursor::Show, terminal::LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()?;
+    result
+}
+
+fn run_loop(
+    chip8: &mut Chip8,
+    ticks_per_sec: u32,
+    releases: bool,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let frame = time::Duration::from_secs(1) / 60;
+    let mut pacer = Pacer::default();
+    // Time of the latest press of every held key
+    let mut held: [Option<time::Instant>; 16] = [None; 16];
+    let mut presented = Vec::new();
+    let mut beeping = false;
+    queue!(out, terminal::Clear(terminal::ClearType::All))?;
+    loop {
+        let started = time::Instant::now();
+        while event::poll(time::Duration::ZERO)? {
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            let ctrl_c =
+                key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+            if key.code == KeyCode::Esc || ctrl_c {
+                return Ok(());
+            }
+            let KeyCode::Char(ch) = key.code else {
+                continue;
+            };
+            if let Some(idx) = keypad_index(ch.to_ascii_lowercase()) {
+                let pressed = key.kind != KeyEventKind::Release;
+                held[idx as usize] = Some(started).filter(|_| pressed);
+                chip8.set_keyboard_key(idx, pressed);
+            }
+        }
+        if !releases {
+            for (idx, pressed_at) in held.iter_mut().enumerate() {
+                if pressed_at.is_some_and(|at| at.elapsed() >= RELEASE_DELAY) {
+                    *pressed_at = None;
+                    chip8.set_keyboard_key(idx as u8, false);
+                }
+            }
+        }
+
+        chip8.poll_inputs();
+        pacer.run(chip8, ticks_per_sec);
+        if chip8.framebuffer() != presented.as_slice() {
+            presented = chip8.framebuffer().to_vec();
+            draw(out, &presented, chip8.screen_size())?;
+        }
+        // The terminal bell rings once at the start of every beep
+        if chip8.sound_active() && !beeping {
+            queue!(out, Print('\x07'))?;
+        }
+        beeping = chip8.sound_active();
+        out.flush()?;
+        if let Some(rest) = frame.checked_sub(started.elapsed()) {
+            std::thread::sleep(rest);
+        }
+    }
+}
+
+/// Draw the screen with two pixels per character cell
+fn draw(out: &mut impl Write, pixels: &[bool], (width, height): (usize, usize)) -> io::Result<()> {
+    for row in 0..height / 2 {
+        let line: String = (0..width)
+            .map(|x| {
+                let top = pixels[row * 2 * width + x];
+                let bottom = pixels[(row * 2 + 1) * width + x];
+                match (top, bottom) {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' ',
+                }
+            })
+            .collect();
+        queue!(
+            out,
+            cursor::MoveTo(0, row as u16),
+            Print(line),
+            terminal::Clear(terminal::ClearType::UntilNewLine)
+        )?;
+    }
+    queue!(
+        out,
+        cursor::MoveTo(0, (height / 2) as u16),
+        terminal::Clear(terminal::ClearType::FromCursorDown),
+        Print("Esc quits")
+    )
+}
+
+/// The keypad key that a character is mapped to, using the same layout as the window
+fn keypad_index(ch: char) -> Option<u8> {
+    let key = match ch {
+        '1' => 0x1,
+        '2' => 0x2,
+        '3' => 0x3,
+        '4' => 0xC,
+        'q' => 0x4,
+        'w' => 0x5,
+        'e' => 0x6,
+        'r' => 0xD,
+        'a' => 0x7,
+        's' => 0x8,
+        'd' => 0x9,
+        'f' => 0xE,
+        'z' => 0xA,
+        'x' => 0x0,
+        'c' => 0xB,
+        'v' => 0xF,
+        _ => return None,
+    };
+    Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::draw;
+    use super::keypad_index;
+
+    #[test]
+    fn keys_follow_the_layout_of_the_window() {
+        let keys: Vec<_> = "1234qwerasdfzxcv".chars().map(keypad_index).collect();
+        let layout = [1, 2, 3, 0xC, 4, 5, 6, 0xD, 7, 8, 9, 0xE, 0xA, 0, 0xB, 0xF];
+        assert_eq!(keys, layout.map(Some));
+        assert_eq!(keypad_index('5'), None);
+        assert_eq!(keypad_index('Q'), None);
+    }
+
+    #[test]
+    fn two_rows_of_pixels_share_a_line() {
+        let pixels = [
+            true, true, false, false, //
+            true, false, true, false,
+        ];
+        let mut out = Vec::new();
+        draw(&mut out, &pixels, (4, 2)).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("█▀▄ "), "{:?}", out);
+        assert!(out.ends_with("Esc quits"));
+    }
+}
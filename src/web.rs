@@ -0,0 +1,501 @@
+//! Exports for a browser frontend, built for `wasm32-unknown-unknown` with the `web` feature and
+//! without the default `window` feature. The page in `web/` loads the module, copies the ROM into
+//! the buffer returned by [`chip8_rom_buffer`] and its file name into [`chip8_text_buffer`], calls
+//! [`chip8_frame`] sixty times per second, draws the screen from [`chip8_screen`] on a canvas,
+//! and plays a WebAudio tone while [`chip8_sound`] is set. Only numbers and pointers cross the
+//! boundary, so no bindings generator is needed.
+//!
+//! The settings, the save state and the SUPER-CHIP user flags of a ROM are kept for the next
+//! visit in the `localStorage` of the page, under the name of the ROM, through a [`Storage`].
+//! `localStorage` is used rather than IndexedDB since it can be read synchronously, like the
+//! files of the native frontends.
+//!
+//! A ROM can be linked to with the `rom` parameter of the URL of the page, which fetches it, and
+//! the speed, quirks and palette parameters read by [`RomConfig::from_query`] and passed to
+//! [`chip8_set_params`] go on top of its settings, without being kept.
+//!
+//! The ROMs of [`crate::bundled`] are compiled into the module, so that the page can offer them
+//! when it runs offline as an installed app: its service worker, `web/sw.js`, keeps the page and
+//! the module in the cache of the browser.
+//!
+//! The page polls the first gamepad of the Gamepad API every frame and passes the changes of its
+//! buttons to [`chip8_gamepad_button`] and its left stick to [`chip8_gamepad_axis`]. Buttons of
+//! the standard layout are named and bound as in the window, see [`KeyMap::gamepad`], with the
+//! `gamepad` bindings of the settings of the ROM on top.
+//!
+//! The module imports `chip8.now` and `chip8.random`, see [`crate::clock`] and [`crate::rng`],
+//! and `chip8.storage_size`, `chip8.storage_read` and `chip8.storage_write` for the storage.
+
+use crate::bundled;
+use crate::bundled::Bundled;
+use crate::datapaths::DataPaths;
+use crate::keymap::KeyMap;
+use crate::keymap::STICK_THRESHOLD;
+use crate::pacer::Pacer;
+use crate::palette::Palette;
+use crate::romconfig::RomConfig;
+use crate::savestate::SaveState;
+use crate::storage::Storage;
+use crate::Chip8;
+use crate::PROGRAM_SIZE;
+use crate::TICKS_PER_SEC;
+use std::cell::RefCell;
+use std::convert::TryFrom;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Size of the buffer where the page writes text, such as the name of the ROM
+pub const TEXT_SIZE: usize = 1024;
+/// Names of the buttons of the standard layout of the Gamepad API, by index
+pub const STANDARD_BUTTONS: [&str; 17] = [
+    "South",
+    "East",
+    "West",
+    "North",
+    "LeftTrigger",
+    "RightTrigger",
+    "LeftTrigger2",
+    "RightTrigger2",
+    "Select",
+    "Start",
+    "LeftThumb",
+    "RightThumb",
+    "DPadUp",
+    "DPadDown",
+    "DPadLeft",
+    "DPadRight",
+    "Mode",
+];
+
+/// The machine run by the page, its ROM and its screen
+struct Session {
+    chip8: Chip8,
+    rom: Box<[u8; PROGRAM_SIZE]>,
+    text: Box<[u8; TEXT_SIZE]>,
+    screen: Vec<u8>,
+    ticks_per_sec: u32,
+    pacer: Pacer,
+    storage: Box<dyn Storage>,
+    /// File name of the ROM, the files kept for it are named after it
+    name: PathBuf,
+    /// The settings kept for the ROM
+    config: RomConfig,
+    /// Settings for the next ROM that take precedence over its own, see [`chip8_set_params`]
+    params: RomConfig,
+    palette: Palette,
+    /// The user flags as last stored
+    rpl: [u8; 8],
+    gamepad: KeyMap,
+    /// Direction of the left stick on its two axes, -1, 0 or 1
+    stick: [i8; 2],
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self {
+            chip8: Chip8::builder().build(),
+            rom: Box::new([0; PROGRAM_SIZE]),
+            text: Box::new([0; TEXT_SIZE]),
+            screen: Vec::new(),
+            ticks_per_sec: TICKS_PER_SEC,
+            pacer: Pacer::default(),
+            storage: page_storage(),
+            name: PathBuf::new(),
+            config: RomConfig::default(),
+            params: RomConfig::default(),
+            palette: Palette::default(),
+            rpl: [0; 8],
+            gamepad: KeyMap::gamepad(),
+            stick: [0; 2],
+        }
+    }
+}
+
+impl Session {
+    /// The file kept for the ROM with the given extension
+    fn file(&self, extension: &str) -> PathBuf {
+        DataPaths::NextToRom.file(&self.name, extension)
+    }
+
+    /// Press or release the keypad key bound to a gamepad button
+    fn gamepad_button(&mut self, button: &str, pressed: bool) {
+        if let Some(key) = self.gamepad.get(button) {
+            self.chip8.set_keyboard_key(key, pressed);
+        }
+    }
+
+    /// Reset the machine and load a program, with the settings and the user flags kept under its
+    /// name and those of [`chip8_set_params`] on top
+    fn load(&mut self, name: PathBuf, program: &[u8]) -> bool {
+        self.name = name;
+        self.config = RomConfig::load(self.storage.as_ref(), &DataPaths::NextToRom, &self.name)
+            .unwrap_or_default();
+        let params = std::mem::take(&mut self.params);
+        let config = &self.config;
+        self.ticks_per_sec = params.speed.or(config.speed).unwrap_or(TICKS_PER_SEC);
+        self.chip8
+            .set_quirks(params.quirks.or(config.quirks).unwrap_or_default());
+        self.palette = params.palette.or(config.palette).unwrap_or_default();
+        self.gamepad = KeyMap::gamepad();
+        if let Some(gamepad) = &self.config.gamepad {
+            self.gamepad.extend(gamepad);
+        }
+        self.chip8.reset();
+        if self.chip8.load(program).is_err() {
+            return false;
+        }
+        self.rpl = self
+            .storage
+            .read(&self.file("rpl"))
+            .ok()
+            .and_then(|flags| <[u8; 8]>::try_from(flags.as_slice()).ok())
+            .unwrap_or_default();
+        self.chip8.set_rpl_flags(self.rpl);
+        true
+    }
+
+    /// Write the settings of the ROM, returns false if they could not be written
+    fn save_config(&self) -> bool {
+        self.config
+            .save(self.storage.as_ref(), &DataPaths::NextToRom, &self.name)
+            .is_ok()
+    }
+}
+
+thread_local! {
+    static SESSION: RefCell<Session> = RefCell::new(Session::default());
+}
+
+/// The `localStorage` of the page, a file is an entry named after its path
+#[cfg(target_arch = "wasm32")]
+struct PageStorage;
+
+#[cfg(target_arch = "wasm32")]
+#[link(wasm_import_module = "chip8")]
+extern "C" {
+    /// Size of a file, -1 if it does not exist
+    fn storage_size(path: *const u8, path_len: usize) -> isize;
+    /// Copy a file into a buffer of its size
+    fn storage_read(path: *const u8, path_len: usize, data: *mut u8);
+    /// Replace a file, returns false if the storage is full
+    fn storage_write(path: *const u8, path_len: usize, data: *const u8, len: usize) -> bool;
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Storage for PageStorage {
+    fn read(&self, path: &std::path::Path) -> std::io::Result<Vec<u8>> {
+        let path = path.to_string_lossy();
+        let size = unsafe { storage_size(path.as_ptr(), path.len()) };
+        let size = usize::try_from(size).map_err(|_| {
+            std::io::Error::new(std::io::ErrorKind::NotFound, "no such file in the page")
+        })?;
+        let mut data = vec![0; size];
+        unsafe { storage_read(path.as_ptr(), path.len(), data.as_mut_ptr()) };
+        Ok(data)
+    }
+
+    fn write(&self, path: &std::path::Path, data: &[u8]) -> std::io::Result<()> {
+        let path = path.to_string_lossy();
+        match unsafe { storage_write(path.as_ptr(), path.len(), data.as_ptr(), data.len()) } {
+            true => Ok(()),
+            false => Err(std::io::Error::other("the storage of the page is full")),
+        }
+    }
+
+    fn exists(&self, path: &std::path::Path) -> bool {
+        let path = path.to_string_lossy();
+        unsafe { storage_size(path.as_ptr(), path.len()) >= 0 }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn page_storage() -> Box<dyn Storage> {
+    Box::new(PageStorage)
+}
+
+/// Outside of a page, e.g. when the feature is checked natively, the files are kept in memory
+#[cfg(not(target_arch = "wasm32"))]
+fn page_storage() -> Box<dyn Storage> {
+    Box::new(crate::storage::MemoryStorage::new())
+}
+
+/// Buffer of [`PROGRAM_SIZE`] bytes where the page writes the ROM before calling [`chip8_load`]
+#[no_mangle]
+pub extern "C" fn chip8_rom_buffer() -> *mut u8 {
+    SESSION.with(|session| session.borrow_mut().rom.as_mut_ptr())
+}
+
+/// Buffer of [`TEXT_SIZE`] bytes where the page writes UTF-8 text passed to the module
+#[no_mangle]
+pub extern "C" fn chip8_text_buffer() -> *mut u8 {
+    SESSION.with(|session| session.borrow_mut().text.as_mut_ptr())
+}
+
+/// Take the settings given by the first `len` bytes of the text buffer, the query of the URL of
+/// the page, for the next ROM, see [`RomConfig::from_query`]. Returns false if they are not
+/// valid.
+#[no_mangle]
+pub extern "C" fn chip8_set_params(len: usize) -> bool {
+    SESSION.with(|session| {
+        let session = &mut *session.borrow_mut();
+        let params = session
+            .text
+            .get(..len)
+            .and_then(|query| std::str::from_utf8(query).ok())
+            .map(RomConfig::from_query);
+        match params {
+            Some(Ok(params)) => {
+                session.params = params;
+                true
+            }
+            _ => false,
+        }
+    })
+}
+
+/// Reset the machine and load the first `len` bytes of the ROM buffer, with the settings and the
+/// user flags kept for the ROM named by the first `name_len` bytes of the text buffer, and those
+/// of [`chip8_set_params`] on top. Returns false if `len` is larger than the buffer or the name
+/// is not valid.
+#[no_mangle]
+pub extern "C" fn chip8_load(len: usize, name_len: usize) -> bool {
+    SESSION.with(|session| {
+        let session = &mut *session.borrow_mut();
+        let name = session
+            .text
+            .get(..name_len)
+            .and_then(|name| std::str::from_utf8(name).ok())
+            .map(PathBuf::from);
+        match (name, session.rom.get(..len)) {
+            (Some(name), Some(program)) => {
+                let program = program.to_vec();
+                session.load(name, &program)
+            }
+            _ => false,
+        }
+    })
+}
+
+/// Number of ROMs compiled into the module, see [`crate::bundled`]
+#[no_mangle]
+pub extern "C" fn chip8_bundled_roms() -> usize {
+    bundled::ROMS.len()
+}
+
+/// Copy the name of a bundled ROM into the text buffer, returns its length in bytes
+#[no_mangle]
+pub extern "C" fn chip8_bundled_name(index: usize) -> usize {
+    SESSION.with(|session| {
+        let name = bundled::ROMS.get(index).map_or("", |rom| rom.name);
+        session.borrow_mut().text[..name.len()].copy_from_slice(name.as_bytes());
+        name.len()
+    })
+}
+
+/// Reset the machine and load a bundled ROM, like [`chip8_load`] does with the ROM buffer. Returns
+/// false if there is no such ROM.
+#[no_mangle]
+pub extern "C" fn chip8_load_bundled(index: usize) -> bool {
+    SESSION.with(|session| {
+        let Some(rom) = bundled::ROMS.get(index) else {
+            return false;
+        };
+        let path = Path::new(bundled::DIR).join(rom.name);
+        match Bundled.read(&path) {
+            Ok(program) => session.borrow_mut().load(PathBuf::from(rom.name), &program),
+            Err(_) => false,
+        }
+    })
+}
+
+/// The number of ticks run per second
+#[no_mangle]
+pub extern "C" fn chip8_speed() -> u32 {
+    SESSION.with(|session| session.borrow().ticks_per_sec)
+}
+
+/// Set the number of ticks run per second, [`TICKS_PER_SEC`] by default, and keep it in the
+/// settings of the ROM. Returns false if the settings could not be written.
+#[no_mangle]
+pub extern "C" fn chip8_set_speed(ticks_per_sec: u32) -> bool {
+    SESSION.with(|session| {
+        let session = &mut *session.borrow_mut();
+        session.ticks_per_sec = ticks_per_sec.max(1);
+        session.config.speed = Some(session.ticks_per_sec);
+        session.save_config()
+    })
+}
+
+/// The color of the pixels lit in the given planes, as `0xRRGGBB`: 0 for unlit pixels and 1 for
+/// lit ones, see [`crate::palette`]
+#[no_mangle]
+pub extern "C" fn chip8_color(planes: usize) -> u32 {
+    SESSION.with(|session| session.borrow().palette.color(planes & 3))
+}
+
+/// Save the state of the machine for the ROM, returns false if it could not be written
+#[no_mangle]
+pub extern "C" fn chip8_save_state() -> bool {
+    SESSION.with(|session| {
+        let session = session.borrow();
+        session
+            .chip8
+            .save_state()
+            .write(session.storage.as_ref(), &session.file("state"))
+            .is_ok()
+    })
+}
+
+/// Restore the state saved for the ROM, returns false if there is none or it is not valid
+#[no_mangle]
+pub extern "C" fn chip8_load_state() -> bool {
+    SESSION.with(|session| {
+        let session = &mut *session.borrow_mut();
+        SaveState::read(session.storage.as_ref(), &session.file("state"))
+            .map(|state| session.chip8.load_state(&state).is_ok())
+            .unwrap_or(false)
+    })
+}
+
+/// Press or release a keypad key
+#[no_mangle]
+pub extern "C" fn chip8_key(key: u8, pressed: bool) {
+    SESSION.with(|session| {
+        session
+            .borrow_mut()
+            .chip8
+            .set_keyboard_key(key & 0xF, pressed)
+    })
+}
+
+/// Press or release the keypad key bound to a button of the standard gamepad layout, by its
+/// index in [`STANDARD_BUTTONS`]
+#[no_mangle]
+pub extern "C" fn chip8_gamepad_button(button: usize, pressed: bool) {
+    SESSION.with(|session| {
+        if let Some(name) = STANDARD_BUTTONS.get(button) {
+            session.borrow_mut().gamepad_button(name, pressed);
+        }
+    })
+}
+
+/// Move the left stick, axis 0 horizontally and 1 vertically with positive values to the right
+/// and down. Past [`STICK_THRESHOLD`] it presses the D-pad, which is only pressed or released when
+/// the direction of the stick changes, so keys held otherwise are left alone.
+#[no_mangle]
+pub extern "C" fn chip8_gamepad_axis(axis: usize, value: f32) {
+    const DIRECTIONS: [(&str, &str); 2] = [("DPadLeft", "DPadRight"), ("DPadUp", "DPadDown")];
+    SESSION.with(|session| {
+        let session = &mut *session.borrow_mut();
+        let Some((negative, positive)) = DIRECTIONS.get(axis) else {
+            return;
+        };
+        let direction = match value {
+            value if value < -STICK_THRESHOLD => -1,
+            value if value > STICK_THRESHOLD => 1,
+            _ => 0,
+        };
+        let previous = std::mem::replace(&mut session.stick[axis], direction);
+        if direction == previous {
+            return;
+        }
+        match previous {
+            -1 => session.gamepad_button(negative, false),
+            1 => session.gamepad_button(positive, false),
+            _ => {}
+        }
+        match direction {
+            -1 => session.gamepad_button(negative, true),
+            1 => session.gamepad_button(positive, true),
+            _ => {}
+        }
+    })
+}
+
+/// Run the machine for the time since the previous frame of the page and update the screen. The
+/// timers follow the clock, so the page may call it at any rate. The user flags are stored when
+/// the program changes them.
+#[no_mangle]
+pub extern "C" fn chip8_frame() {
+    SESSION.with(|session| {
+        let session = &mut *session.borrow_mut();
+        session.chip8.poll_inputs();
+        session.pacer.run(&mut session.chip8, session.ticks_per_sec);
+        let rpl = session.chip8.rpl_flags();
+        if rpl != session.rpl && session.storage.write(&session.file("rpl"), &rpl).is_ok() {
+            session.rpl = rpl;
+        }
+        session.screen.clear();
+        session
+            .screen
+            .extend(session.chip8.framebuffer().iter().map(|lit| *lit as u8));
+    })
+}
+
+/// The screen as of the latest frame, row by row, one byte per pixel set to 1 if it is lit
+#[no_mangle]
+pub extern "C" fn chip8_screen() -> *const u8 {
+    SESSION.with(|session| session.borrow().screen.as_ptr())
+}
+
+/// Width of the screen in pixels
+#[no_mangle]
+pub extern "C" fn chip8_screen_width() -> usize {
+    SESSION.with(|session| session.borrow().chip8.screen_size().0)
+}
+
+/// Height of the screen in pixels
+#[no_mangle]
+pub extern "C" fn chip8_screen_height() -> usize {
+    SESSION.with(|session| session.borrow().chip8.screen_size().1)
+}
+
+/// True while the sound timer runs
+#[no_mangle]
+pub extern "C" fn chip8_sound() -> bool {
+    SESSION.with(|session| session.borrow().chip8.sound_active())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::chip8_load;
+    use super::chip8_load_state;
+    use super::chip8_save_state;
+    use super::chip8_set_speed;
+    use super::chip8_speed;
+    use super::SESSION;
+    use crate::TICKS_PER_SEC;
+
+    /// Copy a ROM and its name into the buffers, as the page does, and load it
+    fn load(name: &str, program: &[u8]) -> bool {
+        SESSION.with(|session| {
+            let session = &mut *session.borrow_mut();
+            session.rom[..program.len()].copy_from_slice(program);
+            session.text[..name.len()].copy_from_slice(name.as_bytes());
+        });
+        chip8_load(program.len(), name.len())
+    }
+
+    #[test]
+    fn settings_are_kept_per_rom() {
+        assert!(load("a.ch8", &[0x12, 0x00]));
+        assert!(chip8_set_speed(900));
+        assert!(load("b.ch8", &[0x12, 0x00]));
+        assert_eq!(chip8_speed(), TICKS_PER_SEC);
+        assert!(load("a.ch8", &[0x12, 0x00]));
+        assert_eq!(chip8_speed(), 900);
+    }
+
+    #[test]
+    fn states_are_kept_per_rom() {
+        assert!(load("a.ch8", &[0x12, 0x00]));
+        SESSION.with(|session| session.borrow_mut().chip8.v[0] = 7);
+        assert!(chip8_save_state());
+        assert!(load("b.ch8", &[0x12, 0x00]));
+        assert!(!chip8_load_state());
+        assert!(load("a.ch8", &[0x12, 0x00]));
+        assert_eq!(SESSION.with(|session| session.borrow().chip8.v[0]), 0);
+        assert!(chip8_load_state());
+        assert_eq!(SESSION.with(|session| session.borrow().chip8.v[0]), 7);
+    }
+}
@@ -0,0 +1,2436 @@
+//! Window frontend built on ggez. It runs the machine in the event loop, draws the screen and
+//! translates the keys of the host keyboard to the keypad. Errors are shown inside the window,
+//! where another ROM from the same directory can be opened. F5 pauses the machine, F6 then runs
+//! it one instruction at a time and F4 sets a breakpoint at the program counter. The backquote
+//! key drops down a console that accepts the commands of [`crate::console`], and F3 plots the
+//! update time, the render time and the instructions of recent frames. F2 saves the state of the
+//! machine next to the ROM and F1 restores it. Holding Backspace rewinds the recent frames. In the
+//! calibration mode the speed and the quirks are changed while the ROM runs and saved to the
+//! settings of the ROM, see [`crate::romconfig`]. The machine can also pause itself when nothing
+//! has been pressed for a while, to save the battery of a forgotten emulator. Gamepads are mapped
+//! to the keypad as well, the left stick doubling as the D-pad. Page Down and Page Up switch to
+//! the next and the previous ROM of a playlist.
+
+use crate::clock::Instant;
+use crate::console::Command;
+use crate::console::Console;
+use crate::console::Target;
+use crate::controls::Hotkey;
+use crate::controls::Hotkeys;
+use crate::datapaths::DataPaths;
+use crate::debugger::Debugger;
+use crate::disasm;
+use crate::i18n::Lang;
+use crate::i18n::Message;
+use crate::keymap::KeyMap;
+use crate::keymap::STICK_THRESHOLD;
+use crate::layout::Layout;
+use crate::library;
+use crate::palette;
+use crate::palette::Palette;
+use crate::phosphor::Phosphor;
+use crate::quirks::QUIRKS;
+#[cfg(feature = "recording")]
+use crate::recording::GifRecorder;
+#[cfg(feature = "recording")]
+use crate::recording::FRAMES_PER_SEC;
+use crate::rewind::Rewind;
+use crate::romconfig::RomConfig;
+use crate::savestate::SaveState;
+use crate::savestate::Thumbnail;
+use crate::savestate::THUMBNAIL_HEIGHT;
+use crate::savestate::THUMBNAIL_WIDTH;
+use crate::screenshot;
+use crate::sonify::Sonifier;
+use crate::sonify::REGION_COLUMNS;
+use crate::storage::FileStorage;
+use crate::storage::Storage;
+use crate::Chip8;
+use crate::CHIP8_SCREEN_HEIGHT;
+use crate::CHIP8_SCREEN_WIDTH;
+use crate::TICKS_PER_SEC;
+use ggez::audio;
+use ggez::audio::SoundSource;
+use ggez::event;
+use ggez::event::winit_event::ElementState;
+use ggez::event::winit_event::Event;
+use ggez::event::winit_event::KeyboardInput;
+use ggez::event::winit_event::MouseScrollDelta;
+use ggez::event::winit_event::WindowEvent;
+use ggez::event::Axis;
+use ggez::event::Button;
+use ggez::event::EventHandler;
+use ggez::event::EventsLoop;
+use ggez::event::GamepadId;
+use ggez::event::KeyCode;
+use ggez::event::KeyMods;
+use ggez::event::MouseButton;
+use ggez::graphics;
+use ggez::graphics::Rect;
+use ggez::input::gamepad::gilrs;
+use ggez::input::keyboard;
+use ggez::timer;
+use ggez::Context;
+use ggez::GameResult;
+use std::collections::VecDeque;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Size of each pixel when render to the host machine, halved in high resolution
+pub const PIXEL_SIZE: i32 = 16;
+/// Width of the window
+pub const WINDOW_WIDTH: f32 = CHIP8_SCREEN_WIDTH as f32 * PIXEL_SIZE as f32;
+/// Height of the window
+pub const WINDOW_HEIGHT: f32 = CHIP8_SCREEN_HEIGHT as f32 * PIXEL_SIZE as f32;
+
+/// Pitch of the tone played while the sound timer runs
+pub const TONE_FREQUENCY: u32 = 440;
+/// Pitch of the cues of the bottom regions when the drawing is turned into sound
+pub const CUE_FREQUENCY: u32 = 220;
+/// Number of frames plotted by the performance graphs
+pub const GRAPH_FRAMES: usize = 300;
+/// Change of the number of ticks per second in the calibration mode
+pub const CALIBRATION_STEP: u32 = 50;
+/// Number of save state slots, the first one is `ROM.state`
+pub const STATE_SLOTS: usize = 4;
+/// Size of a thumbnail pixel in the slot picker
+const THUMBNAIL_PIXEL: f32 = 6.0;
+/// Number of bytes on a row of the memory viewer
+const MEMORY_COLUMNS: usize = 16;
+/// Number of rows on the screen of the memory viewer at once
+const MEMORY_ROWS: usize = 24;
+/// Color of the bytes of the instruction at PC in the memory viewer
+const PC_COLOR: (f32, f32, f32, f32) = (1.0, 0.8, 0.0, 1.0);
+/// Color of the byte at I in the memory viewer
+const INDEX_COLOR: (f32, f32, f32, f32) = (0.3, 0.8, 1.0, 1.0);
+/// Color of the bytes of code that the program rewrote after running it in the memory viewer
+const MODIFIED_COLOR: (f32, f32, f32, f32) = (1.0, 0.4, 0.4, 1.0);
+/// Number of ROMs on the screen of the library at once
+const LIBRARY_ROWS: usize = 10;
+/// Height of a ROM in the library
+const LIBRARY_ROW_HEIGHT: f32 = 44.0;
+/// Top of the first ROM in the library, below the help
+const LIBRARY_TOP: f32 = 48.0;
+/// Size of a thumbnail pixel in the library
+const LIBRARY_PIXEL: f32 = 2.0;
+/// How long a compatibility note stays on the screen
+pub const NOTE_DURATION: Duration = Duration::from_secs(5);
+/// Extensions of the files offered on the error screen
+pub const ROM_EXTENSIONS: [&str; 3] = ["ch8", "c8", "sc8"];
+
+/// Creates a machine running the ROM at a path with the settings of the ROM, or describes why it
+/// could not be loaded
+pub type Loader = Box<dyn FnMut(&Path) -> Result<(Chip8, RomSettings), String>>;
+
+/// Settings of the window that depend on the ROM, see [`crate::romconfig`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RomSettings {
+    /// Number of ticks run per second
+    pub ticks_per_sec: u32,
+    /// Bindings that replace the default layout of the keyboard
+    pub keymap: KeyMap,
+    /// Bindings of gamepad buttons, on top of [`KeyMap::gamepad`]
+    pub gamepad: KeyMap,
+    /// Colors of the screen
+    pub palette: Palette,
+}
+
+impl Default for RomSettings {
+    fn default() -> Self {
+        Self {
+            ticks_per_sec: TICKS_PER_SEC,
+            keymap: KeyMap::new(),
+            gamepad: KeyMap::new(),
+            palette: Palette::default(),
+        }
+    }
+}
+
+/// Samples of the recent frames, plotted by the performance graphs
+struct PerfGraphs {
+    /// Time taken to run the machine, in milliseconds
+    update: VecDeque<f32>,
+    /// Time taken to draw the window, in milliseconds
+    render: VecDeque<f32>,
+    /// Instructions executed
+    instructions: VecDeque<f32>,
+    /// Instruction counter at the end of the previous frame
+    last_instructions: u64,
+}
+
+impl PerfGraphs {
+    fn new(instructions: u64) -> Self {
+        Self {
+            update: VecDeque::with_capacity(GRAPH_FRAMES),
+            render: VecDeque::with_capacity(GRAPH_FRAMES),
+            instructions: VecDeque::with_capacity(GRAPH_FRAMES),
+            last_instructions: instructions,
+        }
+    }
+
+    fn push(samples: &mut VecDeque<f32>, value: f32) {
+        if samples.len() == GRAPH_FRAMES {
+            samples.pop_front();
+        }
+        samples.push_back(value);
+    }
+
+    fn update(&mut self, elapsed: Duration, instructions: u64) {
+        Self::push(&mut self.update, elapsed.as_secs_f32() * 1000.0);
+        let executed = instructions.saturating_sub(self.last_instructions);
+        Self::push(&mut self.instructions, executed as f32);
+        self.last_instructions = instructions;
+    }
+
+    fn render(&mut self, elapsed: Duration) {
+        Self::push(&mut self.render, elapsed.as_secs_f32() * 1000.0);
+    }
+}
+
+/// The screen shown instead of the machine after an error
+struct ErrorScreen {
+    message: String,
+    /// ROMs that can be opened instead
+    roms: Vec<PathBuf>,
+    selected: usize,
+}
+
+/// The ROMs to choose from when the window is opened without one
+struct LibraryScreen {
+    /// Directory of the ROMs
+    dir: PathBuf,
+    entries: Vec<library::Entry>,
+    selected: usize,
+    /// The entry at the top of the screen
+    first: usize,
+}
+
+impl LibraryScreen {
+    /// Select an entry and scroll it into view
+    fn select(&mut self, idx: usize) {
+        self.selected = idx.min(self.entries.len().saturating_sub(1));
+        if self.selected < self.first {
+            self.first = self.selected;
+        } else if self.selected >= self.first + LIBRARY_ROWS {
+            self.first = self.selected + 1 - LIBRARY_ROWS;
+        }
+    }
+}
+
+/// The calibration mode, its settings are those of the running machine
+struct Calibration {
+    /// The ROM whose settings are saved
+    rom: PathBuf,
+    /// Index of the selected quirk in [`QUIRKS`]
+    selected: usize,
+}
+
+/// The save state slots shown by F1 and F2, the machine is paused while they are picked
+struct SlotPicker {
+    /// True if the chosen slot is overwritten, false if its state is restored
+    saving: bool,
+    selected: usize,
+    /// Whether each slot holds a state, and its thumbnail if the state has one
+    slots: Vec<Option<Option<Thumbnail>>>,
+}
+
+/// Runs a machine inside a ggez window
+pub struct Window {
+    chip8: Chip8,
+    /// Show the held keys and the frame counter on top of the screen
+    input_display: bool,
+    /// The first row of the memory viewer, hidden if it is `None`
+    memory_view: Option<usize>,
+    /// Place of the screen, the keypad and the statistics on a chroma key in the streamer mode
+    layout: Option<Layout>,
+    /// Redraw the screen on the next frame even if it has not changed
+    redraw: bool,
+    /// The lit pixels of each row of the screen, only the rows that changed are rebuilt
+    row_meshes: Vec<Option<graphics::Mesh>>,
+    /// Draw the screen with the scanlines, vignette and rounded corners of a CRT
+    crt: bool,
+    /// The CRT effect over the screen, built on the first frame that draws it
+    crt_overlay: Option<graphics::Mesh>,
+    /// Fades out the pixels that go off instead of clearing them, see [`crate::phosphor`]
+    phosphor: Option<Phosphor>,
+    /// The frame of the machine the phosphor was last advanced to
+    phosphor_frame: u64,
+    /// The tone, created on the first beep and left out if there is no audio device
+    tone: Option<Option<audio::Source>>,
+    /// Turns the drawing into cues, see [`crate::sonify`]
+    sonifier: Option<Sonifier>,
+    /// One source for each column of regions, created on the first cue like the tone
+    cue_sources: Option<Option<Vec<audio::SpatialSource>>>,
+    /// Language of the messages shown to the user
+    lang: Lang,
+    /// Opens the ROMs chosen on the error screen
+    loader: Option<Loader>,
+    /// Directory whose ROMs are offered on the error screen
+    rom_dir: PathBuf,
+    /// Where the settings, the save states and the captures of the ROMs are kept
+    data_paths: DataPaths,
+    /// Holds the files at those places
+    storage: Arc<dyn Storage>,
+    /// File of the save state
+    state_path: PathBuf,
+    error: Option<ErrorScreen>,
+    library: Option<LibraryScreen>,
+    debugger: Debugger,
+    console: Console,
+    console_open: bool,
+    /// Lines printed by the ROM through the host traps, shown in the console
+    debug_output: Option<Receiver<String>>,
+    /// Number of ticks run per second
+    ticks_per_sec: u32,
+    /// Colors of the lit and the unlit pixels
+    palette: Palette,
+    /// True if the tone is never played
+    muted: bool,
+    /// Performance graphs, only sampled while they are shown
+    perf: Option<PerfGraphs>,
+    /// Snapshots of the recent frames
+    rewind: Rewind,
+    /// True while the rewind key is held
+    rewinding: bool,
+    calibration: Option<Calibration>,
+    slot_picker: Option<SlotPicker>,
+    /// Pause the machine after this long without input
+    idle_timeout: Option<Duration>,
+    /// When a key was last pressed or released
+    last_input: Instant,
+    /// True while the machine is paused for being idle
+    idle: bool,
+    /// The last compatibility note and when it was made, shown for [`NOTE_DURATION`]
+    note: Option<(String, Instant)>,
+    /// Bindings that replace the default layout
+    keymap: KeyMap,
+    /// Bindings of gamepad buttons
+    gamepad: KeyMap,
+    /// Host keys of the hotkeys
+    hotkeys: Hotkeys,
+    /// ROMs switched with Page Up and Page Down
+    playlist: Vec<PathBuf>,
+    /// Position of the running ROM in the playlist
+    playing: usize,
+    /// The recording of the screen, its file and when it started
+    #[cfg(feature = "recording")]
+    recording: Option<(GifRecorder, PathBuf, Instant)>,
+}
+
+impl Window {
+    /// Wrap a machine, it is run by passing the window to [`run`]
+    pub fn new(chip8: Chip8) -> Self {
+        Self {
+            chip8,
+            input_display: false,
+            memory_view: None,
+            layout: None,
+            redraw: true,
+            row_meshes: Vec::new(),
+            crt: false,
+            crt_overlay: None,
+            phosphor: None,
+            phosphor_frame: 0,
+            tone: None,
+            sonifier: None,
+            cue_sources: None,
+            lang: Lang::default(),
+            loader: None,
+            rom_dir: PathBuf::from("."),
+            data_paths: DataPaths::default(),
+            storage: Arc::new(FileStorage),
+            state_path: PathBuf::from("chip8.state"),
+            error: None,
+            library: None,
+            debugger: Debugger::new(),
+            console: Console::new(),
+            debug_output: None,
+            console_open: false,
+            ticks_per_sec: TICKS_PER_SEC,
+            palette: Palette::default(),
+            muted: false,
+            perf: None,
+            rewind: Rewind::default(),
+            rewinding: false,
+            calibration: None,
+            slot_picker: None,
+            idle_timeout: None,
+            last_input: Instant::now(),
+            idle: false,
+            note: None,
+            keymap: KeyMap::new(),
+            gamepad: KeyMap::gamepad(),
+            hotkeys: Hotkeys::default(),
+            playlist: Vec::new(),
+            playing: 0,
+            #[cfg(feature = "recording")]
+            recording: None,
+        }
+    }
+
+    /// The machine run by the window
+    pub fn chip8(&self) -> &Chip8 {
+        &self.chip8
+    }
+
+    /// The machine run by the window
+    pub fn chip8_mut(&mut self) -> &mut Chip8 {
+        &mut self.chip8
+    }
+
+    /// The debugger that pauses the machine
+    pub fn debugger_mut(&mut self) -> &mut Debugger {
+        &mut self.debugger
+    }
+
+    /// Unwrap the machine once the window is closed
+    pub fn into_inner(self) -> Chip8 {
+        self.chip8
+    }
+
+    /// Show the held keys and the frame counter on top of the screen
+    pub fn set_input_display(&mut self, enabled: bool) {
+        self.input_display = enabled;
+    }
+
+    /// Show the memory in hex next to the screen, scrolled to the row of PC, with the bytes at
+    /// PC and I highlighted
+    pub fn set_memory_view(&mut self, enabled: bool) {
+        self.memory_view = None;
+        if enabled {
+            let row = self.chip8.pc() as usize / MEMORY_COLUMNS;
+            self.memory_view = Some(0);
+            self.scroll_memory(row as isize);
+        }
+        self.redraw = true;
+    }
+
+    /// Scroll the memory viewer by a number of rows, up if it is negative
+    fn scroll_memory(&mut self, rows: isize) {
+        let Some(first) = self.memory_view else {
+            return;
+        };
+        let last = self.chip8.memory().len() / MEMORY_COLUMNS - MEMORY_ROWS;
+        let first = (first as isize + rows).clamp(0, last as isize);
+        self.memory_view = Some(first as usize);
+        self.redraw = true;
+    }
+
+    /// Handle a key that scrolls the memory viewer, returns false if it is not one
+    fn memory_key(&mut self, keycode: KeyCode) -> bool {
+        let rows = match keycode {
+            KeyCode::Up => -1,
+            KeyCode::Down => 1,
+            KeyCode::PageUp => -(MEMORY_ROWS as isize),
+            KeyCode::PageDown => MEMORY_ROWS as isize,
+            _ => return false,
+        };
+        self.scroll_memory(rows);
+        true
+    }
+
+    /// Draw the screen with the scanlines, vignette and rounded corners of a CRT
+    pub fn set_crt(&mut self, enabled: bool) {
+        self.crt = enabled;
+        self.redraw = true;
+    }
+
+    /// Fit the screen, or the layout in the streamer mode, to a window of the given size, see
+    /// [`letterbox`]
+    pub fn fit_to_window(&mut self, ctx: &mut Context, width: f32, height: f32) -> GameResult {
+        let (area_width, area_height) = match &self.layout {
+            Some(layout) => (layout.width, layout.height),
+            None => (WINDOW_WIDTH, WINDOW_HEIGHT),
+        };
+        graphics::set_screen_coordinates(ctx, letterbox(width, height, area_width, area_height))?;
+        self.redraw = true;
+        Ok(())
+    }
+
+    /// Fade out the pixels that go off over the given number of frames against the flicker of
+    /// the sprites, zero disables it
+    pub fn set_phosphor(&mut self, frames: u8) {
+        self.phosphor = if frames > 0 {
+            Some(Phosphor::new(frames))
+        } else {
+            None
+        };
+        self.row_meshes.clear();
+        self.redraw = true;
+    }
+
+    /// Draw the screen, the keypad and the statistics at the places of a layout on its chroma
+    /// key, for capturing the window in a streaming program, see [`crate::layout`]. The window
+    /// is expected to be as large as the layout.
+    pub fn set_layout(&mut self, layout: Option<Layout>) {
+        self.layout = layout;
+        self.redraw = true;
+    }
+
+    /// Show the messages to the user in the given language
+    pub fn set_lang(&mut self, lang: Lang) {
+        self.lang = lang;
+    }
+
+    /// Plot the update time, the render time and the instructions of recent frames at the bottom
+    /// of the window
+    pub fn set_perf_graphs(&mut self, enabled: bool) {
+        self.perf = match self.perf.take() {
+            Some(perf) if enabled => Some(perf),
+            None if enabled => Some(PerfGraphs::new(self.chip8.stats().instructions)),
+            _ => None,
+        };
+        self.redraw = true;
+    }
+
+    /// Run the given number of ticks per second, [`TICKS_PER_SEC`] by default
+    pub fn set_ticks_per_sec(&mut self, ticks: u32) {
+        self.ticks_per_sec = ticks;
+    }
+
+    /// Set the colors of the screen
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+        self.row_meshes.clear();
+        self.redraw = true;
+    }
+
+    /// Show the lines received from the print traps in the console, see [`crate::trap`]
+    pub fn set_debug_output(&mut self, output: Receiver<String>) {
+        self.debug_output = Some(output);
+    }
+
+    /// Never play the tone
+    pub fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+    }
+
+    /// Play a short cue for the sprites drawn in each region of the screen, panned by the column
+    /// and pitched by the row of the region, see [`crate::sonify`]. This is experimental.
+    pub fn set_sonify(&mut self, sonify: bool) {
+        self.sonifier = if sonify { Some(Sonifier::new()) } else { None };
+        self.chip8
+            .set_draw_hook(self.sonifier.as_ref().map(Sonifier::hook));
+    }
+
+    /// Pause the machine and stop drawing after the given time without input, until a key is
+    /// pressed
+    pub fn set_idle_timeout(&mut self, timeout: Option<Duration>) {
+        self.idle_timeout = timeout;
+    }
+
+    /// Bind host keys to keypad keys, on top of the default layout of [`keypad_index`]
+    pub fn set_keymap(&mut self, keymap: KeyMap) {
+        self.keymap = keymap;
+    }
+
+    /// Trigger the hotkeys with other host keys
+    pub fn set_hotkeys(&mut self, hotkeys: Hotkeys) {
+        self.hotkeys = hotkeys;
+    }
+
+    /// Bind gamepad buttons to keypad keys, on top of [`KeyMap::gamepad`]
+    pub fn set_gamepad_map(&mut self, map: KeyMap) {
+        self.gamepad = KeyMap::gamepad();
+        self.gamepad.extend(&map);
+    }
+
+    /// The stick presses one direction of the D-pad at a time, up is positive
+    fn gamepad_axis(&mut self, axis: Axis, value: f32) {
+        let (negative, positive) = match axis {
+            Axis::LeftStickX => (Button::DPadLeft, Button::DPadRight),
+            Axis::LeftStickY => (Button::DPadDown, Button::DPadUp),
+            _ => return,
+        };
+        self.gamepad_button(negative, value < -STICK_THRESHOLD);
+        self.gamepad_button(positive, value > STICK_THRESHOLD);
+    }
+
+    /// Press or release the keypad key bound to a gamepad button
+    fn gamepad_button(&mut self, button: Button, pressed: bool) {
+        if pressed && self.wake() {
+            return;
+        }
+        self.last_input = Instant::now();
+        if self.error.is_some() || self.console_open {
+            return;
+        }
+        if let Some(key) = self.gamepad.get(&format!("{:?}", button)) {
+            self.chip8.set_keyboard_key(key, pressed);
+        }
+    }
+
+    /// The keypad key that a host key is bound to
+    fn keypad_key(&self, keycode: KeyCode) -> Option<u8> {
+        self.keymap
+            .get(&format!("{:?}", keycode))
+            .or_else(|| keypad_index(keycode))
+    }
+
+    /// The hotkey triggered by a host key
+    fn hotkey(&self, keycode: KeyCode) -> Option<Hotkey> {
+        self.hotkeys.action(&format!("{:?}", keycode))
+    }
+
+    /// Keep the given number of frames for rewinding, zero disables it
+    pub fn set_rewind_frames(&mut self, frames: usize) {
+        self.rewind = Rewind::new(frames);
+    }
+
+    /// File of a save state slot, `ROM.state` for the first one and `ROM-N.state` for the others
+    fn slot_path(&self, slot: usize) -> PathBuf {
+        if slot == 0 {
+            return self.state_path.clone();
+        }
+        let stem = self.state_path.file_stem().unwrap_or_default();
+        let name = format!("{}-{}.state", stem.to_string_lossy(), slot + 1);
+        self.state_path.with_file_name(name)
+    }
+
+    /// Show the save state slots, reading the thumbnails of their states
+    fn open_slot_picker(&mut self, saving: bool) {
+        let slots = (0..STATE_SLOTS)
+            .map(|slot| {
+                SaveState::read(self.storage.as_ref(), &self.slot_path(slot))
+                    .ok()
+                    .map(|state| state.thumbnail)
+            })
+            .collect();
+        self.slot_picker = Some(SlotPicker {
+            saving,
+            selected: 0,
+            slots,
+        });
+        self.redraw = true;
+    }
+
+    /// Handle a key while the slot picker is shown
+    fn slot_picker_key(&mut self, keycode: KeyCode) {
+        let hotkey = self.hotkey(keycode);
+        let Some(picker) = self.slot_picker.as_mut() else {
+            return;
+        };
+        match keycode {
+            KeyCode::Left => picker.selected = picker.selected.saturating_sub(1),
+            KeyCode::Right => picker.selected = (picker.selected + 1).min(STATE_SLOTS - 1),
+            _ if matches!(hotkey, Some(Hotkey::LoadState | Hotkey::SaveState)) => {
+                self.slot_picker = None
+            }
+            KeyCode::Escape => self.slot_picker = None,
+            KeyCode::Return => {
+                let (saving, slot) = (picker.saving, picker.selected);
+                self.slot_picker = None;
+                let path = self.slot_path(slot);
+                let display = path.display().to_string();
+                let result = if saving {
+                    self.chip8.save_state().write(self.storage.as_ref(), &path)
+                } else {
+                    SaveState::read(self.storage.as_ref(), &path)
+                        .and_then(|state| self.chip8.load_state(&state))
+                };
+                match result {
+                    Ok(()) if saving => {
+                        println!("{}", Message::SavedState(display).text(self.lang))
+                    }
+                    Ok(()) => println!("{}", Message::LoadedState(display).text(self.lang)),
+                    Err(err) => {
+                        eprintln!("{}", Message::StateFailed(err.to_string()).text(self.lang))
+                    }
+                }
+            }
+            _ => return,
+        }
+        self.redraw = true;
+    }
+
+    /// Draw the save state slots side by side in the middle of the window
+    fn draw_slot_picker(&self, ctx: &mut Context, picker: &SlotPicker) -> GameResult {
+        const GAP: f32 = 16.0;
+        let (slot_width, slot_height) = (
+            THUMBNAIL_WIDTH as f32 * THUMBNAIL_PIXEL,
+            THUMBNAIL_HEIGHT as f32 * THUMBNAIL_PIXEL,
+        );
+        let help = graphics::Text::new(
+            Message::SlotPickerHelp {
+                saving: picker.saving,
+            }
+            .text(self.lang),
+        );
+        let line = help.height(ctx) as f32;
+        let width = STATE_SLOTS as f32 * (slot_width + GAP) + GAP;
+        let height = slot_height + line * 2.0 + GAP * 4.0;
+        let (left, top) = ((WINDOW_WIDTH - width) / 2.0, (WINDOW_HEIGHT - height) / 2.0);
+        let background = graphics::Color::from_rgb_u32(self.palette.color(0));
+        let lit = graphics::Color::from_rgb_u32(self.palette.color(1));
+
+        let mut mesh = graphics::MeshBuilder::new();
+        mesh.rectangle(
+            graphics::DrawMode::fill(),
+            Rect::new(left, top, width, height),
+            (0.0, 0.0, 0.0, 0.75).into(),
+        );
+        let slot_top = top + line + GAP * 2.0;
+        for (slot, thumbnail) in picker.slots.iter().enumerate() {
+            let slot_left = left + GAP + slot as f32 * (slot_width + GAP);
+            if slot == picker.selected {
+                mesh.rectangle(
+                    graphics::DrawMode::stroke(3.0),
+                    Rect::new(
+                        slot_left - 4.0,
+                        slot_top - 4.0,
+                        slot_width + 8.0,
+                        slot_height + 8.0,
+                    ),
+                    graphics::WHITE,
+                );
+            }
+            mesh.rectangle(
+                graphics::DrawMode::fill(),
+                Rect::new(slot_left, slot_top, slot_width, slot_height),
+                background,
+            );
+            let Some(Some(thumbnail)) = thumbnail else {
+                continue;
+            };
+            for y in 0..thumbnail.height {
+                for x in 0..thumbnail.width {
+                    if thumbnail.lit(x, y) {
+                        mesh.rectangle(
+                            graphics::DrawMode::fill(),
+                            Rect::new(
+                                slot_left + x as f32 * THUMBNAIL_PIXEL,
+                                slot_top + y as f32 * THUMBNAIL_PIXEL,
+                                THUMBNAIL_PIXEL,
+                                THUMBNAIL_PIXEL,
+                            ),
+                            lit,
+                        );
+                    }
+                }
+            }
+        }
+        let mesh = mesh.build(ctx)?;
+        graphics::draw(ctx, &mesh, graphics::DrawParam::default())?;
+
+        graphics::draw(
+            ctx,
+            &help,
+            (
+                ggez::mint::Point2 {
+                    x: left + GAP,
+                    y: top + GAP,
+                },
+                graphics::WHITE,
+            ),
+        )?;
+        for (slot, state) in picker.slots.iter().enumerate() {
+            let label = match state {
+                Some(_) => format!("{}", slot + 1),
+                None => format!("{} ({})", slot + 1, Message::EmptySlot.text(self.lang)),
+            };
+            let point = ggez::mint::Point2 {
+                x: left + GAP + slot as f32 * (slot_width + GAP),
+                y: slot_top + slot_height + GAP,
+            };
+            graphics::draw(ctx, &graphics::Text::new(label), (point, graphics::WHITE))?;
+        }
+        Ok(())
+    }
+
+    /// The first free `NAME-N.EXTENSION` next to the ROM, for screenshots, recordings and
+    /// exported ROMs
+    fn capture_path(&self, extension: &str) -> PathBuf {
+        let stem = self.state_path.file_stem().unwrap_or_default();
+        let stem = stem.to_string_lossy();
+        (1..)
+            .map(|n| {
+                let name = format!("{}-{}.{}", stem, n, extension);
+                self.state_path.with_file_name(name)
+            })
+            .find(|path| !self.storage.exists(path))
+            .unwrap()
+    }
+
+    /// Start recording the screen into the first free `NAME-N.gif` next to the ROM, or write
+    /// the running recording
+    #[cfg(feature = "recording")]
+    fn toggle_recording(&mut self) {
+        let message = match self.recording.take() {
+            Some((recorder, path, _)) => match recorder.finish() {
+                Ok(()) => Message::SavedRecording(path.display().to_string()),
+                Err(err) => Message::RecordingFailed(err.to_string()),
+            },
+            None => {
+                let path = self.capture_path("gif");
+                match GifRecorder::create(self.storage.clone(), &path, &self.palette) {
+                    Ok(recorder) => {
+                        let message = Message::StartedRecording(path.display().to_string());
+                        self.recording = Some((recorder, path, Instant::now()));
+                        message
+                    }
+                    Err(err) => Message::RecordingFailed(err.to_string()),
+                }
+            }
+        };
+        println!("{}", message.text(self.lang));
+    }
+
+    /// Capture the frames of the recording that are due since it started
+    #[cfg(feature = "recording")]
+    fn capture_recording(&mut self) {
+        let Some((recorder, _, start)) = self.recording.as_mut() else {
+            return;
+        };
+        let due = (start.elapsed().as_secs_f64() * FRAMES_PER_SEC as f64) as u64;
+        recorder.set_input_display(self.input_display);
+        while recorder.frames() < due {
+            if let Err(err) = recorder.capture(&self.chip8) {
+                self.recording = None;
+                eprintln!(
+                    "{}",
+                    Message::RecordingFailed(err.to_string()).text(self.lang)
+                );
+                return;
+            }
+        }
+    }
+
+    /// Keep the files of the ROMs in other places, to be set before [`Window::set_loader`]
+    pub fn set_data_paths(&mut self, paths: DataPaths) {
+        self.data_paths = paths;
+    }
+
+    /// Keep the files of the ROMs in another storage than the file system, see
+    /// [`crate::storage`]
+    pub fn set_storage(&mut self, storage: Arc<dyn Storage>) {
+        self.storage = storage;
+    }
+
+    /// Allow opening another ROM from the error screen, the ROMs next to `rom` are offered. The
+    /// save state of the ROM is kept in the place given by [`Window::set_data_paths`].
+    pub fn set_loader(&mut self, rom: &Path, loader: Loader) {
+        self.state_path = self.data_paths.state(rom);
+        self.rom_dir = match rom.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        self.loader = Some(loader);
+    }
+
+    /// Replace the machine with one running a ROM and apply the settings of the ROM. Shows the
+    /// error screen and returns false if it cannot be loaded.
+    pub fn load_rom(&mut self, rom: &Path) -> bool {
+        let Some(loader) = self.loader.as_mut() else {
+            return false;
+        };
+        match loader(rom) {
+            Ok((chip8, settings)) => {
+                if let (true, Some(file)) = (rom.is_file(), self.data_paths.recent_file()) {
+                    crate::shell::remember(self.storage.as_ref(), &file, rom);
+                }
+                self.chip8 = chip8;
+                self.chip8
+                    .set_draw_hook(self.sonifier.as_ref().map(Sonifier::hook));
+                self.ticks_per_sec = settings.ticks_per_sec;
+                self.set_keymap(settings.keymap);
+                self.set_gamepad_map(settings.gamepad);
+                self.set_palette(settings.palette);
+                self.state_path = self.data_paths.state(rom);
+                if let Some(calibration) = self.calibration.as_mut() {
+                    calibration.rom = rom.to_path_buf();
+                }
+                self.rewind.clear();
+                self.error = None;
+                self.redraw = true;
+                true
+            }
+            Err(message) => {
+                self.show_error(message);
+                false
+            }
+        }
+    }
+
+    /// Switch between the given ROMs with Page Down and Page Up, the first one is expected to be
+    /// running
+    pub fn set_playlist(&mut self, roms: Vec<PathBuf>) {
+        self.playlist = roms;
+        self.playing = 0;
+    }
+
+    /// Load the ROM that is `offset` entries away in the playlist, wrapping around at both ends
+    fn skip(&mut self, offset: isize) {
+        let count = self.playlist.len();
+        if count < 2 {
+            return;
+        }
+        self.playing = (self.playing as isize + offset).rem_euclid(count as isize) as usize;
+        let rom = self.playlist[self.playing].clone();
+        let message = Message::NowPlaying {
+            position: self.playing + 1,
+            count,
+            rom: rom.display().to_string(),
+        };
+        println!("{}", message.text(self.lang));
+        self.load_rom(&rom);
+    }
+
+    /// Note some input, waking the machine if it is idle. Returns true if it was idle.
+    fn wake(&mut self) -> bool {
+        self.last_input = Instant::now();
+        let idle = std::mem::take(&mut self.idle);
+        self.redraw |= idle;
+        idle
+    }
+
+    /// Draw the idle note in the middle of the window
+    fn draw_idle(&self, ctx: &mut Context) -> GameResult {
+        let text = graphics::Text::new(Message::Idle.text(self.lang));
+        let (width, height) = text.dimensions(ctx);
+        let (x, y) = (
+            (WINDOW_WIDTH - width as f32) / 2.0,
+            (WINDOW_HEIGHT - height as f32) / 2.0,
+        );
+        let panel = graphics::Mesh::new_rectangle(
+            ctx,
+            graphics::DrawMode::fill(),
+            Rect::new(x - 8.0, y - 8.0, width as f32 + 16.0, height as f32 + 16.0),
+            (0.0, 0.0, 0.0, 0.75).into(),
+        )?;
+        graphics::draw(ctx, &panel, graphics::DrawParam::default())?;
+        graphics::draw(ctx, &text, (ggez::mint::Point2 { x, y }, graphics::WHITE))
+    }
+
+    /// The CRT effect over a screen that fills the window, it is drawn with the same parameters
+    /// as the screen so that it follows it in the streamer mode
+    fn build_crt_overlay(ctx: &mut Context) -> GameResult<graphics::Mesh> {
+        const PITCH: f32 = 4.0;
+        const SCANLINE: f32 = 1.5;
+        const VIGNETTE: f32 = 96.0;
+        const RADIUS: f32 = 48.0;
+        const ARC_POINTS: usize = 12;
+        let (width, height) = (WINDOW_WIDTH, WINDOW_HEIGHT);
+        let mut builder = graphics::MeshBuilder::new();
+        // A dark band between the lines of the beam
+        let mut y = PITCH - SCANLINE;
+        while y < height {
+            builder.rectangle(
+                graphics::DrawMode::fill(),
+                Rect::new(0.0, y, width, SCANLINE),
+                (0.0, 0.0, 0.0, 0.3).into(),
+            );
+            y += PITCH;
+        }
+        // The edges darken from a clear rectangle inside the screen to its border
+        let vertex = |pos, alpha| graphics::Vertex {
+            pos,
+            uv: [0.0, 0.0],
+            color: [0.0, 0.0, 0.0, alpha],
+        };
+        let (left, top) = (VIGNETTE, VIGNETTE);
+        let (right, bottom) = (width - VIGNETTE, height - VIGNETTE);
+        let vertices = [
+            vertex([0.0, 0.0], 0.6),
+            vertex([width, 0.0], 0.6),
+            vertex([width, height], 0.6),
+            vertex([0.0, height], 0.6),
+            vertex([left, top], 0.0),
+            vertex([right, top], 0.0),
+            vertex([right, bottom], 0.0),
+            vertex([left, bottom], 0.0),
+        ];
+        let mut indices = Vec::new();
+        for side in 0..4 {
+            let next = (side + 1) % 4;
+            indices.extend_from_slice(&[side, next, next + 4, side, next + 4, side + 4]);
+        }
+        builder.raw(&vertices, &indices, None);
+        // The corners are cut round like the curved glass of a tube
+        let corners = [
+            (0.0, 0.0, 1.0, 1.0),
+            (width, 0.0, -1.0, 1.0),
+            (width, height, -1.0, -1.0),
+            (0.0, height, 1.0, -1.0),
+        ];
+        for (x, y, inward_x, inward_y) in corners.iter() {
+            let (center_x, center_y) = (x + inward_x * RADIUS, y + inward_y * RADIUS);
+            let mut points = vec![ggez::mint::Point2 { x: *x, y: *y }];
+            for step in 0..=ARC_POINTS {
+                let angle = step as f32 / ARC_POINTS as f32 * std::f32::consts::FRAC_PI_2;
+                points.push(ggez::mint::Point2 {
+                    x: center_x - inward_x * RADIUS * angle.cos(),
+                    y: center_y - inward_y * RADIUS * angle.sin(),
+                });
+            }
+            builder.polygon(graphics::DrawMode::fill(), &points, graphics::BLACK)?;
+        }
+        builder.build(ctx)
+    }
+
+    /// Log the warnings and the compatibility notes made for the first time, and show the last
+    /// note on the screen
+    fn show_diagnostics(&mut self) {
+        for warning in self.chip8.take_warnings() {
+            eprintln!("{}", warning);
+        }
+        for diagnostic in self.chip8.take_new_diagnostics() {
+            let hint = diagnostic.hint(&self.chip8.quirks());
+            let text = Message::CompatibilityNote(hint).text(self.lang);
+            eprintln!("{}", text);
+            self.note = Some((text, Instant::now()));
+            self.redraw = true;
+        }
+        if let Some((_, at)) = &self.note {
+            if at.elapsed() >= NOTE_DURATION {
+                self.note = None;
+                self.redraw = true;
+            }
+        }
+    }
+
+    /// Draw the compatibility note at the bottom of the window
+    fn draw_note(&self, ctx: &mut Context, note: &str) -> GameResult {
+        let mut text = graphics::Text::new(note);
+        text.set_bounds(
+            ggez::mint::Point2 {
+                x: WINDOW_WIDTH - 32.0,
+                y: f32::INFINITY,
+            },
+            graphics::Align::Left,
+        );
+        let (width, height) = text.dimensions(ctx);
+        let (x, y) = (16.0, WINDOW_HEIGHT - height as f32 - 16.0);
+        let panel = graphics::Mesh::new_rectangle(
+            ctx,
+            graphics::DrawMode::fill(),
+            Rect::new(x - 8.0, y - 8.0, width as f32 + 16.0, height as f32 + 16.0),
+            (0.0, 0.0, 0.0, 0.75).into(),
+        )?;
+        graphics::draw(ctx, &panel, graphics::DrawParam::default())?;
+        graphics::draw(ctx, &text, (ggez::mint::Point2 { x, y }, graphics::WHITE))
+    }
+
+    /// Let the user change the speed and the quirks while the ROM runs and save them to the
+    /// settings of the ROM
+    pub fn calibrate(&mut self, rom: &Path) {
+        self.calibration = Some(Calibration {
+            rom: rom.to_path_buf(),
+            selected: 0,
+        });
+        self.redraw = true;
+    }
+
+    /// Stop the machine and show an error, the user can then open another ROM or quit
+    pub fn show_error(&mut self, message: String) {
+        let mut roms: Vec<_> = fs::read_dir(&self.rom_dir)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.extension()
+                    .and_then(|ext| ext.to_str())
+                    .is_some_and(|ext| ROM_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+            })
+            .collect();
+        roms.sort();
+        if self.loader.is_none() {
+            roms.clear();
+        }
+        self.error = Some(ErrorScreen {
+            message,
+            roms,
+            selected: 0,
+        });
+        self.redraw = true;
+    }
+
+    /// Show the ROMs of a directory to choose one from instead of running the machine, see
+    /// [`crate::library`]. The chosen ROM is opened with the loader.
+    pub fn show_library(&mut self, dir: &Path, entries: Vec<library::Entry>) {
+        self.library = Some(LibraryScreen {
+            dir: dir.to_path_buf(),
+            entries,
+            selected: 0,
+            first: 0,
+        });
+        self.redraw = true;
+    }
+
+    /// Leave the library and open one of its ROMs
+    fn open_library_entry(&mut self, idx: usize) {
+        let Some(library) = self.library.as_ref() else {
+            return;
+        };
+        let Some(rom) = library.entries.get(idx).map(|entry| entry.path.clone()) else {
+            return;
+        };
+        self.library = None;
+        if let Some(dir) = rom.parent() {
+            self.rom_dir = dir.to_path_buf();
+        }
+        self.load_rom(&rom);
+        self.redraw = true;
+    }
+
+    /// Handle a key while the library is shown
+    fn library_key(&mut self, ctx: &mut Context, keycode: KeyCode) {
+        let Some(library) = self.library.as_mut() else {
+            return;
+        };
+        let selected = library.selected;
+        match keycode {
+            KeyCode::Up => library.select(selected.saturating_sub(1)),
+            KeyCode::Down => library.select(selected + 1),
+            KeyCode::PageUp => library.select(selected.saturating_sub(LIBRARY_ROWS)),
+            KeyCode::PageDown => library.select(selected + LIBRARY_ROWS),
+            KeyCode::Home => library.select(0),
+            KeyCode::End => library.select(usize::MAX),
+            KeyCode::Return => return self.open_library_entry(selected),
+            KeyCode::Escape => event::quit(ctx),
+            _ => return,
+        }
+        self.redraw = true;
+    }
+
+    /// Draw the library, a row per ROM with the thumbnail of its first frame, its title and size
+    fn draw_library(&self, ctx: &mut Context, library: &LibraryScreen) -> GameResult {
+        const MARGIN: f32 = 16.0;
+        let background = graphics::Color::from_rgb_u32(self.palette.color(0));
+        let lit = graphics::Color::from_rgb_u32(self.palette.color(1));
+        graphics::clear(ctx, background);
+        let help = if library.entries.is_empty() {
+            Message::LibraryEmpty(library.dir.display().to_string())
+        } else {
+            Message::LibraryHelp
+        };
+        let help = graphics::Text::new(help.text(self.lang));
+        graphics::draw(
+            ctx,
+            &help,
+            (
+                ggez::mint::Point2 {
+                    x: MARGIN,
+                    y: MARGIN,
+                },
+                lit,
+            ),
+        )?;
+        if library.entries.is_empty() {
+            return Ok(());
+        }
+
+        let (thumbnail_width, thumbnail_height) = (
+            THUMBNAIL_WIDTH as f32 * LIBRARY_PIXEL,
+            THUMBNAIL_HEIGHT as f32 * LIBRARY_PIXEL,
+        );
+        let rows: Vec<_> = library
+            .entries
+            .iter()
+            .enumerate()
+            .skip(library.first)
+            .take(LIBRARY_ROWS)
+            .enumerate()
+            .map(|(row, (idx, entry))| (LIBRARY_TOP + row as f32 * LIBRARY_ROW_HEIGHT, idx, entry))
+            .collect();
+        let mut mesh = graphics::MeshBuilder::new();
+        for (top, idx, entry) in &rows {
+            if *idx == library.selected {
+                mesh.rectangle(
+                    graphics::DrawMode::fill(),
+                    Rect::new(
+                        MARGIN / 2.0,
+                        *top,
+                        WINDOW_WIDTH - MARGIN,
+                        LIBRARY_ROW_HEIGHT,
+                    ),
+                    graphics::Color { a: 0.25, ..lit },
+                );
+            }
+            let left = MARGIN;
+            let thumbnail_top = top + (LIBRARY_ROW_HEIGHT - thumbnail_height) / 2.0;
+            mesh.rectangle(
+                graphics::DrawMode::stroke(1.0),
+                Rect::new(left, thumbnail_top, thumbnail_width, thumbnail_height),
+                lit,
+            );
+            let Some(thumbnail) = &entry.thumbnail else {
+                continue;
+            };
+            for y in 0..thumbnail.height {
+                for x in (0..thumbnail.width).filter(|x| thumbnail.lit(*x, y)) {
+                    mesh.rectangle(
+                        graphics::DrawMode::fill(),
+                        Rect::new(
+                            left + x as f32 * LIBRARY_PIXEL,
+                            thumbnail_top + y as f32 * LIBRARY_PIXEL,
+                            LIBRARY_PIXEL,
+                            LIBRARY_PIXEL,
+                        ),
+                        lit,
+                    );
+                }
+            }
+        }
+        let mesh = mesh.build(ctx)?;
+        graphics::draw(ctx, &mesh, graphics::DrawParam::default())?;
+        for (top, _, entry) in rows {
+            let text = graphics::Text::new(
+                Message::LibraryEntry {
+                    title: entry.title.clone(),
+                    size: entry.size,
+                }
+                .text(self.lang),
+            );
+            let y = top + (LIBRARY_ROW_HEIGHT - text.height(ctx) as f32) / 2.0;
+            let x = MARGIN * 2.0 + thumbnail_width;
+            graphics::draw(ctx, &text, (ggez::mint::Point2 { x, y }, lit))?;
+        }
+        Ok(())
+    }
+
+    /// Handle a key while the error screen is shown
+    fn error_key(&mut self, ctx: &mut Context, keycode: KeyCode) {
+        let Some(error) = self.error.as_mut() else {
+            return;
+        };
+        match keycode {
+            KeyCode::Up => error.selected = error.selected.saturating_sub(1),
+            KeyCode::Down => {
+                error.selected = (error.selected + 1).min(error.roms.len().saturating_sub(1))
+            }
+            KeyCode::Return => {
+                let Some(rom) = error.roms.get(error.selected).cloned() else {
+                    return;
+                };
+                self.load_rom(&rom);
+            }
+            KeyCode::PageDown => self.skip(1),
+            KeyCode::PageUp => self.skip(-1),
+            KeyCode::Escape => event::quit(ctx),
+            _ => return,
+        }
+        self.redraw = true;
+    }
+
+    /// Draw the error with the list of ROMs that can be opened instead
+    fn draw_error(&self, ctx: &mut Context, error: &ErrorScreen) -> GameResult {
+        const MARGIN: f32 = 16.0;
+        const LINE: f32 = 20.0;
+        graphics::clear(ctx, [0.25, 0.0, 0.0, 1.0].into());
+        let mut y = MARGIN;
+        let mut lines = vec![(error.message.clone(), graphics::WHITE)];
+        if error.roms.is_empty() {
+            lines.push((Message::ErrorQuit.text(self.lang), graphics::WHITE));
+        } else {
+            lines.push((Message::ErrorChooseRom.text(self.lang), graphics::WHITE));
+            for (idx, rom) in error.roms.iter().enumerate() {
+                let name = rom.file_name().unwrap_or_default().to_string_lossy();
+                let line = if idx == error.selected {
+                    (format!("> {}", name), (1.0, 0.8, 0.0, 1.0).into())
+                } else {
+                    (format!("  {}", name), graphics::WHITE)
+                };
+                lines.push(line);
+            }
+        }
+        for (text, color) in lines {
+            let mut text = graphics::Text::new(text);
+            text.set_bounds(
+                ggez::mint::Point2 {
+                    x: WINDOW_WIDTH - MARGIN * 2.0,
+                    y: f32::INFINITY,
+                },
+                graphics::Align::Left,
+            );
+            let height = text.height(ctx) as f32;
+            graphics::draw(ctx, &text, (ggez::mint::Point2 { x: MARGIN, y }, color))?;
+            y += height.max(LINE) + 4.0;
+        }
+        Ok(())
+    }
+
+    /// Handle a key of the calibration mode, returns false if it is not one
+    fn calibration_key(&mut self, keycode: KeyCode) -> bool {
+        let Some(calibration) = self.calibration.as_mut() else {
+            return false;
+        };
+        match keycode {
+            KeyCode::Up => self.ticks_per_sec += CALIBRATION_STEP,
+            KeyCode::Down => {
+                self.ticks_per_sec = self
+                    .ticks_per_sec
+                    .saturating_sub(CALIBRATION_STEP)
+                    .max(CALIBRATION_STEP)
+            }
+            KeyCode::Left => calibration.selected = calibration.selected.saturating_sub(1),
+            KeyCode::Right => {
+                calibration.selected = (calibration.selected + 1).min(QUIRKS.len() - 1)
+            }
+            KeyCode::Space => {
+                let info = &QUIRKS[calibration.selected];
+                let mut quirks = self.chip8.quirks();
+                let on = (info.get)(&quirks);
+                (info.set)(&mut quirks, !on);
+                self.chip8.set_quirks(quirks);
+            }
+            KeyCode::Return => {
+                // The other settings of the ROM are kept
+                let paths = &self.data_paths;
+                let path = RomConfig::path(paths, &calibration.rom)
+                    .display()
+                    .to_string();
+                let (speed, quirks) = (self.ticks_per_sec, self.chip8.quirks());
+                let storage = self.storage.as_ref();
+                let saved =
+                    RomConfig::load(storage, paths, &calibration.rom).and_then(|mut config| {
+                        config.speed = Some(speed);
+                        config.quirks = Some(quirks);
+                        config.save(storage, paths, &calibration.rom)
+                    });
+                match saved {
+                    Ok(()) => println!("{}", Message::SavedConfig(path).text(self.lang)),
+                    Err(err) => {
+                        eprintln!("{}", Message::ConfigFailed(err.to_string()).text(self.lang))
+                    }
+                }
+            }
+            _ => return false,
+        }
+        self.redraw = true;
+        true
+    }
+
+    /// Draw the speed and the quirks of the calibration mode in the top-left corner of the window
+    fn draw_calibration(&self, ctx: &mut Context, calibration: &Calibration) -> GameResult {
+        let quirks = self.chip8.quirks();
+        let mut lines = vec![
+            Message::CalibrationHelp.text(self.lang),
+            format!("speed: {} ticks per second", self.ticks_per_sec),
+        ];
+        for (idx, info) in QUIRKS.iter().enumerate() {
+            let selected = if idx == calibration.selected {
+                '>'
+            } else {
+                ' '
+            };
+            let on = if (info.get)(&quirks) { "on" } else { "off" };
+            lines.push(format!("{} {:<16} {}", selected, info.name, on));
+        }
+
+        let text = graphics::Text::new(lines.join("\n"));
+        let (width, height) = text.dimensions(ctx);
+        let panel = graphics::Mesh::new_rectangle(
+            ctx,
+            graphics::DrawMode::fill(),
+            Rect::new(4.0, 4.0, width as f32 + 8.0, height as f32 + 8.0),
+            (0.0, 0.0, 0.0, 0.75).into(),
+        )?;
+        graphics::draw(ctx, &panel, graphics::DrawParam::default())?;
+        graphics::draw(
+            ctx,
+            &text,
+            (ggez::mint::Point2 { x: 8.0, y: 8.0 }, graphics::WHITE),
+        )
+    }
+
+    /// Handle a key while the console is open
+    fn console_key(&mut self, keycode: KeyCode) {
+        match keycode {
+            _ if self.hotkey(keycode) == Some(Hotkey::Console) => self.console_open = false,
+            KeyCode::Escape => self.console_open = false,
+            KeyCode::Back => self.console.backspace(),
+            KeyCode::Up => self.console.history_prev(),
+            KeyCode::Down => self.console.history_next(),
+            KeyCode::Tab => {
+                let mut symbols: Vec<_> = ["PC", "I"].iter().map(|s| s.to_string()).collect();
+                symbols.extend((0..16).map(|x| format!("V{:X}", x)));
+                let lines = disasm::disassemble(self.chip8.export_rom(), 0x200);
+                symbols.extend(disasm::labels(&lines).into_iter().map(disasm::label));
+                self.console.complete(&symbols);
+            }
+            KeyCode::Return => match self.console.submit() {
+                Some(Ok(command)) => self.run_command(command),
+                Some(Err(err)) => self.console.print(err),
+                None => (),
+            },
+            _ => return,
+        }
+        self.redraw = true;
+    }
+
+    /// Execute a command of the console
+    fn run_command(&mut self, command: Command) {
+        match command {
+            Command::Break(addr) => {
+                let state = if self.debugger.toggle_breakpoint(addr) {
+                    "set"
+                } else {
+                    "removed"
+                };
+                self.console
+                    .print(format!("Breakpoint {} at {:#05X}", state, addr));
+            }
+            Command::Continue => self.debugger.resume(&self.chip8),
+            Command::Step => {
+                self.debugger.pause();
+                self.debugger.step_instruction(&mut self.chip8);
+                let next = self.debugger.listing(&self.chip8, 1);
+                self.console.print(next.join(""));
+            }
+            Command::Poke(target, value) => {
+                let byte = value as u8;
+                match target {
+                    Target::Memory(addr) if value > 0xFF => {
+                        return self
+                            .console
+                            .print(format!("{:#X} does not fit at {:#05X}", value, addr))
+                    }
+                    Target::Memory(addr) => match self.chip8.memory_mut().get_mut(addr as usize) {
+                        Some(mem) => *mem = byte,
+                        None => return self.console.print(format!("{:#X} is out of memory", addr)),
+                    },
+                    Target::Register(x) if x < 16 && value <= 0xFF => {
+                        self.chip8.registers_mut()[x] = byte
+                    }
+                    Target::Register(x) => {
+                        return self
+                            .console
+                            .print(format!("{:#X} does not fit in V{:X}", value, x))
+                    }
+                    Target::Index => self.chip8.set_index(value),
+                    Target::Pc => self.chip8.set_pc(value),
+                }
+                self.console.print("Done");
+            }
+            Command::Speed(Some(ticks)) => self.ticks_per_sec = ticks,
+            Command::Speed(None) => self
+                .console
+                .print(format!("{} ticks per second", self.ticks_per_sec)),
+            Command::Palette(on, off) => {
+                self.palette.set_color(1, on);
+                self.palette.set_color(0, off);
+                self.redraw = true;
+            }
+            Command::Theme(name) => match Palette::theme(&name) {
+                Some(palette) => self.set_palette(palette),
+                None => self.console.print(format!(
+                    "unknown palette {}, try one of {}",
+                    name,
+                    palette::THEMES
+                        .iter()
+                        .map(|(theme, _)| *theme)
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                )),
+            },
+            Command::Save(path) => match self
+                .storage
+                .write(Path::new(&path), self.chip8.export_rom())
+            {
+                Ok(()) => self.console.print(format!("Saved to {}", path)),
+                Err(err) => self
+                    .console
+                    .print(format!("Could not save {}: {}", path, err)),
+            },
+            Command::Help => {
+                let help = crate::console::COMMANDS.join("  ");
+                self.console.print(help);
+            }
+        }
+    }
+
+    /// Draw the console over the top half of the window
+    fn draw_console(&self, ctx: &mut Context) -> GameResult {
+        const LINE: f32 = 18.0;
+        let height = WINDOW_HEIGHT / 2.0;
+        let panel = graphics::Mesh::new_rectangle(
+            ctx,
+            graphics::DrawMode::fill(),
+            Rect::new(0.0, 0.0, WINDOW_WIDTH, height),
+            (0.0, 0.0, 0.1, 0.9).into(),
+        )?;
+        graphics::draw(ctx, &panel, graphics::DrawParam::default())?;
+        let rows = (height / LINE) as usize - 1;
+        let output = self.console.output();
+        let mut lines: Vec<_> = output[output.len().saturating_sub(rows)..].to_vec();
+        lines.push(format!("> {}_", self.console.input()));
+        let top = height - LINE * lines.len() as f32 - 4.0;
+        for (idx, line) in lines.iter().enumerate() {
+            let text = graphics::Text::new(line.as_str());
+            let dest = ggez::mint::Point2 {
+                x: 8.0,
+                y: top + idx as f32 * LINE,
+            };
+            graphics::draw(ctx, &text, (dest, graphics::WHITE))?;
+        }
+        Ok(())
+    }
+
+    /// Draw the performance graphs as sparklines in the bottom-left corner of the window
+    fn draw_perf_graphs(&self, ctx: &mut Context, perf: &PerfGraphs) -> GameResult {
+        const HEIGHT: f32 = 32.0;
+        const GAP: f32 = 6.0;
+        let graphs = [
+            ("update", "ms", &perf.update, (0.3, 0.8, 1.0, 1.0)),
+            ("render", "ms", &perf.render, (1.0, 0.6, 0.2, 1.0)),
+            ("instructions", "", &perf.instructions, (0.4, 1.0, 0.4, 1.0)),
+        ];
+        let top = WINDOW_HEIGHT - graphs.len() as f32 * (HEIGHT + GAP) - GAP;
+        let mut builder = graphics::MeshBuilder::new();
+        builder.rectangle(
+            graphics::DrawMode::fill(),
+            Rect::new(4.0, top - GAP, 560.0, WINDOW_HEIGHT - top + GAP - 4.0),
+            (0.0, 0.0, 0.0, 0.75).into(),
+        );
+        let mut labels = Vec::new();
+        for (idx, (name, unit, samples, color)) in graphs.iter().enumerate() {
+            let bottom = top + idx as f32 * (HEIGHT + GAP) + HEIGHT;
+            let max = samples.iter().copied().fold(0.0, f32::max);
+            // Scale every graph to its own maximum, so that spikes stand out
+            let scale = if max > 0.0 { HEIGHT / max } else { 0.0 };
+            let points: Vec<_> = samples
+                .iter()
+                .enumerate()
+                .map(|(x, value)| ggez::mint::Point2 {
+                    x: 8.0 + x as f32,
+                    y: bottom - value * scale,
+                })
+                .collect();
+            if points.len() >= 2 {
+                builder.line(&points, 1.0, (*color).into())?;
+            }
+            let last = samples.back().copied().unwrap_or_default();
+            let label = format!("{} {:.2}{} (max {:.2})", name, last, unit, max);
+            labels.push((label, bottom - HEIGHT / 2.0 - 8.0));
+        }
+        let mesh = builder.build(ctx)?;
+        graphics::draw(ctx, &mesh, graphics::DrawParam::default())?;
+        for (label, y) in labels {
+            let text = graphics::Text::new(label);
+            let dest = ggez::mint::Point2 {
+                x: 16.0 + GRAPH_FRAMES as f32,
+                y,
+            };
+            graphics::draw(ctx, &text, (dest, graphics::WHITE))?;
+        }
+        Ok(())
+    }
+
+    /// Draw the registers and the next instructions in the top-left corner of the window
+    fn draw_debugger(&self, ctx: &mut Context) -> GameResult {
+        const LISTING: usize = 8;
+        let mut lines = vec![
+            Message::DebuggerHelp.text(self.lang),
+            format!(
+                "PC: {:#05X}  I: {:#05X}",
+                self.chip8.pc(),
+                self.chip8.index()
+            ),
+        ];
+        for (row, regs) in self.chip8.registers().chunks(8).enumerate() {
+            let regs: Vec<_> = regs
+                .iter()
+                .enumerate()
+                .map(|(col, v)| format!("V{:X}: {:02X}", row * 8 + col, v))
+                .collect();
+            lines.push(regs.join(" "));
+        }
+        lines.extend(self.debugger.listing(&self.chip8, LISTING));
+
+        let text = graphics::Text::new(lines.join("\n"));
+        let (width, height) = text.dimensions(ctx);
+        let panel = graphics::Mesh::new_rectangle(
+            ctx,
+            graphics::DrawMode::fill(),
+            Rect::new(4.0, 4.0, width as f32 + 8.0, height as f32 + 8.0),
+            (0.0, 0.0, 0.0, 0.75).into(),
+        )?;
+        graphics::draw(ctx, &panel, graphics::DrawParam::default())?;
+        graphics::draw(
+            ctx,
+            &text,
+            (ggez::mint::Point2 { x: 8.0, y: 8.0 }, graphics::WHITE),
+        )
+    }
+
+    /// Draw the memory viewer on the right of the window, a row of bytes per line after its
+    /// address
+    fn draw_memory(&self, ctx: &mut Context, first: usize) -> GameResult {
+        const MARGIN: f32 = 8.0;
+        let memory = self.chip8.memory();
+        let pc = self.chip8.pc() as usize % memory.len();
+        let pc_bytes = [pc, (pc + 1) % memory.len()];
+        let index = self.chip8.index() as usize % memory.len();
+        let mut text = graphics::Text::new(format!("{}\n", Message::MemoryHelp.text(self.lang)));
+        text.add(graphics::TextFragment::new("PC").color(PC_COLOR.into()));
+        text.add("  ");
+        text.add(graphics::TextFragment::new("I").color(INDEX_COLOR.into()));
+        text.add("  ");
+        text.add(graphics::TextFragment::new("~").color(MODIFIED_COLOR.into()));
+        let modifications = self.chip8.code_modifications();
+        for row in first..first + MEMORY_ROWS {
+            let start = row * MEMORY_COLUMNS;
+            text.add(format!("\n{:03X}:", start));
+            for (addr, byte) in memory.iter().enumerate().skip(start).take(MEMORY_COLUMNS) {
+                let fragment = graphics::TextFragment::new(format!(" {:02X}", byte));
+                text.add(if pc_bytes.contains(&addr) {
+                    fragment.color(PC_COLOR.into())
+                } else if addr == index {
+                    fragment.color(INDEX_COLOR.into())
+                } else if modifications.contains_key(&(addr as u16)) {
+                    fragment.color(MODIFIED_COLOR.into())
+                } else {
+                    fragment
+                });
+            }
+        }
+        let (width, height) = text.dimensions(ctx);
+        let (left, top) = (WINDOW_WIDTH - width as f32 - MARGIN * 2.0, MARGIN);
+        let panel = graphics::Mesh::new_rectangle(
+            ctx,
+            graphics::DrawMode::fill(),
+            Rect::new(
+                left - MARGIN,
+                top - MARGIN / 2.0,
+                width as f32 + MARGIN * 2.0,
+                height as f32 + MARGIN,
+            ),
+            (0.0, 0.0, 0.0, 0.75).into(),
+        )?;
+        graphics::draw(ctx, &panel, graphics::DrawParam::default())?;
+        graphics::draw(
+            ctx,
+            &text,
+            (ggez::mint::Point2 { x: left, y: top }, graphics::WHITE),
+        )
+    }
+
+    /// Play the tone while the sound timer runs
+    fn update_tone(&mut self, ctx: &mut Context) -> GameResult {
+        let active = self.chip8.sound_active() && !self.muted;
+        if active && self.tone.is_none() {
+            let data = audio::SoundData::from_bytes(&square_wave(TONE_FREQUENCY));
+            self.tone = Some(match audio::Source::from_data(ctx, data) {
+                Ok(mut source) => {
+                    source.set_repeat(true);
+                    Some(source)
+                }
+                Err(err) => {
+                    eprintln!("{}", Message::ToneFailed(err.to_string()).text(self.lang));
+                    None
+                }
+            });
+        }
+        if let Some(Some(tone)) = self.tone.as_mut() {
+            if active && !tone.playing() {
+                tone.play()?;
+            } else if !active && tone.playing() {
+                tone.stop();
+            }
+        }
+        Ok(())
+    }
+
+    /// Play the cues of the regions drawn in, a column stays silent until its previous cue has
+    /// finished
+    fn play_cues(&mut self, ctx: &mut Context) -> GameResult {
+        let Some(sonifier) = self.sonifier.as_ref() else {
+            return Ok(());
+        };
+        let cues = sonifier.cues();
+        if cues.is_empty() {
+            return Ok(());
+        }
+        if self.cue_sources.is_none() {
+            let data = audio::SoundData::from_bytes(&square_wave(CUE_FREQUENCY));
+            let sources: GameResult<Vec<_>> = (0..REGION_COLUMNS)
+                .map(|_| audio::SpatialSource::from_data(ctx, data.clone()))
+                .collect();
+            self.cue_sources = Some(match sources {
+                Ok(sources) => Some(sources),
+                Err(err) => {
+                    eprintln!("{}", Message::ToneFailed(err.to_string()).text(self.lang));
+                    None
+                }
+            });
+        }
+        if let Some(Some(sources)) = self.cue_sources.as_mut() {
+            for cue in cues {
+                let source = &mut sources[cue.column];
+                if source.playing() {
+                    continue;
+                }
+                source.set_position([cue.pan, 1.0, 0.0]);
+                source.set_pitch(cue.pitch);
+                source.play()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Draw the keypad with the held keys highlighted and the frame counter in the top-right
+    /// corner of the window
+    fn draw_input_display(&self, ctx: &mut Context) -> GameResult {
+        const CELL: f32 = 18.0;
+        let left = WINDOW_WIDTH - 8.0 - CELL * 4.0;
+        let frames = format!("{:>7}", self.chip8.stats().frames);
+        self.draw_keypad(ctx, left, 8.0, CELL, Some(&frames))
+    }
+
+    /// Draw the keypad with its top left key at a place, keys are `cell` wide, with a line of
+    /// text below it
+    fn draw_keypad(
+        &self,
+        ctx: &mut Context,
+        left: f32,
+        top: f32,
+        cell: f32,
+        footer: Option<&str>,
+    ) -> GameResult {
+        // Keys in the order they appear on the original keypad
+        const LAYOUT: [usize; 16] = [
+            0x1, 0x2, 0x3, 0xC, 0x4, 0x5, 0x6, 0xD, 0x7, 0x8, 0x9, 0xE, 0xA, 0x0, 0xB, 0xF,
+        ];
+        // The labels fit keys of 18 pixels at the default size of the text
+        let zoom = cell / 18.0;
+        let rows = if footer.is_some() { 5.0 } else { 4.0 };
+        let keys = self.chip8.keys();
+
+        let mut builder = graphics::MeshBuilder::new();
+        builder.rectangle(
+            graphics::DrawMode::fill(),
+            Rect::new(left - 4.0, top - 4.0, cell * 4.0 + 8.0, cell * rows + 8.0),
+            (0.0, 0.0, 0.0, 0.75).into(),
+        );
+        for (idx, key) in LAYOUT.iter().enumerate() {
+            let cell = Rect::new(
+                left + (idx % 4) as f32 * cell + 1.0,
+                top + (idx / 4) as f32 * cell + 1.0,
+                cell - 2.0,
+                cell - 2.0,
+            );
+            let (mode, color) = if keys[*key] {
+                (graphics::DrawMode::fill(), (1.0, 0.8, 0.0, 1.0))
+            } else {
+                (graphics::DrawMode::stroke(1.0), (0.5, 0.5, 0.5, 1.0))
+            };
+            builder.rectangle(mode, cell, color.into());
+        }
+        let mesh = builder.build(ctx)?;
+        graphics::draw(ctx, &mesh, graphics::DrawParam::default())?;
+
+        let scale = graphics::Scale::uniform(graphics::DEFAULT_FONT_SCALE * zoom);
+        for (idx, key) in LAYOUT.iter().enumerate() {
+            let label =
+                graphics::Text::new(graphics::TextFragment::new(format!("{:X}", key)).scale(scale));
+            let dest = ggez::mint::Point2 {
+                x: left + (idx % 4) as f32 * cell + 5.0 * zoom,
+                y: top + (idx / 4) as f32 * cell + 2.0 * zoom,
+            };
+            let color = if keys[*key] {
+                graphics::BLACK
+            } else {
+                graphics::WHITE
+            };
+            graphics::draw(ctx, &label, (dest, color))?;
+        }
+        if let Some(footer) = footer {
+            let text = graphics::Text::new(graphics::TextFragment::new(footer).scale(scale));
+            let dest = ggez::mint::Point2 {
+                x: left,
+                y: top + cell * 4.0 + 4.0,
+            };
+            graphics::draw(ctx, &text, (dest, graphics::WHITE))?;
+        }
+        Ok(())
+    }
+
+    /// Draw the counters of the session on a panel at a place, `size` is the height of the text
+    fn draw_stats_panel(&self, ctx: &mut Context, left: f32, top: f32, size: f32) -> GameResult {
+        let stats = self.chip8.stats();
+        let lines = format!(
+            "frames {:>9}\ninstructions {:>9}\ndraws {:>9}",
+            stats.frames, stats.instructions, stats.draws
+        );
+        let text = graphics::Text::new(
+            graphics::TextFragment::new(lines).scale(graphics::Scale::uniform(size)),
+        );
+        let (width, height) = text.dimensions(ctx);
+        let panel = graphics::Mesh::new_rectangle(
+            ctx,
+            graphics::DrawMode::fill(),
+            Rect::new(left, top, width as f32 + 16.0, height as f32 + 16.0),
+            (0.0, 0.0, 0.0, 0.75).into(),
+        )?;
+        graphics::draw(ctx, &panel, graphics::DrawParam::default())?;
+        let dest = ggez::mint::Point2 {
+            x: left + 8.0,
+            y: top + 8.0,
+        };
+        graphics::draw(ctx, &text, (dest, graphics::WHITE))
+    }
+}
+
+impl EventHandler for Window {
+    fn mouse_button_down_event(&mut self, ctx: &mut Context, button: MouseButton, x: f32, y: f32) {
+        let Some(library) = &self.library else {
+            return;
+        };
+        if button != MouseButton::Left {
+            return;
+        }
+        // A click on a ROM of the library opens it
+        let (_, y) = screen_point(ctx, x, y);
+        let row = ((y - LIBRARY_TOP) / LIBRARY_ROW_HEIGHT).floor();
+        if (0.0..LIBRARY_ROWS as f32).contains(&row) {
+            let idx = library.first + row as usize;
+            if idx < library.entries.len() {
+                self.open_library_entry(idx);
+            }
+        }
+    }
+
+    fn mouse_wheel_event(&mut self, _ctx: &mut Context, _x: f32, y: f32) {
+        // Three rows of the memory viewer per notch
+        if self.library.is_none() && self.memory_view.is_some() {
+            self.scroll_memory(-(y * 3.0) as isize);
+            return;
+        }
+        let Some(library) = self.library.as_mut() else {
+            return;
+        };
+        if y > 0.0 {
+            library.select(library.selected.saturating_sub(1));
+        } else if y < 0.0 {
+            library.select(library.selected + 1);
+        }
+        self.redraw = true;
+    }
+
+    fn resize_event(&mut self, ctx: &mut Context, width: f32, height: f32) {
+        if let Err(err) = self.fit_to_window(ctx, width, height) {
+            self.show_error(err.to_string());
+        }
+    }
+
+    fn update(&mut self, ctx: &mut Context) -> GameResult<()> {
+        if let Some(output) = &self.debug_output {
+            for line in output.try_iter() {
+                self.console.print(line);
+                self.redraw = true;
+            }
+        }
+        if self.error.is_some() || self.library.is_some() || self.slot_picker.is_some() {
+            while timer::check_update_time(ctx, self.ticks_per_sec) {}
+            self.chip8.pause_timers();
+            if let Some(Some(tone)) = self.tone.as_mut() {
+                tone.stop();
+            }
+            return Ok(());
+        }
+        let start = Instant::now();
+        if self.rewinding {
+            // One frame back per frame, the ticks and the timer periods that were due are dropped
+            while timer::check_update_time(ctx, self.ticks_per_sec) {}
+            self.chip8.pause_timers();
+            if self.rewind.step_back(&mut self.chip8) {
+                self.redraw = true;
+            }
+            return self.update_tone(ctx);
+        }
+        let keys = *self.chip8.keys();
+        self.chip8.poll_inputs();
+        if *self.chip8.keys() != keys {
+            self.wake();
+        }
+        if !self.idle {
+            let timeout = self.idle_timeout;
+            if timeout.is_some_and(|timeout| self.last_input.elapsed() >= timeout) {
+                self.idle = true;
+                self.redraw = true;
+            }
+        }
+        if self.idle {
+            // Nothing runs and nothing is drawn, so the loop can sleep between frames
+            while timer::check_update_time(ctx, self.ticks_per_sec) {}
+            self.chip8.pause_timers();
+            if let Some(Some(tone)) = self.tone.as_mut() {
+                tone.stop();
+            }
+            timer::sleep(Duration::from_millis(50));
+            return Ok(());
+        }
+        let paused = self.debugger.paused();
+        let mut ran = false;
+        // The timers follow the clock, whatever the number of ticks that run
+        if paused {
+            self.chip8.pause_timers();
+        } else {
+            self.chip8.run_timers();
+        }
+        while timer::check_update_time(ctx, self.ticks_per_sec) {
+            ran |= self.debugger.tick(&mut self.chip8);
+        }
+        self.show_diagnostics();
+        if ran {
+            self.rewind.push(&self.chip8);
+        }
+        #[cfg(feature = "recording")]
+        self.capture_recording();
+        if let Some(perf) = self.perf.as_mut() {
+            perf.update(start.elapsed(), self.chip8.stats().instructions);
+        }
+        // Show the debugger as soon as a breakpoint is hit
+        self.redraw |= self.debugger.paused() != paused;
+        // A halted machine opened paused, such as a replayed bundle, is left to the debugger
+        if let Some(fault) = self.chip8.fault().filter(|_| !self.debugger.paused()) {
+            let text = Message::Halted(fault.to_string()).text(self.lang);
+            eprintln!("{}\n{}", text, self.chip8.dump());
+            self.show_error(text);
+        }
+        self.play_cues(ctx)?;
+        self.update_tone(ctx)
+    }
+
+    fn draw(&mut self, ctx: &mut ggez::Context) -> ggez::GameResult {
+        if let Some(error) = &self.error {
+            if std::mem::take(&mut self.redraw) {
+                self.draw_error(ctx, error)?;
+                graphics::present(ctx)?;
+            }
+            timer::yield_now();
+            return Ok(());
+        }
+        if let Some(library) = &self.library {
+            if std::mem::take(&mut self.redraw) {
+                self.draw_library(ctx, library)?;
+                graphics::present(ctx)?;
+            }
+            timer::yield_now();
+            return Ok(());
+        }
+        // The overlays change every frame, so the screen has to be redrawn as well
+        let start = Instant::now();
+        let updated = self.chip8.take_framebuffer_updated();
+        let overlays = !self.idle
+            && (self.input_display
+                || self.memory_view.is_some()
+                || self.perf.is_some()
+                || self.layout.is_some());
+        // The fading pixels change on every frame of the machine even if the screen does not
+        let faded = match self.phosphor.as_mut() {
+            Some(phosphor) => {
+                let frame = self.chip8.stats().frames;
+                let elapsed = frame.saturating_sub(self.phosphor_frame);
+                self.phosphor_frame = frame;
+                phosphor.update(self.chip8.framebuffer(), elapsed)
+            }
+            None => false,
+        };
+        if updated || self.redraw || overlays || faded {
+            self.redraw = false;
+            let background = graphics::Color::from_rgb_u32(self.palette.color(0));
+            let lit = graphics::Color::from_rgb_u32(self.palette.color(1));
+            // In the streamer mode the screen is drawn on the chroma key at the place of the layout
+            let screen = match &self.layout {
+                Some(layout) => {
+                    graphics::clear(ctx, graphics::Color::from_rgb_u32(layout.background.0));
+                    let place = layout.screen;
+                    let bounds = Rect::new(
+                        place.x,
+                        place.y,
+                        CHIP8_SCREEN_WIDTH as f32 * place.size,
+                        CHIP8_SCREEN_HEIGHT as f32 * place.size,
+                    );
+                    let unlit = graphics::Mesh::new_rectangle(
+                        ctx,
+                        graphics::DrawMode::fill(),
+                        bounds,
+                        background,
+                    )?;
+                    graphics::draw(ctx, &unlit, graphics::DrawParam::default())?;
+                    let zoom = place.size / PIXEL_SIZE as f32;
+                    graphics::DrawParam::default()
+                        .dest([place.x, place.y])
+                        .scale([zoom, zoom])
+                }
+                None => {
+                    graphics::clear(ctx, background);
+                    graphics::DrawParam::default()
+                }
+            };
+            let mut dirty = self.chip8.take_dirty_rows();
+            let gfx = self.chip8.framebuffer();
+            let (width, height) = self.chip8.screen_size();
+            if self.row_meshes.len() != height || faded {
+                self.row_meshes = (0..height).map(|_| None).collect();
+                dirty = u64::MAX;
+            }
+            // The high resolution screen is drawn with smaller pixels in the same window
+            let size = PIXEL_SIZE * CHIP8_SCREEN_WIDTH as i32 / width as i32;
+            for (y, mesh) in self.row_meshes.iter_mut().enumerate() {
+                if dirty & (1 << y) == 0 {
+                    continue;
+                }
+                // A mesh cannot be empty, rows without lit pixels have none
+                let mut builder = graphics::MeshBuilder::new();
+                let mut any_lit = false;
+                for x in 0..width {
+                    let brightness = match &self.phosphor {
+                        Some(phosphor) => phosphor.brightness(x + y * width),
+                        None if gfx[x + y * width] => 1.0,
+                        None => continue,
+                    };
+                    if brightness <= 0.0 {
+                        continue;
+                    }
+                    // A fading pixel is drawn between the lit and the unlit color
+                    let color = graphics::Color::new(
+                        background.r + (lit.r - background.r) * brightness,
+                        background.g + (lit.g - background.g) * brightness,
+                        background.b + (lit.b - background.b) * brightness,
+                        1.0,
+                    );
+                    builder.rectangle(
+                        graphics::DrawMode::fill(),
+                        Rect::new_i32(x as i32 * size, y as i32 * size, size, size),
+                        color,
+                    );
+                    any_lit = true;
+                }
+                *mesh = if any_lit {
+                    Some(builder.build(ctx)?)
+                } else {
+                    None
+                };
+            }
+            for mesh in self.row_meshes.iter().flatten() {
+                graphics::draw(ctx, mesh, screen)?;
+            }
+            if self.crt {
+                if self.crt_overlay.is_none() {
+                    self.crt_overlay = Some(Self::build_crt_overlay(ctx)?);
+                }
+                if let Some(overlay) = &self.crt_overlay {
+                    graphics::draw(ctx, overlay, screen)?;
+                }
+            }
+            if let Some(layout) = self.layout {
+                if layout.show_keypad {
+                    let place = layout.keypad;
+                    self.draw_keypad(ctx, place.x, place.y, place.size, None)?;
+                }
+                if layout.show_stats {
+                    let place = layout.stats;
+                    self.draw_stats_panel(ctx, place.x, place.y, place.size)?;
+                }
+            }
+            if self.input_display {
+                self.draw_input_display(ctx)?;
+            }
+            if self.debugger.paused() {
+                self.draw_debugger(ctx)?;
+            } else if let Some(calibration) = &self.calibration {
+                self.draw_calibration(ctx, calibration)?;
+            }
+            if let Some(first) = self.memory_view {
+                self.draw_memory(ctx, first)?;
+            }
+            if let Some(perf) = &self.perf {
+                self.draw_perf_graphs(ctx, perf)?;
+            }
+            if let Some((note, _)) = &self.note {
+                self.draw_note(ctx, note)?;
+            }
+            if let Some(picker) = &self.slot_picker {
+                self.draw_slot_picker(ctx, picker)?;
+            }
+            if self.console_open {
+                self.draw_console(ctx)?;
+            }
+            if self.idle {
+                self.draw_idle(ctx)?;
+            }
+            graphics::present(ctx)?;
+        }
+        if let Some(perf) = self.perf.as_mut() {
+            perf.render(start.elapsed());
+        }
+        timer::yield_now();
+        Ok(())
+    }
+
+    fn key_down_event(
+        &mut self,
+        ctx: &mut ggez::Context,
+        keycode: KeyCode,
+        keymods: KeyMods,
+        repeat: bool,
+    ) {
+        if self.error.is_some() {
+            self.error_key(ctx, keycode);
+            return;
+        }
+        if self.library.is_some() {
+            self.library_key(ctx, keycode);
+            return;
+        }
+        // The key that wakes the machine is not passed on
+        if self.wake() {
+            return;
+        }
+        if self.console_open {
+            self.console_key(keycode);
+            return;
+        }
+        if self.slot_picker.is_some() {
+            self.slot_picker_key(keycode);
+            return;
+        }
+        // Holding Shift scrolls the memory viewer with the arrows and the page keys
+        if self.memory_view.is_some()
+            && keymods.contains(KeyMods::SHIFT)
+            && self.memory_key(keycode)
+        {
+            return;
+        }
+        let hotkey = self.hotkey(keycode);
+        if hotkey == Some(Hotkey::Console) {
+            self.console_open = true;
+            self.redraw = true;
+            return;
+        }
+        if self.calibration_key(keycode) {
+            return;
+        }
+        // Holding Ctrl while pressing a keypad key toggles its turbo-fire
+        if keymods.contains(KeyMods::CTRL) {
+            if let Some(key) = self.keypad_key(keycode) {
+                if !repeat {
+                    let enabled = self.chip8.toggle_turbo(key);
+                    let message = Message::TurboFire { key, on: enabled };
+                    println!("{}", message.text(self.lang));
+                }
+                return;
+            }
+        }
+        match hotkey {
+            Some(hotkey @ (Hotkey::LoadState | Hotkey::SaveState)) => {
+                self.open_slot_picker(hotkey == Hotkey::SaveState);
+                return;
+            }
+            Some(Hotkey::PerfGraphs) => {
+                self.set_perf_graphs(self.perf.is_none());
+                return;
+            }
+            Some(Hotkey::Breakpoint) => {
+                self.debugger.toggle_breakpoint(self.chip8.pc());
+                self.redraw = true;
+                return;
+            }
+            Some(Hotkey::Pause) => {
+                self.debugger.toggle_pause(&self.chip8);
+                self.redraw = true;
+                return;
+            }
+            Some(Hotkey::Step) => {
+                if self.debugger.paused() {
+                    self.debugger.step_instruction(&mut self.chip8);
+                    self.redraw = true;
+                }
+                return;
+            }
+            Some(Hotkey::Rewind) => {
+                self.rewinding = true;
+                return;
+            }
+            Some(Hotkey::NextRom) => {
+                self.skip(1);
+                return;
+            }
+            Some(Hotkey::PreviousRom) => {
+                self.skip(-1);
+                return;
+            }
+            Some(Hotkey::InputDisplay) => {
+                self.input_display = !self.input_display;
+                self.redraw = true;
+                return;
+            }
+            Some(Hotkey::Crt) => {
+                self.set_crt(!self.crt);
+                return;
+            }
+            Some(Hotkey::Memory) => {
+                self.set_memory_view(self.memory_view.is_none());
+                return;
+            }
+            Some(Hotkey::ExportRom) => {
+                let path = self.capture_path("ch8");
+                match self.storage.write(&path, self.chip8.export_rom()) {
+                    Ok(()) => {
+                        let message = Message::Exported(path.display().to_string());
+                        println!("{}", message.text(self.lang))
+                    }
+                    Err(err) => {
+                        let message = Message::ExportFailed(err.to_string());
+                        eprintln!("{}", message.text(self.lang))
+                    }
+                }
+                return;
+            }
+            Some(Hotkey::Dump) => {
+                print!("{}", self.chip8.dump());
+                return;
+            }
+            Some(Hotkey::Reset) => {
+                self.chip8.reload();
+                self.rewind.clear();
+                self.redraw = true;
+                println!("{}", Message::Reloaded.text(self.lang));
+                return;
+            }
+            Some(Hotkey::Theme) => {
+                self.set_palette(self.palette.next_theme());
+                let name = self.palette.theme_name().unwrap_or_default();
+                println!("{}", Message::Theme(name.to_string()).text(self.lang));
+                return;
+            }
+            Some(Hotkey::Stats) => {
+                print!("{}", self.chip8.stats());
+                return;
+            }
+            #[cfg(feature = "recording")]
+            Some(Hotkey::Recording) => {
+                self.toggle_recording();
+                return;
+            }
+            Some(Hotkey::Screenshot) => {
+                let (width, height) = graphics::drawable_size(ctx);
+                let size = (width as usize, height as usize);
+                let path = self.capture_path("png");
+                let storage = self.storage.as_ref();
+                match screenshot::save_screen(storage, &self.chip8, &self.palette, size, &path) {
+                    Ok(()) => {
+                        let message = Message::SavedScreenshot(path.display().to_string());
+                        println!("{}", message.text(self.lang))
+                    }
+                    Err(err) => {
+                        let message = Message::ScreenshotFailed(err.to_string());
+                        eprintln!("{}", message.text(self.lang))
+                    }
+                }
+                return;
+            }
+            _ => (),
+        }
+        if let Some(key) = self.keypad_key(keycode) {
+            self.chip8.set_keyboard_key(key, true);
+        }
+    }
+
+    fn text_input_event(&mut self, _ctx: &mut ggez::Context, character: char) {
+        // The backquote opens and closes the console
+        if self.console_open && character != '`' {
+            self.console.push(character);
+            self.redraw = true;
+        }
+    }
+
+    fn gamepad_button_down_event(&mut self, _ctx: &mut Context, button: Button, _id: GamepadId) {
+        self.gamepad_button(button, true);
+    }
+
+    fn gamepad_button_up_event(&mut self, _ctx: &mut Context, button: Button, _id: GamepadId) {
+        self.gamepad_button(button, false);
+    }
+
+    fn gamepad_axis_event(&mut self, _ctx: &mut Context, axis: Axis, value: f32, _id: GamepadId) {
+        self.gamepad_axis(axis, value);
+    }
+
+    fn key_up_event(&mut self, _ctx: &mut ggez::Context, keycode: KeyCode, _keymods: KeyMods) {
+        self.last_input = Instant::now();
+        if self.hotkey(keycode) == Some(Hotkey::Rewind) {
+            self.rewinding = false;
+        }
+        if let Some(key) = self.keypad_key(keycode) {
+            self.chip8.set_keyboard_key(key, false);
+        }
+    }
+}
+
+/// The screen coordinates that show an area as large as it fits in a window, centred and with
+/// its aspect ratio kept. The rest of the window is a margin on two sides, cleared with the
+/// background color.
+pub fn letterbox(window_width: f32, window_height: f32, width: f32, height: f32) -> Rect {
+    let scale = (window_width / width).min(window_height / height);
+    let (visible_width, visible_height) = (window_width / scale, window_height / scale);
+    Rect::new(
+        (width - visible_width) / 2.0,
+        (height - visible_height) / 2.0,
+        visible_width,
+        visible_height,
+    )
+}
+
+/// A point of the window, as given by the mouse events, in screen coordinates, see [`letterbox`]
+fn screen_point(ctx: &Context, x: f32, y: f32) -> (f32, f32) {
+    let coords = graphics::screen_coordinates(ctx);
+    let (width, height) = graphics::size(ctx);
+    (
+        coords.x + x / width * coords.w,
+        coords.y + y / height * coords.h,
+    )
+}
+
+/// Run the window until it is closed. This is the loop of `ggez::event::run`, which drops the
+/// files dropped onto the window, with a dropped file loaded in place of the running ROM.
+pub fn run(ctx: &mut Context, events_loop: &mut EventsLoop, window: &mut Window) -> GameResult {
+    while ctx.continuing {
+        ctx.timer_context.tick();
+        events_loop.poll_events(|event| {
+            ctx.process_event(&event);
+            let Event::WindowEvent { event, .. } = event else {
+                return;
+            };
+            match event {
+                WindowEvent::CloseRequested => event::quit(ctx),
+                WindowEvent::ReceivedCharacter(ch) => window.text_input_event(ctx, ch),
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state,
+                            virtual_keycode: Some(keycode),
+                            modifiers,
+                            ..
+                        },
+                    ..
+                } => match state {
+                    ElementState::Pressed => {
+                        let repeat = keyboard::is_key_repeated(ctx);
+                        window.key_down_event(ctx, keycode, modifiers.into(), repeat);
+                    }
+                    ElementState::Released => window.key_up_event(ctx, keycode, modifiers.into()),
+                },
+                WindowEvent::DroppedFile(path) => {
+                    window.load_rom(&path);
+                }
+                WindowEvent::MouseInput {
+                    state: ElementState::Pressed,
+                    button,
+                    ..
+                } => {
+                    let position = ggez::input::mouse::position(ctx);
+                    window.mouse_button_down_event(ctx, button, position.x, position.y);
+                }
+                WindowEvent::MouseWheel { delta, .. } => {
+                    let (x, y) = match delta {
+                        MouseScrollDelta::LineDelta(x, y) => (x, y),
+                        MouseScrollDelta::PixelDelta(position) => {
+                            (position.x as f32, position.y as f32)
+                        }
+                    };
+                    window.mouse_wheel_event(ctx, x, y);
+                }
+                WindowEvent::Resized(size) => {
+                    window.resize_event(ctx, size.width as f32, size.height as f32)
+                }
+                _ => {}
+            }
+        });
+        while let Some(gilrs::Event { event, .. }) = ctx.gamepad_context.next_event() {
+            match event {
+                gilrs::EventType::ButtonPressed(button, _) => window.gamepad_button(button, true),
+                gilrs::EventType::ButtonReleased(button, _) => window.gamepad_button(button, false),
+                gilrs::EventType::AxisChanged(axis, value, _) => window.gamepad_axis(axis, value),
+                _ => {}
+            }
+        }
+        window.update(ctx)?;
+        window.draw(ctx)?;
+    }
+    Ok(())
+}
+
+/// The keypad key that a key of the host keyboard is mapped to, the left side of a QWERTY
+/// keyboard is mapped to the keypad
+/// ```text
+/// 1 2 3 4        1 2 3 C
+/// Q W E R        4 5 6 D
+/// A S D F   ->   7 8 9 E
+/// Z X C V        A 0 B F
+/// ```
+pub fn keypad_index(keycode: KeyCode) -> Option<u8> {
+    let key = match keycode {
+        KeyCode::Key1 => 0x1,
+        KeyCode::Key2 => 0x2,
+        KeyCode::Key3 => 0x3,
+        KeyCode::Key4 => 0xC,
+        KeyCode::Q => 0x4,
+        KeyCode::W => 0x5,
+        KeyCode::E => 0x6,
+        KeyCode::R => 0xD,
+        KeyCode::A => 0x7,
+        KeyCode::S => 0x8,
+        KeyCode::D => 0x9,
+        KeyCode::F => 0xE,
+        KeyCode::Z => 0xA,
+        KeyCode::X => 0x0,
+        KeyCode::C => 0xB,
+        KeyCode::V => 0xF,
+        _ => return None,
+    };
+    Some(key)
+}
+
+/// A WAV file holding a whole number of periods of a square wave, so that it can be looped
+/// without clicks
+fn square_wave(frequency: u32) -> Vec<u8> {
+    const SAMPLE_RATE: u32 = 44_000;
+    const AMPLITUDE: i16 = i16::MAX / 8;
+    let period = SAMPLE_RATE / frequency;
+    let samples = period * frequency / 10;
+    let data_len = samples * 2;
+
+    let mut wav = Vec::with_capacity(44 + data_len as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes()); // size of the format chunk
+    wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    wav.extend_from_slice(&1u16.to_le_bytes()); // mono
+    wav.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    wav.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes()); // bytes per second
+    wav.extend_from_slice(&2u16.to_le_bytes()); // bytes per frame
+    wav.extend_from_slice(&16u16.to_le_bytes()); // bits per sample
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+    for sample in 0..samples {
+        let value = if sample % period < period / 2 {
+            AMPLITUDE
+        } else {
+            -AMPLITUDE
+        };
+        wav.extend_from_slice(&value.to_le_bytes());
+    }
+    wav
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Window;
+    use crate::Chip8;
+
+    #[test]
+    fn the_machine_is_handed_back_as_it_was_left() {
+        let mut window = Window::new(Chip8::builder().build());
+        window.chip8_mut().v[0x5] = 7;
+        assert_eq!(window.chip8().v[0x5], 7);
+        assert_eq!(window.into_inner().v[0x5], 7);
+    }
+}
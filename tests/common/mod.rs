@@ -0,0 +1,25 @@
+//! Helpers shared by the integration tests, every test file uses a part of them
+#![allow(dead_code)]
+
+use chip8::testrom::RomBuilder;
+use chip8::Inst;
+
+/// Run a program for as many steps as it has instructions
+pub fn run(insts: &[Inst]) -> chip8::Chip8 {
+    let mut chip8 = RomBuilder::new().insts(insts).load();
+    for _ in insts {
+        chip8.step();
+    }
+    chip8
+}
+
+/// Draws the digit 0 in the top left corner, then loops
+pub const DIGIT: [u8; 8] = [0x60, 0x00, 0xF0, 0x29, 0xD0, 0x15, 0x12, 0x06];
+
+/// A new directory for the files of a test, named after the test and the process so that
+/// concurrent runs do not share it
+pub fn temp_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("chip8-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
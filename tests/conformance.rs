@@ -0,0 +1,108 @@
+mod common;
+
+#[test]
+fn conformance_suites_compare_the_screen_with_snapshots() {
+    use chip8::conformance::Suite;
+
+    let dir = common::temp_dir("conformance");
+    std::fs::write(dir.join("digit.ch8"), common::DIGIT).unwrap();
+    // Draws the byte at 0x300, then loops
+    std::fs::write(dir.join("poke.ch8"), [0xA3, 0x00, 0xD0, 0x01, 0x12, 0x04]).unwrap();
+    let manifest = "[[tests]]\nname = \"digit\"\nrom = \"digit.ch8\"\ncycles = 100\n\n\
+                    [[tests]]\nname = \"poke\"\nrom = \"poke.ch8\"\ncycles = 100\n\
+                    poke = [[0x300, 0xF0]]\n";
+    std::fs::write(dir.join(chip8::conformance::MANIFEST), manifest).unwrap();
+    let suite = Suite::from_dir(&dir).unwrap();
+
+    let unblessed = suite.run(&dir, false);
+    let blessed = suite.run(&dir, true);
+    let checked = suite.run(&dir, false);
+    let poked = std::fs::read_to_string(dir.join("poke.txt")).unwrap();
+    std::fs::write(dir.join("digit.txt"), &poked).unwrap();
+    let changed = suite.run(&dir, false);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert!(unblessed.iter().all(|result| !result.passed));
+    assert!(blessed.iter().all(|result| result.passed));
+    assert!(checked.iter().all(|result| result.passed));
+    assert!(poked.starts_with("####....."));
+    assert_eq!(poked.lines().count(), 32);
+    assert!(!changed[0].passed);
+    assert_eq!(
+        changed[0].message.as_deref(),
+        Some("10 pixels differ from the snapshot, first in row 1")
+    );
+    assert!(changed[1].passed);
+}
+
+#[test]
+#[ignore = "needs the ROMs and snapshots of the Timendus suite in tests/timendus"]
+fn timendus_suite_matches_its_snapshots() {
+    use chip8::conformance::Suite;
+    use std::path::Path;
+
+    // The ROMs are not distributed with the emulator, a run without them checks nothing
+    let dir = Path::new("tests/timendus");
+    let suite = Suite::from_dir(dir).unwrap();
+    let mut missing: Vec<_> = suite
+        .tests
+        .iter()
+        .map(|case| &case.rom)
+        .filter(|rom| !dir.join(rom).exists())
+        .collect();
+    missing.dedup();
+    assert!(
+        missing.is_empty(),
+        "copy the ROMs into {} and bless them, missing: {:?}",
+        dir.display(),
+        missing
+    );
+    for result in suite.run(dir, false) {
+        assert!(result.passed, "{}: {:?}", result.name, result.message);
+    }
+}
+
+#[test]
+fn compliance_suite_passes_with_every_profile() {
+    for profile in chip8::quirks::Profile::ALL.iter() {
+        let summary = chip8::compliance::run(profile.quirks());
+        assert!(summary.passed(), "{}", summary.to_toml().unwrap());
+    }
+}
+
+#[test]
+fn test_traps_report_a_verdict() {
+    use chip8::runner;
+
+    let passing = "
+        LD V3, 7
+        SYS 0x143
+        DB 7, 0
+        LD F, V0
+        DRW V1, V1, 5
+        SYS 0x150
+        DB 0, 0, 1, 0
+        SYS 0x150
+        DB 4, 0, 0, 0
+        SYS 0x160
+    ";
+    let program = chip8::asm::assemble(passing).unwrap();
+    let result = runner::run("passing", &program, Default::default(), 1);
+    assert!(result.passed, "{:?}", result.message);
+
+    let failing = "
+        LD V3, 6
+        SYS 0x143
+        DB 7, 0
+        SYS 0x160
+    ";
+    let program = chip8::asm::assemble(failing).unwrap();
+    let result = runner::run("failing", &program, Default::default(), 1);
+    assert!(!result.passed);
+    let message = "V3 is 0x06 at 0x202, expected 0x07";
+    assert_eq!(result.message.as_deref(), Some(message));
+
+    let program = chip8::asm::assemble("loop: JP loop").unwrap();
+    let result = runner::run("silent", &program, Default::default(), 2);
+    assert_eq!(result.message.as_deref(), Some("timed out after 2 frames"));
+}
@@ -0,0 +1,165 @@
+use chip8::testrom::RomBuilder;
+use chip8::Inst;
+
+#[test]
+fn debugger_pauses_at_breakpoints() {
+    use chip8::debugger::Debugger;
+
+    let mut chip8 = RomBuilder::new()
+        .insts(&[Inst::Op6XKK(0, 1), Inst::Op6XKK(1, 2), Inst::Op6XKK(2, 3)])
+        .load();
+    let mut debugger = Debugger::new();
+    debugger.add_breakpoint(0x202);
+    for _ in 0..3 {
+        debugger.tick(&mut chip8);
+    }
+    assert!(debugger.paused());
+    assert_eq!(chip8.pc(), 0x202);
+    assert_eq!(chip8.registers()[..3], [1, 0, 0]);
+
+    debugger.step_instruction(&mut chip8);
+    assert_eq!(chip8.pc(), 0x204);
+    assert_eq!(chip8.registers()[1], 2);
+
+    debugger.resume(&chip8);
+    debugger.tick(&mut chip8);
+    assert_eq!(chip8.registers()[2], 3);
+}
+
+#[test]
+fn debugger_marks_code_rewritten_by_the_program() {
+    use chip8::debugger::Debugger;
+
+    // Overwrite the first instruction with 1200, then jump back to it
+    let mut chip8 = RomBuilder::new()
+        .insts(&[
+            Inst::Op6XKK(0, 0x12),
+            Inst::Op6XKK(1, 0x00),
+            Inst::OpANNN(0x200),
+            Inst::OpFX55(1),
+            Inst::Op1NNN(0x200),
+        ])
+        .load();
+    for _ in 0..5 {
+        chip8.step();
+    }
+    let listing = Debugger::new().listing(&chip8, 2);
+    assert_eq!(
+        listing[0],
+        "> ~ 0x200  1200  JP 0x200  ; written by 0x206 at cycle 4"
+    );
+    assert_eq!(listing[1], "    0x202  6100  LD V1, 0x00");
+}
+
+#[test]
+fn disassembly_labels_jump_targets() {
+    let rom = RomBuilder::new()
+        .insts(&[Inst::Op00E0, Inst::Op6XKK(0xA, 2), Inst::Op1NNN(0x202)])
+        .build();
+    assert_eq!(
+        chip8::disasm::listing(&rom, 0x200),
+        "0x200  00E0  CLS\nL202:\n0x202  6A02  LD VA, 0x02\n0x204  1202  JP L202\n"
+    );
+}
+
+#[test]
+fn console_completes_and_parses_commands() {
+    use chip8::console::{Command, Console, Target};
+
+    let mut console = Console::new();
+    for ch in "po".chars() {
+        console.push(ch);
+    }
+    console.complete(&[]);
+    assert_eq!(console.input(), "poke ");
+    for ch in "v".chars() {
+        console.push(ch);
+    }
+    console.complete(&["VA".to_string(), "I".to_string()]);
+    assert_eq!(console.input(), "poke VA ");
+    console.push('2');
+    assert_eq!(
+        console.submit(),
+        Some(Ok(Command::Poke(Target::Register(0xA), 2)))
+    );
+    console.history_prev();
+    assert_eq!(console.input(), "poke VA 2");
+}
+
+#[test]
+fn trace_lists_instructions_and_changed_registers() {
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+    impl std::io::Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let output = Shared::default();
+    let mut rom = RomBuilder::new();
+    rom.insts(&[Inst::Op6XKK(0, 5), Inst::OpANNN(0x300), Inst::Op3XKK(0, 5)]);
+    let mut chip8 = rom.load();
+    chip8.set_trace(Some(Box::new(output.clone())));
+    for _ in 0..3 {
+        chip8.step();
+    }
+    let text = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+    let lines: Vec<_> = text.lines().collect();
+    assert_eq!(
+        lines,
+        [
+            "0x200  6005  LD V0, 0x05          V0=05",
+            "0x202  A300  LD I, 0x300          I=300",
+            "0x204  3005  SE V0, 0x05",
+        ]
+    );
+}
+
+#[test]
+fn state_dump_names_return_addresses_after_symbols() {
+    let source = "start: CALL draw\nloop: JP loop\ndraw: CLS\n      JP draw\n";
+    let (program, symbols) = chip8::asm::assemble_with_symbols(source).unwrap();
+    assert_eq!(
+        chip8::symbols::Symbols::parse(&symbols.to_text()).unwrap(),
+        symbols
+    );
+    let mut chip8 = chip8::Chip8::builder().build();
+    chip8.load(&program).unwrap();
+    chip8.set_symbols(symbols);
+    chip8.step();
+    chip8.step();
+    let dump = chip8.dump();
+    assert!(dump.contains("PC: 0x206 (draw+2)"), "{}", dump);
+    assert!(dump.contains("Stack: 0x202 (loop)"), "{}", dump);
+}
+
+#[test]
+fn warnings_are_queued_for_the_frontend() {
+    use chip8::fault::Warning;
+
+    let mut chip8 = RomBuilder::new()
+        .insts(&[
+            Inst::OpANNN(0x300),
+            Inst::OpFX65(0),
+            Inst::OpANNN(0x300),
+            Inst::OpFX65(0),
+        ])
+        .load();
+    chip8.set_warn_uninit(true);
+    for _ in 0..4 {
+        chip8.step();
+    }
+    // Each byte is only reported once
+    let warnings = chip8.take_warnings();
+    assert!(matches!(
+        warnings[..],
+        [Warning::UninitializedRead { addr: 0x300, .. }]
+    ));
+    assert!(chip8.take_warnings().is_empty());
+}
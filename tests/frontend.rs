@@ -0,0 +1,411 @@
+mod common;
+
+use chip8::testrom::RomBuilder;
+use chip8::Inst;
+use common::run;
+
+#[test]
+fn rom_config_round_trip() {
+    let dir = common::temp_dir("rom-config");
+    let rom = dir.join("test.ch8");
+    let paths = chip8::datapaths::DataPaths::NextToRom;
+    let storage = chip8::storage::FileStorage;
+    let missing = chip8::romconfig::RomConfig::load(&storage, &paths, &rom).unwrap();
+    assert_eq!(missing, chip8::romconfig::RomConfig::default());
+
+    let mut quirks = chip8::quirks::Quirks::default();
+    quirks.set("jump_vx", true);
+    let mut keys = chip8::keymap::KeyMap::new();
+    keys.bind("Up", 0x2);
+    keys.bind("Space", 0xA);
+    let config = chip8::romconfig::RomConfig {
+        speed: Some(700),
+        palette: chip8::palette::Palette::from_list(&[0xFFCC00, 0x996600, 0xFF6600]),
+        quirks: Some(quirks),
+        keys: Some(keys),
+        gamepad: Some(chip8::keymap::KeyMap::gamepad()),
+    };
+    config.save(&storage, &paths, &rom).unwrap();
+    let loaded = chip8::romconfig::RomConfig::load(&storage, &paths, &rom);
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(loaded.unwrap(), config);
+
+    let palette = "palette = [\"FFCC00\"]";
+    assert!(toml::from_str::<chip8::romconfig::RomConfig>(palette).is_err());
+}
+
+#[test]
+fn library_lists_roms_with_the_thumbnail_of_their_first_frame() {
+    let dir = common::temp_dir("library");
+    std::fs::write(dir.join("digit.ch8"), common::DIGIT).unwrap();
+    std::fs::write(dir.join("Blank.c8"), [0x12, 0x00]).unwrap();
+    std::fs::write(dir.join("notes.txt"), "not a ROM").unwrap();
+    let entries = chip8::library::scan(&dir, &["ch8", "c8"]);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let entries = entries.unwrap();
+    let titles: Vec<_> = entries.iter().map(|entry| entry.title.as_str()).collect();
+    assert_eq!(titles, ["Blank", "digit"]);
+    assert_eq!(entries[0].size, 2);
+    assert_eq!(entries[0].thumbnail, None);
+    let thumbnail = entries[1].thumbnail.as_ref().unwrap();
+    assert!(thumbnail.lit(0, 0));
+    assert!(!thumbnail.lit(8, 0));
+}
+
+#[test]
+fn phosphor_fades_out_pixels_over_frames() {
+    use chip8::phosphor::Phosphor;
+
+    let mut phosphor = Phosphor::new(4);
+    assert!(phosphor.update(&[true, false], 1));
+    assert_eq!(phosphor.brightness(0), 1.0);
+    assert_eq!(phosphor.brightness(1), 0.0);
+
+    // A sprite erased and drawn again within a frame stays lit
+    assert!(!phosphor.update(&[false, false], 0));
+    assert_eq!(phosphor.brightness(0), 1.0);
+    assert!(phosphor.update(&[false, false], 1));
+    assert_eq!(phosphor.brightness(0), 0.75);
+    assert!(phosphor.update(&[false, false], 2));
+    assert_eq!(phosphor.brightness(0), 0.25);
+    assert!(phosphor.update(&[false, false], 1));
+    assert_eq!(phosphor.brightness(0), 0.0);
+    assert!(!phosphor.update(&[false, false], 1));
+}
+
+#[test]
+fn palettes_can_be_named_themes() {
+    use chip8::palette::Palette;
+    use chip8::palette::THEMES;
+    use chip8::romconfig::RomConfig;
+
+    assert_eq!(Palette::theme("classic"), Some(Palette::default()));
+    let amber = Palette::theme("Amber").unwrap();
+    assert_eq!(amber.theme_name(), Some("amber"));
+    assert_eq!(Palette::theme("sepia"), None);
+
+    let config: RomConfig = toml::from_str("palette = \"amber\"").unwrap();
+    assert_eq!(config.palette, Some(amber));
+    assert!(toml::from_str::<RomConfig>("palette = \"sepia\"").is_err());
+
+    let mut palette = Palette::default();
+    for (name, _) in THEMES.iter().skip(1).chain(THEMES.iter().take(1)) {
+        palette = palette.next_theme();
+        assert_eq!(palette.theme_name(), Some(*name));
+    }
+    let custom = Palette::from_list(&[0x123456, 0x654321]).unwrap();
+    assert_eq!(custom.theme_name(), None);
+    assert_eq!(custom.next_theme(), Palette::default());
+}
+
+#[test]
+fn rom_settings_are_read_from_url_parameters() {
+    use chip8::palette::Palette;
+    use chip8::quirks::Profile;
+    use chip8::romconfig::RomConfig;
+
+    let query = "?rom=games%2Fpong.ch8&speed=700&quirks=schip&quirk=shift_in_place:off";
+    let config = RomConfig::from_query(&format!("{}&palette=%23FFCC00,996600", query)).unwrap();
+    let mut quirks = Profile::Schip.quirks();
+    quirks.shift_in_place = false;
+    assert_eq!(config.speed, Some(700));
+    assert_eq!(config.quirks, Some(quirks));
+    assert_eq!(config.palette, Palette::from_list(&[0xFFCC00, 0x996600]));
+    assert!(RomConfig::from_query("speed=0").is_err());
+    assert!(RomConfig::from_query("quirk=typo:on").is_err());
+}
+
+#[test]
+fn bundled_roms_are_served_as_read_only_files() {
+    use chip8::bundled::Bundled;
+    use chip8::storage::Storage;
+    use std::path::Path;
+
+    let pong = chip8::bundled::find("pong").unwrap();
+    assert_eq!(pong.data, std::fs::read("roms/PONG").unwrap());
+    assert_eq!(Bundled.read(Path::new("roms/PONG")).unwrap(), pong.data);
+    assert!(!Bundled.exists(Path::new("roms/NOPE")));
+    assert!(Bundled.write(Path::new("roms/PONG"), &[]).is_err());
+}
+
+#[test]
+fn memory_storage_keeps_the_files_of_roms() {
+    use chip8::datapaths::DataPaths;
+    use chip8::romconfig::RomConfig;
+    use chip8::savestate::SaveState;
+    use chip8::storage::MemoryStorage;
+    use std::path::Path;
+
+    let storage = MemoryStorage::new();
+    let paths = DataPaths::Portable("saves".into());
+    let rom = Path::new("roms/pong.ch8");
+    let config = RomConfig {
+        speed: Some(700),
+        ..Default::default()
+    };
+    config.save(&storage, &paths, rom).unwrap();
+    assert_eq!(RomConfig::load(&storage, &paths, rom).unwrap(), config);
+
+    let chip8 = run(&[Inst::Op6XKK(3, 7)]);
+    chip8
+        .save_state()
+        .write(&storage, &paths.state(rom))
+        .unwrap();
+    let state = SaveState::read(&storage, &paths.state(rom)).unwrap();
+    assert_eq!(state.registers[3], 7);
+    assert_eq!(
+        storage.paths(),
+        [Path::new("saves/pong.state"), Path::new("saves/pong.toml")]
+    );
+    assert!(!Path::new("saves").exists());
+}
+
+#[test]
+fn captures_and_movies_are_written_into_the_storage() {
+    use chip8::movie::Movie;
+    use chip8::storage::MemoryStorage;
+    use chip8::storage::Storage;
+    use std::path::Path;
+
+    let storage = MemoryStorage::new();
+    let mut chip8 = run(&[Inst::OpFX29(0), Inst::OpDXYN(0, 0, 5)]);
+    chip8.record();
+    let shots = chip8::screenshot::capture(&storage, &mut chip8, Path::new("shots"), 2, 4).unwrap();
+    assert_eq!(shots.len(), 2);
+    let movie = Movie::from(chip8.repro_bundle().unwrap());
+    movie.write(&storage, Path::new("run.movie")).unwrap();
+    assert_eq!(
+        Movie::read(&storage, Path::new("run.movie")).unwrap(),
+        movie
+    );
+    assert!(storage.exists(&Path::new("shots").join(chip8::screenshot::SHEET_FILE)));
+    assert!(!Path::new("shots").exists());
+}
+
+#[cfg(feature = "recording")]
+#[test]
+fn recordings_show_the_held_keys_with_the_input_display() {
+    use chip8::recording::GifRecorder;
+    use chip8::storage::MemoryStorage;
+    use chip8::storage::Storage;
+    use std::path::Path;
+    use std::sync::Arc;
+
+    let storage = Arc::new(MemoryStorage::new());
+    let record = |input_display| {
+        let path = Path::new("clip.gif");
+        let mut chip8 = run(&[]);
+        chip8.set_keyboard_key(5, true);
+        chip8.poll_inputs();
+        let palette = chip8::palette::Palette::default();
+        let mut recorder = GifRecorder::create(storage.clone(), path, &palette).unwrap();
+        recorder.set_input_display(input_display);
+        recorder.capture(&chip8).unwrap();
+        recorder.finish().unwrap();
+        let gif = storage.read(path).unwrap();
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::Indexed);
+        let mut decoder = options.read_info(gif.as_slice()).unwrap();
+        decoder.read_next_frame().unwrap().unwrap().buffer.to_vec()
+    };
+    // Index 4 is the color of the held keys
+    assert!(!record(false).contains(&4));
+    assert!(record(true).contains(&4));
+}
+
+#[test]
+fn portable_mode_keeps_the_files_of_roms_together() {
+    use chip8::datapaths::DataPaths;
+    use std::path::Path;
+
+    let rom = Path::new("roms/games/pong.ch8");
+    assert_eq!(
+        DataPaths::NextToRom.rom_config(rom),
+        Path::new("roms/games/pong.toml")
+    );
+    let portable = DataPaths::Portable("usb/data".into());
+    assert_eq!(portable.rom_config(rom), Path::new("usb/data/pong.toml"));
+    assert_eq!(portable.state(rom), Path::new("usb/data/pong.state"));
+    assert_eq!(
+        portable.recent_file().unwrap(),
+        Path::new("usb/data/recent")
+    );
+}
+
+#[test]
+fn controls_report_keys_bound_twice() {
+    use chip8::controls::Conflict;
+    use chip8::controls::Controls;
+    use chip8::controls::Hotkey;
+
+    let text = "[keys]\nF1 = \"5\"\n\n[hotkeys]\npause = \"F6\"\nscreenshot = \"P\"\n";
+    let controls: Controls = toml::from_str(text).unwrap();
+    assert_eq!(controls.hotkeys.get(Hotkey::Screenshot), "P");
+    assert_eq!(controls.hotkeys.action("F2"), Some(Hotkey::SaveState));
+    assert_eq!(
+        controls.conflicts(),
+        vec![
+            Conflict::Keypad {
+                host: "F1".to_string(),
+                hotkey: Hotkey::LoadState,
+                key: 5,
+            },
+            Conflict::Hotkeys {
+                host: "F6".to_string(),
+                first: Hotkey::Pause,
+                second: Hotkey::Step,
+            },
+        ]
+    );
+    assert!(Controls::default().conflicts().is_empty());
+    assert!(toml::from_str::<Controls>("[hotkeys]\nfly = \"F1\"").is_err());
+}
+
+#[test]
+fn streamer_layouts_keep_the_defaults_they_do_not_set() {
+    use chip8::layout::Layout;
+
+    let text =
+        "background = \"#0000FF\"\nshow_stats = false\n\n[screen]\nx = 0\ny = 80\nsize = 20\n";
+    let layout: Layout = toml::from_str(text).unwrap();
+    assert_eq!(layout.background.0, 0x0000FF);
+    assert_eq!(
+        (layout.screen.x, layout.screen.y, layout.screen.size),
+        (0.0, 80.0, 20.0)
+    );
+    assert_eq!(layout.keypad, Layout::default().keypad);
+    assert!(layout.show_keypad && !layout.show_stats);
+    assert!(toml::from_str::<Layout>("background = \"green\"").is_err());
+
+    let dir = common::temp_dir("layout");
+    let path = dir.join("layout.toml");
+    std::fs::write(&path, "width = 0").unwrap();
+    let invalid = Layout::from_file(&path);
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(invalid.is_err());
+}
+
+#[test]
+fn key_map_rejects_invalid_keys() {
+    let keys: chip8::keymap::KeyMap = toml::from_str("Up = \"2\"\nLeft = \"4\"").unwrap();
+    assert_eq!(keys.get("Up"), Some(0x2));
+    assert_eq!(keys.get("Down"), None);
+    assert!(toml::from_str::<chip8::keymap::KeyMap>("Up = \"G\"").is_err());
+    assert!(toml::from_str::<chip8::keymap::KeyMap>("Up = \"10\"").is_err());
+}
+
+#[test]
+fn playlist_skips_comments() {
+    let text = "# demo\npong.ch8\n\n  games/brix.ch8  \n";
+    let roms = chip8::playlist::parse(text, std::path::Path::new("roms"));
+    assert_eq!(
+        roms,
+        vec![
+            std::path::PathBuf::from("roms/pong.ch8"),
+            std::path::PathBuf::from("roms/games/brix.ch8"),
+        ]
+    );
+    assert!(chip8::playlist::is_playlist(std::path::Path::new(
+        "demo.M3U"
+    )));
+    assert!(!chip8::playlist::is_playlist(std::path::Path::new(
+        "pong.ch8"
+    )));
+}
+
+#[test]
+fn shell_launches_the_selected_rom() {
+    let dir = common::temp_dir("shell");
+    let roms = vec![dir.join("first.ch8"), dir.join("second.ch8")];
+    std::fs::write(&roms[0], [0x12, 0x00]).unwrap();
+    std::fs::write(&roms[1], [0x6A, 0x42, 0x12, 0x02]).unwrap();
+    let names: Vec<_> = roms.iter().map(|rom| chip8::shell::name(rom)).collect();
+    let mut chip8 = RomBuilder::new()
+        .bytes(&chip8::shell::program(&names))
+        .load();
+    // The recent list is kept in memory
+    let paths = chip8::datapaths::DataPaths::Portable(dir.join("saves"));
+    let storage = std::sync::Arc::new(chip8::storage::MemoryStorage::new());
+    chip8.set_interpreter_hook(Some(chip8::shell::launcher(roms, paths, storage.clone())));
+
+    let press = |chip8: &mut chip8::Chip8, key| {
+        for pressed in [true, false] {
+            chip8.set_keyboard_key(key, pressed);
+            chip8.poll_inputs();
+            for _ in 0..2000 {
+                chip8.step();
+            }
+        }
+    };
+    press(&mut chip8, 8);
+    press(&mut chip8, 5);
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(chip8.fault(), None);
+    assert_eq!(&chip8.memory()[0x200..0x204], &[0x6A, 0x42, 0x12, 0x02]);
+    assert_eq!(chip8.registers()[0xA], 0x42);
+    assert_eq!(storage.paths(), [dir.join("saves").join("recent")]);
+}
+
+#[test]
+fn shell_warns_about_roms_it_cannot_launch() {
+    use chip8::fault::Warning;
+    use chip8::shell::LAUNCH_TRAP;
+
+    let dir = common::temp_dir("shell-missing");
+    let rom = dir.join("missing.ch8");
+    let mut chip8 = RomBuilder::new()
+        .insts(&[Inst::Op6XKK(0, 0), Inst::Op0NNN(LAUNCH_TRAP)])
+        .load();
+    let paths = chip8::datapaths::DataPaths::Portable(dir.join("saves"));
+    let storage = std::sync::Arc::new(chip8::storage::MemoryStorage::new());
+    chip8.set_interpreter_hook(Some(chip8::shell::launcher(
+        vec![rom.clone()],
+        paths,
+        storage,
+    )));
+    chip8.step();
+    chip8.step();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(matches!(
+        &chip8.take_warnings()[..],
+        [Warning::LaunchFailed { rom: path, .. }] if *path == rom.display().to_string()
+    ));
+}
+
+#[test]
+fn pool_steps_every_machine_and_copies_the_framebuffers() {
+    use chip8::pool::Chip8Pool;
+    use chip8::pool::FRAMEBUFFER_LEN;
+
+    // Machine N draws the digit N in the top left corner, the last one halts
+    let mut pool = Chip8Pool::new(3, |n| {
+        let mut chip8 = chip8::Chip8::builder().strict(true).build();
+        let program = [0x60, n as u8, 0xF0, 0x29, 0xD1, 0x15, 0x12, 0x06];
+        chip8.load(&program).unwrap();
+        if n == 2 {
+            chip8.memory_mut()[0x206..0x208].copy_from_slice(&[0xFF, 0xFF]);
+        }
+        chip8
+    });
+    pool.step(10);
+    assert!(pool.machines()[2].fault().is_some());
+
+    let mut out = vec![0; 3 * FRAMEBUFFER_LEN];
+    pool.copy_framebuffers(&mut out);
+    // The top row of 0 is 0xF0 and that of 1 is 0x20, scaled up by two
+    assert_eq!(out[..8], [1, 1, 1, 1, 1, 1, 1, 1]);
+    assert_eq!(
+        out[FRAMEBUFFER_LEN..FRAMEBUFFER_LEN + 8],
+        [0, 0, 0, 0, 1, 1, 0, 0]
+    );
+    let row = chip8::SCHIP_SCREEN_WIDTH;
+    assert_eq!(out[row..row + 8], out[..8]);
+
+    let states = pool.save_states();
+    pool.machines_mut()[0].reload();
+    pool.load_states(&states).unwrap();
+    let mut restored = vec![0; 3 * FRAMEBUFFER_LEN];
+    pool.copy_framebuffers(&mut restored);
+    assert_eq!(restored, out);
+}
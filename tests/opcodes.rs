@@ -0,0 +1,697 @@
+mod common;
+
+use chip8::testrom::RomBuilder;
+use chip8::Inst;
+use common::run;
+
+#[test]
+fn encode_decode_round_trip() {
+    for opcode in 0..=u16::MAX {
+        if let Ok(inst) = Inst::decode(opcode) {
+            assert_eq!(inst.encode(), opcode, "{:?}", inst);
+        }
+    }
+}
+
+#[test]
+fn unknown_opcode_is_an_error() {
+    use std::convert::TryFrom;
+
+    assert_eq!(Inst::try_from(0xFFFF), Err(chip8::UnknownOpcode(0xFFFF)));
+    assert_eq!(Inst::try_from(0x00E0), Ok(Inst::Op00E0));
+}
+
+#[test]
+fn load_and_add() {
+    let chip8 = run(&[Inst::Op6XKK(1, 0x20), Inst::Op7XKK(1, 0x05)]);
+    assert_eq!(chip8.registers()[1], 0x25);
+}
+
+#[test]
+fn add_sets_carry() {
+    let chip8 = run(&[
+        Inst::Op6XKK(0, 0xFF),
+        Inst::Op6XKK(1, 0x02),
+        Inst::Op8XY4(0, 1),
+    ]);
+    assert_eq!(chip8.registers()[0], 0x01);
+    assert_eq!(chip8.registers()[0xF], 1);
+}
+
+#[test]
+fn sub_sets_not_borrow() {
+    let chip8 = run(&[
+        Inst::Op6XKK(0, 0x05),
+        Inst::Op6XKK(1, 0x03),
+        Inst::Op8XY5(0, 1),
+    ]);
+    assert_eq!(chip8.registers()[0], 0x02);
+    assert_eq!(chip8.registers()[0xF], 1);
+}
+
+#[test]
+fn skip_if_equal() {
+    let chip8 = run(&[
+        Inst::Op6XKK(0, 0x07),
+        Inst::Op3XKK(0, 0x07),
+        Inst::Op6XKK(1, 0xAA),
+        Inst::Op6XKK(2, 0xBB),
+    ]);
+    assert_eq!(chip8.registers()[1], 0x00);
+    assert_eq!(chip8.registers()[2], 0xBB);
+}
+
+#[test]
+fn call_and_return() {
+    let mut rom = RomBuilder::new();
+    rom.inst(Inst::Op2NNN(0)).patch("sub");
+    rom.inst(Inst::Op6XKK(1, 0x02));
+    let halt = rom.addr();
+    rom.inst(Inst::Op1NNN(halt));
+    rom.define("sub")
+        .inst(Inst::Op6XKK(0, 0x01))
+        .inst(Inst::Op00EE);
+    let mut chip8 = rom.load();
+    for _ in 0..4 {
+        chip8.step();
+    }
+    assert_eq!(chip8.registers()[0], 0x01);
+    assert_eq!(chip8.registers()[1], 0x02);
+    assert_eq!(chip8.pc(), halt);
+}
+
+#[test]
+fn bcd_and_load_registers() {
+    let mut rom = RomBuilder::new();
+    rom.insts(&[Inst::Op6XKK(0, 254), Inst::OpANNN(0x300)]);
+    rom.insts(&[Inst::OpFX33(0), Inst::OpFX65(2)]);
+    let mut chip8 = rom.load();
+    for _ in 0..4 {
+        chip8.step();
+    }
+    assert_eq!(&chip8.memory()[0x300..0x303], &[2, 5, 4]);
+    assert_eq!(&chip8.registers()[..3], &[2, 5, 4]);
+}
+
+#[test]
+fn draw_sets_collision() {
+    let mut rom = RomBuilder::new();
+    rom.inst(Inst::OpANNN(0)).patch("sprite");
+    rom.insts(&[Inst::OpDXYN(0, 0, 1), Inst::OpDXYN(0, 0, 1)]);
+    let halt = rom.addr();
+    rom.inst(Inst::Op1NNN(halt));
+    rom.define("sprite").bytes(&[0x80]);
+    let mut chip8 = rom.load();
+    chip8.step();
+    chip8.step();
+    assert!(chip8.framebuffer()[0]);
+    assert_eq!(chip8.registers()[0xF], 0);
+    chip8.step();
+    assert!(!chip8.framebuffer()[0]);
+    assert_eq!(chip8.registers()[0xF], 1);
+}
+
+#[test]
+fn only_the_drawn_rows_are_dirty() {
+    let mut chip8 = RomBuilder::new()
+        .insts(&[Inst::Op6XKK(1, 30), Inst::OpDXYN(0, 1, 5), Inst::Op00E0])
+        .load();
+    assert_eq!(chip8.take_dirty_rows(), u64::MAX);
+    chip8.step();
+    assert_eq!(chip8.take_dirty_rows(), 0);
+    // The sprite wraps around to the top of the screen
+    chip8.step();
+    assert_eq!(chip8.take_dirty_rows(), 0b111 | 0b11 << 30);
+    chip8.step();
+    assert_eq!(chip8.take_dirty_rows(), u64::MAX);
+}
+
+#[test]
+fn timers_count_down_without_instructions() {
+    let mut chip8 = run(&[Inst::Op6XKK(0, 60), Inst::OpFX15(0)]);
+    std::thread::sleep(chip8::TIMER_PERIOD * 2);
+    chip8.run_timers();
+    let dt = chip8.save_state().delay_timer;
+    assert!((56..=58).contains(&dt), "{}", dt);
+
+    // A long pause only catches up a few periods
+    std::thread::sleep(chip8::TIMER_PERIOD * 20);
+    chip8.run_timers();
+    let caught_up = dt - chip8::MAX_TIMER_CATCH_UP as u8;
+    assert_eq!(chip8.save_state().delay_timer, caught_up);
+}
+
+#[test]
+fn display_wait_holds_the_program_until_the_next_frame() {
+    let mut chip8 = RomBuilder::new()
+        .insts(&[Inst::OpDXYN(0, 0, 1), Inst::Op6XKK(0, 1)])
+        .load();
+    chip8.set_quirks(chip8::quirks::Profile::Chip8.quirks());
+    chip8.step();
+    chip8.step();
+    assert!(!chip8.ready());
+    assert_eq!(chip8.registers()[0], 0);
+
+    chip8.next_frame();
+    chip8.step();
+    assert_eq!(chip8.registers()[0], 1);
+}
+
+#[test]
+fn timers_follow_the_frames_and_not_the_clock() {
+    let mut chip8 = run(&[Inst::Op6XKK(0, 60), Inst::OpFX15(0), Inst::Op1NNN(0x204)]);
+    std::thread::sleep(chip8::TIMER_PERIOD * 2);
+    for _ in 0..10 {
+        chip8.step();
+    }
+    assert_eq!(chip8.save_state().delay_timer, 60);
+
+    chip8.next_frame();
+    assert_eq!(chip8.save_state().delay_timer, 59);
+}
+
+#[test]
+fn wait_for_key_stores_a_key_once_released() {
+    let program = [Inst::OpFX0A(0), Inst::Op6XKK(1, 1)];
+    let mut keys = [false; 16];
+    keys[5] = true;
+
+    let mut chip8 = RomBuilder::new().insts(&program).load();
+    chip8.set_keys(keys);
+    // The key is latched while it is held, other keys pressed meanwhile do not replace it
+    chip8.step();
+    keys[3] = true;
+    chip8.set_keys(keys);
+    chip8.step();
+    chip8.step();
+    assert_eq!(chip8.registers()[1], 0);
+    keys[5] = false;
+    chip8.set_keys(keys);
+    chip8.step();
+    chip8.step();
+    assert_eq!(chip8.registers()[..2], [5, 1]);
+
+    let mut chip8 = RomBuilder::new().insts(&program).load();
+    chip8.set_quirks(chip8::quirks::Quirks {
+        key_release: false,
+        ..Default::default()
+    });
+    chip8.set_keys(keys);
+    chip8.step();
+    assert_eq!(chip8.registers()[0], 3);
+}
+
+#[test]
+fn drawn_sprites_become_cues_of_their_region() {
+    use chip8::sonify::Cue;
+    use chip8::sonify::Sonifier;
+
+    let sonifier = Sonifier::new();
+    let mut chip8 = RomBuilder::new()
+        .insts(&[
+            Inst::Op6XKK(0, 56),
+            Inst::Op6XKK(1, 2),
+            Inst::Op6XKK(2, 31),
+            Inst::OpDXYN(1, 2, 1),
+            Inst::OpDXYN(1, 2, 1),
+            Inst::OpDXYN(0, 3, 1),
+        ])
+        .load();
+    chip8.set_draw_hook(Some(sonifier.hook()));
+    for _ in 0..6 {
+        chip8.step();
+    }
+    let cues = sonifier.cues();
+    // The sprite drawn twice at the bottom left is one cue, after the one at the top right
+    assert_eq!(cues, [Cue::new(2, 0), Cue::new(0, 5)]);
+    assert_eq!((cues[0].pan, cues[0].pitch), (1.0, 2.0));
+    assert_eq!((cues[1].pan, cues[1].pitch), (-1.0, 1.0));
+    assert!(sonifier.cues().is_empty());
+}
+
+#[test]
+fn quirk_sensitive_instructions_are_noted_once() {
+    use chip8::diagnostics::Diagnostic;
+
+    let mut chip8 = run(&[
+        Inst::Op6XKK(1, 3),
+        Inst::Op8XY6(0, 1),
+        Inst::Op8XY6(0, 1),
+        Inst::OpFX65(0),
+        Inst::OpDXYN(0, 0, 1),
+    ]);
+    assert_eq!(chip8.diagnostics().count(Diagnostic::Shift), 2);
+    assert_eq!(
+        chip8.diagnostics().count(Diagnostic::IndexAfterLoadStore),
+        1
+    );
+    assert_eq!(chip8.diagnostics().count(Diagnostic::JumpOffset), 0);
+    assert_eq!(
+        chip8.take_new_diagnostics(),
+        vec![Diagnostic::Shift, Diagnostic::IndexAfterLoadStore]
+    );
+    assert!(chip8.take_new_diagnostics().is_empty());
+    let hint = Diagnostic::Shift.hint(&chip8.quirks());
+    assert!(hint.contains("--quirk shift_in_place=off"), "{}", hint);
+}
+
+#[test]
+fn schip_resolution_switch() {
+    let chip8 = run(&[Inst::Op00FF]);
+    assert_eq!(chip8.screen_size(), (128, 64));
+    assert_eq!(chip8.framebuffer().len(), 128 * 64);
+    let chip8 = run(&[Inst::Op00FF, Inst::Op00FE]);
+    assert_eq!(chip8.screen_size(), (64, 32));
+}
+
+#[test]
+fn schip_big_sprite_and_scroll() {
+    let mut rom = RomBuilder::new();
+    rom.inst(Inst::Op00FF);
+    rom.inst(Inst::OpANNN(0)).patch("sprite");
+    rom.insts(&[Inst::OpDXYN(0, 0, 0), Inst::Op00CN(2), Inst::Op00FB]);
+    let halt = rom.addr();
+    rom.inst(Inst::Op1NNN(halt));
+    rom.define("sprite").bytes(&[0xFF; 32]);
+    let mut chip8 = rom.load();
+    for _ in 0..3 {
+        chip8.step();
+    }
+    let lit = chip8.framebuffer().iter().filter(|p| **p).count();
+    assert_eq!(lit, 16 * 16);
+    assert!(chip8.framebuffer()[15 + 15 * 128]);
+    chip8.step();
+    chip8.step();
+    assert!(!chip8.framebuffer()[3 + 128]);
+    assert!(chip8.framebuffer()[4 + 2 * 128]);
+    assert!(chip8.framebuffer()[19 + 17 * 128]);
+}
+
+#[test]
+fn schip_scrolls_half_pixels_in_low_resolution() {
+    let mut rom = RomBuilder::new();
+    rom.inst(Inst::OpANNN(0)).patch("sprite");
+    rom.insts(&[Inst::OpDXYN(0, 0, 1), Inst::Op00FB, Inst::Op00CN(3)]);
+    rom.define("sprite").bytes(&[0x80]);
+    let mut chip8 = rom.load();
+    chip8.set_quirks(chip8::quirks::Profile::Schip.quirks());
+    for _ in 0..4 {
+        chip8.step();
+    }
+    assert!(chip8.framebuffer()[2 + 64]);
+    assert_eq!(chip8.framebuffer().iter().filter(|p| **p).count(), 1);
+}
+
+#[test]
+fn schip_counts_collided_and_clipped_rows() {
+    let mut rom = RomBuilder::new();
+    rom.insts(&[Inst::Op00FF, Inst::Op6XKK(1, 60)]);
+    rom.inst(Inst::OpANNN(0)).patch("sprite");
+    rom.insts(&[Inst::OpDXYN(0, 1, 8), Inst::OpDXYN(0, 1, 8)]);
+    rom.define("sprite").bytes(&[0xFF; 8]);
+    let mut chip8 = rom.load();
+    chip8.set_quirks(chip8::quirks::Profile::Schip.quirks());
+    for _ in 0..4 {
+        chip8.step();
+    }
+    assert_eq!(chip8.registers()[0xF], 4);
+    chip8.step();
+    assert_eq!(chip8.registers()[0xF], 8);
+}
+
+#[test]
+fn schip_user_flags() {
+    let mut chip8 = run(&[
+        Inst::Op6XKK(0, 0x11),
+        Inst::Op6XKK(1, 0x22),
+        Inst::OpFX75(1),
+        Inst::Op6XKK(0, 0x00),
+        Inst::Op6XKK(1, 0x00),
+    ]);
+    chip8.reset();
+    let mut rom = RomBuilder::new();
+    rom.inst(Inst::OpFX85(1));
+    chip8.load(&rom.build()).unwrap();
+    chip8.step();
+    assert_eq!(&chip8.registers()[..2], &[0x11, 0x22]);
+}
+
+#[test]
+fn load_rejects_programs_past_the_end_of_memory() {
+    let mut chip8 = chip8::Chip8::builder().build();
+    let program = vec![0xAB; chip8::PROGRAM_SIZE];
+    assert_eq!(chip8.load(&program), Ok(()));
+    assert_eq!(chip8.memory()[0xFFF], 0xAB);
+
+    let program = vec![0; chip8::PROGRAM_SIZE + 1];
+    let err = chip8::LoadError::TooLarge { len: 0xE01 };
+    assert_eq!(chip8.load(&program), Err(err));
+}
+
+#[test]
+fn roms_read_until_the_end_of_their_input() {
+    // A reader that hands out a single byte per call
+    struct Trickle<'a>(&'a [u8]);
+    impl std::io::Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            match self.0.split_first() {
+                Some((byte, rest)) if !buf.is_empty() => {
+                    buf[0] = *byte;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    let rom = chip8::rom::Rom::from_reader(Trickle(&[0x00, 0xE0, 0x12, 0x00])).unwrap();
+    assert_eq!(rom.data(), &[0x00, 0xE0, 0x12, 0x00]);
+
+    let program = vec![0; chip8::PROGRAM_SIZE + 1];
+    assert!(chip8::rom::Rom::from_reader(Trickle(&program)).is_err());
+}
+
+#[test]
+fn files_that_are_not_roms_are_rejected_with_a_hint() {
+    use chip8::rom::Problem;
+    use chip8::rom::Rom;
+    use chip8::rom::RomError;
+
+    let problem = |data: &[u8]| match Rom::from_bytes(data.to_vec()) {
+        Err(RomError::Invalid(problem)) => Some(problem),
+        _ => None,
+    };
+    assert_eq!(problem(&[]), Some(Problem::Empty));
+    assert_eq!(
+        problem(b"\x7FELF\x02\x01\x01"),
+        Some(Problem::Format("an ELF executable"))
+    );
+    assert_eq!(
+        problem(b"start:  CLS\n        JP start\n"),
+        Some(Problem::Text)
+    );
+
+    // Sprite data can leave a program with an odd size, it is only a warning
+    let rom = Rom::from_bytes(vec![0x12, 0x00, 0xFF]).unwrap();
+    assert_eq!(rom.warnings(), &[Problem::OddLength { len: 3 }]);
+    // Two-byte signatures are also instructions, SNE VD, 0x5A and JP 0xF8B
+    let rom = Rom::from_bytes(b"MZ\x12\x00".to_vec()).unwrap();
+    assert_eq!(
+        rom.warnings(),
+        &[Problem::MaybeFormat("a Windows executable")]
+    );
+    let rom = Rom::from_bytes(vec![0x1F, 0x8B]).unwrap();
+    assert_eq!(rom.warnings(), &[Problem::MaybeFormat("a gzip archive")]);
+    assert_eq!(
+        problem(b"<!DOCTYPE html>\n<html></html>\n"),
+        Some(Problem::Text)
+    );
+    assert!(Rom::from_bytes(vec![0x12, 0x00])
+        .unwrap()
+        .warnings()
+        .is_empty());
+}
+
+#[test]
+fn reload_runs_the_loaded_program_from_the_start() {
+    // The program overwrites its first instruction before it is reloaded
+    let mut chip8 = run(&[Inst::Op6XKK(0, 0xAB), Inst::OpANNN(0x200), Inst::OpFX55(0)]);
+    chip8.load_interpreter_image(&[0x77; 0x200]);
+    let rom = chip8.rom().to_vec();
+    assert_eq!(chip8.export_rom()[0], 0xAB);
+
+    chip8.reload();
+    assert_eq!(chip8.rom(), &rom[..]);
+    assert_eq!(chip8.export_rom(), &rom[..]);
+    assert_eq!(chip8.registers()[0], 0);
+    assert_eq!(chip8.memory()[0x1FF], 0x77);
+    chip8.step();
+    assert_eq!(chip8.registers()[0], 0xAB);
+}
+
+#[test]
+fn calls_into_the_builtin_interpreter_image_return() {
+    let mut chip8 = chip8::Chip8::builder().strict(true).build();
+    chip8.load_interpreter_image(&chip8::BUILTIN_INTERPRETER_IMAGE);
+    chip8.load(&[0x21, 0xF0, 0x60, 0x01]).unwrap();
+    assert_eq!(chip8.memory()[0x1F0..0x1F2], [0x00, 0xEE]);
+    chip8.step();
+    assert_eq!(chip8.pc(), 0x1F0);
+    chip8.step();
+    chip8.step();
+    assert_eq!(chip8.fault(), None);
+    assert_eq!(chip8.registers()[0], 1);
+}
+
+#[test]
+fn splash_is_shown_before_the_program_starts() {
+    let mut chip8 = chip8::Chip8::builder().splash(true).build();
+    chip8.load(&[0x60, 0x01]).unwrap();
+    assert!(chip8.framebuffer().iter().any(|pixel| *pixel));
+    for _ in 0..chip8::splash::SPLASH_TICKS {
+        chip8.step();
+    }
+    assert_eq!(chip8.pc(), 0x200);
+    assert!(chip8.framebuffer().iter().all(|pixel| !*pixel));
+    chip8.step();
+    assert_eq!(chip8.registers()[0], 1);
+
+    chip8.reset();
+    chip8.set_keyboard_key(5, true);
+    chip8.poll_inputs();
+    assert!(chip8.framebuffer().iter().all(|pixel| !*pixel));
+}
+
+#[test]
+fn text_is_drawn_into_the_framebuffer() {
+    use chip8::text::{draw_text_to_framebuffer, Font};
+
+    let mut screen = vec![false; 12 * 5];
+    let end = draw_text_to_framebuffer(&mut screen, 12, 0, 0, "a:", Font::Mini);
+    draw_text_to_framebuffer(&mut screen, 12, end, 0, "F", Font::Hex);
+    let rows: Vec<String> = screen
+        .chunks(12)
+        .map(|row| row.iter().map(|&lit| if lit { '#' } else { '.' }).collect())
+        .collect();
+    let golden = [
+        ".#......####",
+        "#.#..#..#...",
+        "###.....####",
+        "#.#..#..#...",
+        "#.#.....#...",
+    ];
+    assert_eq!(rows, golden);
+}
+
+#[test]
+fn skips_step_over_long_loads() {
+    let mut rom = RomBuilder::new();
+    rom.inst(Inst::Op6XKK(0, 1))
+        .inst(Inst::Op3XKK(0, 1))
+        .inst(Inst::OpF000)
+        .bytes(&[0x0A, 0xBC])
+        .inst(Inst::OpF000)
+        .bytes(&[0x01, 0x23])
+        .inst(Inst::Op6XKK(1, 2));
+    let mut chip8 = rom.load();
+    for _ in 0..3 {
+        chip8.step();
+    }
+    assert_eq!(chip8.index(), 0x123);
+    assert_eq!(chip8.pc(), 0x20C);
+    chip8.step();
+    assert_eq!(chip8.registers()[1], 2);
+
+    let listing = chip8::disasm::listing(&rom.build(), 0x200);
+    assert!(listing.contains("0x206  0ABC  DW 0x0ABC"), "{}", listing);
+}
+
+#[test]
+fn keys_past_f_wrap_around_or_halt_in_strict_mode() {
+    use chip8::fault::Fault;
+
+    let insts = [
+        Inst::Op6XKK(0, 0x21),
+        Inst::OpEX9E(0),
+        Inst::Op6XKK(1, 1),
+        Inst::Op6XKK(2, 2),
+    ];
+    let mut chip8 = RomBuilder::new().insts(&insts).load();
+    chip8.set_keyboard_key(1, true);
+    chip8.poll_inputs();
+    for _ in 0..3 {
+        chip8.step();
+    }
+    assert_eq!(chip8.fault(), None);
+    assert_eq!(chip8.registers()[1..3], [0, 2]);
+
+    let mut chip8 = RomBuilder::new().insts(&insts).load();
+    chip8.set_strict(true);
+    for _ in insts {
+        chip8.step();
+    }
+    assert_eq!(chip8.fault(), Some(Fault::KeyOutOfRange { key: 0x21 }));
+    assert_eq!(chip8.registers()[1..3], [0, 0]);
+}
+
+#[test]
+fn index_wraps_after_loads_and_stores_at_the_top() {
+    let quirks = chip8::quirks::Quirks {
+        increment_index: true,
+        ..Default::default()
+    };
+    for inst in [Inst::OpFX55(0xF), Inst::OpFX65(0xF)] {
+        let mut chip8 = RomBuilder::new()
+            .inst(Inst::OpF000)
+            .bytes(&[0xFF, 0xFF])
+            .inst(inst)
+            .load();
+        chip8.set_quirks(quirks);
+        chip8.step();
+        chip8.step();
+        assert_eq!(chip8.index(), 0x000F, "{}", inst);
+    }
+}
+
+#[test]
+fn quirk_profiles() {
+    use chip8::quirks::Profile;
+
+    let program = [
+        Inst::Op6XKK(1, 0x81),
+        Inst::Op8XY6(0, 1),
+        Inst::OpANNN(0x300),
+        Inst::OpFX55(0),
+    ];
+    let mut chip8 = RomBuilder::new().insts(&program).load();
+    chip8.set_quirks(Profile::Chip8.quirks());
+    program.iter().for_each(|_| chip8.step());
+    assert_eq!(chip8.registers()[0], 0x40);
+    assert_eq!(chip8.index(), 0x301);
+
+    let mut chip8 = RomBuilder::new().insts(&program).load();
+    chip8.set_quirks(Profile::Schip.quirks());
+    program.iter().for_each(|_| chip8.step());
+    assert_eq!(chip8.registers()[0], 0x00);
+    assert_eq!(chip8.index(), 0x300);
+}
+
+#[test]
+fn assembler_reads_the_disassembler_syntax() {
+    for opcode in 0..=u16::MAX {
+        if let Ok(inst) = Inst::decode(opcode) {
+            let rom = chip8::asm::assemble(&inst.to_string()).unwrap();
+            assert_eq!(rom, opcode.to_be_bytes(), "{}", inst);
+        }
+    }
+}
+
+#[test]
+fn assembler_resolves_labels() {
+    let source = "start: LD I, sprite ; point at the data\n  JP start\nsprite: DB %11110000, $90\n";
+    assert_eq!(
+        chip8::asm::assemble(source).unwrap(),
+        [0xA2, 0x04, 0x12, 0x00, 0xF0, 0x90]
+    );
+    let err = chip8::asm::assemble("CLS\nJP nowhere").unwrap_err();
+    assert_eq!(err.line, 2);
+}
+
+#[test]
+fn assertion_trap_halts_only_when_enabled() {
+    use chip8::fault::Fault;
+    use chip8::trap;
+
+    let insts = [
+        Inst::Op6XKK(3, 1),
+        Inst::Op0NNN(trap::ASSERT + 3),
+        Inst::Op6XKK(3, 0),
+        Inst::Op0NNN(trap::ASSERT + 3),
+        Inst::Op6XKK(4, 1),
+    ];
+    assert_eq!(run(&insts).registers()[4], 1);
+
+    let mut chip8 = RomBuilder::new().insts(&insts).load();
+    chip8.set_interpreter_hook(Some(trap::Traps::builtin(|_| ()).into_hook()));
+    for _ in insts {
+        chip8.step();
+    }
+    let fault = Fault::AssertionFailed {
+        addr: 0x206,
+        register: 3,
+    };
+    assert_eq!(chip8.fault(), Some(fault));
+    assert_eq!(chip8.pc(), 0x206);
+    assert_eq!(chip8.registers()[4], 0);
+}
+
+#[test]
+fn peripherals_update_their_memory_every_frame() {
+    use chip8::peripheral::Peripheral;
+    use std::ops::Range;
+
+    struct Counter(u8);
+
+    impl Peripheral for Counter {
+        fn range(&self) -> Range<u16> {
+            0xF00..0xF01
+        }
+
+        fn update(&mut self, memory: &mut [u8]) {
+            self.0 += 1;
+            memory[0] = self.0;
+        }
+    }
+
+    let mut chip8 = RomBuilder::new()
+        .insts(&[Inst::OpANNN(0xF00), Inst::OpFX65(0)])
+        .load();
+    chip8.attach_peripheral(Box::new(Counter(0)));
+    chip8.poll_inputs();
+    chip8.poll_inputs();
+    chip8.step();
+    chip8.step();
+    assert_eq!(chip8.registers()[0], 2);
+}
+
+#[test]
+fn print_traps_format_registers() {
+    let source = "
+        LD V1, 42
+        PRINT \"V1 is %1; 100%%\"
+        PRINT V1
+        LD V2, 0x4F
+        SYS 0x132
+        LD V2, 10
+        SYS 0x132
+        LD V3, 1
+    ";
+    let program = chip8::asm::assemble(source).unwrap();
+    let mut chip8 = RomBuilder::new().bytes(&program).load();
+    let (echo, lines) = std::sync::mpsc::channel();
+    let traps = chip8::trap::Traps::builtin(move |line| {
+        let _ = echo.send(line);
+    });
+    chip8.set_interpreter_hook(Some(traps.into_hook()));
+    for _ in 0..8 {
+        chip8.step();
+    }
+    let lines: Vec<_> = lines.try_iter().collect();
+    assert_eq!(lines, ["V1 is 42; 100%", "V1 = 0x2A (42)", "O"]);
+    assert_eq!(chip8.registers()[3], 1);
+}
+
+#[test]
+fn assembler_moves_programs_to_their_origin() {
+    let program = chip8::asm::assemble("ORG 0x600\nstart: LD I, start\nJP start").unwrap();
+    assert_eq!(program.len(), 0x404);
+    assert_eq!(program[..2], [0x16, 0x00]);
+    assert_eq!(program[0x400..], [0xA6, 0x00, 0x16, 0x00]);
+    let mut chip8 = RomBuilder::new().bytes(&program).load();
+    chip8.step();
+    chip8.step();
+    assert_eq!((chip8.pc(), chip8.index()), (0x602, 0x600));
+    let err = chip8::asm::assemble("CLS\nORG 0x100").unwrap_err();
+    assert_eq!(err.line, 2);
+}
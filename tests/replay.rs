@@ -0,0 +1,304 @@
+mod common;
+
+use chip8::testrom::RomBuilder;
+use chip8::Inst;
+use common::run;
+
+#[test]
+fn audit_passes_for_seeded_random_numbers() {
+    let mut rom = RomBuilder::new();
+    rom.insts(&[Inst::OpCXKK(0, 0xFF), Inst::Op1NNN(0x200)]);
+    let report = chip8::audit::audit(rom.load(), rom.load(), &[], 60);
+    assert_eq!(report.divergence, None);
+}
+
+#[test]
+fn movies_play_back_the_recorded_keys() {
+    // Count the frames key 5 is held in V1, a random byte in V2
+    let program = [
+        0x60, 0x05, // LD V0, 5
+        0xE0, 0xA1, // SKNP V0
+        0x71, 0x01, // ADD V1, 1
+        0xC2, 0xFF, // RND V2, 0xFF
+        0x12, 0x02, // JP 0x202
+    ];
+    let mut chip8 = chip8::Chip8::builder().seed(7).build();
+    chip8.load(&program).unwrap();
+    chip8.record();
+    for step in 0..200 {
+        chip8.set_keyboard_key(5, (50..120).contains(&step));
+        chip8.poll_inputs();
+        chip8.step();
+    }
+    let movie = chip8::movie::Movie::from(chip8.repro_bundle().unwrap());
+    assert_eq!(movie.inputs.len(), 2);
+
+    let mut replay = chip8::Chip8::builder().seed(movie.seed).build();
+    replay.load(&program).unwrap();
+    replay.play(&movie);
+    for _ in 0..movie.steps {
+        // Live input is ignored while the movie plays
+        replay.set_keyboard_key(5, true);
+        replay.poll_inputs();
+        replay.step();
+    }
+    assert!(!replay.playing());
+    assert_eq!(replay.registers(), chip8.registers());
+}
+
+#[test]
+fn movies_are_edited_and_resumed_from_saved_states() {
+    use chip8::repro::InputChange;
+
+    let change = |step, keys| InputChange { step, keys };
+    let mut chip8 = chip8::Chip8::builder().seed(u64::MAX).build();
+    chip8.load(&[0x12, 0x00]).unwrap();
+    chip8.record();
+    let mut state = None;
+    for step in 0..200 {
+        if step == 100 {
+            state = Some(chip8.save_state());
+        }
+        chip8.set_keyboard_key(5, (50..150).contains(&step));
+        chip8.poll_inputs();
+        chip8.step();
+    }
+    // The mistake after step 100 is redone without the key
+    chip8.load_state(&state.unwrap()).unwrap();
+    chip8.set_keyboard_key(5, false);
+    for _ in 100..200 {
+        chip8.poll_inputs();
+        chip8.step();
+    }
+    let movie = chip8::movie::Movie::from(chip8.repro_bundle().unwrap());
+    assert_eq!(movie.inputs, [change(50, 0x20), change(100, 0)]);
+    assert_eq!(movie.steps, 200);
+    let text = toml::to_string(&movie).unwrap();
+    assert_eq!(toml::from_str::<chip8::movie::Movie>(&text).unwrap(), movie);
+
+    let mut trimmed = movie.clone();
+    trimmed.trim(80);
+    assert_eq!(
+        (trimmed.inputs.as_slice(), trimmed.steps),
+        (&movie.inputs[..1], 80)
+    );
+    let mut retimed = movie.clone();
+    retimed.retime(500, 1000);
+    assert_eq!(retimed.inputs, [change(100, 0x20), change(200, 0)]);
+    let spliced = trimmed.splice(&retimed, 150).unwrap();
+    assert_eq!(spliced.inputs, [change(50, 0x20), change(200, 0)]);
+    assert_eq!(spliced.steps, 400);
+}
+
+#[test]
+fn save_state_round_trip() {
+    let mut rom = RomBuilder::new();
+    rom.insts(&[
+        Inst::Op6XKK(3, 0x42),
+        Inst::OpANNN(0x300),
+        Inst::OpDXYN(3, 3, 5),
+        Inst::OpCXKK(4, 0xFF),
+    ]);
+    let mut chip8 = rom.load();
+    for _ in 0..3 {
+        chip8.step();
+    }
+    let state = chip8.save_state();
+    let text = toml::to_string(&state).unwrap();
+    assert_eq!(
+        toml::from_str::<chip8::savestate::SaveState>(&text).unwrap(),
+        state
+    );
+
+    chip8.step();
+    let mut restored = rom.load();
+    restored.load_state(&state).unwrap();
+    restored.step();
+    assert_eq!(restored.state_digest(), chip8.state_digest());
+    assert_eq!(restored.registers(), chip8.registers());
+}
+
+#[test]
+fn save_states_keep_a_thumbnail_of_the_screen() {
+    // The digit 0 in the top-left corner
+    let mut chip8 = run(&[Inst::OpFX29(0), Inst::OpDXYN(0, 0, 5)]);
+    let mut state = chip8.save_state();
+    let thumbnail = state.thumbnail.clone().unwrap();
+    assert!(thumbnail.lit(0, 0));
+    assert!(thumbnail.lit(1, 2));
+    assert!(!thumbnail.lit(3, 0));
+    assert!(!thumbnail.lit(10, 10));
+
+    // States written before thumbnails still load
+    state.thumbnail = None;
+    let text = state.to_toml().unwrap();
+    assert!(!text.contains("thumbnail"));
+    let state = chip8::savestate::SaveState::from_toml(&text).unwrap();
+    chip8.load_state(&state).unwrap();
+}
+
+#[test]
+fn rewind_steps_back_through_recent_frames() {
+    let mut chip8 = RomBuilder::new()
+        .insts(&[Inst::Op7XKK(0, 1), Inst::Op1NNN(0x200)])
+        .load();
+    let mut rewind = chip8::rewind::Rewind::new(3);
+    for _ in 0..5 {
+        chip8.step();
+        chip8.step();
+        rewind.push(&chip8);
+    }
+    assert_eq!(chip8.registers()[0], 5);
+    assert_eq!(rewind.len(), 3);
+
+    assert!(rewind.step_back(&mut chip8));
+    assert_eq!(chip8.registers()[0], 4);
+    assert!(rewind.step_back(&mut chip8));
+    assert_eq!(chip8.registers()[0], 3);
+    assert!(!rewind.step_back(&mut chip8));
+    assert_eq!(chip8.registers()[0], 3);
+    assert!(rewind.is_empty());
+}
+
+#[test]
+fn rewind_history_is_bounded() {
+    use chip8::rewind;
+
+    assert_eq!(rewind::frames(0), Some(0));
+    assert_eq!(rewind::frames(10), Some(600));
+    assert_eq!(rewind::frames(rewind::MAX_SECONDS), Some(36_000));
+    assert_eq!(rewind::frames(rewind::MAX_SECONDS + 1), None);
+    assert_eq!(rewind::frames(u64::MAX), None);
+}
+
+#[cfg(feature = "fault-injection")]
+#[test]
+fn injected_faults_are_caught_by_the_audit() {
+    use chip8::inject::FaultInjection;
+
+    let program = RomBuilder::new().insts(&[Inst::Op1NNN(0x200)]).build();
+    let machine = |faults: FaultInjection| {
+        let mut chip8 = chip8::Chip8::builder()
+            .seed(0)
+            .fault_injection(faults)
+            .build();
+        chip8.load(&program).unwrap();
+        chip8
+    };
+    let flips = FaultInjection {
+        flip_bit_every: Some(5),
+        ..FaultInjection::default()
+    };
+    let report = chip8::audit::audit(machine(flips), machine(flips), &[], 10);
+    assert_eq!(report.divergence, None, "the faults are deterministic");
+    let report = chip8::audit::audit(machine(FaultInjection::default()), machine(flips), &[], 10);
+    let divergence = report.divergence.expect("a flipped bit is a divergence");
+    assert_eq!(divergence.frame, 1);
+    assert_eq!(divergence.parts, ["memory"]);
+
+    let mut chip8 = machine(FaultInjection {
+        drop_key_every: Some(2),
+        ..FaultInjection::default()
+    });
+    let mut keys = [false; 16];
+    keys[1] = true;
+    chip8.set_keys(keys);
+    assert!(chip8.keys()[1]);
+    chip8.set_keys([false; 16]);
+    assert!(chip8.keys()[1], "every second key change is dropped");
+
+    // Timers twice as slow count down every second frame
+    let mut chip8 = machine(FaultInjection {
+        clock_skew: 2.0,
+        ..FaultInjection::default()
+    });
+    chip8
+        .load(
+            &RomBuilder::new()
+                .insts(&[Inst::Op6XKK(0, 10), Inst::OpFX15(0)])
+                .build(),
+        )
+        .unwrap();
+    chip8.step();
+    chip8.step();
+    for _ in 0..4 {
+        chip8.next_frame();
+    }
+    assert_eq!(chip8.save_state().delay_timer, 8);
+}
+
+#[test]
+fn repro_bundles_replay_the_recorded_frames() {
+    // Wait for the delay timer, then halt on an unknown opcode
+    let program = [
+        0x60, 0x03, // LD V0, 3
+        0xF0, 0x15, // LD DT, V0
+        0xF1, 0x07, // LD V1, DT
+        0x31, 0x00, // SE V1, 0
+        0x12, 0x04, // JP 0x204
+        0xFF, 0xFF, // unknown opcode
+    ];
+    let mut chip8 = chip8::Chip8::builder().strict(true).build();
+    chip8.load(&program).unwrap();
+    chip8.record();
+    let mut steps = 0;
+    while chip8.fault().is_none() {
+        chip8.step();
+        steps += 1;
+        if steps % 8 == 0 {
+            chip8.next_frame();
+        }
+    }
+    let bundle = chip8.repro_bundle().unwrap();
+    assert_eq!(bundle.manifest.frames, [8, 16, 24]);
+
+    let replay = bundle.replay().unwrap();
+    assert_eq!(replay.fault(), chip8.fault());
+    assert_eq!(replay.pc(), 0x20A);
+}
+
+#[test]
+fn repro_bundles_keep_the_settings_of_the_machine() {
+    use chip8::cost::CostTable;
+    use chip8::patch::Patch;
+    use chip8::quirks::Profile;
+    use chip8::rng::RandomSource;
+
+    // Store a random byte and the byte at 0x1FF, then halt on an unknown opcode
+    let program = [
+        0xC3, 0xFF, // RND V3, 0xFF
+        0xA1, 0xFF, // LD I, 0x1FF
+        0xF0, 0x65, // LD V0, [I]
+        0xFF, 0xFF, // unknown opcode
+    ];
+    let mut chip8 = chip8::Chip8::builder()
+        .seed(u64::MAX)
+        .strict(true)
+        .quirks(Profile::Chip8.quirks())
+        .random_source(RandomSource::Constant(0x42))
+        .costs("[costs]\nCXKK = 3".parse::<CostTable>().unwrap())
+        .build();
+    chip8.load_interpreter_image(&[0xAB; 0x200]);
+    chip8.load(&program).unwrap();
+    let patch: Patch = toml::from_str("[registers]\nv2 = 7").unwrap();
+    chip8.apply_patch(&patch).unwrap();
+    chip8.record();
+    while chip8.fault().is_none() {
+        chip8.step();
+    }
+
+    let dir = common::temp_dir("repro-settings");
+    chip8.repro_bundle().unwrap().write(&dir).unwrap();
+    let bundle = chip8::repro::Bundle::read(&dir).unwrap();
+    assert_eq!(bundle.manifest.seed, u64::MAX);
+    assert_eq!(bundle.manifest.quirks, Profile::Chip8.quirks());
+    assert_eq!(bundle.manifest.random_source, "constant:66");
+    assert_eq!(bundle.manifest.costs.get("CXKK"), Some(&3));
+    assert_eq!(bundle.manifest.patches, [patch]);
+
+    let replay = bundle.replay().unwrap();
+    assert_eq!(replay.fault(), chip8.fault());
+    assert_eq!(replay.registers()[..4], [0xAB, 0, 7, 0x42]);
+    assert_eq!(replay.stats().instructions, chip8.stats().instructions);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
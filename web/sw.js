@@ -0,0 +1,23 @@
+// Keeps the page and the module in the cache of the browser, so that the emulator and its bundled
+// ROMs work offline once the page has been opened. Bump CACHE when the files change.
+const CACHE = "chip8-v1";
+const FILES = ["./", "index.html", "chip8.wasm", "manifest.webmanifest", "icon-192.png", "icon-512.png"];
+
+self.addEventListener("install", (event) => {
+  event.waitUntil(caches.open(CACHE).then((cache) => cache.addAll(FILES)));
+});
+
+self.addEventListener("activate", (event) => {
+  event.waitUntil(
+    caches.keys().then((names) =>
+      Promise.all(names.filter((name) => name !== CACHE).map((name) => caches.delete(name)))
+    )
+  );
+});
+
+// Files of the page come from the cache, anything else, such as a linked ROM, from the network
+self.addEventListener("fetch", (event) => {
+  event.respondWith(
+    caches.match(event.request, { ignoreSearch: true }).then((cached) => cached || fetch(event.request))
+  );
+});